@@ -0,0 +1,426 @@
+//! Declarative validation of the accounts of a program instruction.
+//!
+//! An instruction declares the accounts it expects as a list of
+//! [`AccountRule`]s, which [`validate_accounts`] checks against the
+//! [`AccountView`]s received by the program in one pass:
+//!
+//! ```
+//! use {
+//!     solana_account_validation::{validate_accounts, AccountRule},
+//!     solana_account_view::AccountView,
+//!     solana_address::Address,
+//!     solana_program_error::ProgramResult,
+//!     solana_rent::Rent,
+//! };
+//!
+//! fn process_deposit(program_id: &Address, accounts: &[AccountView], user: &Address) -> ProgramResult {
+//!     let rent = Rent::default();
+//!     let vault_seeds = [b"vault".as_ref(), user.as_ref()];
+//!     let vault = AccountRule::new()
+//!         .writable()
+//!         .owned_by(program_id)
+//!         .program_address(&vault_seeds, program_id)
+//!         .rent_exempt(&rent);
+//!     validate_accounts(
+//!         accounts,
+//!         &[AccountRule::new().signer().writable(), vault],
+//!     )?;
+//!     Ok(())
+//! }
+//! ```
+#![no_std]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+use {
+    solana_account_view::AccountView,
+    solana_address::Address,
+    solana_program_error::{ProgramError, ProgramResult},
+    solana_rent::Rent,
+};
+
+/// Expected properties of an account.
+///
+/// Each property is checked in the order below, and the first one that does
+/// not hold determines the error:
+///
+/// | Property          | Error                              |
+/// |-------------------|------------------------------------|
+/// | [`signer`]        | [`ProgramError::MissingRequiredSignature`] |
+/// | [`writable`]      | [`ProgramError::Immutable`]        |
+/// | [`executable`]    | [`ProgramError::IncorrectProgramId`] |
+/// | [`owned_by`]      | [`ProgramError::InvalidAccountOwner`] |
+/// | [`address`]       | [`ProgramError::InvalidArgument`]  |
+/// | program address   | [`ProgramError::InvalidSeeds`]     |
+/// | [`rent_exempt`]   | [`ProgramError::AccountNotRentExempt`] |
+/// | [`not_borrowed`]  | [`ProgramError::AccountBorrowFailed`] |
+///
+/// [`signer`]: AccountRule::signer
+/// [`writable`]: AccountRule::writable
+/// [`executable`]: AccountRule::executable
+/// [`owned_by`]: AccountRule::owned_by
+/// [`address`]: AccountRule::address
+/// [`rent_exempt`]: AccountRule::rent_exempt
+/// [`not_borrowed`]: AccountRule::not_borrowed
+#[derive(Debug, Clone, Default)]
+pub struct AccountRule<'a> {
+    signer: bool,
+    writable: bool,
+    executable: bool,
+    not_borrowed: bool,
+    owner: Option<&'a Address>,
+    address: Option<Address>,
+    program_address: Option<ProgramAddress<'a>>,
+    rent: Option<&'a Rent>,
+}
+
+/// Program derived address required by a rule, derived when it is needed.
+#[derive(Debug, Clone, Copy)]
+struct ProgramAddress<'a> {
+    seeds: &'a [&'a [u8]],
+    bump: Option<u8>,
+    program_id: &'a Address,
+    /// [`derive`] for the number of seeds, which the derivation functions
+    /// take as a const parameter.
+    derive: DeriveFn,
+}
+
+type DeriveFn = fn(&[&[u8]], Option<u8>, &Address) -> Option<(Address, u8)>;
+
+impl ProgramAddress<'_> {
+    fn address(&self) -> Option<(Address, u8)> {
+        (self.derive)(self.seeds, self.bump, self.program_id)
+    }
+}
+
+/// Derives the program address of `N` `seeds` with `bump`, or with the
+/// canonical bump if there is none.
+fn derive<const N: usize>(
+    seeds: &[&[u8]],
+    bump: Option<u8>,
+    program_id: &Address,
+) -> Option<(Address, u8)> {
+    let seeds: &[&[u8]; N] = seeds.try_into().ok()?;
+    match bump {
+        Some(bump) => Some((Address::derive_address(seeds, Some(bump), program_id), bump)),
+        None => Address::derive_program_address(seeds, program_id),
+    }
+}
+
+impl<'a> AccountRule<'a> {
+    /// Creates a rule that any account satisfies.
+    pub const fn new() -> Self {
+        Self {
+            signer: false,
+            writable: false,
+            executable: false,
+            not_borrowed: false,
+            owner: None,
+            address: None,
+            program_address: None,
+            rent: None,
+        }
+    }
+
+    /// Requires the account to have signed the transaction.
+    pub const fn signer(mut self) -> Self {
+        self.signer = true;
+        self
+    }
+
+    /// Requires the account to be writable.
+    pub const fn writable(mut self) -> Self {
+        self.writable = true;
+        self
+    }
+
+    /// Requires the account to be an executable program.
+    pub const fn executable(mut self) -> Self {
+        self.executable = true;
+        self
+    }
+
+    /// Requires the data of the account not to be borrowed.
+    pub const fn not_borrowed(mut self) -> Self {
+        self.not_borrowed = true;
+        self
+    }
+
+    /// Requires the account to be owned by `owner`.
+    pub const fn owned_by(mut self, owner: &'a Address) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    /// Requires the account to be `address`.
+    pub fn address(mut self, address: &Address) -> Self {
+        self.address = Some(*address);
+        self
+    }
+
+    /// Requires the account to be the program derived address of `seeds`
+    /// with the canonical bump, which [`Self::bump`] returns.
+    ///
+    /// The address is derived with [`Address::derive_program_address`] each
+    /// time the rule is checked, not when it is declared, so rules that are
+    /// never checked cost nothing.
+    pub const fn program_address<const N: usize>(
+        mut self,
+        seeds: &'a [&'a [u8]; N],
+        program_id: &'a Address,
+    ) -> Self {
+        self.program_address = Some(ProgramAddress {
+            seeds,
+            bump: None,
+            program_id,
+            derive: derive::<N>,
+        });
+        self
+    }
+
+    /// Requires the account to be the program derived address of `seeds`
+    /// with a known `bump`.
+    ///
+    /// This is much cheaper than [`Self::program_address`], but does not check
+    /// that `bump` is canonical.
+    pub const fn program_address_with_bump<const N: usize>(
+        mut self,
+        seeds: &'a [&'a [u8]; N],
+        bump: u8,
+        program_id: &'a Address,
+    ) -> Self {
+        self.program_address = Some(ProgramAddress {
+            seeds,
+            bump: Some(bump),
+            program_id,
+            derive: derive::<N>,
+        });
+        self
+    }
+
+    /// Requires the lamports of the account to make it rent exempt.
+    pub const fn rent_exempt(mut self, rent: &'a Rent) -> Self {
+        self.rent = Some(rent);
+        self
+    }
+
+    /// Returns the bump seed of the required program derived address, if the
+    /// rule requires one that could be derived.
+    ///
+    /// For a rule made with [`Self::program_address`], this derives the
+    /// address again.
+    pub fn bump(&self) -> Option<u8> {
+        let program_address = self.program_address.as_ref()?;
+        match program_address.bump {
+            Some(bump) => Some(bump),
+            None => program_address.address().map(|(_, bump)| bump),
+        }
+    }
+
+    /// Checks that `account` has the required properties.
+    pub fn check(&self, account: &AccountView) -> ProgramResult {
+        if self.signer && !account.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if self.writable && !account.is_writable() {
+            return Err(ProgramError::Immutable);
+        }
+        if self.executable && !account.executable() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        if let Some(owner) = self.owner {
+            if !account.owned_by(owner) {
+                return Err(ProgramError::InvalidAccountOwner);
+            }
+        }
+        if let Some(address) = &self.address {
+            if account.address() != address {
+                return Err(ProgramError::InvalidArgument);
+            }
+        }
+        if let Some(program_address) = &self.program_address {
+            let (address, _) = program_address
+                .address()
+                .ok_or(ProgramError::InvalidSeeds)?;
+            if account.address() != &address {
+                return Err(ProgramError::InvalidSeeds);
+            }
+        }
+        if let Some(rent) = self.rent {
+            if !rent.is_exempt(account.lamports(), account.data_len()) {
+                return Err(ProgramError::AccountNotRentExempt);
+            }
+        }
+        if self.not_borrowed && account.is_borrowed() {
+            return Err(ProgramError::AccountBorrowFailed);
+        }
+        Ok(())
+    }
+}
+
+/// Checks each of `accounts` against the rule at the same position.
+///
+/// Fails with [`ProgramError::NotEnoughAccountKeys`] if there are fewer
+/// accounts than rules; accounts past the rules are not checked.
+pub fn validate_accounts(accounts: &[AccountView], rules: &[AccountRule]) -> ProgramResult {
+    if accounts.len() < rules.len() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    rules
+        .iter()
+        .zip(accounts)
+        .try_for_each(|(rule, account)| rule.check(account))
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use {
+        super::*,
+        core::mem::size_of,
+        solana_account_view::{RuntimeAccount, NOT_BORROWED},
+        std::{vec, vec::Vec},
+    };
+
+    const DATA_LEN: usize = 16;
+    const WORDS: usize = (size_of::<RuntimeAccount>() + DATA_LEN) / 8;
+
+    /// Backing memory of an account, 8-byte aligned.
+    struct TestAccount(Vec<u64>);
+
+    impl TestAccount {
+        fn new(address: Address, owner: Address, lamports: u64) -> Self {
+            let mut memory = vec![0u64; WORDS];
+            let account = memory.as_mut_ptr() as *mut RuntimeAccount;
+            unsafe {
+                (*account).borrow_state = NOT_BORROWED;
+                (*account).address = address;
+                (*account).owner = owner;
+                (*account).lamports = lamports;
+                (*account).data_len = DATA_LEN as u64;
+            }
+            Self(memory)
+        }
+
+        fn runtime_account(&mut self) -> &mut RuntimeAccount {
+            unsafe { &mut *(self.0.as_mut_ptr() as *mut RuntimeAccount) }
+        }
+
+        fn view(&mut self) -> AccountView {
+            unsafe { AccountView::new_unchecked(self.0.as_mut_ptr() as *mut RuntimeAccount) }
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let program_id = Address::new_from_array([1; 32]);
+        let rent = Rent::default();
+        let mut account = TestAccount::new(
+            Address::new_from_array([2; 32]),
+            program_id,
+            rent.minimum_balance(DATA_LEN),
+        );
+
+        let other = Address::new_from_array([3; 32]);
+        let rules = [
+            (
+                AccountRule::new().signer(),
+                ProgramError::MissingRequiredSignature,
+            ),
+            (AccountRule::new().writable(), ProgramError::Immutable),
+            (
+                AccountRule::new().executable(),
+                ProgramError::IncorrectProgramId,
+            ),
+            (
+                AccountRule::new().owned_by(&other),
+                ProgramError::InvalidAccountOwner,
+            ),
+            (
+                AccountRule::new().address(&other),
+                ProgramError::InvalidArgument,
+            ),
+        ];
+        for (rule, error) in &rules {
+            assert_eq!(rule.check(&account.view()), Err(error.clone()));
+        }
+
+        let runtime_account = account.runtime_account();
+        runtime_account.is_signer = 1;
+        runtime_account.is_writable = 1;
+        runtime_account.executable = 1;
+        for (rule, _) in &rules[..3] {
+            assert_eq!(rule.check(&account.view()), Ok(()));
+        }
+        let rule = AccountRule::new()
+            .signer()
+            .writable()
+            .executable()
+            .owned_by(&program_id)
+            .address(&Address::new_from_array([2; 32]))
+            .rent_exempt(&rent)
+            .not_borrowed();
+        assert_eq!(rule.check(&account.view()), Ok(()));
+
+        account.runtime_account().lamports -= 1;
+        assert_eq!(
+            rule.check(&account.view()),
+            Err(ProgramError::AccountNotRentExempt)
+        );
+        account.runtime_account().lamports += 1;
+
+        let view = account.view();
+        let data = view.try_borrow().unwrap();
+        assert_eq!(rule.check(&view), Err(ProgramError::AccountBorrowFailed));
+        drop(data);
+        assert_eq!(rule.check(&view), Ok(()));
+    }
+
+    #[test]
+    fn test_program_address() {
+        let program_id = Address::new_from_array([1; 32]);
+        let seeds: &[&[u8]; 2] = &[b"vault", &[7; 32]];
+        let (address, bump) = Address::derive_program_address(seeds, &program_id).unwrap();
+        let mut account = TestAccount::new(address, program_id, 0);
+
+        let rule = AccountRule::new().program_address(seeds, &program_id);
+        assert_eq!(rule.bump(), Some(bump));
+        assert_eq!(rule.check(&account.view()), Ok(()));
+        assert_eq!(
+            AccountRule::new()
+                .program_address_with_bump(seeds, bump, &program_id)
+                .check(&account.view()),
+            Ok(())
+        );
+
+        let rule = AccountRule::new().program_address(&[b"other"], &program_id);
+        assert_eq!(rule.check(&account.view()), Err(ProgramError::InvalidSeeds));
+        let rule =
+            AccountRule::new().program_address_with_bump(seeds, bump.wrapping_sub(1), &program_id);
+        assert_eq!(rule.check(&account.view()), Err(ProgramError::InvalidSeeds));
+    }
+
+    #[test]
+    fn test_validate_accounts() {
+        let program_id = Address::new_from_array([1; 32]);
+        let mut payer = TestAccount::new(Address::new_from_array([2; 32]), Address::default(), 1);
+        let mut state = TestAccount::new(Address::new_from_array([3; 32]), program_id, 1);
+        payer.runtime_account().is_signer = 1;
+        let accounts = [payer.view(), state.view()];
+
+        let rules = [
+            AccountRule::new().signer(),
+            AccountRule::new().owned_by(&program_id),
+        ];
+        assert_eq!(validate_accounts(&accounts, &rules), Ok(()));
+        assert_eq!(validate_accounts(&accounts, &rules[..1]), Ok(()));
+        assert_eq!(
+            validate_accounts(&accounts[..1], &rules),
+            Err(ProgramError::NotEnoughAccountKeys)
+        );
+        assert_eq!(
+            validate_accounts(&[state.view(), payer.view()], &rules),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+    }
+}
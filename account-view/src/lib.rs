@@ -58,9 +58,10 @@ pub struct RuntimeAccount {
 
     /// Padding for alignment.
     ///
-    /// The value of this field is not directly used and always set to `0`.
-    /// Entrypoint implementations may use this space for their own purposes,
-    /// e.g., to track account resizing.
+    /// The runtime always sets this field to `0`. Entrypoint implementations
+    /// may use this space for their own purposes, e.g., to track account
+    /// resizing: callers of [`AccountView::resize`] must make sure it holds
+    /// the original data length as a native-endian `u32`.
     pub padding: [u8; 4],
 
     /// Address of the account.
@@ -378,6 +379,82 @@ impl AccountView {
         write_bytes(self.data_mut_ptr().sub(48), 0, 48);
     }
 
+    /// Return the length of the account data when the program was entered.
+    ///
+    /// # Safety
+    ///
+    /// This method reads the original data length from the padding of the
+    /// `RuntimeAccount`. It should only be called for instances of
+    /// `AccountView` created by an entrypoint that records it there, such
+    /// as `solana_program_entrypoint::deserialize_account_views`.
+    #[inline(always)]
+    pub unsafe fn original_data_len(&self) -> usize {
+        u32::from_ne_bytes((*self.raw).padding) as usize
+    }
+
+    /// Resize the account's data: Either truncating or zero extending.
+    ///
+    /// Account data can be increased within a single call by up to
+    /// [`MAX_PERMITTED_DATA_INCREASE`] bytes over its original length.
+    ///
+    /// # Safety
+    ///
+    /// This method makes assumptions about the layout and location of memory
+    /// referenced by `RuntimeAccount` fields. It should only be called for
+    /// instances of `AccountView` that were created by the runtime and received
+    /// in the `process_instruction` entrypoint of a program.
+    ///
+    /// It also trusts the original data length read from the padding of the
+    /// `RuntimeAccount`, as [`Self::original_data_len`] does, to bound the
+    /// zero-extended region. The entrypoint must have recorded it there: with
+    /// any other value in the padding, growing the data can write past the
+    /// memory reserved for the account.
+    pub unsafe fn resize(&mut self, new_len: usize) -> ProgramResult {
+        // Make sure the account is not borrowed since we are about to
+        // change the length of the data.
+        if self.is_borrowed() {
+            return Err(ProgramError::AccountBorrowFailed);
+        }
+
+        // SAFETY: There are no active borrows on the account data, and the
+        // caller upholds the remaining requirements.
+        self.resize_unchecked(new_len)
+    }
+
+    /// Resize the account's data: Either truncating or zero extending.
+    ///
+    /// Account data can be increased within a single call by up to
+    /// [`MAX_PERMITTED_DATA_INCREASE`] bytes over its original length.
+    ///
+    /// # Safety
+    ///
+    /// This method is unsafe because it does not check if the account data is already
+    /// borrowed. It should only be called when the account is not being used.
+    ///
+    /// It also has the same requirements as [`Self::resize`].
+    pub unsafe fn resize_unchecked(&mut self, new_len: usize) -> ProgramResult {
+        let old_len = self.data_len();
+
+        // Return early if length hasn't changed
+        if new_len == old_len {
+            return Ok(());
+        }
+
+        // Return early if the length increase from the original serialized data
+        // length is too large and would result in an out of bounds allocation.
+        if new_len.saturating_sub(self.original_data_len()) > MAX_PERMITTED_DATA_INCREASE {
+            return Err(ProgramError::InvalidRealloc);
+        }
+
+        (*self.raw).data_len = new_len as u64;
+
+        if new_len > old_len {
+            write_bytes(self.data_mut_ptr().add(old_len), 0, new_len - old_len);
+        }
+
+        Ok(())
+    }
+
     /// Returns a raw pointer to the `RuntimeAccount` struct.
     pub fn account_ptr(&self) -> *const RuntimeAccount {
         self.raw as *const _
@@ -746,4 +823,58 @@ mod tests {
         let borrow_state = unsafe { (*account_view.raw).borrow_state };
         assert!(borrow_state == NOT_BORROWED);
     }
+
+    #[test]
+    fn test_resize() {
+        const DATA_LEN: usize = 8;
+        // 8-bytes aligned account data, followed by the space the runtime
+        // reserves for growing it.
+        let mut data = [0u64;
+            (size_of::<RuntimeAccount>() + DATA_LEN + MAX_PERMITTED_DATA_INCREASE)
+                / size_of::<u64>()];
+        data[0] = NOT_BORROWED as u64;
+
+        let account = data.as_mut_ptr() as *mut RuntimeAccount;
+        unsafe {
+            (*account).data_len = DATA_LEN as u64;
+            (*account).padding = (DATA_LEN as u32).to_ne_bytes();
+        }
+        let mut account_view = AccountView { raw: account };
+        unsafe { account_view.borrow_unchecked_mut() }.fill(1);
+        // Stale bytes past the end of the data
+        unsafe { write_bytes(account_view.data_mut_ptr().add(DATA_LEN), 2, 8) };
+
+        // Shrink, then grow again: the regrown bytes are zeroed
+        unsafe { account_view.resize(4) }.unwrap();
+        assert_eq!(account_view.data_len(), 4);
+        unsafe { account_view.resize(12) }.unwrap();
+        assert_eq!(
+            &*account_view.try_borrow().unwrap(),
+            &[1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+
+        // Growth is limited relative to the original length
+        unsafe { account_view.resize(DATA_LEN + MAX_PERMITTED_DATA_INCREASE) }.unwrap();
+        assert_eq!(
+            unsafe { account_view.resize(DATA_LEN + MAX_PERMITTED_DATA_INCREASE + 1) },
+            Err(ProgramError::InvalidRealloc)
+        );
+        assert_eq!(
+            account_view.data_len(),
+            DATA_LEN + MAX_PERMITTED_DATA_INCREASE
+        );
+        unsafe { account_view.resize(0) }.unwrap();
+        assert!(account_view.is_data_empty());
+
+        // Borrowed data cannot be resized
+        let other_view = AccountView { raw: account };
+        let data_ref = other_view.try_borrow().unwrap();
+        assert_eq!(
+            unsafe { account_view.resize(DATA_LEN) },
+            Err(ProgramError::AccountBorrowFailed)
+        );
+        drop(data_ref);
+        unsafe { account_view.resize(DATA_LEN) }.unwrap();
+        assert_eq!(&*account_view.try_borrow().unwrap(), &[0; DATA_LEN]);
+    }
 }
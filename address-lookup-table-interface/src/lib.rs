@@ -6,6 +6,8 @@
 
 pub mod error;
 pub mod instruction;
+#[cfg(any(feature = "wincode", feature = "bincode"))]
+pub mod planner;
 pub mod state;
 
 pub mod program {
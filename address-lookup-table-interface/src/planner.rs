@@ -0,0 +1,636 @@
+//! Planning the instructions that create, extend and freeze a lookup table.
+//!
+//! [`LookupTablePlanner`] turns a set of target addresses into the ordered
+//! batches of instructions that make a lookup table hold them, with each
+//! batch fitting in a single legacy transaction.
+//!
+//! # Warm-up
+//!
+//! Addresses appended to a table cannot be looked up in the slot in which
+//! they were appended, only in later slots. The planner cannot know when its
+//! batches will land, so it does not wait for them: every address in
+//! [`LookupTablePlan::new_addresses`] is only usable in slots after the one in
+//! which its batch lands, and every address in
+//! [`LookupTablePlan::warming_up_addresses`] only in slots after
+//! [`LookupTablePlan::warm_up_slot`]. Callers must wait for those slots before
+//! submitting transactions which look the addresses up, or the transactions
+//! will fail to load.
+
+use {
+    crate::{
+        instruction::{create_lookup_table, extend_lookup_table, freeze_lookup_table},
+        state::{AddressLookupTable, LOOKUP_TABLE_MAX_ADDRESSES},
+    },
+    core::fmt,
+    solana_clock::Slot,
+    solana_instruction::Instruction,
+    solana_pubkey::Pubkey,
+    solana_short_vec::encoded_shortu16_len_size,
+    std::collections::{BTreeMap, HashSet},
+};
+
+// inlined to avoid solana_packet dep
+/// Maximum size of a serialized legacy transaction.
+const PACKET_DATA_SIZE: usize = 1280 - 40 - 8;
+#[cfg(test)]
+static_assertions::const_assert_eq!(PACKET_DATA_SIZE, solana_packet::PACKET_DATA_SIZE);
+
+/// Plans the instructions needed to make a lookup table hold a set of
+/// addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupTablePlanner {
+    authority_address: Pubkey,
+    payer_address: Pubkey,
+    recent_slot: Slot,
+    freeze: bool,
+}
+
+/// Instructions to be submitted together in a single transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupTableBatch {
+    /// The instructions, in order.
+    pub instructions: Vec<Instruction>,
+    /// Serialized size of a legacy transaction carrying the instructions,
+    /// paid for by the planner's payer.
+    pub transaction_size: usize,
+}
+
+/// Instructions planned by [`LookupTablePlanner::plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupTablePlan {
+    /// Address of the lookup table.
+    pub lookup_table_address: Pubkey,
+    /// Batches of instructions which must be submitted in order, each in its
+    /// own transaction.
+    ///
+    /// Addresses added by a batch can only be looked up in slots after the
+    /// slot in which the batch lands.
+    pub batches: Vec<LookupTableBatch>,
+    /// Target addresses which are not yet in the table, in the order they
+    /// are added.
+    pub new_addresses: Vec<Pubkey>,
+    /// Target addresses already in the table which were added in or after
+    /// the planner's recent slot, and may not be usable for lookups yet.
+    ///
+    /// See the [module documentation](self#warm-up).
+    pub warming_up_addresses: Vec<Pubkey>,
+    /// Slot in which `warming_up_addresses` were added, if any: they can only
+    /// be looked up in later slots.
+    pub warm_up_slot: Option<Slot>,
+    /// Every address held by the table once all batches have landed, in
+    /// table index order.
+    pub addresses: Vec<Pubkey>,
+}
+
+/// Error returned by [`LookupTablePlanner::plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LookupTablePlanError {
+    /// The table would hold more than [`LOOKUP_TABLE_MAX_ADDRESSES`].
+    TooManyAddresses { num_addresses: usize },
+    /// The existing table is frozen and cannot be modified.
+    Frozen,
+    /// The existing table is deactivated and cannot be modified.
+    Deactivated,
+    /// The existing table is controlled by a different authority.
+    IncorrectAuthority,
+    /// The table would be frozen without holding any addresses.
+    EmptyFreeze,
+    /// An instruction adding a single address does not fit in a transaction.
+    TransactionTooLarge,
+}
+
+impl core::error::Error for LookupTablePlanError {}
+
+impl fmt::Display for LookupTablePlanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooManyAddresses { num_addresses } => write!(
+                f,
+                "Lookup table would hold {num_addresses} addresses, more than the maximum of \
+                 {LOOKUP_TABLE_MAX_ADDRESSES}"
+            ),
+            Self::Frozen => f.write_str("Lookup table is frozen"),
+            Self::Deactivated => f.write_str("Lookup table is deactivated"),
+            Self::IncorrectAuthority => f.write_str("Lookup table has a different authority"),
+            Self::EmptyFreeze => f.write_str("Empty lookup tables cannot be frozen"),
+            Self::TransactionTooLarge => {
+                f.write_str("Lookup table instructions do not fit in a transaction")
+            }
+        }
+    }
+}
+
+impl LookupTablePlanner {
+    /// Create a planner for tables controlled by `authority_address`, with
+    /// rent paid by `payer_address`.
+    ///
+    /// `recent_slot` must be a recently observed slot. It derives the
+    /// address of a new table, and so must still be in the `SlotHashes`
+    /// sysvar when the first batch lands. For an existing table, addresses
+    /// added in or after `recent_slot` are reported as warming up.
+    pub fn new(authority_address: Pubkey, payer_address: Pubkey, recent_slot: Slot) -> Self {
+        Self {
+            authority_address,
+            payer_address,
+            recent_slot,
+            freeze: false,
+        }
+    }
+
+    /// Freeze the table once it holds every target address.
+    #[must_use]
+    pub fn with_freeze(mut self, freeze: bool) -> Self {
+        self.freeze = freeze;
+        self
+    }
+
+    /// Plan the instructions that make a lookup table hold every address in
+    /// `addresses`.
+    ///
+    /// If `existing` is `None`, a new table is created at the address derived
+    /// from the planner's authority and recent slot. Otherwise `existing`
+    /// holds the address and current state of the table to extend, and
+    /// addresses it already holds are not added again.
+    ///
+    /// Each batch is filled with as many addresses as fit in a transaction,
+    /// so the plan has as few batches as possible. The table creation is
+    /// included in the first batch, and the freeze in the last batch.
+    pub fn plan(
+        &self,
+        addresses: &[Pubkey],
+        existing: Option<(Pubkey, &AddressLookupTable)>,
+    ) -> Result<LookupTablePlan, LookupTablePlanError> {
+        let mut table_addresses = Vec::new();
+        let mut warming_up_addresses = Vec::new();
+        let mut warm_up_slot = None;
+        let mut pending = Vec::new();
+        let lookup_table_address = match existing {
+            Some((lookup_table_address, lookup_table)) => {
+                table_addresses.extend_from_slice(&lookup_table.addresses);
+                // Same rule as `AddressLookupTable::get_active_addresses_len`,
+                // with the recent slot as the current slot.
+                let warm_up_start = if self.recent_slot > lookup_table.meta.last_extended_slot {
+                    table_addresses.len()
+                } else {
+                    usize::from(lookup_table.meta.last_extended_slot_start_index)
+                };
+                let target: HashSet<&Pubkey> = addresses.iter().collect();
+                warming_up_addresses.extend(
+                    table_addresses
+                        .iter()
+                        .skip(warm_up_start)
+                        .filter(|address| target.contains(address)),
+                );
+                if !warming_up_addresses.is_empty() {
+                    warm_up_slot = Some(lookup_table.meta.last_extended_slot);
+                }
+                lookup_table_address
+            }
+            None => {
+                let (instruction, lookup_table_address) = create_lookup_table(
+                    self.authority_address,
+                    self.payer_address,
+                    self.recent_slot,
+                );
+                pending.push(instruction);
+                lookup_table_address
+            }
+        };
+
+        let mut seen: HashSet<Pubkey> = table_addresses.iter().copied().collect();
+        let new_addresses: Vec<Pubkey> = addresses
+            .iter()
+            .filter(|address| seen.insert(**address))
+            .copied()
+            .collect();
+        let num_addresses = table_addresses.len().saturating_add(new_addresses.len());
+        if num_addresses > LOOKUP_TABLE_MAX_ADDRESSES {
+            return Err(LookupTablePlanError::TooManyAddresses { num_addresses });
+        }
+        if self.freeze && num_addresses == 0 {
+            return Err(LookupTablePlanError::EmptyFreeze);
+        }
+        let is_frozen = existing.is_some_and(|(_, table)| table.meta.authority.is_none());
+        let freeze = self.freeze && !is_frozen;
+        if let Some((_, lookup_table)) = existing {
+            if is_frozen && !new_addresses.is_empty() {
+                return Err(LookupTablePlanError::Frozen);
+            }
+            if !new_addresses.is_empty() || freeze {
+                if lookup_table.meta.deactivation_slot != Slot::MAX {
+                    return Err(LookupTablePlanError::Deactivated);
+                }
+                if lookup_table.meta.authority != Some(self.authority_address) {
+                    return Err(LookupTablePlanError::IncorrectAuthority);
+                }
+            }
+        }
+
+        let mut batches = Vec::new();
+        let mut remaining = new_addresses.as_slice();
+        while !remaining.is_empty() {
+            let (extend, num_added) =
+                match self.largest_extend(lookup_table_address, &pending, remaining) {
+                    Some(extend) => extend,
+                    None if !pending.is_empty() => {
+                        // Only the creation can be pending; move the extension
+                        // to a transaction of its own.
+                        batches.push(self.batch(core::mem::take(&mut pending)));
+                        self.largest_extend(lookup_table_address, &[], remaining)
+                            .ok_or(LookupTablePlanError::TransactionTooLarge)?
+                    }
+                    None => return Err(LookupTablePlanError::TransactionTooLarge),
+                };
+            remaining = &remaining[num_added..];
+            pending.push(extend);
+            batches.push(self.batch(core::mem::take(&mut pending)));
+        }
+
+        if freeze {
+            let freeze = freeze_lookup_table(lookup_table_address, self.authority_address);
+            match batches.last_mut() {
+                Some(last)
+                    if pending.is_empty()
+                        && self.fits(last.instructions.iter().chain([&freeze])) =>
+                {
+                    last.instructions.push(freeze);
+                    *last = self.batch(core::mem::take(&mut last.instructions));
+                }
+                _ => pending.push(freeze),
+            }
+        }
+        if !pending.is_empty() {
+            batches.push(self.batch(pending));
+        }
+
+        table_addresses.extend_from_slice(&new_addresses);
+        Ok(LookupTablePlan {
+            lookup_table_address,
+            batches,
+            new_addresses,
+            warming_up_addresses,
+            warm_up_slot,
+            addresses: table_addresses,
+        })
+    }
+
+    /// Returns the extension with the most leading addresses of `remaining`
+    /// which fits in a transaction after `pending`, and the number of
+    /// addresses it adds.
+    ///
+    /// Each address grows the extension's data by 32 bytes, so the size of
+    /// the transaction is computed once for an empty extension, and the
+    /// number of addresses solved for from there.
+    #[allow(clippy::arithmetic_side_effects)]
+    fn largest_extend(
+        &self,
+        lookup_table_address: Pubkey,
+        pending: &[Instruction],
+        remaining: &[Pubkey],
+    ) -> Option<(Instruction, usize)> {
+        let mut extend = extend_lookup_table(
+            lookup_table_address,
+            self.authority_address,
+            Some(self.payer_address),
+            Vec::new(),
+        );
+        let empty_size = transaction_size(&self.payer_address, pending.iter().chain([&extend]));
+        let empty_data_len = extend.data.len();
+        let size = |num_addresses: usize| {
+            let data_len = empty_data_len + num_addresses * ADDRESS_SIZE;
+            empty_size + num_addresses * ADDRESS_SIZE + encoded_shortu16_len_size(data_len)
+                - encoded_shortu16_len_size(empty_data_len)
+        };
+
+        let available = PACKET_DATA_SIZE.checked_sub(empty_size)?;
+        let mut num_addresses = remaining.len().min(available / ADDRESS_SIZE);
+        // The data length prefix may have grown past the available space.
+        while num_addresses > 0 && size(num_addresses) > PACKET_DATA_SIZE {
+            num_addresses -= 1;
+        }
+        if num_addresses == 0 {
+            return None;
+        }
+
+        extend = extend_lookup_table(
+            lookup_table_address,
+            self.authority_address,
+            Some(self.payer_address),
+            remaining[..num_addresses].to_vec(),
+        );
+        debug_assert_eq!(
+            transaction_size(&self.payer_address, pending.iter().chain([&extend])),
+            size(num_addresses)
+        );
+        Some((extend, num_addresses))
+    }
+
+    fn fits<'a>(&self, instructions: impl Iterator<Item = &'a Instruction>) -> bool {
+        transaction_size(&self.payer_address, instructions) <= PACKET_DATA_SIZE
+    }
+
+    fn batch(&self, instructions: Vec<Instruction>) -> LookupTableBatch {
+        LookupTableBatch {
+            transaction_size: transaction_size(&self.payer_address, instructions.iter()),
+            instructions,
+        }
+    }
+}
+
+/// Size of an address in the data of an extension.
+const ADDRESS_SIZE: usize = 32;
+
+/// Serialized size of a legacy transaction carrying `instructions`, paid for
+/// by `payer`.
+#[allow(clippy::arithmetic_side_effects)]
+fn transaction_size<'a>(
+    payer: &Pubkey,
+    instructions: impl Iterator<Item = &'a Instruction>,
+) -> usize {
+    let mut is_signer = BTreeMap::from([(*payer, true)]);
+    let mut num_instructions = 0;
+    let mut instructions_size = 0;
+    for instruction in instructions {
+        is_signer.entry(instruction.program_id).or_insert(false);
+        for account in &instruction.accounts {
+            *is_signer.entry(account.pubkey).or_insert(false) |= account.is_signer;
+        }
+        num_instructions += 1;
+        instructions_size += 1 // program id index
+            + encoded_shortu16_len_size(instruction.accounts.len())
+            + instruction.accounts.len()
+            + encoded_shortu16_len_size(instruction.data.len())
+            + instruction.data.len();
+    }
+    let num_signatures = is_signer.values().filter(|is_signer| **is_signer).count();
+
+    encoded_shortu16_len_size(num_signatures)
+        + num_signatures * 64 // signatures
+        + 3 // message header
+        + encoded_shortu16_len_size(is_signer.len())
+        + is_signer.len() * 32 // account keys
+        + 32 // recent blockhash
+        + encoded_shortu16_len_size(num_instructions)
+        + instructions_size
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            instruction::{derive_lookup_table_address, ProgramInstruction},
+            state::LookupTableMeta,
+        },
+        solana_hash::Hash,
+        solana_message::{v1::TransactionConfig, MessageVersion},
+        std::borrow::Cow,
+    };
+
+    fn new_addresses(count: usize) -> Vec<Pubkey> {
+        (0..count).map(|_| Pubkey::new_unique()).collect()
+    }
+
+    fn existing_table(authority: Pubkey, addresses: &[Pubkey]) -> AddressLookupTable<'static> {
+        AddressLookupTable {
+            meta: LookupTableMeta::new(authority),
+            addresses: Cow::Owned(addresses.to_vec()),
+        }
+    }
+
+    fn decode(instruction: &Instruction) -> ProgramInstruction {
+        wincode::deserialize(&instruction.data).unwrap()
+    }
+
+    fn extended_addresses(batch: &LookupTableBatch) -> Vec<Pubkey> {
+        batch
+            .instructions
+            .iter()
+            .filter_map(|instruction| match decode(instruction) {
+                ProgramInstruction::ExtendLookupTable { new_addresses } => Some(new_addresses),
+                _ => None,
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Checks each batch's size, and that no batch but the last has room for
+    /// another address.
+    fn assert_batches(payer: &Pubkey, plan: &LookupTablePlan) {
+        for (i, batch) in plan.batches.iter().enumerate() {
+            let (_, size) = MessageVersion::Legacy
+                .try_compile(
+                    payer,
+                    &batch.instructions,
+                    &[],
+                    Hash::default(),
+                    TransactionConfig::empty(),
+                )
+                .unwrap();
+            assert_eq!(batch.transaction_size, size);
+            assert!(batch.transaction_size <= PACKET_DATA_SIZE);
+
+            if i < plan.batches.len().saturating_sub(1) {
+                let mut instructions = batch.instructions.clone();
+                let extend = instructions
+                    .iter_mut()
+                    .find(|instruction| {
+                        matches!(
+                            decode(instruction),
+                            ProgramInstruction::ExtendLookupTable { .. }
+                        )
+                    })
+                    .unwrap();
+                extend.data.extend_from_slice(Pubkey::new_unique().as_ref());
+                assert!(transaction_size(payer, instructions.iter()) > PACKET_DATA_SIZE);
+            }
+        }
+    }
+
+    #[test]
+    fn plans_new_table() {
+        let authority = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let addresses = new_addresses(LOOKUP_TABLE_MAX_ADDRESSES);
+        let plan = LookupTablePlanner::new(authority, payer, 42)
+            .with_freeze(true)
+            .plan(&addresses, None)
+            .unwrap();
+
+        let (lookup_table_address, _) = derive_lookup_table_address(&authority, 42);
+        assert_eq!(plan.lookup_table_address, lookup_table_address);
+        assert_eq!(plan.new_addresses, addresses);
+        assert_eq!(plan.addresses, addresses);
+        assert!(plan.warming_up_addresses.is_empty());
+        assert_eq!(
+            plan.batches
+                .iter()
+                .flat_map(extended_addresses)
+                .collect::<Vec<_>>(),
+            addresses
+        );
+        assert_batches(&payer, &plan);
+
+        let first = &plan.batches[0].instructions;
+        assert!(matches!(
+            decode(&first[0]),
+            ProgramInstruction::CreateLookupTable {
+                recent_slot: 42,
+                ..
+            }
+        ));
+        let last = &plan.batches.last().unwrap().instructions;
+        assert_eq!(
+            decode(last.last().unwrap()),
+            ProgramInstruction::FreezeLookupTable
+        );
+        assert_eq!(last.len(), 2);
+    }
+
+    #[test]
+    fn plans_empty_new_table() {
+        let authority = Pubkey::new_unique();
+        let plan = LookupTablePlanner::new(authority, authority, 42)
+            .plan(&[], None)
+            .unwrap();
+
+        assert_eq!(plan.batches.len(), 1);
+        assert_eq!(plan.batches[0].instructions.len(), 1);
+        assert!(plan.addresses.is_empty());
+    }
+
+    #[test]
+    fn dedupes_against_existing_table() {
+        let authority = Pubkey::new_unique();
+        let existing = new_addresses(3);
+        let added = new_addresses(2);
+        let table = existing_table(authority, &existing);
+        let target = [added[0], existing[1], added[1], added[0], existing[2]];
+
+        let lookup_table_address = Pubkey::new_unique();
+        let plan = LookupTablePlanner::new(authority, authority, 10)
+            .plan(&target, Some((lookup_table_address, &table)))
+            .unwrap();
+
+        assert_eq!(plan.lookup_table_address, lookup_table_address);
+        assert_eq!(plan.new_addresses, added);
+        assert_eq!(plan.addresses, [existing, added.clone()].concat());
+        assert_eq!(plan.batches.len(), 1);
+        assert_eq!(extended_addresses(&plan.batches[0]), added);
+        assert_batches(&authority, &plan);
+    }
+
+    #[test]
+    fn plans_nothing_for_existing_addresses() {
+        let authority = Pubkey::new_unique();
+        let existing = new_addresses(3);
+        let mut table = existing_table(authority, &existing);
+        table.meta.authority = None;
+        let plan = LookupTablePlanner::new(authority, authority, 10)
+            .with_freeze(true)
+            .plan(&existing[..2], Some((Pubkey::new_unique(), &table)))
+            .unwrap();
+
+        assert!(plan.batches.is_empty());
+        assert!(plan.new_addresses.is_empty());
+        assert_eq!(plan.addresses, existing);
+    }
+
+    #[test]
+    fn freezes_existing_table() {
+        let authority = Pubkey::new_unique();
+        let table = existing_table(authority, &new_addresses(3));
+        let plan = LookupTablePlanner::new(authority, authority, 10)
+            .with_freeze(true)
+            .plan(&[], Some((Pubkey::new_unique(), &table)))
+            .unwrap();
+
+        assert_eq!(plan.batches.len(), 1);
+        assert_eq!(
+            decode(&plan.batches[0].instructions[0]),
+            ProgramInstruction::FreezeLookupTable
+        );
+    }
+
+    #[test]
+    fn fills_existing_table() {
+        let authority = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let table = existing_table(authority, &new_addresses(100));
+        let addresses = new_addresses(LOOKUP_TABLE_MAX_ADDRESSES - 100);
+        let plan = LookupTablePlanner::new(authority, payer, 10)
+            .plan(&addresses, Some((Pubkey::new_unique(), &table)))
+            .unwrap();
+
+        assert!(plan.batches.len() > 1);
+        assert_eq!(plan.addresses.len(), LOOKUP_TABLE_MAX_ADDRESSES);
+        assert_batches(&payer, &plan);
+    }
+
+    #[test]
+    fn reports_warming_up_addresses() {
+        let authority = Pubkey::new_unique();
+        let existing = new_addresses(4);
+        let mut table = existing_table(authority, &existing);
+        table.meta.last_extended_slot = 10;
+        table.meta.last_extended_slot_start_index = 2;
+
+        let planner = LookupTablePlanner::new(authority, authority, 10);
+        let target = [existing[0], existing[3]];
+        let plan = planner
+            .plan(&target, Some((Pubkey::new_unique(), &table)))
+            .unwrap();
+        assert_eq!(plan.warming_up_addresses, vec![existing[3]]);
+        assert_eq!(plan.warm_up_slot, Some(10));
+
+        let planner = LookupTablePlanner::new(authority, authority, 11);
+        let plan = planner
+            .plan(&target, Some((Pubkey::new_unique(), &table)))
+            .unwrap();
+        assert!(plan.warming_up_addresses.is_empty());
+        assert_eq!(plan.warm_up_slot, None);
+    }
+
+    #[test]
+    fn rejects_invalid_plans() {
+        let authority = Pubkey::new_unique();
+        let planner = LookupTablePlanner::new(authority, authority, 10);
+        let lookup_table_address = Pubkey::new_unique();
+        let table = existing_table(authority, &new_addresses(200));
+
+        assert_eq!(
+            planner.plan(&new_addresses(57), Some((lookup_table_address, &table))),
+            Err(LookupTablePlanError::TooManyAddresses { num_addresses: 257 })
+        );
+        assert_eq!(
+            planner.clone().with_freeze(true).plan(&[], None),
+            Err(LookupTablePlanError::EmptyFreeze)
+        );
+
+        let mut frozen = table.clone();
+        frozen.meta.authority = None;
+        assert_eq!(
+            planner.plan(&new_addresses(1), Some((lookup_table_address, &frozen))),
+            Err(LookupTablePlanError::Frozen)
+        );
+
+        let mut deactivated = table.clone();
+        deactivated.meta.deactivation_slot = 5;
+        assert_eq!(
+            planner.plan(
+                &new_addresses(1),
+                Some((lookup_table_address, &deactivated))
+            ),
+            Err(LookupTablePlanError::Deactivated)
+        );
+
+        let other_authority = existing_table(Pubkey::new_unique(), &[]);
+        assert_eq!(
+            planner.with_freeze(true).plan(
+                &new_addresses(1),
+                Some((lookup_table_address, &other_authority))
+            ),
+            Err(LookupTablePlanError::IncorrectAuthority)
+        );
+    }
+}
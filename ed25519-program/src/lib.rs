@@ -3,10 +3,13 @@
 //! [np]: https://docs.solanalabs.com/runtime/programs#ed25519-program
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+pub use solana_precompile_builder::DataRef;
 use {
     bytemuck::bytes_of,
     bytemuck_derive::{Pod, Zeroable},
     solana_instruction::Instruction,
+    solana_precompile_builder::InstructionData,
+    solana_precompile_error::PrecompileError,
 };
 
 pub const PUBKEY_SERIALIZED_SIZE: usize = 32;
@@ -107,3 +110,352 @@ pub fn new_ed25519_instruction_with_signature(
         data: instruction_data,
     }
 }
+
+/// Builds an ed25519 instruction verifying many signatures.
+///
+/// ```
+/// # use solana_ed25519_program::Ed25519InstructionBuilder;
+/// # let (pubkey_a, pubkey_b, signature_a, signature_b) = ([1; 32], [2; 32], [3; 64], [4; 64]);
+/// let message = b"guardian set update";
+/// let instruction = Ed25519InstructionBuilder::default()
+///     .add_signature(&pubkey_a, &message[..], &signature_a)
+///     .add_signature(&pubkey_b, &message[..], &signature_b)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Ed25519InstructionBuilder<'a> {
+    signatures: Vec<[DataRef<'a, [u8]>; 3]>,
+}
+
+impl<'a> Ed25519InstructionBuilder<'a> {
+    /// Adds a signature of `message` by `pubkey` to verify.
+    pub fn add_signature(
+        &mut self,
+        pubkey: impl Into<DataRef<'a, [u8; PUBKEY_SERIALIZED_SIZE]>>,
+        message: impl Into<DataRef<'a, [u8]>>,
+        signature: impl Into<DataRef<'a, [u8; SIGNATURE_SERIALIZED_SIZE]>>,
+    ) -> &mut Self {
+        self.signatures.push([
+            pubkey.into().as_bytes(),
+            message.into().as_bytes(),
+            signature.into().as_bytes(),
+        ]);
+        self
+    }
+
+    /// Builds the instruction.
+    ///
+    /// Fails with `InvalidInstructionDataSize` if more than 255 signatures
+    /// were added or if the inline data does not fit in the 16-bit offsets.
+    pub fn build(&self) -> Result<Instruction, PrecompileError> {
+        let mut instruction_data = InstructionData::new(
+            self.signatures.len(),
+            SIGNATURE_OFFSETS_START,
+            SIGNATURE_OFFSETS_SERIALIZED_SIZE,
+            u16::MAX,
+        )?;
+        for (i, [pubkey, message, signature]) in self.signatures.iter().enumerate() {
+            let pubkey = instruction_data.locate(*pubkey)?;
+            let message = instruction_data.locate(*message)?;
+            let signature = instruction_data.locate(*signature)?;
+            let offsets = Ed25519SignatureOffsets {
+                signature_offset: signature.offset,
+                signature_instruction_index: signature.instruction_index,
+                public_key_offset: pubkey.offset,
+                public_key_instruction_index: pubkey.instruction_index,
+                message_data_offset: message.offset,
+                message_data_size: message.size,
+                message_instruction_index: message.instruction_index,
+            };
+            instruction_data.set_offsets(i, bytes_of(&offsets))?;
+        }
+
+        Ok(Instruction {
+            program_id: solana_sdk_ids::ed25519_program::id(),
+            accounts: vec![],
+            data: instruction_data.into_vec(),
+        })
+    }
+}
+
+/// Verifies an ed25519 program instruction the way the runtime does.
+///
+/// `data` is the instruction data of the ed25519 instruction and
+/// `instruction_datas` the data of every instruction in the transaction, in
+/// order, which the offsets can refer to by index. An instruction index of
+/// `u16::MAX` refers to `data` itself.
+#[cfg(feature = "verify")]
+pub fn verify(
+    data: &[u8],
+    instruction_datas: &[&[u8]],
+) -> Result<(), solana_precompile_error::PrecompileError> {
+    use solana_precompile_error::PrecompileError;
+
+    if data.len() < SIGNATURE_OFFSETS_START {
+        return Err(PrecompileError::InvalidInstructionDataSize);
+    }
+    let num_signatures = data[0] as usize;
+    if num_signatures == 0 && data.len() > SIGNATURE_OFFSETS_START {
+        return Err(PrecompileError::InvalidInstructionDataSize);
+    }
+    let expected_data_size = num_signatures
+        .saturating_mul(SIGNATURE_OFFSETS_SERIALIZED_SIZE)
+        .saturating_add(SIGNATURE_OFFSETS_START);
+    // The byte at data[1] is padding and is not checked
+    if data.len() < expected_data_size {
+        return Err(PrecompileError::InvalidInstructionDataSize);
+    }
+    for i in 0..num_signatures {
+        let start = i
+            .saturating_mul(SIGNATURE_OFFSETS_SERIALIZED_SIZE)
+            .saturating_add(SIGNATURE_OFFSETS_START);
+        let end = start.saturating_add(SIGNATURE_OFFSETS_SERIALIZED_SIZE);
+        let offsets: Ed25519SignatureOffsets = bytemuck::pod_read_unaligned(&data[start..end]);
+
+        let signature = get_data_slice(
+            data,
+            instruction_datas,
+            offsets.signature_instruction_index,
+            offsets.signature_offset,
+            SIGNATURE_SERIALIZED_SIZE,
+        )?;
+        let signature = ed25519_dalek::Signature::from_slice(signature)
+            .map_err(|_| PrecompileError::InvalidSignature)?;
+
+        let pubkey = get_data_slice(
+            data,
+            instruction_datas,
+            offsets.public_key_instruction_index,
+            offsets.public_key_offset,
+            PUBKEY_SERIALIZED_SIZE,
+        )?;
+        let pubkey = pubkey
+            .try_into()
+            .ok()
+            .and_then(|pubkey| ed25519_dalek::VerifyingKey::from_bytes(pubkey).ok())
+            .ok_or(PrecompileError::InvalidPublicKey)?;
+
+        let message = get_data_slice(
+            data,
+            instruction_datas,
+            offsets.message_instruction_index,
+            offsets.message_data_offset,
+            offsets.message_data_size as usize,
+        )?;
+
+        pubkey
+            .verify_strict(message, &signature)
+            .map_err(|_| PrecompileError::InvalidSignature)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "verify")]
+fn get_data_slice<'a>(
+    data: &'a [u8],
+    instruction_datas: &'a [&[u8]],
+    instruction_index: u16,
+    offset_start: u16,
+    size: usize,
+) -> Result<&'a [u8], solana_precompile_error::PrecompileError> {
+    use solana_precompile_error::PrecompileError;
+
+    let instruction = if instruction_index == u16::MAX {
+        data
+    } else {
+        instruction_datas
+            .get(instruction_index as usize)
+            .ok_or(PrecompileError::InvalidDataOffsets)?
+    };
+    let start = offset_start as usize;
+    let end = start.saturating_add(size);
+    instruction
+        .get(start..end)
+        .ok_or(PrecompileError::InvalidDataOffsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        ed25519_dalek::{Signer, SigningKey},
+    };
+
+    fn signed_instruction(message: &[u8]) -> Instruction {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        new_ed25519_instruction_with_signature(
+            message,
+            &key.sign(message).to_bytes(),
+            key.verifying_key().as_bytes(),
+        )
+    }
+
+    #[test]
+    fn test_verify() {
+        let instruction = signed_instruction(b"hello");
+        assert_eq!(verify(&instruction.data, &[&instruction.data]), Ok(()));
+
+        let mut data = instruction.data.clone();
+        *data.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            verify(&data, &[&data]),
+            Err(PrecompileError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_verify_data_size() {
+        assert_eq!(
+            verify(&[], &[]),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+        assert_eq!(verify(&[0, 0], &[]), Ok(()));
+        assert_eq!(
+            verify(&[0, 0, 0], &[]),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+        let instruction = signed_instruction(b"hello");
+        assert_eq!(
+            verify(&instruction.data[..DATA_START - 1], &[]),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+    }
+
+    #[test]
+    fn test_verify_cross_instruction() {
+        let signed = signed_instruction(b"hello");
+        let offsets: Ed25519SignatureOffsets =
+            bytemuck::pod_read_unaligned(&signed.data[SIGNATURE_OFFSETS_START..DATA_START]);
+        let offsets = Ed25519SignatureOffsets {
+            signature_instruction_index: 0,
+            public_key_instruction_index: 0,
+            message_instruction_index: 0,
+            ..offsets
+        };
+        let instruction = offsets_to_ed25519_instruction(&[offsets]);
+        assert_eq!(
+            verify(&instruction.data, &[&signed.data, &instruction.data]),
+            Ok(())
+        );
+        assert_eq!(
+            verify(&instruction.data, &[&instruction.data, &signed.data]),
+            Err(PrecompileError::InvalidDataOffsets)
+        );
+        assert_eq!(
+            verify(&instruction.data, &[]),
+            Err(PrecompileError::InvalidDataOffsets)
+        );
+
+        let instruction = offsets_to_ed25519_instruction(&[Ed25519SignatureOffsets {
+            message_data_size: u16::MAX,
+            ..offsets
+        }]);
+        assert_eq!(
+            verify(&instruction.data, &[&signed.data]),
+            Err(PrecompileError::InvalidDataOffsets)
+        );
+    }
+
+    #[test]
+    fn test_builder() {
+        let keys: Vec<_> = (1..=3).map(|i| SigningKey::from_bytes(&[i; 32])).collect();
+        let pubkeys: Vec<_> = keys
+            .iter()
+            .map(|key| key.verifying_key().to_bytes())
+            .collect();
+        let message = b"guardian set";
+        let other_message = b"other message";
+        let signatures: Vec<_> = keys
+            .iter()
+            .map(|key| key.sign(message).to_bytes())
+            .collect();
+        let other_signature = keys[0].sign(other_message).to_bytes();
+
+        let mut builder = Ed25519InstructionBuilder::default();
+        for (pubkey, signature) in pubkeys.iter().zip(&signatures) {
+            builder.add_signature(pubkey, &message[..], signature);
+        }
+        builder.add_signature(&pubkeys[0], &other_message[..], &other_signature);
+        let instruction = builder.build().unwrap();
+        assert_eq!(instruction.data[0], 4);
+        // The repeated pubkey and message are stored once
+        assert_eq!(
+            instruction.data.len(),
+            SIGNATURE_OFFSETS_START
+                + 4 * SIGNATURE_OFFSETS_SERIALIZED_SIZE
+                + 3 * PUBKEY_SERIALIZED_SIZE
+                + 4 * SIGNATURE_SERIALIZED_SIZE
+                + message.len()
+                + other_message.len()
+        );
+        assert_eq!(verify(&instruction.data, &[&instruction.data]), Ok(()));
+    }
+
+    #[test]
+    fn test_builder_limits() {
+        let pubkey = [1; PUBKEY_SERIALIZED_SIZE];
+        let signature = [2; SIGNATURE_SERIALIZED_SIZE];
+        let mut builder = Ed25519InstructionBuilder::default();
+        for _ in 0..256 {
+            builder.add_signature(&pubkey, &b"message"[..], &signature);
+        }
+        assert_eq!(
+            builder.build(),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+
+        let message = vec![3; usize::from(u16::MAX)];
+        assert_eq!(
+            Ed25519InstructionBuilder::default()
+                .add_signature(&pubkey, &message[..], &signature)
+                .build(),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+    }
+
+    #[test]
+    fn test_builder_instruction_data() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let pubkey = key.verifying_key().to_bytes();
+        let message = b"message in another instruction";
+        let signature = key.sign(message).to_bytes();
+        let other_data = [&[0; 5][..], &message[..]].concat();
+
+        let instruction = Ed25519InstructionBuilder::default()
+            .add_signature(
+                &pubkey,
+                DataRef::Instruction {
+                    index: 0,
+                    offset: 5,
+                    data: &message[..],
+                },
+                &signature,
+            )
+            .build()
+            .unwrap();
+        assert_eq!(
+            verify(&instruction.data, &[&other_data, &instruction.data]),
+            Ok(())
+        );
+        assert_eq!(
+            verify(&instruction.data, &[&instruction.data, &other_data]),
+            Err(PrecompileError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_verify_invalid_public_key() {
+        let message = b"hello";
+        let key = SigningKey::from_bytes(&[7; 32]);
+        // Not the y coordinate of a curve point
+        let mut pubkey = [0xff; PUBKEY_SERIALIZED_SIZE];
+        pubkey[0] = 2;
+        let instruction =
+            new_ed25519_instruction_with_signature(message, &key.sign(message).to_bytes(), &pubkey);
+        assert_eq!(
+            verify(&instruction.data, &[]),
+            Err(PrecompileError::InvalidPublicKey)
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use solana_program_error::ProgramError;
 #[cfg(feature = "bincode")]
 use {
     crate::state::Feature,
@@ -8,6 +9,40 @@ use {
     solana_system_interface::instruction as system_instruction,
 };
 
+/// Instructions supported by the feature gate program.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Deserialize, serde_derive::Serialize)
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeatureGateInstruction {
+    /// Revoke a pending feature activation.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[w+s]` Feature account
+    ///   1. `[w]` Incinerator
+    ///   2. `[]` System program
+    RevokePendingActivation,
+}
+
+impl FeatureGateInstruction {
+    /// Unpack instruction data, which is a single discriminator byte.
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        match input {
+            [0] => Ok(Self::RevokePendingActivation),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+
+    /// Pack into instruction data.
+    pub fn pack(&self) -> [u8; 1] {
+        match self {
+            Self::RevokePendingActivation => [0],
+        }
+    }
+}
+
 /// Activate a feature
 #[cfg(feature = "bincode")]
 #[deprecated(
@@ -47,6 +82,34 @@ pub fn revoke_pending_activation(feature_id: &Pubkey) -> Instruction {
     Instruction {
         program_id: crate::id(),
         accounts,
-        data: vec![0],
+        data: FeatureGateInstruction::RevokePendingActivation
+            .pack()
+            .to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_gate_instruction_pack_unpack() {
+        let instruction = FeatureGateInstruction::RevokePendingActivation;
+        assert_eq!(
+            FeatureGateInstruction::unpack(&instruction.pack()),
+            Ok(instruction)
+        );
+        assert_eq!(
+            FeatureGateInstruction::unpack(&[]),
+            Err(ProgramError::InvalidInstructionData)
+        );
+        assert_eq!(
+            FeatureGateInstruction::unpack(&[0, 0]),
+            Err(ProgramError::InvalidInstructionData)
+        );
+        assert_eq!(
+            FeatureGateInstruction::unpack(&[1]),
+            Err(ProgramError::InvalidInstructionData)
+        );
     }
 }
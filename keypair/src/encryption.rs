@@ -0,0 +1,600 @@
+//! Passphrase-encrypted keypair files
+//!
+//! An encrypted keypair is stored as a single line of text in a format
+//! modelled after the [PHC string format]:
+//!
+//! ```text
+//! $solana-keypair$v=1$<pubkey>$argon2id$m=<KiB>,t=<passes>,p=<lanes>$<salt>$xchacha20poly1305$<nonce>$<ciphertext>
+//! ```
+//!
+//! The 32-byte secret key is encrypted with XChaCha20-Poly1305 under a key
+//! derived from the passphrase with Argon2id. Salt, nonce and ciphertext are
+//! base64-encoded without padding, and the pubkey is base58-encoded so the
+//! owner of the file can be identified without the passphrase. Everything
+//! before the ciphertext is authenticated as associated data, so the header
+//! cannot be altered without failing decryption.
+//!
+//! [PHC string format]: https://github.com/P-H-C/phc-string-format/blob/master/phc-sf-spec.md
+
+use {
+    crate::{read_keypair, Keypair, ENCRYPTED_KEYPAIR_PREFIX},
+    argon2::{Algorithm, Argon2, Params, Version},
+    base64::{prelude::BASE64_STANDARD_NO_PAD, Engine},
+    chacha20poly1305::{
+        aead::{Aead, KeyInit, Payload},
+        XChaCha20Poly1305, XNonce,
+    },
+    solana_address::Address,
+    solana_signer::{EncodableKey, EncodableKeypair, Signer},
+    std::{
+        error, fmt,
+        io::{Read, Write},
+        path::Path,
+    },
+    zeroize::Zeroizing,
+};
+
+/// Current version of the encrypted keypair format.
+pub const ENCRYPTED_KEYPAIR_VERSION: u32 = 1;
+
+const KDF_ARGON2ID: &str = "argon2id";
+const CIPHER_XCHACHA20POLY1305: &str = "xchacha20poly1305";
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 24;
+const KEY_LENGTH: usize = 32;
+
+/// Maximum Argon2id memory size, in KiB, accepted in an encrypted keypair.
+pub const MAX_MEMORY_COST: u32 = 4 * 1024 * 1024;
+/// Maximum number of Argon2id passes accepted in an encrypted keypair.
+pub const MAX_TIME_COST: u32 = 64;
+/// Maximum Argon2id degree of parallelism accepted in an encrypted keypair.
+pub const MAX_PARALLELISM: u32 = 16;
+
+/// Argon2id cost parameters used to derive the encryption key.
+///
+/// The parameters are read from the keypair file before the passphrase is
+/// checked, so they are capped to keep a crafted file from exhausting memory
+/// or time: at most [`MAX_MEMORY_COST`] KiB of memory (4 GiB),
+/// [`MAX_TIME_COST`] passes and [`MAX_PARALLELISM`] lanes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    /// Memory size in KiB.
+    pub memory_cost: u32,
+    /// Number of passes.
+    pub time_cost: u32,
+    /// Degree of parallelism.
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// The defaults recommended by the `argon2` crate: 19 MiB of memory, two
+    /// passes and one lane.
+    fn default() -> Self {
+        Self {
+            memory_cost: Params::DEFAULT_M_COST,
+            time_cost: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Checks that the parameters do not exceed the documented maximums.
+    fn check_limits(&self) -> Result<(), EncryptedKeypairError> {
+        if self.memory_cost > MAX_MEMORY_COST
+            || self.time_cost > MAX_TIME_COST
+            || self.parallelism > MAX_PARALLELISM
+        {
+            return Err(EncryptedKeypairError::InvalidKdfParams);
+        }
+        Ok(())
+    }
+
+    fn derive_key(
+        &self,
+        passphrase: &str,
+        salt: &[u8],
+    ) -> Result<Zeroizing<[u8; KEY_LENGTH]>, EncryptedKeypairError> {
+        self.check_limits()?;
+        let params = Params::new(
+            self.memory_cost,
+            self.time_cost,
+            self.parallelism,
+            Some(KEY_LENGTH),
+        )
+        .map_err(|_| EncryptedKeypairError::InvalidKdfParams)?;
+        let mut key = Zeroizing::new([0u8; KEY_LENGTH]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+            .map_err(|_| EncryptedKeypairError::InvalidKdfParams)?;
+        Ok(key)
+    }
+}
+
+/// Errors returned when encrypting, decrypting or parsing an encrypted
+/// keypair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncryptedKeypairError {
+    /// The input is not an encrypted keypair.
+    InvalidFormat(&'static str),
+    /// The format version is not supported by this library.
+    UnsupportedVersion(String),
+    /// The key derivation function is not supported.
+    UnsupportedKdf(String),
+    /// The cipher is not supported.
+    UnsupportedCipher(String),
+    /// The key derivation parameters are out of range.
+    InvalidKdfParams,
+    /// The secret key could not be encrypted.
+    EncryptionFailed,
+    /// The passphrase is wrong or the file has been tampered with.
+    DecryptionFailed,
+    /// The decrypted secret key does not match the pubkey of the header.
+    PubkeyMismatch,
+}
+
+impl error::Error for EncryptedKeypairError {}
+
+impl fmt::Display for EncryptedKeypairError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFormat(reason) => write!(f, "invalid encrypted keypair: {reason}"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported encrypted keypair version: {version}")
+            }
+            Self::UnsupportedKdf(kdf) => write!(f, "unsupported key derivation function: {kdf}"),
+            Self::UnsupportedCipher(cipher) => write!(f, "unsupported cipher: {cipher}"),
+            Self::InvalidKdfParams => f.write_str("invalid key derivation parameters"),
+            Self::EncryptionFailed => f.write_str("encryption failed"),
+            Self::DecryptionFailed => {
+                f.write_str("decryption failed: wrong passphrase or corrupted keypair")
+            }
+            Self::PubkeyMismatch => f.write_str("decrypted keypair does not match its pubkey"),
+        }
+    }
+}
+
+/// A keypair encrypted with a passphrase.
+///
+/// Implements [`EncodableKey`], so it can be read and written with
+/// [`EncodableKey::read_from_file`] and [`EncodableKey::write_to_file`]
+/// without knowing the passphrase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedKeypair {
+    pubkey: Address,
+    kdf_params: KdfParams,
+    salt: [u8; SALT_LENGTH],
+    nonce: [u8; NONCE_LENGTH],
+    ciphertext: Vec<u8>,
+}
+
+impl EncryptedKeypair {
+    /// Encrypts `keypair` with `passphrase` using the default key derivation
+    /// parameters.
+    pub fn encrypt(keypair: &Keypair, passphrase: &str) -> Result<Self, EncryptedKeypairError> {
+        Self::encrypt_with_params(keypair, passphrase, KdfParams::default())
+    }
+
+    /// Encrypts `keypair` with `passphrase`, deriving the encryption key with
+    /// the given Argon2id parameters.
+    pub fn encrypt_with_params(
+        keypair: &Keypair,
+        passphrase: &str,
+        kdf_params: KdfParams,
+    ) -> Result<Self, EncryptedKeypairError> {
+        let mut encrypted = Self {
+            pubkey: keypair.pubkey(),
+            kdf_params,
+            salt: rand::random(),
+            nonce: rand::random(),
+            ciphertext: Vec::new(),
+        };
+        let key = kdf_params.derive_key(passphrase, &encrypted.salt)?;
+        let header = encrypted.header();
+        encrypted.ciphertext = XChaCha20Poly1305::new(key.as_ref().into())
+            .encrypt(
+                XNonce::from_slice(&encrypted.nonce),
+                Payload {
+                    msg: keypair.secret_bytes(),
+                    aad: header.as_bytes(),
+                },
+            )
+            .map_err(|_| EncryptedKeypairError::EncryptionFailed)?;
+        Ok(encrypted)
+    }
+
+    /// Decrypts the keypair with `passphrase`.
+    pub fn decrypt(&self, passphrase: &str) -> Result<Keypair, EncryptedKeypairError> {
+        let key = self.kdf_params.derive_key(passphrase, &self.salt)?;
+        let secret_key = Zeroizing::new(
+            XChaCha20Poly1305::new(key.as_ref().into())
+                .decrypt(
+                    XNonce::from_slice(&self.nonce),
+                    Payload {
+                        msg: &self.ciphertext,
+                        aad: self.header().as_bytes(),
+                    },
+                )
+                .map_err(|_| EncryptedKeypairError::DecryptionFailed)?,
+        );
+        let secret_key: [u8; Keypair::SECRET_KEY_LENGTH] = secret_key
+            .as_slice()
+            .try_into()
+            .map_err(|_| EncryptedKeypairError::InvalidFormat("wrong secret key length"))?;
+        let keypair = Keypair::new_from_array(secret_key);
+        if keypair.pubkey() != self.pubkey {
+            return Err(EncryptedKeypairError::PubkeyMismatch);
+        }
+        Ok(keypair)
+    }
+
+    /// Returns the pubkey of the encrypted keypair.
+    pub fn pubkey(&self) -> Address {
+        self.pubkey
+    }
+
+    /// Returns the key derivation parameters.
+    pub fn kdf_params(&self) -> KdfParams {
+        self.kdf_params
+    }
+
+    /// Everything up to, but excluding, the ciphertext. Authenticated as
+    /// associated data.
+    fn header(&self) -> String {
+        let KdfParams {
+            memory_cost,
+            time_cost,
+            parallelism,
+        } = self.kdf_params;
+        format!(
+            "{ENCRYPTED_KEYPAIR_PREFIX}v={ENCRYPTED_KEYPAIR_VERSION}${}${KDF_ARGON2ID}$m={memory_cost},t={time_cost},p={parallelism}${}${CIPHER_XCHACHA20POLY1305}${}$",
+            self.pubkey,
+            BASE64_STANDARD_NO_PAD.encode(self.salt),
+            BASE64_STANDARD_NO_PAD.encode(self.nonce),
+        )
+    }
+}
+
+impl fmt::Display for EncryptedKeypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            self.header(),
+            BASE64_STANDARD_NO_PAD.encode(&self.ciphertext)
+        )
+    }
+}
+
+impl std::str::FromStr for EncryptedKeypair {
+    type Err = EncryptedKeypairError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields = s
+            .trim()
+            .strip_prefix(ENCRYPTED_KEYPAIR_PREFIX)
+            .ok_or(EncryptedKeypairError::InvalidFormat("missing prefix"))?;
+        let mut fields = fields.split('$');
+        let mut next = |name| {
+            fields
+                .next()
+                .ok_or(EncryptedKeypairError::InvalidFormat(name))
+        };
+
+        let version = next("missing version")?;
+        if version != format!("v={ENCRYPTED_KEYPAIR_VERSION}") {
+            return Err(EncryptedKeypairError::UnsupportedVersion(
+                version.to_string(),
+            ));
+        }
+        let pubkey = next("missing pubkey")?
+            .parse()
+            .map_err(|_| EncryptedKeypairError::InvalidFormat("invalid pubkey"))?;
+        let kdf = next("missing key derivation function")?;
+        if kdf != KDF_ARGON2ID {
+            return Err(EncryptedKeypairError::UnsupportedKdf(kdf.to_string()));
+        }
+        let kdf_params = parse_kdf_params(next("missing key derivation parameters")?)?;
+        let salt = decode_array(next("missing salt")?, "invalid salt")?;
+        let cipher = next("missing cipher")?;
+        if cipher != CIPHER_XCHACHA20POLY1305 {
+            return Err(EncryptedKeypairError::UnsupportedCipher(cipher.to_string()));
+        }
+        let nonce = decode_array(next("missing nonce")?, "invalid nonce")?;
+        let ciphertext = BASE64_STANDARD_NO_PAD
+            .decode(next("missing ciphertext")?)
+            .map_err(|_| EncryptedKeypairError::InvalidFormat("invalid ciphertext"))?;
+        if fields.next().is_some() {
+            return Err(EncryptedKeypairError::InvalidFormat("trailing fields"));
+        }
+
+        Ok(Self {
+            pubkey,
+            kdf_params,
+            salt,
+            nonce,
+            ciphertext,
+        })
+    }
+}
+
+fn parse_kdf_params(s: &str) -> Result<KdfParams, EncryptedKeypairError> {
+    let mut params = s.split(',').map(|param| param.split_once('='));
+    let mut next = |name| match params.next() {
+        Some(Some((key, value))) if key == name => value
+            .parse()
+            .map_err(|_| EncryptedKeypairError::InvalidKdfParams),
+        _ => Err(EncryptedKeypairError::InvalidKdfParams),
+    };
+    let kdf_params = KdfParams {
+        memory_cost: next("m")?,
+        time_cost: next("t")?,
+        parallelism: next("p")?,
+    };
+    if params.next().is_some() {
+        return Err(EncryptedKeypairError::InvalidKdfParams);
+    }
+    kdf_params.check_limits()?;
+    Ok(kdf_params)
+}
+
+fn decode_array<const N: usize>(
+    s: &str,
+    error: &'static str,
+) -> Result<[u8; N], EncryptedKeypairError> {
+    BASE64_STANDARD_NO_PAD
+        .decode(s)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(EncryptedKeypairError::InvalidFormat(error))
+}
+
+impl EncodableKey for EncryptedKeypair {
+    fn read<R: Read>(reader: &mut R) -> Result<Self, Box<dyn error::Error>> {
+        let mut buffer = String::new();
+        reader.read_to_string(&mut buffer)?;
+        Ok(buffer.parse()?)
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<String, Box<dyn error::Error>> {
+        let encoded = self.to_string();
+        writer.write_all(encoded.as_bytes())?;
+        Ok(encoded)
+    }
+}
+
+impl EncodableKeypair for EncryptedKeypair {
+    type Pubkey = Address;
+
+    fn encodable_pubkey(&self) -> Self::Pubkey {
+        self.pubkey
+    }
+}
+
+/// Reads a `Keypair` from a `Reader` implementor, decrypting it with
+/// `passphrase` if it is encrypted
+///
+/// Both the plain JSON format and the encrypted format are accepted.
+pub fn read_keypair_with_passphrase<R: Read>(
+    reader: &mut R,
+    passphrase: &str,
+) -> Result<Keypair, Box<dyn error::Error>> {
+    let mut buffer = Zeroizing::new(String::new());
+    reader.read_to_string(&mut buffer)?;
+    if buffer.trim_start().starts_with(ENCRYPTED_KEYPAIR_PREFIX) {
+        let encrypted: EncryptedKeypair = buffer.parse()?;
+        Ok(encrypted.decrypt(passphrase)?)
+    } else {
+        read_keypair(&mut buffer.as_bytes())
+    }
+}
+
+/// Reads a `Keypair` from a file, decrypting it with `passphrase` if it is
+/// encrypted
+pub fn read_keypair_file_with_passphrase<F: AsRef<Path>>(
+    path: F,
+    passphrase: &str,
+) -> Result<Keypair, Box<dyn error::Error>> {
+    let mut file = std::fs::File::open(path.as_ref())?;
+    read_keypair_with_passphrase(&mut file, passphrase)
+}
+
+/// Encrypts a `Keypair` with `passphrase` and writes it to a file
+pub fn write_encrypted_keypair_file<F: AsRef<Path>>(
+    keypair: &Keypair,
+    passphrase: &str,
+    outfile: F,
+) -> Result<String, Box<dyn error::Error>> {
+    EncryptedKeypair::encrypt(keypair, passphrase)?.write_to_file(outfile)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::write_keypair_file};
+
+    // Weak parameters to keep the tests fast.
+    const TEST_KDF_PARAMS: KdfParams = KdfParams {
+        memory_cost: 64,
+        time_cost: 1,
+        parallelism: 1,
+    };
+
+    fn tmp_file_path(name: &str) -> String {
+        let out_dir = std::env::var("FARF_DIR").unwrap_or_else(|_| "farf".to_string());
+        format!("{}/tmp/{}-{}", out_dir, name, Keypair::new().pubkey())
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let keypair = Keypair::new();
+        let encrypted =
+            EncryptedKeypair::encrypt_with_params(&keypair, "hunter2", TEST_KDF_PARAMS).unwrap();
+        assert_eq!(encrypted.pubkey(), keypair.pubkey());
+        assert_eq!(encrypted.kdf_params(), TEST_KDF_PARAMS);
+
+        let encoded = encrypted.to_string();
+        assert!(encoded.starts_with(&format!(
+            "$solana-keypair$v=1${}$argon2id$m=64,t=1,p=1$",
+            keypair.pubkey()
+        )));
+        let decoded: EncryptedKeypair = encoded.parse().unwrap();
+        assert_eq!(decoded, encrypted);
+        assert_eq!(
+            decoded.decrypt("hunter2").unwrap().to_bytes(),
+            keypair.to_bytes()
+        );
+        assert_eq!(
+            decoded.decrypt("hunter3").unwrap_err(),
+            EncryptedKeypairError::DecryptionFailed
+        );
+    }
+
+    #[test]
+    fn test_default_kdf_params() {
+        let keypair = Keypair::new();
+        let encrypted = EncryptedKeypair::encrypt(&keypair, "").unwrap();
+        assert_eq!(encrypted.kdf_params(), KdfParams::default());
+        assert_eq!(encrypted.decrypt("").unwrap().pubkey(), keypair.pubkey());
+    }
+
+    #[test]
+    fn test_header_is_authenticated() {
+        let keypair = Keypair::new();
+        let encoded = EncryptedKeypair::encrypt_with_params(&keypair, "pass", TEST_KDF_PARAMS)
+            .unwrap()
+            .to_string();
+
+        let tampered = encoded.replacen(
+            &keypair.pubkey().to_string(),
+            &Keypair::new().pubkey().to_string(),
+            1,
+        );
+        let tampered: EncryptedKeypair = tampered.parse().unwrap();
+        assert_eq!(
+            tampered.decrypt("pass").unwrap_err(),
+            EncryptedKeypairError::DecryptionFailed
+        );
+
+        let tampered: EncryptedKeypair = encoded.replacen("m=64", "m=65", 1).parse().unwrap();
+        assert_eq!(
+            tampered.decrypt("pass").unwrap_err(),
+            EncryptedKeypairError::DecryptionFailed
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let encoded = EncryptedKeypair::encrypt_with_params(&Keypair::new(), "", TEST_KDF_PARAMS)
+            .unwrap()
+            .to_string();
+
+        assert_eq!(
+            "[1,2,3]".parse::<EncryptedKeypair>().unwrap_err(),
+            EncryptedKeypairError::InvalidFormat("missing prefix")
+        );
+        assert_eq!(
+            encoded
+                .replacen("v=1", "v=2", 1)
+                .parse::<EncryptedKeypair>()
+                .unwrap_err(),
+            EncryptedKeypairError::UnsupportedVersion("v=2".to_string())
+        );
+        assert_eq!(
+            encoded
+                .replacen("argon2id", "scrypt", 1)
+                .parse::<EncryptedKeypair>()
+                .unwrap_err(),
+            EncryptedKeypairError::UnsupportedKdf("scrypt".to_string())
+        );
+        assert_eq!(
+            encoded
+                .replacen("xchacha20poly1305", "aes256gcm", 1)
+                .parse::<EncryptedKeypair>()
+                .unwrap_err(),
+            EncryptedKeypairError::UnsupportedCipher("aes256gcm".to_string())
+        );
+        assert_eq!(
+            encoded
+                .replacen(",p=1", "", 1)
+                .parse::<EncryptedKeypair>()
+                .unwrap_err(),
+            EncryptedKeypairError::InvalidKdfParams
+        );
+        assert_eq!(
+            format!("{encoded}$")
+                .parse::<EncryptedKeypair>()
+                .unwrap_err(),
+            EncryptedKeypairError::InvalidFormat("trailing fields")
+        );
+
+        let zero_memory: EncryptedKeypair = encoded.replacen("m=64", "m=0", 1).parse().unwrap();
+        assert_eq!(
+            zero_memory.decrypt("").unwrap_err(),
+            EncryptedKeypairError::InvalidKdfParams
+        );
+
+        for (param, name, max) in [
+            ("m=64", "m", MAX_MEMORY_COST),
+            ("t=1", "t", MAX_TIME_COST),
+            ("p=1", "p", MAX_PARALLELISM),
+        ] {
+            assert!(encoded
+                .replacen(param, &format!("{name}={max}"), 1)
+                .parse::<EncryptedKeypair>()
+                .is_ok());
+            assert_eq!(
+                encoded
+                    .replacen(param, &format!("{name}={}", max + 1), 1)
+                    .parse::<EncryptedKeypair>()
+                    .unwrap_err(),
+                EncryptedKeypairError::InvalidKdfParams
+            );
+        }
+        assert_eq!(
+            EncryptedKeypair::encrypt_with_params(
+                &Keypair::new(),
+                "",
+                KdfParams {
+                    time_cost: MAX_TIME_COST + 1,
+                    ..TEST_KDF_PARAMS
+                },
+            )
+            .unwrap_err(),
+            EncryptedKeypairError::InvalidKdfParams
+        );
+    }
+
+    #[test]
+    fn test_read_write_encrypted_keypair_file() {
+        let keypair = Keypair::new();
+        let outfile = tmp_file_path("test_read_write_encrypted_keypair_file");
+        EncryptedKeypair::encrypt_with_params(&keypair, "pass", TEST_KDF_PARAMS)
+            .unwrap()
+            .write_to_file(&outfile)
+            .unwrap();
+
+        let encrypted = EncryptedKeypair::read_from_file(&outfile).unwrap();
+        assert_eq!(encrypted.encodable_pubkey(), keypair.pubkey());
+        assert_eq!(
+            read_keypair_file_with_passphrase(&outfile, "pass")
+                .unwrap()
+                .pubkey(),
+            keypair.pubkey()
+        );
+        assert!(read_keypair_file_with_passphrase(&outfile, "wrong").is_err());
+        assert!(crate::read_keypair_file(&outfile)
+            .unwrap_err()
+            .to_string()
+            .contains("encrypted"));
+
+        // Plain keypair files are read regardless of the passphrase
+        write_keypair_file(&keypair, &outfile).unwrap();
+        assert_eq!(
+            read_keypair_file_with_passphrase(&outfile, "ignored")
+                .unwrap()
+                .pubkey(),
+            keypair.pubkey()
+        );
+        std::fs::remove_file(&outfile).unwrap();
+    }
+}
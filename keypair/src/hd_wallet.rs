@@ -0,0 +1,413 @@
+//! Hierarchical deterministic wallet deriving many Solana accounts from one
+//! seed
+//!
+//! Accounts are derived along the standard Solana [BIP-44] path
+//!
+//! > `m/44'/501'/<account>'/0'`
+//!
+//! The master seed is computed once, kept in memory that is zeroed on drop,
+//! and reused for every derivation.
+//!
+//! [BIP-44]: https://github.com/bitcoin/bips/blob/master/bip-0044.mediawiki
+
+use {
+    crate::{seed_derivable::keypair_from_seed_and_derivation_path, Keypair},
+    solana_address::Address,
+    solana_derivation_path::DerivationPath,
+    solana_seed_phrase::generate_seed_from_seed_phrase_and_passphrase,
+    solana_signer::Signer,
+    std::{error, fmt, str::FromStr},
+    zeroize::Zeroizing,
+};
+
+/// Number of accounts that can be derived; account indexes are hardened, so
+/// they must be below `2^31`.
+pub const MAX_ACCOUNTS: u32 = 1 << 31;
+
+/// Default number of consecutive unused accounts after which
+/// [`HdWallet::discover_accounts`] stops scanning.
+pub const DEFAULT_GAP_LIMIT: u32 = 20;
+
+/// Errors returned by [`HdWallet`] and [`DerivationManifest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HdWalletError {
+    /// The account index is not below [`MAX_ACCOUNTS`].
+    InvalidAccountIndex(u32),
+    /// Key derivation along the given path failed.
+    DerivationFailed(String),
+    /// A line of a derivation manifest could not be parsed.
+    InvalidManifest(String),
+}
+
+impl error::Error for HdWalletError {}
+
+impl fmt::Display for HdWalletError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidAccountIndex(index) => write!(
+                f,
+                "invalid account index {index}, must be less than {MAX_ACCOUNTS}"
+            ),
+            Self::DerivationFailed(err) => write!(f, "key derivation failed: {err}"),
+            Self::InvalidManifest(line) => write!(f, "invalid derivation manifest line: {line}"),
+        }
+    }
+}
+
+/// A public description of a derived account, safe to share.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HdAccount {
+    /// Account index, the third component of the derivation path.
+    pub index: u32,
+    /// Full derivation path of the account.
+    pub derivation_path: DerivationPath,
+    /// Pubkey of the derived keypair.
+    pub pubkey: Address,
+}
+
+/// A wallet deriving Solana accounts from a master seed.
+pub struct HdWallet {
+    seed: Zeroizing<Vec<u8>>,
+}
+
+impl fmt::Debug for HdWallet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HdWallet").finish_non_exhaustive()
+    }
+}
+
+impl HdWallet {
+    /// Creates a wallet from a BIP-39 seed.
+    pub fn from_seed(seed: &[u8]) -> Self {
+        Self {
+            seed: Zeroizing::new(seed.to_vec()),
+        }
+    }
+
+    /// Creates a wallet from a BIP-39 seed phrase and passphrase.
+    pub fn from_seed_phrase_and_passphrase(seed_phrase: &str, passphrase: &str) -> Self {
+        Self {
+            seed: Zeroizing::new(generate_seed_from_seed_phrase_and_passphrase(
+                seed_phrase,
+                passphrase,
+            )),
+        }
+    }
+
+    /// Returns the derivation path of the account at `index`,
+    /// `m/44'/501'/<index>'/0'`.
+    pub fn derivation_path(index: u32) -> Result<DerivationPath, HdWalletError> {
+        if index >= MAX_ACCOUNTS {
+            return Err(HdWalletError::InvalidAccountIndex(index));
+        }
+        Ok(DerivationPath::new_bip44(Some(index), Some(0)))
+    }
+
+    /// Derives the keypair at an arbitrary derivation path.
+    pub fn derive(&self, derivation_path: &DerivationPath) -> Result<Keypair, HdWalletError> {
+        keypair_from_seed_and_derivation_path(&self.seed, Some(derivation_path.clone()))
+            .map_err(|err| HdWalletError::DerivationFailed(err.to_string()))
+    }
+
+    /// Derives the keypair of the account at `index`.
+    pub fn keypair(&self, index: u32) -> Result<Keypair, HdWalletError> {
+        self.derive(&Self::derivation_path(index)?)
+    }
+
+    /// Derives the public description of the account at `index`.
+    pub fn account(&self, index: u32) -> Result<HdAccount, HdWalletError> {
+        let derivation_path = Self::derivation_path(index)?;
+        let pubkey = self.derive(&derivation_path)?.pubkey();
+        Ok(HdAccount {
+            index,
+            derivation_path,
+            pubkey,
+        })
+    }
+
+    /// Returns an iterator lazily deriving accounts, starting at index 0.
+    pub fn accounts(&self) -> Accounts<'_> {
+        self.accounts_from(0)
+    }
+
+    /// Returns an iterator lazily deriving accounts, starting at `start`.
+    pub fn accounts_from(&self, start: u32) -> Accounts<'_> {
+        Accounts {
+            wallet: self,
+            next: start,
+        }
+    }
+
+    /// Scans accounts in order and returns the used ones, as reported by
+    /// `is_used`.
+    ///
+    /// Scanning stops after `gap_limit` consecutive unused accounts; BIP-44
+    /// account discovery corresponds to a gap limit of 1. Errors returned by
+    /// `is_used`, such as failed RPC requests, abort the scan.
+    pub fn discover_accounts<F>(
+        &self,
+        gap_limit: u32,
+        mut is_used: F,
+    ) -> Result<Vec<HdAccount>, Box<dyn error::Error>>
+    where
+        F: FnMut(&Address) -> Result<bool, Box<dyn error::Error>>,
+    {
+        let mut used = Vec::new();
+        let mut gap = 0;
+        for index in 0..MAX_ACCOUNTS {
+            if gap >= gap_limit {
+                break;
+            }
+            let account = self.account(index)?;
+            if is_used(&account.pubkey)? {
+                used.push(account);
+                gap = 0;
+            } else {
+                gap = gap.saturating_add(1);
+            }
+        }
+        Ok(used)
+    }
+
+    /// Builds a manifest of the given account indexes, mapping derivation
+    /// paths to pubkeys without any secret material.
+    pub fn manifest<I>(&self, indexes: I) -> Result<DerivationManifest, HdWalletError>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        indexes
+            .into_iter()
+            .map(|index| self.account(index))
+            .collect::<Result<_, _>>()
+            .map(DerivationManifest)
+    }
+}
+
+/// Iterator over the accounts of an [`HdWallet`], created with
+/// [`HdWallet::accounts`].
+#[derive(Debug)]
+pub struct Accounts<'a> {
+    wallet: &'a HdWallet,
+    next: u32,
+}
+
+impl Iterator for Accounts<'_> {
+    type Item = (u32, Keypair);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.next;
+        let keypair = self.wallet.keypair(index).ok()?;
+        self.next = index.saturating_add(1);
+        Some((index, keypair))
+    }
+}
+
+/// A list of derivation paths and their pubkeys.
+///
+/// Its text form has one `<derivation path> <pubkey>` pair per line:
+///
+/// ```text
+/// m/44'/501'/0'/0' <pubkey of account 0>
+/// m/44'/501'/1'/0' <pubkey of account 1>
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DerivationManifest(Vec<HdAccount>);
+
+impl DerivationManifest {
+    /// Returns the accounts of the manifest.
+    pub fn accounts(&self) -> &[HdAccount] {
+        &self.0
+    }
+
+    /// Returns the pubkey derived at `derivation_path`, if it is listed.
+    pub fn pubkey(&self, derivation_path: &DerivationPath) -> Option<&Address> {
+        self.0
+            .iter()
+            .find(|account| &account.derivation_path == derivation_path)
+            .map(|account| &account.pubkey)
+    }
+}
+
+impl fmt::Display for DerivationManifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for account in &self.0 {
+            writeln!(f, "{:?} {}", account.derivation_path, account.pubkey)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DerivationManifest {
+    type Err = HdWalletError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let invalid = || HdWalletError::InvalidManifest(line.to_string());
+                let (path, pubkey) = line.split_once(' ').ok_or_else(invalid)?;
+                let derivation_path =
+                    DerivationPath::from_absolute_path_str(path).map_err(|_| invalid())?;
+                let index = match derivation_path.path() {
+                    [_, _, account, _] => account.to_u32(),
+                    _ => return Err(invalid()),
+                };
+                if derivation_path != HdWallet::derivation_path(index)? {
+                    return Err(invalid());
+                }
+                let pubkey = pubkey.trim().parse().map_err(|_| invalid())?;
+                Ok(HdAccount {
+                    index,
+                    derivation_path,
+                    pubkey,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::keypair_from_seed_phrase_and_passphrase,
+        std::{cell::Cell, collections::HashSet},
+    };
+
+    const PHRASE: &str =
+        "vocal tonight sister pact lonely stage grass smile clinic dry horse wealth";
+
+    #[test]
+    fn test_derive_matches_single_key_derivation() {
+        let wallet = HdWallet::from_seed_phrase_and_passphrase(PHRASE, "");
+        let seed = generate_seed_from_seed_phrase_and_passphrase(PHRASE, "");
+        for index in [0, 1, 7] {
+            let expected = keypair_from_seed_and_derivation_path(
+                &seed,
+                Some(DerivationPath::new_bip44(Some(index), Some(0))),
+            )
+            .unwrap();
+            assert_eq!(wallet.keypair(index).unwrap().pubkey(), expected.pubkey());
+        }
+
+        // The root BIP-44 path is still reachable
+        let expected = keypair_from_seed_and_derivation_path(&seed, None).unwrap();
+        assert_eq!(
+            wallet.derive(&DerivationPath::default()).unwrap().pubkey(),
+            expected.pubkey()
+        );
+        // A different passphrase yields a different wallet
+        assert_ne!(
+            HdWallet::from_seed_phrase_and_passphrase(PHRASE, "extra")
+                .keypair(0)
+                .unwrap()
+                .pubkey(),
+            wallet.keypair(0).unwrap().pubkey()
+        );
+        assert_ne!(
+            keypair_from_seed_phrase_and_passphrase(PHRASE, "")
+                .unwrap()
+                .pubkey(),
+            wallet.keypair(0).unwrap().pubkey()
+        );
+    }
+
+    #[test]
+    fn test_account_index_bounds() {
+        let wallet = HdWallet::from_seed(&[7; 64]);
+        assert_eq!(
+            wallet.keypair(MAX_ACCOUNTS).unwrap_err(),
+            HdWalletError::InvalidAccountIndex(MAX_ACCOUNTS)
+        );
+        assert!(wallet.keypair(MAX_ACCOUNTS - 1).is_ok());
+
+        let mut accounts = wallet.accounts_from(MAX_ACCOUNTS - 1);
+        assert_eq!(accounts.next().unwrap().0, MAX_ACCOUNTS - 1);
+        assert!(accounts.next().is_none());
+    }
+
+    #[test]
+    fn test_accounts() {
+        let wallet = HdWallet::from_seed(&[1; 64]);
+        let accounts: Vec<_> = wallet.accounts().take(5).collect();
+        assert_eq!(
+            accounts.iter().map(|(index, _)| *index).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4]
+        );
+        let pubkeys: HashSet<_> = accounts.iter().map(|(_, k)| k.pubkey()).collect();
+        assert_eq!(pubkeys.len(), 5);
+        for (index, keypair) in accounts {
+            assert_eq!(wallet.account(index).unwrap().pubkey, keypair.pubkey());
+        }
+    }
+
+    #[test]
+    fn test_discover_accounts() {
+        let wallet = HdWallet::from_seed(&[2; 64]);
+        let used: HashSet<_> = [0, 1, 3, 8]
+            .into_iter()
+            .map(|index| wallet.account(index).unwrap().pubkey)
+            .collect();
+        let checked = Cell::new(0);
+        let mut is_used = |pubkey: &Address| {
+            checked.set(checked.get() + 1);
+            Ok(used.contains(pubkey))
+        };
+
+        // BIP-44 discovery stops at the first unused account
+        let found = wallet.discover_accounts(1, &mut is_used).unwrap();
+        assert_eq!(
+            found.iter().map(|a| a.index).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert_eq!(checked.get(), 3);
+
+        checked.set(0);
+        let found = wallet.discover_accounts(5, &mut is_used).unwrap();
+        assert_eq!(
+            found.iter().map(|a| a.index).collect::<Vec<_>>(),
+            vec![0, 1, 3, 8]
+        );
+        assert_eq!(checked.get(), 14);
+
+        assert!(wallet
+            .discover_accounts(0, &mut is_used)
+            .unwrap()
+            .is_empty());
+
+        let err = wallet
+            .discover_accounts(DEFAULT_GAP_LIMIT, |_| Err("rpc unavailable".into()))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "rpc unavailable");
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let wallet = HdWallet::from_seed(&[3; 64]);
+        let manifest = wallet.manifest([0, 2, 5]).unwrap();
+        let text = manifest.to_string();
+        assert_eq!(text.lines().count(), 3);
+        assert!(text.starts_with(&format!(
+            "m/44'/501'/0'/0' {}\n",
+            wallet.account(0).unwrap().pubkey
+        )));
+
+        let parsed: DerivationManifest = text.parse().unwrap();
+        assert_eq!(parsed, manifest);
+        assert_eq!(
+            parsed.pubkey(&HdWallet::derivation_path(5).unwrap()),
+            Some(&wallet.keypair(5).unwrap().pubkey())
+        );
+        assert_eq!(parsed.pubkey(&HdWallet::derivation_path(1).unwrap()), None);
+
+        assert!("m/44'/501'/0' 11111111111111111111111111111111"
+            .parse::<DerivationManifest>()
+            .is_err());
+        assert!("m/44'/501'/0'/0'".parse::<DerivationManifest>().is_err());
+        assert!("m/44'/501'/0'/0' not-a-pubkey"
+            .parse::<DerivationManifest>()
+            .is_err());
+    }
+}
@@ -16,6 +16,10 @@ pub use {
     solana_signer::{EncodableKey, EncodableKeypair, Signer},
 };
 
+#[cfg(feature = "encryption")]
+pub mod encryption;
+#[cfg(feature = "hd-wallet")]
+pub mod hd_wallet;
 #[cfg(feature = "seed-derivable")]
 pub mod seed_derivable;
 pub mod signable;
@@ -26,6 +30,9 @@ pub struct Keypair(ed25519_dalek::SigningKey);
 
 pub const KEYPAIR_LENGTH: usize = 64;
 
+/// Prefix of a passphrase-encrypted keypair, see the `encryption` module
+pub const ENCRYPTED_KEYPAIR_PREFIX: &str = "$solana-keypair$";
+
 impl Keypair {
     /// Can be used for generating a Keypair without a dependency on `rand` types
     pub const SECRET_KEY_LENGTH: usize = 32;
@@ -168,6 +175,13 @@ pub fn read_keypair<R: Read>(reader: &mut R) -> Result<Keypair, Box<dyn error::E
     let mut buffer = String::new();
     reader.read_to_string(&mut buffer)?;
     let trimmed = buffer.trim();
+    if trimmed.starts_with(ENCRYPTED_KEYPAIR_PREFIX) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Keypair is encrypted, a passphrase is required to read it",
+        )
+        .into());
+    }
     if !trimmed.starts_with('[') || !trimmed.ends_with(']') {
         return Err(std::io::Error::new(
             std::io::ErrorKind::InvalidData,
@@ -427,6 +441,20 @@ mod tests {
         assert_eq!(keypair.pubkey(), expected_keypair.pubkey());
     }
 
+    #[test]
+    fn test_keypair_from_validated_mnemonic() {
+        let mnemonic = solana_seed_phrase::Mnemonic::from_entropy(
+            &[42; 32],
+            solana_seed_phrase::Language::English,
+        )
+        .unwrap();
+        let passphrase = "42";
+        let expected_keypair = keypair_from_seed(&mnemonic.to_seed(passphrase)).unwrap();
+        let keypair =
+            keypair_from_seed_phrase_and_passphrase(mnemonic.phrase(), passphrase).unwrap();
+        assert_eq!(keypair.pubkey(), expected_keypair.pubkey());
+    }
+
     #[test]
     fn test_base58() {
         let keypair = keypair_from_seed(&[0u8; 32]).unwrap();
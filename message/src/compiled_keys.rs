@@ -2,7 +2,7 @@ use {
     crate::{
         inline_nonce::is_advance_nonce_instruction_data,
         v0::{LoadedAddresses, MessageAddressTableLookup},
-        AddressLookupTableAccount, MessageHeader,
+        AddressLookupTableAccount, MessageHeader, StaticKeyReason,
     },
     alloc::{collections::BTreeMap, vec::Vec},
     core::fmt,
@@ -43,40 +43,89 @@ impl fmt::Display for CompileError {
     }
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
-struct CompiledKeyMeta {
-    is_signer: bool,
-    is_writable: bool,
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CompiledKeyMeta {
+    pub(crate) is_signer: bool,
+    pub(crate) is_writable: bool,
     is_invoked: bool,
     is_nonce: bool,
 }
 
+impl CompiledKeyMeta {
+    /// Returns the reason the key must be a static account key, or `None` if
+    /// it may be loaded from a lookup table.
+    pub(crate) fn static_key_reason(&self) -> Option<StaticKeyReason> {
+        if self.is_signer {
+            Some(StaticKeyReason::Signer)
+        } else if self.is_invoked {
+            Some(StaticKeyReason::InvokedProgram)
+        } else if self.is_nonce {
+            Some(StaticKeyReason::DurableNonce)
+        } else {
+            None
+        }
+    }
+}
+
 impl CompiledKeys {
     /// Compiles the pubkeys referenced by a list of instructions and organizes by
     /// signer/non-signer and writable/readonly.
     pub(crate) fn compile(instructions: &[Instruction], payer: Option<Address>) -> Self {
-        let mut key_meta_map = BTreeMap::<Address, CompiledKeyMeta>::new();
-        for ix in instructions {
-            let meta = key_meta_map.entry(ix.program_id).or_default();
-            meta.is_invoked = true;
-            for account_meta in &ix.accounts {
-                let meta = key_meta_map.entry(account_meta.pubkey).or_default();
-                meta.is_signer |= account_meta.is_signer;
-                meta.is_writable |= account_meta.is_writable;
-            }
-        }
-        if let Some(nonce_pubkey) = get_nonce_pubkey(instructions) {
-            let meta = key_meta_map.entry(*nonce_pubkey).or_default();
-            meta.is_nonce = true;
+        let mut compiled_keys = Self {
+            payer,
+            key_meta_map: BTreeMap::new(),
+        };
+        for (index, ix) in instructions.iter().enumerate() {
+            compiled_keys.add_instruction(index, ix, |_, _, _| {});
         }
         if let Some(payer) = &payer {
-            let meta = key_meta_map.entry(*payer).or_default();
+            let meta = compiled_keys.key_meta_map.entry(*payer).or_default();
             meta.is_signer = true;
             meta.is_writable = true;
         }
-        Self {
-            payer,
-            key_meta_map,
+        compiled_keys
+    }
+
+    /// Adds the pubkeys referenced by the instruction at `index` of a message,
+    /// calling `on_update` with the key, its previous meta and its new meta
+    /// whenever the meta of a key changes.
+    pub(crate) fn add_instruction(
+        &mut self,
+        index: usize,
+        ix: &Instruction,
+        mut on_update: impl FnMut(&Address, Option<CompiledKeyMeta>, CompiledKeyMeta),
+    ) {
+        let mut update = |key: &Address, f: &dyn Fn(&mut CompiledKeyMeta)| {
+            let old = self.key_meta_map.get(key).copied();
+            let meta = self.key_meta_map.entry(*key).or_default();
+            f(meta);
+            if old != Some(*meta) {
+                on_update(key, old, *meta);
+            }
+        };
+        update(&ix.program_id, &|meta| meta.is_invoked = true);
+        for account_meta in &ix.accounts {
+            update(&account_meta.pubkey, &|meta| {
+                meta.is_signer |= account_meta.is_signer;
+                meta.is_writable |= account_meta.is_writable;
+            });
+        }
+        if index == NONCED_TX_MARKER_IX_INDEX {
+            if let Some(nonce_pubkey) = get_nonce_pubkey(ix) {
+                update(nonce_pubkey, &|meta| meta.is_nonce = true);
+            }
+        }
+    }
+
+    /// Replaces the meta of `key`, removing the key if `meta` is `None`.
+    pub(crate) fn set(&mut self, key: Address, meta: Option<CompiledKeyMeta>) {
+        match meta {
+            Some(meta) => {
+                self.key_meta_map.insert(key, meta);
+            }
+            None => {
+                self.key_meta_map.remove(&key);
+            }
         }
     }
 
@@ -137,17 +186,28 @@ impl CompiledKeys {
         Ok((header, static_account_keys))
     }
 
+    /// Returns each compiled key, whether it is writable, and the reason it
+    /// must be a static account key, or `None` if it may be loaded from a
+    /// lookup table.
+    pub(crate) fn lookup_eligibility(
+        &self,
+    ) -> impl Iterator<Item = (&Address, bool, Option<StaticKeyReason>)> {
+        self.key_meta_map
+            .iter()
+            .map(|(key, meta)| (key, meta.is_writable, meta.static_key_reason()))
+    }
+
     pub(crate) fn try_extract_table_lookup(
         &mut self,
         lookup_table_account: &AddressLookupTableAccount,
     ) -> Result<Option<(MessageAddressTableLookup, LoadedAddresses)>, CompileError> {
         let (writable_indexes, drained_writable_keys) = self
             .try_drain_keys_found_in_lookup_table(&lookup_table_account.addresses, |meta| {
-                !meta.is_signer && !meta.is_invoked && !meta.is_nonce && meta.is_writable
+                meta.static_key_reason().is_none() && meta.is_writable
             })?;
         let (readonly_indexes, drained_readonly_keys) = self
             .try_drain_keys_found_in_lookup_table(&lookup_table_account.addresses, |meta| {
-                !meta.is_signer && !meta.is_invoked && !meta.is_nonce && !meta.is_writable
+                meta.static_key_reason().is_none() && !meta.is_writable
             })?;
 
         // Don't extract lookup if no keys were found
@@ -204,8 +264,7 @@ impl CompiledKeys {
 // inlined to avoid solana_nonce dep
 const NONCED_TX_MARKER_IX_INDEX: usize = 0;
 
-fn get_nonce_pubkey(instructions: &[Instruction]) -> Option<&Address> {
-    let ix = instructions.get(NONCED_TX_MARKER_IX_INDEX)?;
+fn get_nonce_pubkey(ix: &Instruction) -> Option<&Address> {
     if !system_program::check_id(&ix.program_id) {
         return None;
     }
@@ -0,0 +1,663 @@
+//! Selection of the smallest valid message version for a set of instructions.
+//!
+//! [`VersionedMessage::try_compile`] compiles the instructions into each
+//! message version, checks the result against the limits of that version and
+//! returns the smallest valid encoding, along with the reason every other
+//! version was rejected. [`MessageVersion::try_compile`] does the same for a
+//! single, caller-chosen version.
+
+use {
+    crate::{
+        compiled_instruction::CompiledInstruction,
+        legacy::Message as LegacyMessage,
+        v0,
+        v1::{self, TransactionConfig, SIGNATURE_SIZE},
+        AddressLookupTableAccount, CompileError, VersionedMessage, MESSAGE_HEADER_LENGTH,
+    },
+    alloc::vec::Vec,
+    core::fmt,
+    solana_address::Address,
+    solana_hash::Hash,
+    solana_instruction::Instruction,
+    solana_sanitize::{Sanitize, SanitizeError},
+    solana_short_vec::encoded_shortu16_len_size,
+};
+
+// inlined to avoid solana_packet dep
+const PACKET_DATA_SIZE: usize = 1280 - 40 - 8;
+#[cfg(test)]
+static_assertions::const_assert_eq!(PACKET_DATA_SIZE, solana_packet::PACKET_DATA_SIZE);
+
+/// Message versions considered by [`VersionedMessage::try_compile`], in order of
+/// preference when two encodings have the same size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MessageVersion {
+    Legacy,
+    V0,
+    V1,
+}
+
+impl MessageVersion {
+    /// All message versions, in order of preference.
+    pub const ALL: [MessageVersion; 3] = [Self::Legacy, Self::V0, Self::V1];
+
+    /// Maximum size in bytes of a serialized transaction of this version.
+    pub const fn max_transaction_size(&self) -> usize {
+        match self {
+            Self::Legacy | Self::V0 => PACKET_DATA_SIZE,
+            Self::V1 => v1::MAX_TRANSACTION_SIZE,
+        }
+    }
+
+    /// Compile `instructions` into a message of this version and check it
+    /// against the limits of this version.
+    ///
+    /// Returns the message along with the serialized size of the transaction
+    /// carrying it, including signatures. Legacy and v1 messages ignore
+    /// `address_lookup_table_accounts`, and only v1 messages can carry a
+    /// non-empty `config`.
+    pub fn try_compile(
+        self,
+        payer: &Address,
+        instructions: &[Instruction],
+        address_lookup_table_accounts: &[AddressLookupTableAccount],
+        recent_blockhash: Hash,
+        config: TransactionConfig,
+    ) -> Result<(VersionedMessage, usize), VersionRejection> {
+        let has_config = config != TransactionConfig::empty();
+        let message = match self {
+            Self::Legacy if has_config => return Err(VersionRejection::ConfigNotSupported),
+            Self::Legacy => compile_legacy(payer, instructions, recent_blockhash)?,
+            Self::V0 if has_config => return Err(VersionRejection::ConfigNotSupported),
+            Self::V0 => {
+                let message = v0::Message::try_compile(
+                    payer,
+                    instructions,
+                    address_lookup_table_accounts,
+                    recent_blockhash,
+                )
+                .map_err(VersionRejection::Compile)?;
+                message.sanitize().map_err(VersionRejection::Sanitize)?;
+                VersionedMessage::V0(message)
+            }
+            Self::V1 => {
+                // Address table lookups are not part of SIMD-0385, so v1
+                // messages only use static account keys.
+                let message = v1::Message::try_compile_with_config(
+                    payer,
+                    instructions,
+                    recent_blockhash,
+                    config,
+                )
+                .map_err(VersionRejection::Compile)?;
+                message.validate().map_err(VersionRejection::V1)?;
+                VersionedMessage::V1(message)
+            }
+        };
+
+        let size = transaction_size(&message);
+        let max_size = self.max_transaction_size();
+        if size > max_size {
+            return Err(VersionRejection::TransactionTooLarge { size, max_size });
+        }
+        Ok((message, size))
+    }
+}
+
+impl fmt::Display for MessageVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Legacy => f.write_str("legacy"),
+            Self::V0 => f.write_str("v0"),
+            Self::V1 => f.write_str("v1"),
+        }
+    }
+}
+
+/// Reason a message version was not selected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionRejection {
+    /// The instructions could not be compiled, e.g. because they reference
+    /// more accounts than can be indexed.
+    Compile(CompileError),
+    /// The compiled legacy or v0 message failed sanitization.
+    Sanitize(SanitizeError),
+    /// The compiled v1 message violates a v1 limit, e.g. too many addresses.
+    V1(v1::MessageError),
+    /// The message version cannot carry the requested `TransactionConfig`.
+    ConfigNotSupported,
+    /// The serialized transaction exceeds the size limit of the version.
+    TransactionTooLarge { size: usize, max_size: usize },
+    /// The version is valid, but a smaller encoding was selected.
+    NotSmallest { size: usize },
+}
+
+impl fmt::Display for VersionRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Compile(err) => write!(f, "failed to compile: {err}"),
+            Self::Sanitize(err) => write!(f, "failed to sanitize: {err}"),
+            Self::V1(err) => write!(f, "invalid v1 message: {err}"),
+            Self::ConfigNotSupported => f.write_str("transaction config is not supported"),
+            Self::TransactionTooLarge { size, max_size } => {
+                write!(f, "transaction size {size} exceeds max size {max_size}")
+            }
+            Self::NotSmallest { size } => {
+                write!(f, "transaction size {size} is not the smallest")
+            }
+        }
+    }
+}
+
+/// A message compiled by [`VersionedMessage::try_compile`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionSelection {
+    /// The smallest valid message.
+    pub message: VersionedMessage,
+    /// Serialized size of the transaction carrying `message`, including signatures.
+    pub transaction_size: usize,
+    /// Reason every other message version was rejected.
+    pub rejections: Vec<(MessageVersion, VersionRejection)>,
+}
+
+impl VersionSelection {
+    /// The version of the selected message.
+    pub fn version(&self) -> MessageVersion {
+        match self.message {
+            VersionedMessage::Legacy(_) => MessageVersion::Legacy,
+            VersionedMessage::V0(_) => MessageVersion::V0,
+            VersionedMessage::V1(_) => MessageVersion::V1,
+        }
+    }
+}
+
+/// Error returned by [`VersionedMessage::try_compile`] when no message version
+/// can encode the instructions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionSelectionError {
+    /// Reason each message version was rejected.
+    pub rejections: Vec<(MessageVersion, VersionRejection)>,
+}
+
+impl core::error::Error for VersionSelectionError {}
+
+impl fmt::Display for VersionSelectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("no valid message version")?;
+        for (i, (version, rejection)) in self.rejections.iter().enumerate() {
+            let separator = if i == 0 { ": " } else { "; " };
+            write!(f, "{separator}{version} {rejection}")?;
+        }
+        Ok(())
+    }
+}
+
+impl VersionedMessage {
+    /// Compile `instructions` into the message version with the smallest
+    /// serialized transaction.
+    ///
+    /// Every version is compiled and checked against its own limits: legacy and
+    /// v0 transactions must fit in `PACKET_DATA_SIZE` bytes, and v1 transactions
+    /// must fit in [`v1::MAX_TRANSACTION_SIZE`] and satisfy the v1 account,
+    /// instruction and signature limits. Legacy messages ignore
+    /// `address_lookup_table_accounts`, and only v1 messages can carry a
+    /// non-empty `config`. When two versions serialize to the same size, the
+    /// older version is preferred.
+    ///
+    /// V1 messages are always compiled without address table lookups, which
+    /// are not part of SIMD-0385, so a v1 fallback only uses static account
+    /// keys.
+    ///
+    /// The returned [`VersionSelection`] records why each other version was not
+    /// selected. If no version is valid, the error records why each one was
+    /// rejected.
+    pub fn try_compile(
+        payer: &Address,
+        instructions: &[Instruction],
+        address_lookup_table_accounts: &[AddressLookupTableAccount],
+        recent_blockhash: Hash,
+        config: Option<TransactionConfig>,
+    ) -> Result<VersionSelection, VersionSelectionError> {
+        let config = config.unwrap_or_default();
+
+        let mut candidates = Vec::with_capacity(MessageVersion::ALL.len());
+        let mut rejections = Vec::with_capacity(MessageVersion::ALL.len());
+
+        for version in MessageVersion::ALL {
+            let checked = version.try_compile(
+                payer,
+                instructions,
+                address_lookup_table_accounts,
+                recent_blockhash,
+                config,
+            );
+
+            match checked {
+                Ok((message, size)) => candidates.push((version, message, size)),
+                Err(rejection) => rejections.push((version, rejection)),
+            }
+        }
+
+        // `min_by_key` keeps the first of equal elements, which is the
+        // preferred (older) version.
+        let Some(selected) = candidates
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, (_, _, size))| *size)
+            .map(|(i, _)| i)
+        else {
+            return Err(VersionSelectionError { rejections });
+        };
+        let (_, message, transaction_size) = candidates.swap_remove(selected);
+
+        rejections.extend(
+            candidates
+                .into_iter()
+                .map(|(version, _, size)| (version, VersionRejection::NotSmallest { size })),
+        );
+        rejections.sort_by_key(|(version, _)| *version);
+
+        Ok(VersionSelection {
+            message,
+            transaction_size,
+            rejections,
+        })
+    }
+}
+
+fn compile_legacy(
+    payer: &Address,
+    instructions: &[Instruction],
+    recent_blockhash: Hash,
+) -> Result<VersionedMessage, VersionRejection> {
+    // A v0 message compiled without lookup tables has the same layout as a
+    // legacy message, without panicking on overflow.
+    let v0::Message {
+        header,
+        account_keys,
+        recent_blockhash,
+        instructions,
+        ..
+    } = v0::Message::try_compile(payer, instructions, &[], recent_blockhash)
+        .map_err(VersionRejection::Compile)?;
+    let message = LegacyMessage {
+        header,
+        account_keys,
+        recent_blockhash,
+        instructions,
+    };
+    message.sanitize().map_err(VersionRejection::Sanitize)?;
+    Ok(VersionedMessage::Legacy(message))
+}
+
+/// Serialized size of a transaction carrying `message` and its required
+/// signatures.
+#[allow(clippy::arithmetic_side_effects)]
+fn transaction_size(message: &VersionedMessage) -> usize {
+    let num_signatures = usize::from(message.header().num_required_signatures);
+    let signatures_size = num_signatures * SIGNATURE_SIZE;
+    match message {
+        VersionedMessage::Legacy(message) => {
+            encoded_shortu16_len_size(num_signatures)
+                + signatures_size
+                + legacy_message_size(&message.account_keys, &message.instructions)
+        }
+        VersionedMessage::V0(message) => {
+            encoded_shortu16_len_size(num_signatures)
+                + signatures_size
+                + size_of::<u8>() // version prefix
+                + legacy_message_size(&message.account_keys, &message.instructions)
+                + encoded_shortu16_len_size(message.address_table_lookups.len())
+                + message
+                    .address_table_lookups
+                    .iter()
+                    .map(|lookup| {
+                        address_table_lookup_size(
+                            lookup.writable_indexes.len(),
+                            lookup.readonly_indexes.len(),
+                        )
+                    })
+                    .sum::<usize>()
+        }
+        // V1 signatures are written as a fixed length array after the message.
+        VersionedMessage::V1(message) => size_of::<u8>() + message.size() + signatures_size,
+    }
+}
+
+/// Serialized size of the fields shared by legacy and v0 messages.
+#[allow(clippy::arithmetic_side_effects)]
+fn legacy_message_size(account_keys: &[Address], instructions: &[CompiledInstruction]) -> usize {
+    MESSAGE_HEADER_LENGTH
+        + encoded_shortu16_len_size(account_keys.len())
+        + core::mem::size_of_val(account_keys)
+        + size_of::<Hash>()
+        + encoded_shortu16_len_size(instructions.len())
+        + instructions
+            .iter()
+            .map(|ix| legacy_instruction_size(ix.accounts.len(), ix.data.len()))
+            .sum::<usize>()
+}
+
+/// Serialized size of a legacy or v0 instruction.
+#[allow(clippy::arithmetic_side_effects)]
+pub(super) fn legacy_instruction_size(num_accounts: usize, data_len: usize) -> usize {
+    size_of::<u8>()
+        + encoded_shortu16_len_size(num_accounts)
+        + num_accounts
+        + encoded_shortu16_len_size(data_len)
+        + data_len
+}
+
+/// Serialized size of an address table lookup of a v0 message.
+#[allow(clippy::arithmetic_side_effects)]
+pub(super) fn address_table_lookup_size(num_writable: usize, num_readonly: usize) -> usize {
+    size_of::<Address>()
+        + encoded_shortu16_len_size(num_writable)
+        + num_writable
+        + encoded_shortu16_len_size(num_readonly)
+        + num_readonly
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        alloc::{string::ToString, vec},
+        solana_instruction::AccountMeta,
+        wincode::{config::DefaultConfig, SchemaWrite},
+    };
+
+    fn accounts_instruction(num_accounts: usize, data_len: usize) -> Instruction {
+        Instruction::new_with_bytes(
+            Address::new_unique(),
+            &vec![7; data_len],
+            (0..num_accounts)
+                .map(|_| AccountMeta::new(Address::new_unique(), false))
+                .collect(),
+        )
+    }
+
+    fn rejection(
+        rejections: &[(MessageVersion, VersionRejection)],
+        version: MessageVersion,
+    ) -> &VersionRejection {
+        &rejections
+            .iter()
+            .find(|(v, _)| *v == version)
+            .expect("version should be rejected")
+            .1
+    }
+
+    #[test]
+    fn transaction_size_matches_serialized_size() {
+        let payer = Address::new_unique();
+        let instruction = accounts_instruction(4, 200);
+        let table = AddressLookupTableAccount {
+            key: Address::new_unique(),
+            addresses: instruction
+                .accounts
+                .iter()
+                .map(|meta| meta.pubkey)
+                .collect(),
+        };
+        let messages = [
+            VersionedMessage::V0(
+                v0::Message::try_compile(
+                    &payer,
+                    core::slice::from_ref(&instruction),
+                    core::slice::from_ref(&table),
+                    Hash::new_unique(),
+                )
+                .unwrap(),
+            ),
+            VersionedMessage::V1(
+                v1::Message::try_compile_with_config(
+                    &payer,
+                    core::slice::from_ref(&instruction),
+                    Hash::new_unique(),
+                    TransactionConfig::empty().with_priority_fee(1),
+                )
+                .unwrap(),
+            ),
+            compile_legacy(&payer, &[instruction], Hash::new_unique()).unwrap(),
+        ];
+
+        for message in &messages {
+            let num_signatures = usize::from(message.header().num_required_signatures);
+            let message_size =
+                <VersionedMessage as SchemaWrite<DefaultConfig>>::size_of(message).unwrap();
+            let expected = match message {
+                VersionedMessage::V1(_) => message_size + num_signatures * SIGNATURE_SIZE,
+                _ => 1 + message_size + num_signatures * SIGNATURE_SIZE,
+            };
+            assert_eq!(transaction_size(message), expected);
+        }
+    }
+
+    #[test]
+    fn selects_legacy_for_small_messages() {
+        let selection = VersionedMessage::try_compile(
+            &Address::new_unique(),
+            &[accounts_instruction(2, 8)],
+            &[],
+            Hash::new_unique(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(selection.version(), MessageVersion::Legacy);
+        assert!(matches!(
+            rejection(&selection.rejections, MessageVersion::V0),
+            VersionRejection::NotSmallest { size } if *size == selection.transaction_size + 2
+        ));
+        assert!(matches!(
+            rejection(&selection.rejections, MessageVersion::V1),
+            VersionRejection::NotSmallest { .. }
+        ));
+    }
+
+    #[test]
+    fn selects_v0_when_lookup_tables_shrink_the_message() {
+        let instruction = accounts_instruction(10, 8);
+        let table = AddressLookupTableAccount {
+            key: Address::new_unique(),
+            addresses: instruction
+                .accounts
+                .iter()
+                .map(|meta| meta.pubkey)
+                .collect(),
+        };
+        let selection = VersionedMessage::try_compile(
+            &Address::new_unique(),
+            &[instruction],
+            &[table],
+            Hash::new_unique(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(selection.version(), MessageVersion::V0);
+        assert_eq!(selection.rejections.len(), 2);
+    }
+
+    #[test]
+    fn v1_fallback_has_no_address_table_lookups() {
+        let instruction = accounts_instruction(2, PACKET_DATA_SIZE);
+        let table = AddressLookupTableAccount {
+            key: Address::new_unique(),
+            addresses: instruction
+                .accounts
+                .iter()
+                .map(|meta| meta.pubkey)
+                .collect(),
+        };
+        let selection = VersionedMessage::try_compile(
+            &Address::new_unique(),
+            &[instruction],
+            &[table],
+            Hash::new_unique(),
+            None,
+        )
+        .unwrap();
+
+        let VersionedMessage::V1(message) = &selection.message else {
+            panic!("expected a v1 message");
+        };
+        assert_eq!(message.account_keys.len(), 4);
+    }
+
+    #[test]
+    fn selects_v1_when_config_is_requested() {
+        let selection = VersionedMessage::try_compile(
+            &Address::new_unique(),
+            &[accounts_instruction(2, 8)],
+            &[],
+            Hash::new_unique(),
+            Some(TransactionConfig::empty().with_compute_unit_limit(100_000)),
+        )
+        .unwrap();
+
+        assert_eq!(selection.version(), MessageVersion::V1);
+        assert_eq!(
+            selection.rejections,
+            vec![
+                (MessageVersion::Legacy, VersionRejection::ConfigNotSupported),
+                (MessageVersion::V0, VersionRejection::ConfigNotSupported),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_config_does_not_force_v1() {
+        let selection = VersionedMessage::try_compile(
+            &Address::new_unique(),
+            &[accounts_instruction(2, 8)],
+            &[],
+            Hash::new_unique(),
+            Some(TransactionConfig::empty()),
+        )
+        .unwrap();
+
+        assert_eq!(selection.version(), MessageVersion::Legacy);
+    }
+
+    #[test]
+    fn selects_v1_when_over_packet_data_size() {
+        let selection = VersionedMessage::try_compile(
+            &Address::new_unique(),
+            &[accounts_instruction(2, PACKET_DATA_SIZE)],
+            &[],
+            Hash::new_unique(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(selection.version(), MessageVersion::V1);
+        assert!(matches!(
+            rejection(&selection.rejections, MessageVersion::Legacy),
+            VersionRejection::TransactionTooLarge { max_size, .. } if *max_size == PACKET_DATA_SIZE
+        ));
+        assert!(matches!(
+            rejection(&selection.rejections, MessageVersion::V0),
+            VersionRejection::TransactionTooLarge { .. }
+        ));
+    }
+
+    #[test]
+    fn rejects_v1_with_too_many_addresses() {
+        let selection = VersionedMessage::try_compile(
+            &Address::new_unique(),
+            &[accounts_instruction(usize::from(v1::MAX_ADDRESSES), 0)],
+            &[],
+            Hash::new_unique(),
+            None,
+        );
+
+        // 66 addresses do not fit in a packet either.
+        let err = selection.unwrap_err();
+        assert_eq!(
+            rejection(&err.rejections, MessageVersion::V1),
+            &VersionRejection::V1(v1::MessageError::TooManyAddresses)
+        );
+        assert!(matches!(
+            rejection(&err.rejections, MessageVersion::Legacy),
+            VersionRejection::TransactionTooLarge { .. }
+        ));
+    }
+
+    #[test]
+    fn rejects_all_versions_when_too_large() {
+        let err = VersionedMessage::try_compile(
+            &Address::new_unique(),
+            &[accounts_instruction(1, v1::MAX_TRANSACTION_SIZE)],
+            &[],
+            Hash::new_unique(),
+            None,
+        )
+        .unwrap_err();
+
+        assert_eq!(err.rejections.len(), 3);
+        for (version, rejection) in &err.rejections {
+            assert!(matches!(
+                rejection,
+                VersionRejection::TransactionTooLarge { max_size, .. }
+                    if *max_size == version.max_transaction_size()
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_all_versions_on_account_index_overflow() {
+        let err = VersionedMessage::try_compile(
+            &Address::new_unique(),
+            &[accounts_instruction(256, 0)],
+            &[],
+            Hash::new_unique(),
+            None,
+        )
+        .unwrap_err();
+
+        assert!(err.rejections.iter().all(|(_, rejection)| *rejection
+            == VersionRejection::Compile(CompileError::AccountIndexOverflow)));
+        assert!(err
+            .to_string()
+            .starts_with("no valid message version: legacy"));
+    }
+
+    #[test]
+    fn compiles_requested_version() {
+        let payer = Address::new_unique();
+        let instructions = [accounts_instruction(2, 10)];
+
+        for version in MessageVersion::ALL {
+            let (message, size) = version
+                .try_compile(
+                    &payer,
+                    &instructions,
+                    &[],
+                    Hash::new_unique(),
+                    TransactionConfig::empty(),
+                )
+                .unwrap();
+            assert_eq!(message.header().num_required_signatures, 1);
+            assert_eq!(size, transaction_size(&message));
+            assert_eq!(
+                matches!(message, VersionedMessage::V1(_)),
+                version == MessageVersion::V1
+            );
+        }
+
+        for version in [MessageVersion::Legacy, MessageVersion::V0] {
+            assert_eq!(
+                version.try_compile(
+                    &payer,
+                    &instructions,
+                    &[],
+                    Hash::new_unique(),
+                    TransactionConfig::empty().with_priority_fee(1),
+                ),
+                Err(VersionRejection::ConfigNotSupported)
+            );
+        }
+    }
+}
@@ -0,0 +1,669 @@
+//! Selection of the address lookup tables referenced by a message.
+//!
+//! [`select_lookup_tables`] picks, from a pool of candidate tables, the subset
+//! that minimizes the size of a message compiled from a set of instructions,
+//! and reports which keys must remain static account keys.
+
+use {
+    super::compile::address_table_lookup_size,
+    crate::{compiled_keys::CompiledKeys, AddressLookupTableAccount},
+    alloc::{collections::BTreeMap, vec::Vec},
+    core::{fmt, mem::size_of},
+    solana_address::Address,
+    solana_instruction::Instruction,
+    solana_short_vec::encoded_shortu16_len_size,
+};
+
+// inlined to avoid solana_address_lookup_table_interface dep
+/// Maximum number of addresses stored in a lookup table.
+const LOOKUP_TABLE_MAX_ADDRESSES: usize = 256;
+#[cfg(test)]
+static_assertions::const_assert_eq!(
+    LOOKUP_TABLE_MAX_ADDRESSES,
+    solana_address_lookup_table_interface::state::LOOKUP_TABLE_MAX_ADDRESSES
+);
+
+/// Candidate pools up to this size are searched exhaustively; larger pools
+/// are searched greedily.
+const MAX_EXACT_SEARCH_TABLES: usize = 16;
+
+/// Reason a key is a static account key rather than loaded from a lookup
+/// table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StaticKeyReason {
+    /// The key is the fee payer or another signer.
+    Signer,
+    /// The key is invoked as a program.
+    InvokedProgram,
+    /// The key is the nonce account advanced by the first instruction.
+    DurableNonce,
+    /// No candidate table contains the key.
+    NotInLookupTables,
+    /// Candidate tables contain the key, but referencing them would not
+    /// shrink the message.
+    Unprofitable,
+}
+
+impl fmt::Display for StaticKeyReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Signer => f.write_str("signer"),
+            Self::InvokedProgram => f.write_str("invoked program"),
+            Self::DurableNonce => f.write_str("durable nonce account"),
+            Self::NotInLookupTables => f.write_str("not in any lookup table"),
+            Self::Unprofitable => f.write_str("lookup would not reduce message size"),
+        }
+    }
+}
+
+/// Lookup tables selected by [`select_lookup_tables`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupTableSelection {
+    /// The selected tables, in candidate order.
+    ///
+    /// Messages do not reference tables by explicit index: compiling a
+    /// message walks the tables in the order they are passed, and loads each
+    /// key from the first table that contains it. Passing these tables, in
+    /// this order, to `v0::Message::try_compile` or
+    /// `v1::Message::try_compile_with_lookup_tables` loads each key in
+    /// `loaded_keys` from the corresponding table; reordering them may load
+    /// keys from different tables and change the size of the message.
+    pub tables: Vec<AddressLookupTableAccount>,
+    /// Keys loaded from each selected table, in the same order as `tables`.
+    pub loaded_keys: Vec<Vec<Address>>,
+    /// Keys which remain static account keys, and why.
+    pub static_keys: Vec<(Address, StaticKeyReason)>,
+}
+
+/// Select the subset of `candidates` which minimizes the size of a message
+/// compiled from `payer` and `instructions`.
+///
+/// Signers, invoked programs and the durable nonce account are always static
+/// account keys. Every other key is loaded from the first selected table, in
+/// candidate order, that contains it, so when several candidates hold the same
+/// key the earliest one wins. A table is only worth referencing if it replaces
+/// at least two static keys, so the selection never contains a table whose
+/// keys are all loaded from other selected tables, or which would load a
+/// single key.
+///
+/// Sizes are computed as serialized, including the `short_vec` length
+/// prefixes of the account keys and of each table's index lists, which grow
+/// by a byte past 127 entries.
+///
+/// Candidate tables holding more than 256 addresses are invalid and ignored.
+/// Up to 16 useful candidates are searched exhaustively; larger pools are
+/// searched greedily, picking the table that saves the most bytes until no
+/// table saves any.
+pub fn select_lookup_tables(
+    payer: &Address,
+    instructions: &[Instruction],
+    candidates: &[AddressLookupTableAccount],
+) -> LookupTableSelection {
+    let compiled_keys = CompiledKeys::compile(instructions, Some(*payer));
+
+    let mut static_keys = Vec::new();
+    let mut eligible_keys = Vec::new();
+    let mut writable_ids = Vec::new();
+    for (key, is_writable, reason) in compiled_keys.lookup_eligibility() {
+        match reason {
+            Some(reason) => static_keys.push((*key, reason)),
+            None => {
+                if is_writable {
+                    writable_ids.push(eligible_keys.len());
+                }
+                eligible_keys.push(*key);
+            }
+        }
+    }
+    let mut writable = KeySet::new(eligible_keys.len());
+    for id in writable_ids {
+        writable.insert(id);
+    }
+    let key_ids: BTreeMap<Address, usize> = eligible_keys
+        .iter()
+        .enumerate()
+        .map(|(id, key)| (*key, id))
+        .collect();
+
+    let mut tables: Vec<(usize, KeySet)> = candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, table)| table.addresses.len() <= LOOKUP_TABLE_MAX_ADDRESSES)
+        .map(|(index, table)| {
+            let mut coverage = KeySet::new(eligible_keys.len());
+            for address in &table.addresses {
+                if let Some(id) = key_ids.get(address) {
+                    coverage.insert(*id);
+                }
+            }
+            (index, coverage)
+        })
+        .collect();
+    let in_any_table = tables.iter().fold(
+        KeySet::new(eligible_keys.len()),
+        |mut keys, (_, coverage)| {
+            keys.union_with(coverage);
+            keys
+        },
+    );
+    // Referencing a table to load a single key makes the message larger.
+    tables.retain(|(_, coverage)| coverage.len() >= 2);
+
+    // A table whose keys are all contained by another table never needs to
+    // be referenced: the other table can load the same keys instead.
+    let dominated: Vec<bool> = tables
+        .iter()
+        .enumerate()
+        .map(|(i, (_, coverage))| {
+            tables.iter().enumerate().any(|(j, (_, other))| {
+                i != j && coverage.is_subset(other) && (j < i || !other.is_subset(coverage))
+            })
+        })
+        .collect();
+    let mut dominated = dominated.into_iter();
+    tables.retain(|_| !dominated.next().unwrap_or(false));
+
+    let model = SizeModel {
+        coverages: tables.iter().map(|(_, coverage)| coverage).collect(),
+        writable,
+        num_keys: eligible_keys.len(),
+        num_static_keys: static_keys.len(),
+    };
+    let mut selected = if model.coverages.len() <= MAX_EXACT_SEARCH_TABLES {
+        exact_search(&model)
+    } else {
+        greedy_search(&model)
+    };
+    prune(&model, &mut selected);
+
+    let mut loaded = KeySet::new(eligible_keys.len());
+    let mut selected_tables = Vec::with_capacity(selected.len());
+    let mut loaded_keys = Vec::with_capacity(selected.len());
+    for i in selected {
+        let (index, coverage) = &tables[i];
+        loaded_keys.push(
+            coverage
+                .iter()
+                .filter(|id| !loaded.contains(*id))
+                .map(|id| eligible_keys[id])
+                .collect(),
+        );
+        loaded.union_with(coverage);
+        selected_tables.push(candidates[*index].clone());
+    }
+
+    static_keys.extend(
+        eligible_keys
+            .iter()
+            .enumerate()
+            .filter(|(id, _)| !loaded.contains(*id))
+            .map(|(id, key)| {
+                let reason = if in_any_table.contains(id) {
+                    StaticKeyReason::Unprofitable
+                } else {
+                    StaticKeyReason::NotInLookupTables
+                };
+                (*key, reason)
+            }),
+    );
+
+    LookupTableSelection {
+        tables: selected_tables,
+        loaded_keys,
+        static_keys,
+    }
+}
+
+/// Size model of the account keys and address table lookups of a message.
+struct SizeModel<'a> {
+    /// Eligible keys contained by each useful table, in candidate order.
+    coverages: Vec<&'a KeySet>,
+    /// Eligible keys which are writable.
+    writable: KeySet,
+    /// Number of keys eligible to be loaded from a table.
+    num_keys: usize,
+    /// Number of keys which must be static account keys.
+    num_static_keys: usize,
+}
+
+impl SizeModel<'_> {
+    /// Serialized size of the account keys and the address table lookups of
+    /// a message referencing the tables in `selected`, which must be sorted.
+    #[allow(clippy::arithmetic_side_effects)]
+    fn size(&self, selected: &[usize]) -> usize {
+        let mut loaded = KeySet::new(self.num_keys);
+        let mut num_lookups = 0;
+        let mut lookups_size = 0;
+        for i in selected {
+            let mut keys = self.coverages[*i].clone();
+            keys.difference_with(&loaded);
+            let num_loaded = keys.len();
+            if num_loaded == 0 {
+                // Compilation skips tables which load no keys.
+                continue;
+            }
+            let num_writable = keys.count_in(&self.writable);
+            let num_readonly = num_loaded - num_writable;
+            num_lookups += 1;
+            lookups_size += address_table_lookup_size(num_writable, num_readonly);
+            loaded.union_with(&keys);
+        }
+        let num_static_keys = self.num_static_keys + self.num_keys - loaded.len();
+        encoded_shortu16_len_size(num_static_keys)
+            + num_static_keys * size_of::<Address>()
+            + encoded_shortu16_len_size(num_lookups)
+            + lookups_size
+    }
+
+    /// Size with the table `i` inserted into `selected`, keeping it sorted.
+    fn size_with(&self, selected: &[usize], i: usize) -> usize {
+        let mut selected = selected.to_vec();
+        let position = selected.partition_point(|j| *j < i);
+        selected.insert(position, i);
+        self.size(&selected)
+    }
+}
+
+/// Try every subset of the tables, keeping the first with the smallest size.
+#[allow(clippy::arithmetic_side_effects)]
+fn exact_search(model: &SizeModel) -> Vec<usize> {
+    let num_tables = model.coverages.len();
+    let mut best = Vec::new();
+    let mut best_size = model.size(&best);
+    let mut selected = Vec::with_capacity(num_tables);
+    for subset in 1..1usize << num_tables {
+        selected.clear();
+        selected.extend((0..num_tables).filter(|i| subset & (1 << i) != 0));
+        let size = model.size(&selected);
+        if size < best_size {
+            best_size = size;
+            best.clone_from(&selected);
+        }
+    }
+    best
+}
+
+/// Repeatedly select the table which saves the most bytes.
+fn greedy_search(model: &SizeModel) -> Vec<usize> {
+    let mut selected: Vec<usize> = Vec::new();
+    let mut size = model.size(&selected);
+    loop {
+        let best = (0..model.coverages.len())
+            .filter(|i| selected.binary_search(i).is_err())
+            .map(|i| (i, model.size_with(&selected, i)))
+            .filter(|(_, new_size)| *new_size < size)
+            // `min_by_key` keeps the first of equal elements, preferring
+            // earlier candidates.
+            .min_by_key(|(_, new_size)| *new_size);
+        let Some((i, new_size)) = best else {
+            return selected;
+        };
+        let position = selected.partition_point(|j| *j < i);
+        selected.insert(position, i);
+        size = new_size;
+    }
+}
+
+/// Drop selected tables which load too few keys that no other selected table
+/// contains to be worth referencing.
+fn prune(model: &SizeModel, selected: &mut Vec<usize>) {
+    let mut size = model.size(selected);
+    while let Some((position, new_size)) = (0..selected.len()).find_map(|position| {
+        let mut without = selected.clone();
+        without.remove(position);
+        let new_size = model.size(&without);
+        (new_size <= size).then_some((position, new_size))
+    }) {
+        selected.remove(position);
+        size = new_size;
+    }
+}
+
+/// A set of key ids, stored as a bitset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct KeySet(Vec<u64>);
+
+#[allow(clippy::arithmetic_side_effects)]
+impl KeySet {
+    fn new(num_keys: usize) -> Self {
+        Self(alloc::vec![0; num_keys.div_ceil(64)])
+    }
+
+    fn insert(&mut self, id: usize) {
+        self.0[id / 64] |= 1 << (id % 64);
+    }
+
+    fn contains(&self, id: usize) -> bool {
+        self.0[id / 64] & (1 << (id % 64)) != 0
+    }
+
+    fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    fn union_with(&mut self, other: &Self) {
+        for (word, other) in self.0.iter_mut().zip(&other.0) {
+            *word |= other;
+        }
+    }
+
+    fn is_subset(&self, other: &Self) -> bool {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .all(|(word, other)| word & !other == 0)
+    }
+
+    fn difference_with(&mut self, other: &Self) {
+        for (word, other) in self.0.iter_mut().zip(&other.0) {
+            *word &= !other;
+        }
+    }
+
+    fn count_in(&self, other: &Self) -> usize {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .map(|(word, other)| (word & other).count_ones() as usize)
+            .sum()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().flat_map(|(i, word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| i * 64 + bit)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*, crate::v0, alloc::vec, solana_hash::Hash, solana_instruction::AccountMeta,
+        solana_system_interface::instruction::advance_nonce_account,
+    };
+
+    fn readonly_accounts_instruction(accounts: &[Address]) -> Instruction {
+        Instruction::new_with_bytes(
+            Address::new_unique(),
+            &[],
+            accounts
+                .iter()
+                .map(|key| AccountMeta::new_readonly(*key, false))
+                .collect(),
+        )
+    }
+
+    fn table(addresses: &[Address]) -> AddressLookupTableAccount {
+        AddressLookupTableAccount {
+            key: Address::new_unique(),
+            addresses: addresses.to_vec(),
+        }
+    }
+
+    fn message_size(
+        payer: &Address,
+        instructions: &[Instruction],
+        tables: &[AddressLookupTableAccount],
+    ) -> usize {
+        v0::Message::try_compile(payer, instructions, tables, Hash::default())
+            .unwrap()
+            .serialize()
+            .len()
+    }
+
+    fn static_reason(selection: &LookupTableSelection, key: &Address) -> StaticKeyReason {
+        selection
+            .static_keys
+            .iter()
+            .find(|(static_key, _)| static_key == key)
+            .expect("key should be static")
+            .1
+    }
+
+    #[test]
+    fn reports_static_keys() {
+        let payer = Address::new_unique();
+        let signer = Address::new_unique();
+        let nonce = Address::new_unique();
+        let keys: Vec<Address> = (0..5).map(|_| Address::new_unique()).collect();
+        let unlisted = Address::new_unique();
+        let lonely = Address::new_unique();
+
+        let mut instruction = readonly_accounts_instruction(&keys);
+        instruction
+            .accounts
+            .push(AccountMeta::new_readonly(signer, true));
+        instruction
+            .accounts
+            .push(AccountMeta::new_readonly(unlisted, false));
+        instruction
+            .accounts
+            .push(AccountMeta::new_readonly(lonely, false));
+        let instructions = [advance_nonce_account(&nonce, &payer), instruction];
+        let program_id = instructions[1].program_id;
+
+        let selection = select_lookup_tables(
+            &payer,
+            &instructions,
+            &[
+                table(&[payer, signer, nonce, program_id, keys[0], keys[1], keys[4]]),
+                table(&[keys[2], keys[3], keys[0]]),
+                table(&[lonely]),
+            ],
+        );
+
+        assert_eq!(selection.tables.len(), 2);
+        assert_eq!(
+            selection.loaded_keys,
+            vec![vec![keys[0], keys[1], keys[4]], vec![keys[2], keys[3]]]
+        );
+        assert_eq!(static_reason(&selection, &payer), StaticKeyReason::Signer);
+        assert_eq!(static_reason(&selection, &signer), StaticKeyReason::Signer);
+        assert_eq!(
+            static_reason(&selection, &program_id),
+            StaticKeyReason::InvokedProgram
+        );
+        assert_eq!(
+            static_reason(&selection, &nonce),
+            StaticKeyReason::DurableNonce
+        );
+        assert_eq!(
+            static_reason(&selection, &unlisted),
+            StaticKeyReason::NotInLookupTables
+        );
+        assert_eq!(
+            static_reason(&selection, &lonely),
+            StaticKeyReason::Unprofitable
+        );
+    }
+
+    #[test]
+    fn loads_keys_from_selected_tables() {
+        let payer = Address::new_unique();
+        let keys: Vec<Address> = (0..6).map(|_| Address::new_unique()).collect();
+        let instructions = [readonly_accounts_instruction(&keys)];
+        let selection = select_lookup_tables(
+            &payer,
+            &instructions,
+            &[table(&keys[..3]), table(&keys[2..])],
+        );
+
+        let message =
+            v0::Message::try_compile(&payer, &instructions, &selection.tables, Hash::default())
+                .unwrap();
+        assert_eq!(
+            message.address_table_lookups.len(),
+            selection.loaded_keys.len()
+        );
+        for ((lookup, table), loaded_keys) in message
+            .address_table_lookups
+            .iter()
+            .zip(&selection.tables)
+            .zip(&selection.loaded_keys)
+        {
+            assert_eq!(lookup.account_key, table.key);
+            let looked_up: Vec<Address> = lookup
+                .readonly_indexes
+                .iter()
+                .map(|index| table.addresses[usize::from(*index)])
+                .collect();
+            assert_eq!(&looked_up, loaded_keys);
+        }
+    }
+
+    #[test]
+    fn prefers_table_covering_more_keys() {
+        let payer = Address::new_unique();
+        let keys: Vec<Address> = (0..4).map(|_| Address::new_unique()).collect();
+        let instructions = [readonly_accounts_instruction(&keys)];
+        let candidates = [
+            table(&keys[..2]),
+            table(&keys[2..]),
+            table(&[keys[3], keys[2], keys[1], keys[0]]),
+        ];
+        let selection = select_lookup_tables(&payer, &instructions, &candidates);
+
+        assert_eq!(selection.tables, vec![candidates[2].clone()]);
+    }
+
+    #[test]
+    fn skips_oversized_tables() {
+        let payer = Address::new_unique();
+        let keys: Vec<Address> = (0..4).map(|_| Address::new_unique()).collect();
+        let instructions = [readonly_accounts_instruction(&keys)];
+        let mut addresses = vec![Address::new_unique(); LOOKUP_TABLE_MAX_ADDRESSES];
+        addresses.extend_from_slice(&keys);
+        let selection = select_lookup_tables(&payer, &instructions, &[table(&addresses)]);
+
+        assert!(selection.tables.is_empty());
+        for key in &keys {
+            assert_eq!(
+                static_reason(&selection, key),
+                StaticKeyReason::NotInLookupTables
+            );
+        }
+    }
+
+    #[test]
+    fn minimizes_message_size() {
+        let payer = Address::new_unique();
+        let keys: Vec<Address> = (0..10).map(|_| Address::new_unique()).collect();
+        let instructions = [readonly_accounts_instruction(&keys)];
+        let candidates = [
+            table(&keys[0..3]),
+            table(&keys[2..6]),
+            table(&keys[5..7]),
+            table(&[keys[6], keys[7], keys[9]]),
+            table(&[keys[0], keys[8]]),
+            table(&[keys[1], keys[4], keys[7]]),
+            table(&keys[8..]),
+        ];
+
+        let smallest = (0..1usize << candidates.len())
+            .map(|subset| {
+                let tables: Vec<AddressLookupTableAccount> = candidates
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| subset & (1 << i) != 0)
+                    .map(|(_, table)| table.clone())
+                    .collect();
+                message_size(&payer, &instructions, &tables)
+            })
+            .min()
+            .unwrap();
+        let selection = select_lookup_tables(&payer, &instructions, &candidates);
+
+        assert_eq!(
+            message_size(&payer, &instructions, &selection.tables),
+            smallest
+        );
+    }
+
+    #[test]
+    fn models_short_vec_lengths() {
+        let payer = Address::new_unique();
+        let mut keys: Vec<Address> = (0..250).map(|_| Address::new_unique()).collect();
+        keys.sort_unstable();
+        let instruction = Instruction::new_with_bytes(
+            Address::new_unique(),
+            &[],
+            keys.iter()
+                .enumerate()
+                .map(|(i, key)| {
+                    if i % 3 == 0 {
+                        AccountMeta::new(*key, false)
+                    } else {
+                        AccountMeta::new_readonly(*key, false)
+                    }
+                })
+                .collect(),
+        );
+        let instructions = [instruction];
+        let candidates = [
+            table(&keys[..200]),
+            table(&keys[100..230]),
+            table(&keys[220..]),
+            table(&keys[..3]),
+        ];
+
+        let coverages: Vec<KeySet> = candidates
+            .iter()
+            .map(|candidate| {
+                let mut coverage = KeySet::new(keys.len());
+                for address in &candidate.addresses {
+                    coverage.insert(keys.binary_search(address).unwrap());
+                }
+                coverage
+            })
+            .collect();
+        let mut writable = KeySet::new(keys.len());
+        for id in (0..keys.len()).step_by(3) {
+            writable.insert(id);
+        }
+        let model = SizeModel {
+            coverages: coverages.iter().collect(),
+            writable,
+            num_keys: keys.len(),
+            num_static_keys: 2,
+        };
+
+        let overhead = message_size(&payer, &instructions, &[]) - model.size(&[]);
+        for subset in 1..1usize << candidates.len() {
+            let selected: Vec<usize> = (0..candidates.len())
+                .filter(|i| subset & (1 << i) != 0)
+                .collect();
+            let tables: Vec<AddressLookupTableAccount> =
+                selected.iter().map(|i| candidates[*i].clone()).collect();
+            assert_eq!(
+                message_size(&payer, &instructions, &tables),
+                overhead + model.size(&selected),
+            );
+        }
+    }
+
+    #[test]
+    fn searches_large_pools_greedily() {
+        let payer = Address::new_unique();
+        let keys: Vec<Address> = (0..60).map(|_| Address::new_unique()).collect();
+        let instructions = [readonly_accounts_instruction(&keys)];
+        // Pairs of keys, and one table covering most keys.
+        let mut candidates: Vec<AddressLookupTableAccount> = keys.chunks(2).map(table).collect();
+        candidates.push(table(&keys[..50]));
+        assert!(candidates.len() > MAX_EXACT_SEARCH_TABLES);
+
+        let selection = select_lookup_tables(&payer, &instructions, &candidates);
+
+        assert_eq!(selection.tables.last(), candidates.last());
+        assert_eq!(selection.tables.len(), 1 + (60 - 50) / 2);
+        assert!(selection
+            .static_keys
+            .iter()
+            .all(|(_, reason)| *reason != StaticKeyReason::Unprofitable));
+        assert!(
+            message_size(&payer, &instructions, &selection.tables)
+                < message_size(&payer, &instructions, &[])
+        );
+    }
+}
@@ -32,11 +32,14 @@ use {
     },
 };
 
+mod compile;
+mod lookup_table_selection;
 mod sanitized;
+mod size_tracker;
 pub mod v0;
 pub mod v1;
 
-pub use sanitized::*;
+pub use {compile::*, lookup_table_selection::*, sanitized::*, size_tracker::*};
 
 /// Bit mask that indicates whether a serialized message is versioned.
 pub const MESSAGE_VERSION_PREFIX: u8 = 0x80;
@@ -0,0 +1,361 @@
+//! Incremental tracking of the serialized size of a transaction.
+//!
+//! [`TransactionSizeTracker`] keeps the size and account counts of a
+//! transaction of a single [`MessageVersion`] up to date as instructions are
+//! appended, without compiling the message again.
+
+use {
+    super::compile::{address_table_lookup_size, legacy_instruction_size},
+    crate::{
+        compiled_keys::{CompiledKeyMeta, CompiledKeys},
+        v1::{self, InstructionHeader, TransactionConfig, SIGNATURE_SIZE},
+        AddressLookupTableAccount, MessageVersion, MESSAGE_HEADER_LENGTH,
+    },
+    alloc::{collections::BTreeMap, vec::Vec},
+    core::mem::size_of,
+    solana_address::Address,
+    solana_hash::Hash,
+    solana_instruction::Instruction,
+    solana_short_vec::encoded_shortu16_len_size,
+};
+
+/// Serialized size and account counts of a transaction, updated as
+/// instructions are appended.
+///
+/// Account keys are classified as by message compilation: signers, invoked
+/// programs and the durable nonce account are static account keys, and the
+/// other keys of a v0 message are loaded from the first lookup table that
+/// contains them. Legacy and v1 messages ignore the lookup tables, and only v1
+/// messages include the transaction config.
+#[derive(Debug, Clone)]
+pub struct TransactionSizeTracker<'a> {
+    version: MessageVersion,
+    payer: Address,
+    address_lookup_table_accounts: &'a [AddressLookupTableAccount],
+    config: TransactionConfig,
+    /// Index of the first lookup table containing each address, for v0
+    /// messages.
+    lookup_tables: BTreeMap<Address, usize>,
+    keys: CompiledKeys,
+    num_signers: usize,
+    num_static_keys: usize,
+    /// Number of writable and readonly keys loaded from each lookup table.
+    num_loaded_keys: Vec<(usize, usize)>,
+    num_lookups: usize,
+    lookups_size: usize,
+    num_instructions: usize,
+    instructions_size: usize,
+}
+
+impl<'a> TransactionSizeTracker<'a> {
+    /// Create a tracker for a transaction of `version` paid for by `payer`,
+    /// with no instructions.
+    pub fn new(
+        version: MessageVersion,
+        payer: Address,
+        address_lookup_table_accounts: &'a [AddressLookupTableAccount],
+        config: TransactionConfig,
+    ) -> Self {
+        let mut lookup_tables = BTreeMap::new();
+        if version == MessageVersion::V0 {
+            for (index, table) in address_lookup_table_accounts.iter().enumerate() {
+                for address in &table.addresses {
+                    lookup_tables.entry(*address).or_insert(index);
+                }
+            }
+        }
+        let mut tracker = Self {
+            version,
+            payer,
+            address_lookup_table_accounts,
+            config,
+            lookup_tables,
+            keys: CompiledKeys::default(),
+            num_signers: 0,
+            num_static_keys: 0,
+            num_loaded_keys: Vec::new(),
+            num_lookups: 0,
+            lookups_size: 0,
+            num_instructions: 0,
+            instructions_size: 0,
+        };
+        tracker.reset();
+        tracker
+    }
+
+    /// Remove all instructions from the transaction.
+    pub fn reset(&mut self) {
+        self.keys = CompiledKeys::compile(&[], Some(self.payer));
+        self.num_signers = 1;
+        self.num_static_keys = 1;
+        self.num_loaded_keys.clear();
+        self.num_loaded_keys
+            .resize(self.address_lookup_table_accounts.len(), (0, 0));
+        self.num_lookups = 0;
+        self.lookups_size = 0;
+        self.num_instructions = 0;
+        self.instructions_size = 0;
+    }
+
+    /// Append `instructions` to the transaction if it is still within the
+    /// size and account limits of its version afterwards, and return whether
+    /// they were appended.
+    ///
+    /// Only limits that depend on more than one instruction are checked: each
+    /// instruction must also be valid on its own, which is checked by compiling
+    /// it.
+    pub fn try_push(&mut self, instructions: &[Instruction]) -> bool {
+        let num_instructions = self.num_instructions;
+        let instructions_size = self.instructions_size;
+        let mut undo = Vec::new();
+        for instruction in instructions {
+            let mut updates = Vec::new();
+            self.keys
+                .add_instruction(self.num_instructions, instruction, |key, old, new| {
+                    updates.push((*key, old, new));
+                });
+            for (key, old, new) in updates {
+                if let Some(old) = old {
+                    self.count_key(&key, &old, false);
+                }
+                self.count_key(&key, &new, true);
+                undo.push((key, old, new));
+            }
+            self.num_instructions = self.num_instructions.saturating_add(1);
+            self.instructions_size = self
+                .instructions_size
+                .saturating_add(self.instruction_size(instruction));
+        }
+
+        if self.fits() {
+            return true;
+        }
+        for (key, old, new) in undo.into_iter().rev() {
+            self.count_key(&key, &new, false);
+            if let Some(old) = old {
+                self.count_key(&key, &old, true);
+            }
+            self.keys.set(key, old);
+        }
+        self.num_instructions = num_instructions;
+        self.instructions_size = instructions_size;
+        false
+    }
+
+    /// Add or remove a key with `meta` from the account counts.
+    fn count_key(&mut self, key: &Address, meta: &CompiledKeyMeta, add: bool) {
+        let update = |count: &mut usize| {
+            *count = if add {
+                count.saturating_add(1)
+            } else {
+                count.saturating_sub(1)
+            };
+        };
+        match self.lookup_tables.get(key) {
+            Some(&table) if meta.static_key_reason().is_none() => {
+                let Some(counts) = self.num_loaded_keys.get_mut(table) else {
+                    return;
+                };
+                let old_size = lookup_size(*counts);
+                if meta.is_writable {
+                    update(&mut counts.0);
+                } else {
+                    update(&mut counts.1);
+                }
+                let new_size = lookup_size(*counts);
+                if old_size == 0 && new_size != 0 {
+                    self.num_lookups = self.num_lookups.saturating_add(1);
+                } else if old_size != 0 && new_size == 0 {
+                    self.num_lookups = self.num_lookups.saturating_sub(1);
+                }
+                self.lookups_size = self
+                    .lookups_size
+                    .saturating_sub(old_size)
+                    .saturating_add(new_size);
+            }
+            _ => {
+                update(&mut self.num_static_keys);
+                if meta.is_signer {
+                    update(&mut self.num_signers);
+                }
+            }
+        }
+    }
+
+    #[allow(clippy::arithmetic_side_effects)]
+    fn instruction_size(&self, instruction: &Instruction) -> usize {
+        let num_accounts = instruction.accounts.len();
+        let data_len = instruction.data.len();
+        match self.version {
+            MessageVersion::Legacy | MessageVersion::V0 => {
+                legacy_instruction_size(num_accounts, data_len)
+            }
+            MessageVersion::V1 => size_of::<InstructionHeader>() + num_accounts + data_len,
+        }
+    }
+
+    /// Number of signatures required by the transaction.
+    pub fn num_signatures(&self) -> usize {
+        self.num_signers
+    }
+
+    /// Serialized size of the transaction, including signatures.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn transaction_size(&self) -> usize {
+        let signatures_size = self.num_signers * SIGNATURE_SIZE;
+        let account_keys_size = self.num_static_keys * size_of::<Address>();
+        match self.version {
+            MessageVersion::Legacy | MessageVersion::V0 => {
+                let lookups_size = if self.version == MessageVersion::V0 {
+                    size_of::<u8>() // version prefix
+                        + encoded_shortu16_len_size(self.num_lookups)
+                        + self.lookups_size
+                } else {
+                    0
+                };
+                encoded_shortu16_len_size(self.num_signers)
+                    + signatures_size
+                    + MESSAGE_HEADER_LENGTH
+                    + encoded_shortu16_len_size(self.num_static_keys)
+                    + account_keys_size
+                    + size_of::<Hash>()
+                    + encoded_shortu16_len_size(self.num_instructions)
+                    + self.instructions_size
+                    + lookups_size
+            }
+            MessageVersion::V1 => {
+                size_of::<u8>() // version prefix
+                    + v1::FIXED_HEADER_SIZE
+                    + account_keys_size
+                    + self.config.size()
+                    + self.instructions_size
+                    + signatures_size
+            }
+        }
+    }
+
+    /// Returns true if the transaction is within the size and account limits
+    /// of its version.
+    fn fits(&self) -> bool {
+        let num_loaded_keys = self
+            .num_loaded_keys
+            .iter()
+            .fold(0usize, |sum, (writable, readonly)| {
+                sum.saturating_add(*writable).saturating_add(*readonly)
+            });
+        let within_account_limits = match self.version {
+            MessageVersion::Legacy | MessageVersion::V0 => {
+                self.num_static_keys.saturating_add(num_loaded_keys) <= 256
+            }
+            MessageVersion::V1 => {
+                self.num_static_keys <= usize::from(v1::MAX_ADDRESSES)
+                    && self.num_instructions <= usize::from(v1::MAX_INSTRUCTIONS)
+                    && self.num_signers <= usize::from(v1::MAX_SIGNATURES)
+            }
+        };
+        within_account_limits && self.transaction_size() <= self.version.max_transaction_size()
+    }
+}
+
+/// Serialized size of an address table lookup loading `(writable, readonly)`
+/// keys, or zero if it loads none.
+fn lookup_size((writable, readonly): (usize, usize)) -> usize {
+    if writable == 0 && readonly == 0 {
+        return 0;
+    }
+    address_table_lookup_size(writable, readonly)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        alloc::vec,
+        solana_instruction::AccountMeta,
+        solana_system_interface::instruction::{advance_nonce_account, transfer},
+    };
+
+    fn transfers(payer: &Address, count: usize) -> Vec<Instruction> {
+        (0..count)
+            .map(|_| transfer(payer, &Address::new_unique(), 1))
+            .collect()
+    }
+
+    #[test]
+    fn tracks_compiled_size() {
+        let payer = Address::new_unique();
+        let nonce = Address::new_unique();
+        let mut instructions = vec![advance_nonce_account(&nonce, &payer)];
+        instructions.extend(transfers(&payer, 3));
+        instructions.push(transfer(&Address::new_unique(), &payer, 1));
+        instructions.push(Instruction::new_with_bytes(
+            Address::new_unique(),
+            &[0; 200],
+            vec![AccountMeta::new_readonly(
+                instructions[1].accounts[1].pubkey,
+                false,
+            )],
+        ));
+        let tables = [AddressLookupTableAccount {
+            key: Address::new_unique(),
+            addresses: vec![nonce, instructions[2].accounts[1].pubkey],
+        }];
+
+        for version in MessageVersion::ALL {
+            let mut tracker =
+                TransactionSizeTracker::new(version, payer, &tables, TransactionConfig::empty());
+            for end in 1..=instructions.len() {
+                assert!(tracker.try_push(&instructions[end - 1..end]));
+                let (message, size) = version
+                    .try_compile(
+                        &payer,
+                        &instructions[..end],
+                        &tables,
+                        Hash::default(),
+                        TransactionConfig::empty(),
+                    )
+                    .unwrap();
+                assert_eq!(tracker.transaction_size(), size, "{version:?} {end}");
+                assert_eq!(
+                    tracker.num_signatures(),
+                    usize::from(message.header().num_required_signatures)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_instructions_over_the_limit() {
+        let payer = Address::new_unique();
+        let instructions = transfers(&payer, 40);
+        let mut tracker = TransactionSizeTracker::new(
+            MessageVersion::Legacy,
+            payer,
+            &[],
+            TransactionConfig::empty(),
+        );
+        let fitting = instructions
+            .iter()
+            .take_while(|instruction| tracker.try_push(core::slice::from_ref(*instruction)))
+            .count();
+        assert!(fitting < instructions.len());
+
+        // A rejected push leaves the transaction as it was.
+        let size = tracker.transaction_size();
+        assert!(!tracker.try_push(&instructions[fitting..]));
+        assert_eq!(tracker.transaction_size(), size);
+        let (_, compiled_size) = MessageVersion::Legacy
+            .try_compile(
+                &payer,
+                &instructions[..fitting],
+                &[],
+                Hash::default(),
+                TransactionConfig::empty(),
+            )
+            .unwrap();
+        assert_eq!(size, compiled_size);
+
+        tracker.reset();
+        assert!(tracker.try_push(&instructions[fitting..]));
+    }
+}
@@ -210,6 +210,13 @@ impl Message {
     /// `recent_blockhash`, list of `instructions`, and a list of
     /// `address_lookup_table_accounts`.
     ///
+    /// Each non-signer, non-program key is loaded from the first table that
+    /// contains it, and every table that loads a key is referenced. Use
+    /// [`select_lookup_tables`] to choose the tables which minimize the message
+    /// size from a larger pool.
+    ///
+    /// [`select_lookup_tables`]: crate::select_lookup_tables
+    ///
     /// # Examples
     ///
     /// This example uses the [`solana_rpc_client`], [`solana_account`], and [`anyhow`] crates.
@@ -3,6 +3,7 @@
 use {
     num_enum::{IntoPrimitive, TryFromPrimitive},
     solana_hash::Hash,
+    solana_pubkey::Pubkey,
     solana_sanitize::SanitizeError,
     solana_signature::Signature,
     solana_signer::Signer,
@@ -14,6 +15,8 @@ static_assertions::const_assert_eq!(OffchainMessage::HEADER_LEN, 17);
 static_assertions::const_assert_eq!(v0::OffchainMessage::MAX_LEN, 65515);
 #[cfg(test)]
 static_assertions::const_assert_eq!(v0::OffchainMessage::MAX_LEN_LEDGER, 1212);
+#[cfg(test)]
+static_assertions::const_assert_eq!(v1::OffchainMessage::HEADER_LEN, 36);
 
 /// Check if given bytes contain only printable ASCII characters
 pub fn is_printable_ascii(data: &[u8]) -> bool {
@@ -158,9 +161,225 @@ pub mod v0 {
     }
 }
 
+#[allow(clippy::arithmetic_side_effects)]
+pub mod v1 {
+    use {
+        super::{is_printable_ascii, is_utf8, MessageFormat, OffchainMessage as Base},
+        solana_hash::Hash,
+        solana_packet::PACKET_DATA_SIZE,
+        solana_pubkey::Pubkey,
+        solana_sanitize::SanitizeError,
+        solana_sha256_hasher::Hasher,
+    };
+
+    /// OffchainMessage Version 1.
+    /// Binds the message to an application domain and to the list of
+    /// signers expected to sign it. Struct always contains a non-empty valid
+    /// message and between 1 and 255 distinct signers.
+    ///
+    /// The layout after the signing domain and header version is the message
+    /// preamble of the Agave off-chain message signing proposal
+    /// (`docs/src/proposals/off-chain-message-signing.md`):
+    ///
+    /// | Field              | Length (bytes)    |
+    /// |--------------------|-------------------|
+    /// | Application domain | 32                |
+    /// | Message format     | 1                 |
+    /// | Signer count       | 1                 |
+    /// | Signers            | 32 * signer count |
+    /// | Message length     | 2                 |
+    /// | Message            | message length    |
+    ///
+    /// The proposal numbers this header version 0, but header version 0 is
+    /// already used by the earlier layout without an application domain and
+    /// signers, implemented by [`v0::OffchainMessage`] and by deployed
+    /// wallets. This layout is serialized with header version 1 so that both
+    /// can be decoded unambiguously.
+    ///
+    /// [`v0::OffchainMessage`]: super::v0::OffchainMessage
+    #[derive(Debug, PartialEq, Eq, Clone)]
+    pub struct OffchainMessage {
+        application_domain: [u8; 32],
+        format: MessageFormat,
+        signers: Vec<Pubkey>,
+        message: Vec<u8>,
+    }
+
+    impl OffchainMessage {
+        // Header Length = Application Domain (32) + Message Format (1) + Signer Count (1)
+        //               + Message Length (2), excluding the signers
+        pub const HEADER_LEN: usize = 36;
+        pub const MAX_SIGNERS: usize = u8::MAX as usize;
+
+        /// Max length of the message signed by `num_signers` signers
+        pub const fn max_len(num_signers: usize) -> usize {
+            (u16::MAX as usize)
+                .saturating_sub(Base::HEADER_LEN + Self::HEADER_LEN)
+                .saturating_sub(num_signers.saturating_mul(32))
+        }
+
+        /// Max length of the message signed by `num_signers` signers
+        /// supported by the Ledger
+        pub const fn max_len_ledger(num_signers: usize) -> usize {
+            PACKET_DATA_SIZE
+                .saturating_sub(Base::HEADER_LEN + Self::HEADER_LEN)
+                .saturating_sub(num_signers.saturating_mul(32))
+        }
+
+        /// Construct a new OffchainMessage object for the given application
+        /// domain, signers and message
+        pub fn new(
+            application_domain: [u8; 32],
+            signers: &[Pubkey],
+            message: &[u8],
+        ) -> Result<Self, SanitizeError> {
+            Self::check_signers(signers)?;
+            let format = if message.is_empty() {
+                return Err(SanitizeError::InvalidValue);
+            } else if message.len() <= Self::max_len_ledger(signers.len()) {
+                if is_printable_ascii(message) {
+                    MessageFormat::RestrictedAscii
+                } else if is_utf8(message) {
+                    MessageFormat::LimitedUtf8
+                } else {
+                    return Err(SanitizeError::InvalidValue);
+                }
+            } else if message.len() <= Self::max_len(signers.len()) {
+                if is_utf8(message) {
+                    MessageFormat::ExtendedUtf8
+                } else {
+                    return Err(SanitizeError::InvalidValue);
+                }
+            } else {
+                return Err(SanitizeError::ValueOutOfBounds);
+            };
+            Ok(Self {
+                application_domain,
+                format,
+                signers: signers.to_vec(),
+                message: message.to_vec(),
+            })
+        }
+
+        fn check_signers(signers: &[Pubkey]) -> Result<(), SanitizeError> {
+            if signers.is_empty() || signers.len() > Self::MAX_SIGNERS {
+                return Err(SanitizeError::ValueOutOfBounds);
+            }
+            for (i, signer) in signers.iter().enumerate() {
+                if signers[..i].contains(signer) {
+                    return Err(SanitizeError::InvalidValue);
+                }
+            }
+            Ok(())
+        }
+
+        /// Serialize the message to bytes, including the full header
+        pub fn serialize(&self, data: &mut Vec<u8>) -> Result<(), SanitizeError> {
+            // invalid messages shouldn't be possible, but a quick sanity check never hurts
+            assert!(
+                !self.message.is_empty() && self.message.len() <= Self::max_len(self.signers.len())
+            );
+            data.reserve(Self::HEADER_LEN + self.signers.len() * 32 + self.message.len());
+            // application domain
+            data.extend_from_slice(&self.application_domain);
+            // format
+            data.push(self.format.into());
+            // signers
+            data.push(self.signers.len() as u8);
+            for signer in &self.signers {
+                data.extend_from_slice(signer.as_ref());
+            }
+            // message length
+            data.extend_from_slice(&(self.message.len() as u16).to_le_bytes());
+            // message
+            data.extend_from_slice(&self.message);
+            Ok(())
+        }
+
+        /// Deserialize the message from bytes that include a full header
+        pub fn deserialize(data: &[u8]) -> Result<Self, SanitizeError> {
+            // validate data length
+            if data.len() <= Self::HEADER_LEN || data.len() > u16::MAX as usize - Base::HEADER_LEN {
+                return Err(SanitizeError::ValueOutOfBounds);
+            }
+            // decode header
+            let application_domain: [u8; 32] = data[..32]
+                .try_into()
+                .map_err(|_| SanitizeError::ValueOutOfBounds)?;
+            let format =
+                MessageFormat::try_from(data[32]).map_err(|_| SanitizeError::InvalidValue)?;
+            let num_signers = usize::from(data[33]);
+            let signers_end = 34 + num_signers * 32;
+            if data.len() <= signers_end + 2 {
+                return Err(SanitizeError::ValueOutOfBounds);
+            }
+            let signers = data[34..signers_end]
+                .chunks_exact(32)
+                .map(|signer| Pubkey::try_from(signer).map_err(|_| SanitizeError::InvalidValue))
+                .collect::<Result<Vec<_>, _>>()?;
+            Self::check_signers(&signers)?;
+            let message_len =
+                u16::from_le_bytes([data[signers_end], data[signers_end + 1]]) as usize;
+            // check header
+            if signers_end + 2 + message_len != data.len() {
+                return Err(SanitizeError::InvalidValue);
+            }
+            let message = &data[signers_end + 2..];
+            // check format
+            let is_valid = match format {
+                MessageFormat::RestrictedAscii => {
+                    (message.len() <= Self::max_len_ledger(num_signers))
+                        && is_printable_ascii(message)
+                }
+                MessageFormat::LimitedUtf8 => {
+                    (message.len() <= Self::max_len_ledger(num_signers)) && is_utf8(message)
+                }
+                MessageFormat::ExtendedUtf8 => {
+                    (message.len() <= Self::max_len(num_signers)) && is_utf8(message)
+                }
+            };
+
+            if is_valid {
+                Ok(Self {
+                    application_domain,
+                    format,
+                    signers,
+                    message: message.to_vec(),
+                })
+            } else {
+                Err(SanitizeError::InvalidValue)
+            }
+        }
+
+        /// Compute the SHA256 hash of the serialized off-chain message
+        pub fn hash(serialized_message: &[u8]) -> Result<Hash, SanitizeError> {
+            let mut hasher = Hasher::default();
+            hasher.hash(serialized_message);
+            Ok(hasher.result())
+        }
+
+        pub fn get_application_domain(&self) -> &[u8; 32] {
+            &self.application_domain
+        }
+
+        pub fn get_format(&self) -> MessageFormat {
+            self.format
+        }
+
+        pub fn get_signers(&self) -> &[Pubkey] {
+            &self.signers
+        }
+
+        pub fn get_message(&self) -> &Vec<u8> {
+            &self.message
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum OffchainMessage {
     V0(v0::OffchainMessage),
+    V1(v1::OffchainMessage),
 }
 
 impl OffchainMessage {
@@ -176,6 +395,20 @@ impl OffchainMessage {
         }
     }
 
+    /// Construct a new version 1 OffchainMessage object, to be signed by
+    /// every one of `signers`
+    pub fn new_with_signers(
+        application_domain: [u8; 32],
+        signers: &[Pubkey],
+        message: &[u8],
+    ) -> Result<Self, SanitizeError> {
+        Ok(Self::V1(v1::OffchainMessage::new(
+            application_domain,
+            signers,
+            message,
+        )?))
+    }
+
     /// Serialize the off-chain message to bytes including full header
     pub fn serialize(&self) -> Result<Vec<u8>, SanitizeError> {
         // serialize signing domain
@@ -187,6 +420,10 @@ impl OffchainMessage {
                 data.push(0);
                 msg.serialize(&mut data)?;
             }
+            Self::V1(msg) => {
+                data.push(1);
+                msg.serialize(&mut data)?;
+            }
         }
         Ok(data)
     }
@@ -200,6 +437,7 @@ impl OffchainMessage {
         let data = &data[Self::SIGNING_DOMAIN.len().saturating_add(1)..];
         match version {
             0 => Ok(Self::V0(v0::OffchainMessage::deserialize(data)?)),
+            1 => Ok(Self::V1(v1::OffchainMessage::deserialize(data)?)),
             _ => Err(SanitizeError::ValueOutOfBounds),
         }
     }
@@ -208,41 +446,84 @@ impl OffchainMessage {
     pub fn hash(&self) -> Result<Hash, SanitizeError> {
         match self {
             Self::V0(_) => v0::OffchainMessage::hash(&self.serialize()?),
+            Self::V1(_) => v1::OffchainMessage::hash(&self.serialize()?),
         }
     }
 
     pub fn get_version(&self) -> u8 {
         match self {
             Self::V0(_) => 0,
+            Self::V1(_) => 1,
         }
     }
 
     pub fn get_format(&self) -> MessageFormat {
         match self {
             Self::V0(msg) => msg.get_format(),
+            Self::V1(msg) => msg.get_format(),
         }
     }
 
     pub fn get_message(&self) -> &Vec<u8> {
         match self {
             Self::V0(msg) => msg.get_message(),
+            Self::V1(msg) => msg.get_message(),
+        }
+    }
+
+    /// Application domain of the message, only set from version 1
+    pub fn get_application_domain(&self) -> Option<&[u8; 32]> {
+        match self {
+            Self::V0(_) => None,
+            Self::V1(msg) => Some(msg.get_application_domain()),
+        }
+    }
+
+    /// Signers required to sign the message, only set from version 1
+    pub fn get_signers(&self) -> Option<&[Pubkey]> {
+        match self {
+            Self::V0(_) => None,
+            Self::V1(msg) => Some(msg.get_signers()),
         }
     }
 
-    /// Sign the message with provided keypair
+    /// Sign the message with provided keypair. From version 1, the keypair
+    /// must be one of the message signers
     pub fn sign(&self, signer: &dyn Signer) -> Result<Signature, SanitizeError> {
+        if let Some(signers) = self.get_signers() {
+            if !signers.contains(&signer.pubkey()) {
+                return Err(SanitizeError::InvalidValue);
+            }
+        }
         Ok(signer.sign_message(&self.serialize()?))
     }
 
     #[cfg(feature = "verify")]
-    /// Verify that the message signature is valid for the given public key
-    pub fn verify(
-        &self,
-        signer: &solana_pubkey::Pubkey,
-        signature: &Signature,
-    ) -> Result<bool, SanitizeError> {
+    /// Verify that the message signature is valid for the given public key.
+    /// From version 1, the public key must be one of the message signers
+    pub fn verify(&self, signer: &Pubkey, signature: &Signature) -> Result<bool, SanitizeError> {
+        if let Some(signers) = self.get_signers() {
+            if !signers.contains(signer) {
+                return Ok(false);
+            }
+        }
         Ok(signature.verify(signer.as_ref(), &self.serialize()?))
     }
+
+    #[cfg(feature = "verify")]
+    /// Verify the signatures of every signer of a version 1 message, given in
+    /// the same order as the signers
+    pub fn verify_signatures(&self, signatures: &[Signature]) -> Result<bool, SanitizeError> {
+        let signers = self.get_signers().ok_or(SanitizeError::InvalidValue)?;
+        if signatures.len() != signers.len() {
+            return Err(SanitizeError::InvalidValue);
+        }
+        let data = self.serialize()?;
+        Ok(signers
+            .iter()
+            .zip(signatures)
+            .all(|(signer, signature)| signature.verify(signer.as_ref(), &data)))
+    }
 }
 
 #[cfg(test)]
@@ -299,4 +580,152 @@ mod tests {
         let signature = message.sign(&keypair).unwrap();
         assert!(message.verify(&keypair.pubkey(), &signature).unwrap());
     }
+
+    #[test]
+    fn test_offchain_message_v1() {
+        let signers = [
+            Pubkey::new_from_array([1; 32]),
+            Pubkey::new_from_array([2; 32]),
+        ];
+        let message = OffchainMessage::new_with_signers([7; 32], &signers, b"Login").unwrap();
+        assert_eq!(message.get_version(), 1);
+        assert_eq!(message.get_format(), MessageFormat::RestrictedAscii);
+        assert_eq!(message.get_message().as_slice(), b"Login");
+        assert_eq!(message.get_application_domain(), Some(&[7; 32]));
+        assert_eq!(message.get_signers(), Some(signers.as_slice()));
+
+        let serialized = message.serialize().unwrap();
+        let mut expected = OffchainMessage::SIGNING_DOMAIN.to_vec();
+        expected.push(1);
+        expected.extend_from_slice(&[7; 32]);
+        expected.extend_from_slice(&[0, 2]);
+        expected.extend_from_slice(&[1; 32]);
+        expected.extend_from_slice(&[2; 32]);
+        expected.extend_from_slice(&[5, 0]);
+        expected.extend_from_slice(b"Login");
+        assert_eq!(serialized, expected);
+        assert_eq!(message, OffchainMessage::deserialize(&serialized).unwrap());
+        assert_eq!(
+            message.hash().unwrap(),
+            v0::OffchainMessage::hash(&serialized).unwrap()
+        );
+
+        // Truncated signers or message length
+        assert!(
+            OffchainMessage::deserialize(&serialized[..OffchainMessage::HEADER_LEN + 40]).is_err()
+        );
+        let mut bad_len = serialized.clone();
+        bad_len[OffchainMessage::HEADER_LEN + 34 + 64] = 6;
+        assert!(OffchainMessage::deserialize(&bad_len).is_err());
+        // Signers must be distinct
+        let mut duplicate = serialized.clone();
+        duplicate[OffchainMessage::HEADER_LEN + 34 + 32..OffchainMessage::HEADER_LEN + 34 + 64]
+            .copy_from_slice(&[1; 32]);
+        assert_eq!(
+            OffchainMessage::deserialize(&duplicate),
+            Err(SanitizeError::InvalidValue)
+        );
+    }
+
+    #[test]
+    fn test_offchain_message_v1_limits() {
+        let signer = Pubkey::new_from_array([1; 32]);
+        assert_eq!(
+            OffchainMessage::new_with_signers([0; 32], &[], b"Test"),
+            Err(SanitizeError::ValueOutOfBounds)
+        );
+        assert_eq!(
+            OffchainMessage::new_with_signers([0; 32], &[signer, signer], b"Test"),
+            Err(SanitizeError::InvalidValue)
+        );
+        let too_many: Vec<_> = (0..=255u16)
+            .map(|i| {
+                let mut bytes = [0; 32];
+                bytes[..2].copy_from_slice(&i.to_le_bytes());
+                Pubkey::new_from_array(bytes)
+            })
+            .collect();
+        assert!(OffchainMessage::new_with_signers([0; 32], &too_many[..255], b"Test").is_ok());
+        assert_eq!(
+            OffchainMessage::new_with_signers([0; 32], &too_many, b"Test"),
+            Err(SanitizeError::ValueOutOfBounds)
+        );
+        assert_eq!(
+            OffchainMessage::new_with_signers([0; 32], &[signer], b""),
+            Err(SanitizeError::InvalidValue)
+        );
+
+        // Each signer takes room from the message
+        let max_ledger = v1::OffchainMessage::max_len_ledger(2);
+        assert_eq!(max_ledger, 1232 - 17 - 36 - 64);
+        let signers = &too_many[..2];
+        let message =
+            OffchainMessage::new_with_signers([0; 32], signers, &vec![b'a'; max_ledger]).unwrap();
+        assert_eq!(message.get_format(), MessageFormat::RestrictedAscii);
+        let message =
+            OffchainMessage::new_with_signers([0; 32], signers, &vec![b'a'; max_ledger + 1])
+                .unwrap();
+        assert_eq!(message.get_format(), MessageFormat::ExtendedUtf8);
+        let max = v1::OffchainMessage::max_len(2);
+        let message =
+            OffchainMessage::new_with_signers([0; 32], signers, &vec![b'a'; max]).unwrap();
+        assert_eq!(message.serialize().unwrap().len(), usize::from(u16::MAX));
+        assert_eq!(
+            OffchainMessage::new_with_signers([0; 32], signers, &vec![b'a'; max + 1]),
+            Err(SanitizeError::ValueOutOfBounds)
+        );
+    }
+
+    #[test]
+    fn test_offchain_message_v1_sign_and_verify() {
+        let alice = Keypair::new();
+        let bob = Keypair::new();
+        let mallory = Keypair::new();
+        let message = OffchainMessage::new_with_signers(
+            [7; 32],
+            &[alice.pubkey(), bob.pubkey()],
+            "Attestation ✓".as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(message.get_format(), MessageFormat::LimitedUtf8);
+
+        let alice_signature = message.sign(&alice).unwrap();
+        let bob_signature = message.sign(&bob).unwrap();
+        assert!(message.verify(&alice.pubkey(), &alice_signature).unwrap());
+        assert!(message.verify(&bob.pubkey(), &bob_signature).unwrap());
+        assert!(!message.verify(&bob.pubkey(), &alice_signature).unwrap());
+        assert!(message
+            .verify_signatures(&[alice_signature, bob_signature])
+            .unwrap());
+        assert!(!message
+            .verify_signatures(&[bob_signature, alice_signature])
+            .unwrap());
+        assert_eq!(
+            message.verify_signatures(&[alice_signature]),
+            Err(SanitizeError::InvalidValue)
+        );
+
+        // Only listed signers may sign
+        assert_eq!(message.sign(&mallory), Err(SanitizeError::InvalidValue));
+        let signature = mallory.sign_message(&message.serialize().unwrap());
+        assert!(!message.verify(&mallory.pubkey(), &signature).unwrap());
+
+        // Signatures are bound to the application domain
+        let other_domain = OffchainMessage::new_with_signers(
+            [8; 32],
+            &[alice.pubkey(), bob.pubkey()],
+            "Attestation ✓".as_bytes(),
+        )
+        .unwrap();
+        assert!(!other_domain
+            .verify(&alice.pubkey(), &alice_signature)
+            .unwrap());
+
+        // Version 0 messages have no signer list
+        let v0 = OffchainMessage::new(0, b"Test Message").unwrap();
+        assert_eq!(
+            v0.verify_signatures(&[alice_signature]),
+            Err(SanitizeError::InvalidValue)
+        );
+    }
 }
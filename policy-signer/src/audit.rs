@@ -0,0 +1,121 @@
+//! Audit log of signing decisions.
+
+use {
+    crate::PolicyViolation,
+    solana_hash::Hash,
+    solana_pubkey::Pubkey,
+    std::{
+        fmt,
+        io::{self, Write},
+        sync::Mutex,
+        time::{SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// Outcome of checking a message against the policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditDecision {
+    /// The message complied with the policy and was passed to the signer.
+    Approved,
+    /// The message violated the policy and was not signed.
+    Rejected(PolicyViolation),
+}
+
+/// One entry of the audit log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// When the decision was made.
+    pub timestamp: SystemTime,
+    /// Pubkey of the wrapped signer.
+    pub signer: Pubkey,
+    /// Blake3 hash of the message bytes, as in `VersionedMessage::hash`.
+    pub message_hash: Hash,
+    pub decision: AuditDecision,
+}
+
+/// Formats the entry as a single line:
+/// `<unix timestamp> <signer> <message hash> approved|rejected: <reason>`.
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let timestamp = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        write!(f, "{timestamp} {} {} ", self.signer, self.message_hash)?;
+        match &self.decision {
+            AuditDecision::Approved => f.write_str("approved"),
+            AuditDecision::Rejected(violation) => write!(f, "rejected: {violation}"),
+        }
+    }
+}
+
+/// Destination of audit entries.
+pub trait AuditLog: Send + Sync {
+    /// Appends `entry` to the log.
+    ///
+    /// [`PolicySigner`](crate::PolicySigner) refuses to sign when this
+    /// fails, so an approval is never acted on without being recorded.
+    fn append(&self, entry: AuditEntry) -> io::Result<()>;
+}
+
+/// Audit log kept in memory.
+#[derive(Debug, Default)]
+pub struct MemoryAuditLog {
+    entries: Mutex<Vec<AuditEntry>>,
+}
+
+impl MemoryAuditLog {
+    /// Returns a copy of the entries appended so far.
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.entries
+            .lock()
+            .map(|entries| entries.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl AuditLog for MemoryAuditLog {
+    fn append(&self, entry: AuditEntry) -> io::Result<()> {
+        self.entries
+            .lock()
+            .map_err(|_| io::Error::other("audit log lock poisoned"))?
+            .push(entry);
+        Ok(())
+    }
+}
+
+/// Audit log writing one line per entry to a file or any other writer.
+///
+/// Every entry is flushed before `append` returns, and write errors are
+/// returned to the caller.
+#[derive(Debug)]
+pub struct WriterAuditLog<W> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> WriterAuditLog<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    /// Returns the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<W: Write + Send> AuditLog for WriterAuditLog<W> {
+    fn append(&self, entry: AuditEntry) -> io::Result<()> {
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|_| io::Error::other("audit log lock poisoned"))?;
+        writeln!(writer, "{entry}")?;
+        writer.flush()
+    }
+}
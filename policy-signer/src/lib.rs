@@ -0,0 +1,697 @@
+//! A `Signer` wrapper that enforces a signing policy.
+//!
+//! [`PolicySigner`] decodes every message it is asked to sign as a
+//! `VersionedMessage` and checks it against a [`SigningPolicy`] before
+//! passing it to the wrapped signer. Every decision, approved or rejected,
+//! is appended to an [`AuditLog`], and nothing is signed if that fails.
+//!
+//! [`PolicySigner::try_sign_message_checked`] reports why a message was not
+//! signed as a [`PolicySignerError`]. The `Signer` implementation can only
+//! return a [`SignerError`], which has no variant for policy violations or
+//! audit log failures, so it reports them as [`SignerError::Custom`] with the
+//! formatted error.
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+pub mod audit;
+
+pub use audit::{AuditDecision, AuditEntry, AuditLog, MemoryAuditLog, WriterAuditLog};
+use {
+    solana_borsh::v1::try_from_slice_unchecked,
+    solana_compute_budget_interface::ComputeBudgetInstruction,
+    solana_message::{inline_nonce::is_advance_nonce_instruction_data, VersionedMessage},
+    solana_pubkey::Pubkey,
+    solana_sdk_ids::{compute_budget, system_program},
+    solana_signature::Signature,
+    solana_signer::{Signer, SignerError},
+    solana_system_interface::instruction::SystemInstruction,
+    std::{collections::HashSet, fmt, io, sync::Arc, time::SystemTime},
+};
+
+const MICRO_LAMPORTS_PER_LAMPORT: u64 = 1_000_000;
+
+/// Rules a message must follow to be signed.
+///
+/// The default policy allows everything; each field restricts it further.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SigningPolicy {
+    /// Programs the message may invoke, or `None` to allow any program.
+    pub allowed_programs: Option<HashSet<Pubkey>>,
+    /// Maximum total lamports moved by system program `Transfer` and
+    /// `TransferWithSeed` instructions.
+    pub max_lamports_transferred: Option<u64>,
+    /// Accounts the message must not write to.
+    pub forbidden_writable_accounts: HashSet<Pubkey>,
+    /// Maximum compute unit price in micro-lamports. For v1 messages, the
+    /// price is derived from the priority fee and compute unit limit.
+    ///
+    /// A v1 message without a compute unit limit gets a default limit that
+    /// depends on which of its programs the runtime counts as builtins, which
+    /// the policy cannot determine, so a v1 message setting a priority fee must
+    /// also set a compute unit limit.
+    pub max_compute_unit_price: Option<u64>,
+    /// Whether the message must use a durable nonce.
+    pub require_durable_nonce: bool,
+}
+
+/// Reason a message was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyViolation {
+    /// The bytes to sign are not a valid `VersionedMessage`.
+    UndecodableMessage,
+    /// The message invokes a program that is not allowed.
+    ProgramNotAllowed(Pubkey),
+    /// The message transfers more lamports than allowed.
+    TransferLimitExceeded { lamports: u64, max: u64 },
+    /// The message writes to a forbidden account.
+    WritableAccountForbidden(Pubkey),
+    /// The message writes to accounts loaded from address lookup tables,
+    /// which cannot be checked against the forbidden accounts.
+    UnresolvedWritableAccounts,
+    /// The compute unit price is above the maximum.
+    ComputeUnitPriceExceeded { micro_lamports: u64, max: u64 },
+    /// The v1 message sets a priority fee without a compute unit limit, so
+    /// its compute unit price cannot be determined.
+    ComputeUnitLimitRequired,
+    /// The message does not use a durable nonce.
+    DurableNonceRequired,
+}
+
+impl std::error::Error for PolicyViolation {}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UndecodableMessage => f.write_str("message is not a valid transaction message"),
+            Self::ProgramNotAllowed(program_id) => write!(f, "program {program_id} is not allowed"),
+            Self::TransferLimitExceeded { lamports, max } => write!(
+                f,
+                "transfers of {lamports} lamports exceed the maximum of {max}"
+            ),
+            Self::WritableAccountForbidden(account) => {
+                write!(f, "writing to account {account} is forbidden")
+            }
+            Self::UnresolvedWritableAccounts => {
+                f.write_str("writable accounts loaded from lookup tables cannot be checked")
+            }
+            Self::ComputeUnitPriceExceeded {
+                micro_lamports,
+                max,
+            } => write!(
+                f,
+                "compute unit price of {micro_lamports} micro-lamports exceeds the maximum of \
+                 {max}"
+            ),
+            Self::ComputeUnitLimitRequired => {
+                f.write_str("message sets a priority fee without a compute unit limit")
+            }
+            Self::DurableNonceRequired => f.write_str("message does not use a durable nonce"),
+        }
+    }
+}
+
+/// Reason a [`PolicySigner`] did not sign a message.
+#[derive(Debug)]
+pub enum PolicySignerError {
+    /// The message does not comply with the signing policy.
+    PolicyViolation(PolicyViolation),
+    /// The decision could not be appended to the audit log.
+    AuditLog(io::Error),
+    /// The wrapped signer failed.
+    Signer(SignerError),
+}
+
+impl std::error::Error for PolicySignerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::PolicyViolation(violation) => Some(violation),
+            Self::AuditLog(err) => Some(err),
+            Self::Signer(err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for PolicySignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PolicyViolation(violation) => write!(f, "signing policy violation: {violation}"),
+            Self::AuditLog(err) => write!(f, "audit log failed: {err}"),
+            Self::Signer(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<PolicyViolation> for PolicySignerError {
+    fn from(violation: PolicyViolation) -> Self {
+        Self::PolicyViolation(violation)
+    }
+}
+
+impl From<PolicySignerError> for SignerError {
+    fn from(err: PolicySignerError) -> Self {
+        match err {
+            PolicySignerError::Signer(err) => err,
+            err => SignerError::Custom(err.to_string()),
+        }
+    }
+}
+
+impl SigningPolicy {
+    /// Checks `message` against the policy.
+    pub fn check(&self, message: &VersionedMessage) -> Result<(), PolicyViolation> {
+        let account_keys = message.static_account_keys();
+        let program_id =
+            |program_id_index: u8| account_keys.get(usize::from(program_id_index)).copied();
+
+        if let Some(allowed_programs) = &self.allowed_programs {
+            for instruction in message.instructions() {
+                let program_id = program_id(instruction.program_id_index)
+                    .ok_or(PolicyViolation::UndecodableMessage)?;
+                if !allowed_programs.contains(&program_id) {
+                    return Err(PolicyViolation::ProgramNotAllowed(program_id));
+                }
+            }
+        }
+
+        if let Some(max) = self.max_lamports_transferred {
+            let lamports = message
+                .instructions()
+                .iter()
+                .filter(|ix| program_id(ix.program_id_index) == Some(system_program::id()))
+                .filter_map(|ix| match wincode::deserialize(&ix.data) {
+                    Ok(SystemInstruction::Transfer { lamports })
+                    | Ok(SystemInstruction::TransferWithSeed { lamports, .. }) => Some(lamports),
+                    _ => None,
+                })
+                .fold(0u64, u64::saturating_add);
+            if lamports > max {
+                return Err(PolicyViolation::TransferLimitExceeded { lamports, max });
+            }
+        }
+
+        if !self.forbidden_writable_accounts.is_empty() {
+            for (index, account) in account_keys.iter().enumerate() {
+                if self.forbidden_writable_accounts.contains(account)
+                    && message.is_maybe_writable(index, None)
+                {
+                    return Err(PolicyViolation::WritableAccountForbidden(*account));
+                }
+            }
+            if message
+                .address_table_lookups()
+                .unwrap_or_default()
+                .iter()
+                .any(|lookup| !lookup.writable_indexes.is_empty())
+            {
+                return Err(PolicyViolation::UnresolvedWritableAccounts);
+            }
+        }
+
+        if let Some(max) = self.max_compute_unit_price {
+            let micro_lamports = compute_unit_price(message)?;
+            if micro_lamports > max {
+                return Err(PolicyViolation::ComputeUnitPriceExceeded {
+                    micro_lamports,
+                    max,
+                });
+            }
+        }
+
+        if self.require_durable_nonce && !uses_durable_nonce(message) {
+            return Err(PolicyViolation::DurableNonceRequired);
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the highest compute unit price set by the message, in
+/// micro-lamports.
+fn compute_unit_price(message: &VersionedMessage) -> Result<u64, PolicyViolation> {
+    let account_keys = message.static_account_keys();
+    let instruction_price = message
+        .instructions()
+        .iter()
+        .filter(|ix| {
+            account_keys.get(usize::from(ix.program_id_index)) == Some(&compute_budget::id())
+        })
+        .filter_map(|ix| match try_from_slice_unchecked(&ix.data) {
+            Ok(ComputeBudgetInstruction::SetComputeUnitPrice(micro_lamports)) => {
+                Some(micro_lamports)
+            }
+            _ => None,
+        })
+        .max()
+        .unwrap_or_default();
+
+    let config_price = match message {
+        VersionedMessage::V1(message) => match message.config.priority_fee {
+            None | Some(0) => 0,
+            Some(priority_fee) => {
+                let compute_unit_limit = message
+                    .config
+                    .compute_unit_limit
+                    .ok_or(PolicyViolation::ComputeUnitLimitRequired)?;
+                // A fee paid for no compute units is an unbounded price
+                priority_fee
+                    .saturating_mul(MICRO_LAMPORTS_PER_LAMPORT)
+                    .checked_div(u64::from(compute_unit_limit))
+                    .unwrap_or(u64::MAX)
+            }
+        },
+        _ => 0,
+    };
+
+    Ok(instruction_price.max(config_price))
+}
+
+/// Returns true if the first instruction advances a nonce account.
+fn uses_durable_nonce(message: &VersionedMessage) -> bool {
+    message.instructions().first().is_some_and(|ix| {
+        message
+            .static_account_keys()
+            .get(usize::from(ix.program_id_index))
+            == Some(&system_program::id())
+            && is_advance_nonce_instruction_data(&ix.data)
+            && ix
+                .accounts
+                .first()
+                .is_some_and(|index| message.is_maybe_writable(usize::from(*index), None))
+    })
+}
+
+/// A `Signer` that only signs messages complying with a [`SigningPolicy`].
+pub struct PolicySigner<S> {
+    signer: S,
+    policy: SigningPolicy,
+    audit_log: Arc<dyn AuditLog>,
+}
+
+impl<S: fmt::Debug> fmt::Debug for PolicySigner<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PolicySigner")
+            .field("signer", &self.signer)
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: Signer> PolicySigner<S> {
+    pub fn new(signer: S, policy: SigningPolicy, audit_log: Arc<dyn AuditLog>) -> Self {
+        Self {
+            signer,
+            policy,
+            audit_log,
+        }
+    }
+
+    pub fn policy(&self) -> &SigningPolicy {
+        &self.policy
+    }
+
+    /// Decodes `message` and checks it against the policy, without signing
+    /// or logging.
+    pub fn check(&self, message: &[u8]) -> Result<(), PolicyViolation> {
+        let message: VersionedMessage =
+            wincode::deserialize(message).map_err(|_| PolicyViolation::UndecodableMessage)?;
+        self.policy.check(&message)
+    }
+
+    /// Checks `message` against the policy, appends the decision to the audit
+    /// log and signs it if it complies.
+    ///
+    /// Unlike [`Signer::try_sign_message`], this reports policy violations and
+    /// audit log failures as structured errors.
+    pub fn try_sign_message_checked(&self, message: &[u8]) -> Result<Signature, PolicySignerError> {
+        let result = self.check(message);
+        self.audit_log
+            .append(AuditEntry {
+                timestamp: SystemTime::now(),
+                signer: self.signer.pubkey(),
+                message_hash: VersionedMessage::hash_raw_message(message),
+                decision: match &result {
+                    Ok(()) => AuditDecision::Approved,
+                    Err(violation) => AuditDecision::Rejected(violation.clone()),
+                },
+            })
+            .map_err(PolicySignerError::AuditLog)?;
+        result?;
+        self.signer
+            .try_sign_message(message)
+            .map_err(PolicySignerError::Signer)
+    }
+}
+
+impl<S: Signer> Signer for PolicySigner<S> {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        self.signer.try_pubkey()
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        Ok(self.try_sign_message_checked(message)?)
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.signer.is_interactive()
+    }
+}
+
+impl<S: Signer, T: Signer> PartialEq<T> for PolicySigner<S> {
+    fn eq(&self, other: &T) -> bool {
+        self.pubkey() == other.pubkey()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_hash::Hash,
+        solana_instruction::{AccountMeta, Instruction},
+        solana_keypair::Keypair,
+        solana_message::{v0, v1, AddressLookupTableAccount, Message},
+        solana_sdk_ids::ed25519_program,
+        solana_system_interface::instruction as system_instruction,
+    };
+
+    fn legacy(payer: &Pubkey, instructions: &[Instruction]) -> Vec<u8> {
+        VersionedMessage::Legacy(Message::new_with_blockhash(
+            instructions,
+            Some(payer),
+            &Hash::new_from_array([1; 32]),
+        ))
+        .serialize()
+    }
+
+    fn policy_signer(policy: SigningPolicy) -> (PolicySigner<Keypair>, Arc<MemoryAuditLog>) {
+        let audit_log = Arc::new(MemoryAuditLog::default());
+        let signer = PolicySigner::new(Keypair::new(), policy, audit_log.clone());
+        (signer, audit_log)
+    }
+
+    #[test]
+    fn test_default_policy_signs_transactions_only() {
+        let (signer, audit_log) = policy_signer(SigningPolicy::default());
+        let payer = signer.pubkey();
+        let message = legacy(
+            &payer,
+            &[system_instruction::transfer(
+                &payer,
+                &Pubkey::new_unique(),
+                1,
+            )],
+        );
+        let signature = signer.try_sign_message(&message).unwrap();
+        assert!(signature.verify(payer.as_ref(), &message));
+
+        assert_eq!(
+            signer.try_sign_message(b"not a message").unwrap_err(),
+            SignerError::Custom(
+                "signing policy violation: message is not a valid transaction message".to_string()
+            )
+        );
+        assert!(matches!(
+            signer.try_sign_message_checked(b"not a message"),
+            Err(PolicySignerError::PolicyViolation(
+                PolicyViolation::UndecodableMessage
+            ))
+        ));
+
+        let entries = audit_log.entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].decision, AuditDecision::Approved);
+        assert_eq!(entries[0].signer, payer);
+        assert_eq!(
+            entries[0].message_hash,
+            VersionedMessage::hash_raw_message(&message)
+        );
+        assert_eq!(
+            entries[1].decision,
+            AuditDecision::Rejected(PolicyViolation::UndecodableMessage)
+        );
+    }
+
+    #[test]
+    fn test_allowed_programs() {
+        let (signer, _) = policy_signer(SigningPolicy {
+            allowed_programs: Some([system_program::id(), compute_budget::id()].into()),
+            ..SigningPolicy::default()
+        });
+        let payer = signer.pubkey();
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+        signer
+            .check(&legacy(
+                &payer,
+                &[
+                    ComputeBudgetInstruction::set_compute_unit_price(1),
+                    transfer.clone(),
+                ],
+            ))
+            .unwrap();
+
+        let program_id = Pubkey::new_unique();
+        let other = Instruction::new_with_bytes(program_id, &[], vec![]);
+        assert_eq!(
+            signer.check(&legacy(&payer, &[transfer, other])),
+            Err(PolicyViolation::ProgramNotAllowed(program_id))
+        );
+    }
+
+    #[test]
+    fn test_max_lamports_transferred() {
+        let (signer, audit_log) = policy_signer(SigningPolicy {
+            max_lamports_transferred: Some(100),
+            ..SigningPolicy::default()
+        });
+        let payer = signer.pubkey();
+        let to = Pubkey::new_unique();
+        signer
+            .try_sign_message(&legacy(
+                &payer,
+                &[
+                    system_instruction::transfer(&payer, &to, 60),
+                    system_instruction::transfer(&payer, &to, 40),
+                ],
+            ))
+            .unwrap();
+
+        let message = legacy(
+            &payer,
+            &[
+                system_instruction::transfer(&payer, &to, 60),
+                system_instruction::transfer_with_seed(
+                    &Pubkey::new_unique(),
+                    &payer,
+                    "seed".to_string(),
+                    &Pubkey::new_unique(),
+                    &to,
+                    41,
+                ),
+            ],
+        );
+        assert!(signer.try_sign_message(&message).is_err());
+        assert_eq!(
+            audit_log.entries()[1].decision,
+            AuditDecision::Rejected(PolicyViolation::TransferLimitExceeded {
+                lamports: 101,
+                max: 100
+            })
+        );
+    }
+
+    #[test]
+    fn test_forbidden_writable_accounts() {
+        let treasury = Pubkey::new_unique();
+        let (signer, _) = policy_signer(SigningPolicy {
+            forbidden_writable_accounts: [treasury].into(),
+            ..SigningPolicy::default()
+        });
+        let payer = signer.pubkey();
+        let program_id = Pubkey::new_unique();
+
+        // Read-only access is fine
+        let read = Instruction::new_with_bytes(
+            program_id,
+            &[],
+            vec![AccountMeta::new_readonly(treasury, false)],
+        );
+        signer.check(&legacy(&payer, &[read])).unwrap();
+
+        let write =
+            Instruction::new_with_bytes(program_id, &[], vec![AccountMeta::new(treasury, false)]);
+        assert_eq!(
+            signer.check(&legacy(&payer, std::slice::from_ref(&write))),
+            Err(PolicyViolation::WritableAccountForbidden(treasury))
+        );
+
+        // Writes through lookup tables cannot be resolved
+        let lookup_table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: vec![treasury],
+        };
+        let message = VersionedMessage::V0(
+            v0::Message::try_compile(&payer, &[write], &[lookup_table], Hash::default()).unwrap(),
+        );
+        assert_eq!(
+            signer.check(&message.serialize()),
+            Err(PolicyViolation::UnresolvedWritableAccounts)
+        );
+    }
+
+    #[test]
+    fn test_max_compute_unit_price() {
+        let (signer, _) = policy_signer(SigningPolicy {
+            max_compute_unit_price: Some(1_000),
+            ..SigningPolicy::default()
+        });
+        let payer = signer.pubkey();
+        signer
+            .check(&legacy(
+                &payer,
+                &[ComputeBudgetInstruction::set_compute_unit_price(1_000)],
+            ))
+            .unwrap();
+        assert_eq!(
+            signer.check(&legacy(
+                &payer,
+                &[ComputeBudgetInstruction::set_compute_unit_price(1_001)]
+            )),
+            Err(PolicyViolation::ComputeUnitPriceExceeded {
+                micro_lamports: 1_001,
+                max: 1_000
+            })
+        );
+
+        // 200 lamports over 200_000 compute units is 1_000 micro-lamports per
+        // unit
+        let v1_message = |priority_fee, compute_unit_limit| {
+            VersionedMessage::V1(
+                v1::Message::try_compile_with_config(
+                    &payer,
+                    &[],
+                    Hash::default(),
+                    v1::TransactionConfig::empty()
+                        .with_priority_fee(priority_fee)
+                        .with_compute_unit_limit(compute_unit_limit),
+                )
+                .unwrap(),
+            )
+            .serialize()
+        };
+        signer.check(&v1_message(200, 200_000)).unwrap();
+        assert_eq!(
+            signer.check(&v1_message(201, 200_000)),
+            Err(PolicyViolation::ComputeUnitPriceExceeded {
+                micro_lamports: 1_005,
+                max: 1_000
+            })
+        );
+        assert!(signer.check(&v1_message(1, 0)).is_err());
+
+        // The default compute unit limit depends on which programs the
+        // runtime counts as builtins, such as the precompiles, so a priority
+        // fee requires an explicit limit
+        let precompile = Instruction::new_with_bytes(ed25519_program::id(), &[0], vec![]);
+        let v1_message_with_precompile = |config| {
+            VersionedMessage::V1(
+                v1::Message::try_compile_with_config(
+                    &payer,
+                    std::slice::from_ref(&precompile),
+                    Hash::default(),
+                    config,
+                )
+                .unwrap(),
+            )
+            .serialize()
+        };
+        assert_eq!(
+            signer.check(&v1_message_with_precompile(
+                v1::TransactionConfig::empty().with_priority_fee(1)
+            )),
+            Err(PolicyViolation::ComputeUnitLimitRequired)
+        );
+        signer
+            .check(&v1_message_with_precompile(v1::TransactionConfig::empty()))
+            .unwrap();
+        assert_eq!(
+            signer.check(&v1_message_with_precompile(
+                v1::TransactionConfig::empty()
+                    .with_priority_fee(4)
+                    .with_compute_unit_limit(3_000)
+            )),
+            Err(PolicyViolation::ComputeUnitPriceExceeded {
+                micro_lamports: 1_333,
+                max: 1_000
+            })
+        );
+    }
+
+    #[test]
+    fn test_require_durable_nonce() {
+        let (signer, _) = policy_signer(SigningPolicy {
+            require_durable_nonce: true,
+            ..SigningPolicy::default()
+        });
+        let payer = signer.pubkey();
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+        assert_eq!(
+            signer.check(&legacy(&payer, std::slice::from_ref(&transfer))),
+            Err(PolicyViolation::DurableNonceRequired)
+        );
+
+        let nonced =
+            Message::new_with_nonce(vec![transfer], Some(&payer), &Pubkey::new_unique(), &payer);
+        signer
+            .check(&VersionedMessage::Legacy(nonced).serialize())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_writer_audit_log() {
+        let audit_log = Arc::new(WriterAuditLog::new(Vec::new()));
+        let signer = PolicySigner::new(Keypair::new(), SigningPolicy::default(), audit_log.clone());
+        assert!(signer.try_sign_message(&[]).is_err());
+        drop(signer);
+
+        let log = String::from_utf8(Arc::into_inner(audit_log).unwrap().into_inner()).unwrap();
+        assert!(log.ends_with("rejected: message is not a valid transaction message\n"));
+        assert_eq!(log.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_audit_log_failure_prevents_signing() {
+        struct FailingWriter;
+
+        impl std::io::Write for FailingWriter {
+            fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("disk full"))
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let signer = PolicySigner::new(
+            Keypair::new(),
+            SigningPolicy::default(),
+            Arc::new(WriterAuditLog::new(FailingWriter)),
+        );
+        let payer = signer.pubkey();
+        let message = legacy(
+            &payer,
+            &[system_instruction::transfer(
+                &payer,
+                &Pubkey::new_unique(),
+                1,
+            )],
+        );
+        assert_eq!(
+            signer.try_sign_message(&message).unwrap_err(),
+            SignerError::Custom("audit log failed: disk full".to_string())
+        );
+        assert!(matches!(
+            signer.try_sign_message_checked(&message),
+            Err(PolicySignerError::AuditLog(err)) if err.to_string() == "disk full"
+        ));
+    }
+}
@@ -0,0 +1,257 @@
+//! Instruction data layout shared by the builders of the ed25519, secp256k1
+//! and secp256r1 precompile instructions.
+//!
+//! The data of all three instructions starts with the number of signatures,
+//! followed by one fixed-size offsets struct per signature, followed by the
+//! public keys, messages and signatures the offsets point to. Offsets may
+//! also point into the data of other instructions of the transaction.
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+use solana_precompile_error::PrecompileError;
+
+/// Location of a piece of signature data for a precompile instruction
+/// builder.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DataRef<'a, T: ?Sized> {
+    /// Data stored in the built instruction. Identical inline data is stored
+    /// only once.
+    Inline(&'a T),
+    /// Data already stored at `offset` in the data of the instruction at
+    /// `index` of the transaction. Only the length of `data` is encoded.
+    Instruction {
+        index: u16,
+        offset: u16,
+        data: &'a T,
+    },
+}
+
+// Derived impls would require `T: Copy`
+impl<T: ?Sized> Clone for DataRef<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for DataRef<'_, T> {}
+
+impl<'a, T: ?Sized> From<&'a T> for DataRef<'a, T> {
+    fn from(data: &'a T) -> Self {
+        Self::Inline(data)
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> DataRef<'a, T> {
+    /// Erases the size of the referenced data.
+    pub fn as_bytes(self) -> DataRef<'a, [u8]> {
+        match self {
+            Self::Inline(data) => DataRef::Inline(data.as_ref()),
+            Self::Instruction {
+                index,
+                offset,
+                data,
+            } => DataRef::Instruction {
+                index,
+                offset,
+                data: data.as_ref(),
+            },
+        }
+    }
+}
+
+/// Where a piece of signature data is read from, as encoded in the offsets
+/// of a signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataLocation {
+    pub instruction_index: u16,
+    pub offset: u16,
+    pub size: u16,
+}
+
+/// Data of a precompile instruction being built.
+#[derive(Debug)]
+pub struct InstructionData<'a> {
+    data: Vec<u8>,
+    offsets_start: usize,
+    offsets_size: usize,
+    instruction_index: u16,
+    inline: Vec<(&'a [u8], u16)>,
+}
+
+impl<'a> InstructionData<'a> {
+    /// Starts the data of an instruction verifying `num_signatures`
+    /// signatures, whose offsets are `offsets_size` bytes each and start at
+    /// `offsets_start`. Inline data is referenced by `instruction_index`.
+    ///
+    /// Fails with `InvalidInstructionDataSize` if there are more than 255
+    /// signatures.
+    pub fn new(
+        num_signatures: usize,
+        offsets_start: usize,
+        offsets_size: usize,
+        instruction_index: u16,
+    ) -> Result<Self, PrecompileError> {
+        let count = u8::try_from(num_signatures)
+            .map_err(|_| PrecompileError::InvalidInstructionDataSize)?;
+        let data_start = offsets_size
+            .saturating_mul(num_signatures)
+            .saturating_add(offsets_start);
+        let mut data = vec![0; data_start.max(1)];
+        data[0] = count;
+        Ok(Self {
+            data,
+            offsets_start,
+            offsets_size,
+            instruction_index,
+            inline: Vec::new(),
+        })
+    }
+
+    /// Returns where `data_ref` is read from, appending inline data unless
+    /// identical data was appended before.
+    ///
+    /// Fails with `InvalidInstructionDataSize` if the offset or size of the
+    /// data does not fit in 16 bits.
+    pub fn locate(&mut self, data_ref: DataRef<'a, [u8]>) -> Result<DataLocation, PrecompileError> {
+        let (instruction_index, offset, size) = match data_ref {
+            DataRef::Instruction {
+                index,
+                offset,
+                data,
+            } => (index, offset, Self::size(data)?),
+            DataRef::Inline(data) => {
+                let size = Self::size(data)?;
+                let offset = match self.inline.iter().find(|(inlined, _)| *inlined == data) {
+                    Some((_, offset)) => *offset,
+                    None => {
+                        let offset = u16::try_from(self.data.len())
+                            .map_err(|_| PrecompileError::InvalidInstructionDataSize)?;
+                        self.data.extend_from_slice(data);
+                        self.inline.push((data, offset));
+                        offset
+                    }
+                };
+                (self.instruction_index, offset, size)
+            }
+        };
+        Ok(DataLocation {
+            instruction_index,
+            offset,
+            size,
+        })
+    }
+
+    fn size(data: &[u8]) -> Result<u16, PrecompileError> {
+        u16::try_from(data.len()).map_err(|_| PrecompileError::InvalidInstructionDataSize)
+    }
+
+    /// Writes the serialized offsets of the signature at `index`.
+    ///
+    /// Fails with `InvalidDataOffsets` if there is no such signature or
+    /// `offsets` has the wrong size.
+    pub fn set_offsets(&mut self, index: usize, offsets: &[u8]) -> Result<(), PrecompileError> {
+        if offsets.len() != self.offsets_size {
+            return Err(PrecompileError::InvalidDataOffsets);
+        }
+        if index >= usize::from(self.data[0]) {
+            return Err(PrecompileError::InvalidDataOffsets);
+        }
+        let start = index
+            .checked_mul(self.offsets_size)
+            .and_then(|start| start.checked_add(self.offsets_start))
+            .ok_or(PrecompileError::InvalidDataOffsets)?;
+        self.data
+            .get_mut(start..start.saturating_add(self.offsets_size))
+            .ok_or(PrecompileError::InvalidDataOffsets)?
+            .copy_from_slice(offsets);
+        Ok(())
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate() {
+        let mut data = InstructionData::new(2, 2, 4, u16::MAX).unwrap();
+        let pubkey = [1; 3];
+        let message = b"message";
+        assert_eq!(
+            data.locate(DataRef::from(&pubkey).as_bytes()),
+            Ok(DataLocation {
+                instruction_index: u16::MAX,
+                offset: 10,
+                size: 3,
+            })
+        );
+        assert_eq!(
+            data.locate(DataRef::Inline(&message[..])),
+            Ok(DataLocation {
+                instruction_index: u16::MAX,
+                offset: 13,
+                size: 7,
+            })
+        );
+        // Identical inline data is stored once
+        assert_eq!(
+            data.locate(DataRef::Inline(&[1, 1, 1][..])),
+            Ok(DataLocation {
+                instruction_index: u16::MAX,
+                offset: 10,
+                size: 3,
+            })
+        );
+        assert_eq!(
+            data.locate(DataRef::Instruction {
+                index: 3,
+                offset: 7,
+                data: &message[..],
+            }),
+            Ok(DataLocation {
+                instruction_index: 3,
+                offset: 7,
+                size: 7,
+            })
+        );
+
+        data.set_offsets(1, &[9; 4]).unwrap();
+        assert_eq!(
+            data.set_offsets(2, &[9; 4]),
+            Err(PrecompileError::InvalidDataOffsets)
+        );
+        assert_eq!(
+            data.set_offsets(0, &[9; 3]),
+            Err(PrecompileError::InvalidDataOffsets)
+        );
+        assert_eq!(
+            data.into_vec(),
+            [&[2, 0, 0, 0, 0, 0, 9, 9, 9, 9, 1, 1, 1][..], message].concat()
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        assert_eq!(
+            InstructionData::new(256, 1, 11, 0).unwrap_err(),
+            PrecompileError::InvalidInstructionDataSize
+        );
+
+        let mut data = InstructionData::new(1, 1, 11, 0).unwrap();
+        let large = vec![1; usize::from(u16::MAX) + 1];
+        assert_eq!(
+            data.locate(DataRef::Inline(&large[..])),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+        let large = vec![2; usize::from(u16::MAX)];
+        data.locate(DataRef::Inline(&large[..])).unwrap();
+        // The offset of any further data no longer fits
+        assert_eq!(
+            data.locate(DataRef::Inline(&[3][..])),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+    }
+}
@@ -2,6 +2,11 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
 extern crate alloc;
+// need to re-export `AccountView` for entrypoint_account_view macro
+#[cfg(feature = "account-view")]
+pub use solana_account_view::AccountView as __AccountView;
+#[cfg(feature = "account-view")]
+use solana_account_view::{AccountView, RuntimeAccount};
 use {
     alloc::vec::Vec,
     solana_account_info::AccountInfo,
@@ -196,6 +201,83 @@ macro_rules! entrypoint_no_alloc {
     };
 }
 
+/// Declare the program entrypoint and set up global handlers.
+///
+/// This is similar to the `entrypoint_no_alloc!` macro, except that the
+/// accounts are passed to the program as lightweight [`AccountView`]s, which
+/// point directly into the input buffer, rather than as `AccountInfo`s.
+///
+/// The optional second argument is the maximum number of input accounts,
+/// 64 by default.
+///
+/// For more information about how the program entrypoint behaves and what it
+/// does, please see the documentation for [`entrypoint!`].
+///
+/// [`AccountView`]: solana_account_view::AccountView
+///
+/// # Examples
+///
+/// ```no_run
+/// #[cfg(not(feature = "no-entrypoint"))]
+/// pub mod entrypoint {
+///
+///     use solana_account_view::AccountView;
+///     use solana_program_entrypoint::entrypoint_account_view;
+///     use solana_program_entrypoint::ProgramResult;
+///     use solana_pubkey::Pubkey;
+///
+///     entrypoint_account_view!(process_instruction, 16);
+///
+///     pub fn process_instruction(
+///         program_id: &Pubkey,
+///         accounts: &mut [AccountView],
+///         instruction_data: &[u8],
+///     ) -> ProgramResult {
+///         Ok(())
+///     }
+///
+/// }
+/// ```
+#[cfg(feature = "account-view")]
+#[macro_export]
+macro_rules! entrypoint_account_view {
+    ($process_instruction:ident) => {
+        $crate::entrypoint_account_view!($process_instruction, 64);
+    };
+    ($process_instruction:ident, $max_accounts:expr) => {
+        /// # Safety
+        #[no_mangle]
+        pub unsafe extern "C" fn entrypoint(input: *mut u8) -> u64 {
+            use std::mem::MaybeUninit;
+            const UNINIT_ACCOUNT_VIEW: MaybeUninit<$crate::__AccountView> =
+                MaybeUninit::<$crate::__AccountView>::uninit();
+            let mut accounts = [UNINIT_ACCOUNT_VIEW; $max_accounts];
+            let (program_id, num_accounts, instruction_data) =
+                unsafe { $crate::deserialize_account_views(input, &mut accounts) };
+            // Use `slice_assume_init_mut` once it's stabilized
+            let accounts = &mut *(&mut accounts[..num_accounts]
+                as *mut [MaybeUninit<$crate::__AccountView>]
+                as *mut [$crate::__AccountView]);
+
+            #[inline(never)]
+            fn call_program(
+                program_id: &$crate::__Pubkey,
+                accounts: &mut [$crate::__AccountView],
+                data: &[u8],
+            ) -> u64 {
+                match $process_instruction(program_id, accounts, data) {
+                    Ok(()) => $crate::SUCCESS,
+                    Err(error) => error.into(),
+                }
+            }
+
+            call_program(program_id, accounts, instruction_data)
+        }
+        $crate::custom_heap_default!();
+        $crate::custom_panic_default!();
+    };
+}
+
 /// Define the default global allocator.
 ///
 /// The default global allocator is enabled only if the calling crate has not
@@ -563,6 +645,92 @@ pub unsafe fn deserialize_into<'a>(
     (program_id, num_accounts, instruction_data)
 }
 
+#[cfg(feature = "account-view")]
+#[allow(clippy::arithmetic_side_effects)]
+#[inline(always)]
+unsafe fn deserialize_account_view(input: *mut u8, mut offset: usize) -> (AccountView, usize) {
+    // The serialized account is laid out as a `RuntimeAccount`, whose
+    // `borrow_state` is the duplicate marker. For non-duplicate accounts
+    // the marker is `NON_DUP_MARKER`, which means the data is not borrowed.
+    #[allow(clippy::cast_ptr_alignment)]
+    let account = input.add(offset) as *mut RuntimeAccount;
+    let data_len = (*account).data_len as usize;
+
+    // Store the original data length for detecting invalid reallocations and
+    // requires that MAX_PERMITTED_DATA_LENGTH fits in a u32
+    (*account).padding = (data_len as u32).to_ne_bytes();
+
+    // rent epoch is not deserialized, so skip it
+    offset +=
+        size_of::<RuntimeAccount>() + data_len + MAX_PERMITTED_DATA_INCREASE + size_of::<u64>();
+    offset += (offset as *const u8).align_offset(BPF_ALIGN_OF_U128); // padding
+
+    (AccountView::new_unchecked(account), offset)
+}
+
+/// Deserialize the input arguments into `AccountView`s
+///
+/// Differs from `deserialize_into` by writing lightweight account views,
+/// which point into the input buffer, instead of account infos. Duplicate
+/// accounts are views of the same account, so they share its borrow state.
+///
+/// Panics if the input array is not large enough.
+///
+/// The integer arithmetic in this method is safe when called on a buffer that was
+/// serialized by runtime. Use with buffers serialized otherwise is unsupported and
+/// done at one's own risk.
+///
+/// # Safety
+#[cfg(feature = "account-view")]
+#[allow(clippy::arithmetic_side_effects)]
+pub unsafe fn deserialize_account_views<'a, const MAX_ACCOUNTS: usize>(
+    input: *mut u8,
+    accounts: &mut [MaybeUninit<AccountView>; MAX_ACCOUNTS],
+) -> (&'a Pubkey, usize, &'a [u8]) {
+    let mut offset: usize = 0;
+
+    // Number of accounts present
+
+    #[allow(clippy::cast_ptr_alignment)]
+    let num_accounts = *(input.add(offset) as *const u64) as usize;
+    offset += size_of::<u64>();
+
+    if num_accounts > MAX_ACCOUNTS {
+        panic!(
+            "{} accounts provided, but only {} are supported",
+            num_accounts, MAX_ACCOUNTS
+        );
+    }
+
+    // Account Views
+
+    for i in 0..num_accounts {
+        let dup_info = *input.add(offset);
+        if dup_info == NON_DUP_MARKER {
+            let (account_view, new_offset) = deserialize_account_view(input, offset);
+            offset = new_offset;
+            accounts[i].write(account_view);
+        } else {
+            offset += size_of::<u64>(); // marker and padding
+
+            // Duplicate account, copy the original
+            #[allow(clippy::clone_on_copy)]
+            accounts[i].write(accounts[dup_info as usize].assume_init_ref().clone());
+        }
+    }
+
+    // Instruction data
+
+    let (instruction_data, new_offset) = deserialize_instruction_data(input, offset);
+    offset = new_offset;
+
+    // Program Id
+
+    let program_id: &Pubkey = &*(input.add(offset) as *const Pubkey);
+
+    (program_id, num_accounts, instruction_data)
+}
+
 #[cfg(test)]
 mod test {
     use {super::*, std::alloc::GlobalAlloc};
@@ -619,4 +787,213 @@ mod test {
             assert_eq!(0, ptr.align_offset(size_of::<u64>()));
         }
     }
+
+    /// An account serialized into the input buffer.
+    enum SerializedAccount<'a> {
+        Account {
+            key: Pubkey,
+            owner: Pubkey,
+            is_signer: bool,
+            is_writable: bool,
+            lamports: u64,
+            data: &'a [u8],
+        },
+        Duplicate(u8),
+    }
+
+    /// Serializes accounts, instruction data and program id the way the
+    /// runtime does, into an 8-byte aligned buffer.
+    fn serialize_input(
+        accounts: &[SerializedAccount],
+        instruction_data: &[u8],
+        program_id: &Pubkey,
+    ) -> Vec<u64> {
+        let mut input = Vec::new();
+        input.extend_from_slice(&(accounts.len() as u64).to_le_bytes());
+        for account in accounts {
+            match account {
+                SerializedAccount::Account {
+                    key,
+                    owner,
+                    is_signer,
+                    is_writable,
+                    lamports,
+                    data,
+                } => {
+                    input.extend_from_slice(&[
+                        NON_DUP_MARKER,
+                        *is_signer as u8,
+                        *is_writable as u8,
+                        0,
+                    ]);
+                    input.extend_from_slice(&[0; 4]);
+                    input.extend_from_slice(key.as_ref());
+                    input.extend_from_slice(owner.as_ref());
+                    input.extend_from_slice(&lamports.to_le_bytes());
+                    input.extend_from_slice(&(data.len() as u64).to_le_bytes());
+                    input.extend_from_slice(data);
+                    input.resize(input.len().saturating_add(MAX_PERMITTED_DATA_INCREASE), 0);
+                    input.resize(input.len().next_multiple_of(BPF_ALIGN_OF_U128), 0);
+                    // rent epoch
+                    input.extend_from_slice(&u64::MAX.to_le_bytes());
+                }
+                SerializedAccount::Duplicate(index) => {
+                    input.extend_from_slice(&[*index, 0, 0, 0, 0, 0, 0, 0]);
+                }
+            }
+        }
+        input.extend_from_slice(&(instruction_data.len() as u64).to_le_bytes());
+        input.extend_from_slice(instruction_data);
+        input.extend_from_slice(program_id.as_ref());
+
+        input.resize(input.len().next_multiple_of(size_of::<u64>()), 0);
+        input
+            .chunks_exact(size_of::<u64>())
+            .map(|chunk| u64::from_ne_bytes(chunk.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_deserialize_account_views() {
+        let keys: Vec<_> = (0..3).map(|i| Pubkey::new_from_array([i; 32])).collect();
+        let owner = Pubkey::new_from_array([7; 32]);
+        let program_id = Pubkey::new_from_array([9; 32]);
+        let accounts = [
+            SerializedAccount::Account {
+                key: keys[0],
+                owner,
+                is_signer: true,
+                is_writable: true,
+                lamports: 42,
+                data: &[1, 2, 3],
+            },
+            SerializedAccount::Account {
+                key: keys[1],
+                owner,
+                is_signer: false,
+                is_writable: false,
+                lamports: 1,
+                data: &[],
+            },
+            SerializedAccount::Duplicate(0),
+            SerializedAccount::Account {
+                key: keys[2],
+                owner: program_id,
+                is_signer: false,
+                is_writable: true,
+                lamports: 0,
+                data: &[5; 11],
+            },
+            SerializedAccount::Duplicate(3),
+        ];
+        let mut input = serialize_input(&accounts, &[4, 5, 6], &program_id);
+
+        const UNINIT: MaybeUninit<AccountView> = MaybeUninit::uninit();
+        let mut views = [UNINIT; 8];
+        let (deserialized_program_id, num_accounts, instruction_data) =
+            unsafe { deserialize_account_views(input.as_mut_ptr() as *mut u8, &mut views) };
+        assert_eq!(deserialized_program_id, &program_id);
+        assert_eq!(num_accounts, accounts.len());
+        assert_eq!(instruction_data, &[4, 5, 6]);
+
+        let views: Vec<_> = views[..num_accounts]
+            .iter()
+            .map(|view| unsafe { view.assume_init_ref() })
+            .collect();
+        for (view, account) in views.iter().zip(&accounts) {
+            let SerializedAccount::Account {
+                key,
+                owner,
+                is_signer,
+                is_writable,
+                lamports,
+                data,
+            } = account
+            else {
+                continue;
+            };
+            assert_eq!(view.address(), key);
+            assert_eq!(view.owner(), owner);
+            assert_eq!(view.is_signer(), *is_signer);
+            assert_eq!(view.is_writable(), *is_writable);
+            assert!(!view.executable());
+            assert_eq!(view.lamports(), *lamports);
+            assert_eq!(&*view.try_borrow().unwrap(), *data);
+        }
+        // Duplicates are views of the same account
+        assert_eq!(views[2], views[0]);
+        assert_eq!(views[4], views[3]);
+        #[allow(clippy::clone_on_copy)]
+        let mut account = views[3].clone();
+        let data = account.try_borrow_mut().unwrap();
+        assert!(views[4].check_borrow().is_err());
+        drop(data);
+        assert!(views[4].check_borrow().is_ok());
+
+        // The original data length is recorded for resizing
+        assert_eq!(unsafe { account.original_data_len() }, 11);
+        unsafe { account.resize(11 + MAX_PERMITTED_DATA_INCREASE) }.unwrap();
+        assert_eq!(views[4].data_len(), 11 + MAX_PERMITTED_DATA_INCREASE);
+        assert_eq!(
+            unsafe { account.resize(12 + MAX_PERMITTED_DATA_INCREASE) },
+            Err(solana_program_error::ProgramError::InvalidRealloc)
+        );
+    }
+
+    #[test]
+    fn test_deserialize_account_views_matches_account_infos() {
+        let key = Pubkey::new_from_array([1; 32]);
+        let owner = Pubkey::new_from_array([2; 32]);
+        let program_id = Pubkey::new_from_array([3; 32]);
+        let accounts = [
+            SerializedAccount::Account {
+                key,
+                owner,
+                is_signer: true,
+                is_writable: false,
+                lamports: 10,
+                data: &[8; 13],
+            },
+            SerializedAccount::Duplicate(0),
+        ];
+        let mut input = serialize_input(&accounts, &[], &program_id);
+        let input = input.as_mut_ptr() as *mut u8;
+
+        let (infos_program_id, infos, infos_data) = unsafe { deserialize(input) };
+        const UNINIT: MaybeUninit<AccountView> = MaybeUninit::uninit();
+        let mut views = [UNINIT; 2];
+        let (views_program_id, num_accounts, views_data) =
+            unsafe { deserialize_account_views(input, &mut views) };
+        assert_eq!(views_program_id, infos_program_id);
+        assert_eq!(views_data, infos_data);
+        assert_eq!(num_accounts, infos.len());
+        for (view, info) in views.iter().zip(&infos) {
+            let view = unsafe { view.assume_init_ref() };
+            assert_eq!(view.address(), info.key);
+            assert_eq!(view.owner(), info.owner);
+            assert_eq!(view.lamports(), info.lamports());
+            assert_eq!(view.data_ptr(), info.data.borrow().as_ptr());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "2 accounts provided, but only 1 are supported")]
+    fn test_deserialize_account_views_too_many_accounts() {
+        let key = Pubkey::new_from_array([1; 32]);
+        let accounts = [
+            SerializedAccount::Account {
+                key,
+                owner: key,
+                is_signer: false,
+                is_writable: false,
+                lamports: 0,
+                data: &[],
+            },
+            SerializedAccount::Duplicate(0),
+        ];
+        let mut input = serialize_input(&accounts, &[], &key);
+        const UNINIT: MaybeUninit<AccountView> = MaybeUninit::uninit();
+        let mut views = [UNINIT; 1];
+        unsafe { deserialize_account_views(input.as_mut_ptr() as *mut u8, &mut views) };
+    }
 }
@@ -0,0 +1,109 @@
+//! Reference signing daemon serving any [`Signer`] over the
+//! [protocol](crate::protocol).
+//!
+//! ```no_run
+//! use {solana_keypair::read_keypair_file, solana_remote_signer::daemon};
+//!
+//! let keypair = read_keypair_file("id.json").unwrap();
+//! // Serve the parent process, which spawned this one with
+//! // `RemoteSigner::spawn`
+//! daemon::serve_stdio(&keypair).unwrap();
+//! ```
+
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+use {
+    crate::protocol::{read_frame, write_frame, Request, Response},
+    solana_message::VersionedMessage,
+    solana_offchain_message::OffchainMessage,
+    solana_signer::{Signer, SignerError},
+    std::io::{self, Read, Write},
+};
+
+/// Handles one request, returning the response to send back.
+///
+/// `SignMessage` requests are only signed if they hold a serialized
+/// transaction message, and `SignOffchainMessage` requests if they hold a
+/// serialized off-chain message. Off-chain messages start with a signing
+/// domain which no transaction message starts with, so neither request can
+/// be used to obtain a signature meant for the other.
+pub fn handle_request<S: Signer + ?Sized>(signer: &S, request: Request) -> Response {
+    let result = match request {
+        Request::GetPubkey => signer.try_pubkey().map(Response::Pubkey),
+        Request::SignMessage(message) => check_transaction_message(&message)
+            .and_then(|()| signer.try_sign_message(&message))
+            .map(Response::Signature),
+        Request::SignOffchainMessage(message) => OffchainMessage::deserialize(&message)
+            .map_err(|err| SignerError::InvalidInput(format!("invalid off-chain message: {err}")))
+            .and_then(|_| signer.try_sign_message(&message))
+            .map(Response::Signature),
+    };
+    result.unwrap_or_else(Response::Error)
+}
+
+/// Checks that `message` is exactly a serialized transaction message.
+fn check_transaction_message(message: &[u8]) -> Result<(), SignerError> {
+    let invalid = || SignerError::InvalidInput("not a transaction message".to_string());
+    let parsed: VersionedMessage = wincode::deserialize(message).map_err(|_| invalid())?;
+    if parsed.serialize() != message {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// Serves requests from `transport` until the client disconnects.
+///
+/// Malformed requests are answered with a `Protocol` error; only I/O
+/// failures end the session early.
+pub fn serve<S: Signer + ?Sized, T: Read + Write>(signer: &S, transport: &mut T) -> io::Result<()> {
+    while let Some(payload) = read_frame(transport)? {
+        let response = match Request::decode(&payload) {
+            Ok(request) => handle_request(signer, request),
+            Err(err) => Response::Error(err),
+        };
+        write_frame(transport, &response.encode())?;
+    }
+    Ok(())
+}
+
+/// Serves requests read from stdin, writing responses to stdout.
+pub fn serve_stdio<S: Signer + ?Sized>(signer: &S) -> io::Result<()> {
+    let mut transport = Stdio {
+        stdin: io::stdin().lock(),
+        stdout: io::stdout().lock(),
+    };
+    serve(signer, &mut transport)
+}
+
+/// Serves clients connecting to `listener`, one at a time.
+///
+/// Errors of a single connection are ignored so a misbehaving client cannot
+/// stop the daemon; only failing to accept a connection is returned.
+#[cfg(unix)]
+pub fn serve_unix<S: Signer + ?Sized>(signer: &S, listener: &UnixListener) -> io::Result<()> {
+    loop {
+        let (mut stream, _) = listener.accept()?;
+        let _ = serve(signer, &mut stream);
+    }
+}
+
+struct Stdio<'a> {
+    stdin: io::StdinLock<'a>,
+    stdout: io::StdoutLock<'a>,
+}
+
+impl Read for Stdio<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stdin.read(buf)
+    }
+}
+
+impl Write for Stdio<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stdout.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()
+    }
+}
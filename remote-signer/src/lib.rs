@@ -0,0 +1,359 @@
+//! A `Signer` implementation delegating to a signing daemon in another
+//! process.
+//!
+//! Keeping hot keys in a separate, isolated process limits what a
+//! compromise of the process building transactions can reach. The daemon
+//! speaks the length-prefixed [protocol](protocol) over a Unix socket or its
+//! stdin and stdout; [`daemon`] serves any `Signer` this way.
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+pub mod daemon;
+pub mod protocol;
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use {
+    crate::protocol::{read_frame, write_frame, Request, Response},
+    solana_offchain_message::OffchainMessage,
+    solana_pubkey::Pubkey,
+    solana_signature::Signature,
+    solana_signer::{Signer, SignerError},
+    std::{
+        io::{self, Read, Write},
+        path::Path,
+        process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+        sync::Mutex,
+    },
+};
+
+/// A `Signer` whose key lives in a signing daemon reached through
+/// `transport`.
+///
+/// The pubkey is fetched once on construction. Requests are serialized, so
+/// one `RemoteSigner` can be shared between threads.
+///
+/// [`Signer::try_sign_message`] only signs serialized transaction messages,
+/// which the daemon checks before signing; sign off-chain messages with
+/// [`RemoteSigner::sign_offchain_message`].
+#[derive(Debug)]
+pub struct RemoteSigner<T> {
+    pubkey: Pubkey,
+    transport: Mutex<T>,
+    interactive: bool,
+}
+
+impl<T: Read + Write> RemoteSigner<T> {
+    /// Creates a signer talking to a daemon over `transport`.
+    pub fn new(transport: T) -> Result<Self, SignerError> {
+        let transport = Mutex::new(transport);
+        match call(&transport, &Request::GetPubkey)? {
+            Response::Pubkey(pubkey) => Ok(Self {
+                pubkey,
+                transport,
+                interactive: false,
+            }),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Set whether the daemon asks its operator to approve each request,
+    /// which [`Signer::is_interactive`] reports. Defaults to `false`.
+    #[must_use]
+    pub fn with_interactive(mut self, interactive: bool) -> Self {
+        self.interactive = interactive;
+        self
+    }
+
+    /// Signs an off-chain message; the daemon checks that `message` is a
+    /// well-formed off-chain message, which cannot be a transaction message,
+    /// before signing.
+    pub fn sign_offchain_message(
+        &self,
+        message: &OffchainMessage,
+    ) -> Result<Signature, SignerError> {
+        let message = message
+            .serialize()
+            .map_err(|err| SignerError::InvalidInput(err.to_string()))?;
+        self.sign(Request::SignOffchainMessage(message))
+    }
+
+    fn sign(&self, request: Request) -> Result<Signature, SignerError> {
+        match call(&self.transport, &request)? {
+            Response::Signature(signature) => Ok(signature),
+            _ => Err(unexpected_response()),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl RemoteSigner<UnixStream> {
+    /// Connects to a daemon listening on the Unix socket at `path`.
+    pub fn connect<P: AsRef<Path>>(path: P) -> Result<Self, SignerError> {
+        let stream = UnixStream::connect(path).map_err(connection_error)?;
+        Self::new(stream)
+    }
+}
+
+impl RemoteSigner<ChildProcess> {
+    /// Spawns `command` as a daemon serving requests on its stdin and
+    /// stdout, such as a program calling [`daemon::serve_stdio`].
+    ///
+    /// The daemon is killed when the signer is dropped.
+    pub fn spawn(mut command: Command) -> Result<Self, SignerError> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(connection_error)?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            let _ = child.kill();
+            return Err(SignerError::Connection(
+                "daemon stdio is not piped".to_string(),
+            ));
+        };
+        Self::new(ChildProcess {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+}
+
+impl<T: Read + Write> Signer for RemoteSigner<T> {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.sign(Request::SignMessage(message.to_vec()))
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.interactive
+    }
+}
+
+impl<T, S> PartialEq<S> for RemoteSigner<T>
+where
+    T: Read + Write,
+    S: Signer,
+{
+    fn eq(&self, other: &S) -> bool {
+        self.pubkey() == other.pubkey()
+    }
+}
+
+/// Stdin and stdout of a daemon spawned with [`RemoteSigner::spawn`].
+#[derive(Debug)]
+pub struct ChildProcess {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: ChildStdout,
+}
+
+impl Read for ChildProcess {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stdout.read(buf)
+    }
+}
+
+impl Write for ChildProcess {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stdin.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdin.flush()
+    }
+}
+
+impl Drop for ChildProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Sends `request` and waits for the response, turning error responses into
+/// errors.
+fn call<T: Read + Write>(transport: &Mutex<T>, request: &Request) -> Result<Response, SignerError> {
+    let mut transport = transport
+        .lock()
+        .map_err(|_| SignerError::Connection("transport poisoned".to_string()))?;
+    write_frame(&mut *transport, &request.encode()).map_err(connection_error)?;
+    let payload = read_frame(&mut *transport)
+        .map_err(connection_error)?
+        .ok_or_else(|| SignerError::Connection("daemon closed the connection".to_string()))?;
+    match Response::decode(&payload)? {
+        Response::Error(err) => Err(err),
+        response => Ok(response),
+    }
+}
+
+fn connection_error(err: io::Error) -> SignerError {
+    SignerError::Connection(err.to_string())
+}
+
+fn unexpected_response() -> SignerError {
+    SignerError::Protocol("unexpected response".to_string())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use {
+        super::*,
+        solana_keypair::Keypair,
+        solana_message::{Hash, Message, VersionedMessage},
+        std::{os::unix::net::UnixListener, sync::Arc, thread},
+    };
+
+    /// A serialized transaction message paid for by `payer`.
+    fn transaction_message(payer: &Pubkey, blockhash: u8) -> Vec<u8> {
+        VersionedMessage::Legacy(Message::new_with_blockhash(
+            &[],
+            Some(payer),
+            &Hash::new_from_array([blockhash; 32]),
+        ))
+        .serialize()
+    }
+
+    /// A signer whose operator rejects every request.
+    struct RejectingSigner(Pubkey);
+
+    impl Signer for RejectingSigner {
+        fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+            Ok(self.0)
+        }
+
+        fn try_sign_message(&self, _message: &[u8]) -> Result<Signature, SignerError> {
+            Err(SignerError::UserCancel("rejected by operator".to_string()))
+        }
+
+        fn is_interactive(&self) -> bool {
+            true
+        }
+    }
+
+    fn serve_pair<S: Signer + Send + 'static>(signer: S) -> RemoteSigner<UnixStream> {
+        let (client, mut server) = UnixStream::pair().unwrap();
+        thread::spawn(move || daemon::serve(&signer, &mut server).unwrap());
+        RemoteSigner::new(client).unwrap()
+    }
+
+    #[test]
+    fn test_remote_signer() {
+        let keypair = Keypair::new();
+        let pubkey = keypair.pubkey();
+        let remote = serve_pair(keypair.insecure_clone());
+        assert_eq!(remote.try_pubkey().unwrap(), pubkey);
+        assert_eq!(remote, keypair);
+        assert!(!remote.is_interactive());
+
+        let message = transaction_message(&pubkey, 0);
+        let signature = remote.try_sign_message(&message).unwrap();
+        assert_eq!(signature, keypair.sign_message(&message));
+
+        // Arbitrary bytes, and off-chain messages, are not transactions
+        assert!(matches!(
+            remote.try_sign_message(b"hello").unwrap_err(),
+            SignerError::InvalidInput(_)
+        ));
+        let offchain = OffchainMessage::new(0, b"off-chain hello").unwrap();
+        assert!(matches!(
+            remote
+                .try_sign_message(&offchain.serialize().unwrap())
+                .unwrap_err(),
+            SignerError::InvalidInput(_)
+        ));
+        let mut trailing = message.clone();
+        trailing.push(0);
+        assert!(remote.try_sign_message(&trailing).is_err());
+
+        let signature = remote.sign_offchain_message(&offchain).unwrap();
+        assert!(offchain.verify(&pubkey, &signature).unwrap());
+
+        // Shared between threads
+        let remote = Arc::new(remote);
+        let handles: Vec<_> = (0..4u8)
+            .map(|i| {
+                let remote = remote.clone();
+                thread::spawn(move || {
+                    remote
+                        .try_sign_message(&transaction_message(&pubkey, i))
+                        .unwrap()
+                })
+            })
+            .collect();
+        for (i, handle) in handles.into_iter().enumerate() {
+            let message = transaction_message(&pubkey, u8::try_from(i).unwrap());
+            assert_eq!(handle.join().unwrap(), keypair.sign_message(&message));
+        }
+
+        let remote = serve_pair(Keypair::new()).with_interactive(true);
+        assert!(remote.is_interactive());
+    }
+
+    #[test]
+    fn test_remote_errors() {
+        let pubkey = Pubkey::new_from_array([1; 32]);
+        let message = transaction_message(&pubkey, 0);
+        let remote = serve_pair(RejectingSigner(pubkey));
+        assert_eq!(
+            remote.try_sign_message(&message).unwrap_err(),
+            SignerError::UserCancel("rejected by operator".to_string())
+        );
+        assert_eq!(remote.sign_message(&message), Signature::default());
+
+        // A transaction disguised as an off-chain message is refused by the
+        // daemon before reaching the signer
+        let remote = serve_pair(Keypair::new());
+        let err = remote
+            .sign(Request::SignOffchainMessage(vec![1, 2, 3]))
+            .unwrap_err();
+        assert!(matches!(err, SignerError::InvalidInput(_)));
+        // The session survives errors
+        assert!(remote
+            .try_sign_message(&transaction_message(&remote.pubkey(), 0))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_connection_errors() {
+        let (client, server) = UnixStream::pair().unwrap();
+        drop(server);
+        assert!(matches!(
+            RemoteSigner::new(client).unwrap_err(),
+            SignerError::Connection(_)
+        ));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("signer.sock");
+        assert!(matches!(
+            RemoteSigner::connect(&path).unwrap_err(),
+            SignerError::Connection(_)
+        ));
+
+        let listener = UnixListener::bind(&path).unwrap();
+        let keypair = Keypair::new();
+        let pubkey = keypair.pubkey();
+        thread::spawn(move || daemon::serve_unix(&keypair, &listener));
+        for _ in 0..2 {
+            let remote = RemoteSigner::connect(&path).unwrap();
+            assert_eq!(remote.pubkey(), pubkey);
+        }
+    }
+
+    #[test]
+    fn test_spawn() {
+        // `cat` echoes requests back, which the client must reject as
+        // invalid responses
+        assert_eq!(
+            RemoteSigner::spawn(Command::new("cat")).unwrap_err(),
+            SignerError::Protocol("invalid response".to_string())
+        );
+        assert!(matches!(
+            RemoteSigner::spawn(Command::new("/nonexistent/signer-daemon")).unwrap_err(),
+            SignerError::Connection(_)
+        ));
+    }
+}
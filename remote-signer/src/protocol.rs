@@ -0,0 +1,326 @@
+//! Wire protocol between a [`RemoteSigner`](crate::RemoteSigner) and a
+//! signing daemon.
+//!
+//! The client sends one request frame and waits for one response frame;
+//! requests are handled strictly in order. Every frame is
+//!
+//! | Field   | Size     | Description                          |
+//! |---------|----------|--------------------------------------|
+//! | length  | 4        | length of the payload, little-endian |
+//! | payload | `length` | at most [`MAX_FRAME_LEN`] bytes      |
+//!
+//! A request payload starts with the protocol version, [`VERSION`], followed
+//! by a one-byte tag and its body:
+//!
+//! | Tag    | Request                  | Body                                 |
+//! |--------|--------------------------|--------------------------------------|
+//! | `0x01` | get pubkey               | empty                                |
+//! | `0x02` | sign message             | serialized transaction message       |
+//! | `0x03` | sign off-chain message   | serialized `OffchainMessage`         |
+//!
+//! A response payload is a one-byte tag followed by its body:
+//!
+//! | Tag    | Response  | Body                                                |
+//! |--------|-----------|-----------------------------------------------------|
+//! | `0x81` | pubkey    | 32-byte pubkey                                      |
+//! | `0x82` | signature | 64-byte signature                                   |
+//! | `0xff` | error     | one-byte [`SignerError`] code, UTF-8 message        |
+//!
+//! Error codes are listed in [`encode_error`].
+
+use {
+    solana_pubkey::Pubkey,
+    solana_signature::Signature,
+    solana_signer::SignerError,
+    std::io::{self, Read, Write},
+};
+
+/// Current protocol version.
+pub const VERSION: u8 = 1;
+
+/// Maximum length of a frame payload, large enough for any off-chain
+/// message.
+pub const MAX_FRAME_LEN: usize = 1 << 17;
+
+const REQUEST_GET_PUBKEY: u8 = 0x01;
+const REQUEST_SIGN_MESSAGE: u8 = 0x02;
+const REQUEST_SIGN_OFFCHAIN_MESSAGE: u8 = 0x03;
+
+const RESPONSE_PUBKEY: u8 = 0x81;
+const RESPONSE_SIGNATURE: u8 = 0x82;
+const RESPONSE_ERROR: u8 = 0xff;
+
+/// A request from the client to the daemon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    GetPubkey,
+    /// Sign a serialized transaction message, as signed by `Signer`s.
+    SignMessage(Vec<u8>),
+    /// Sign a serialized `OffchainMessage`.
+    SignOffchainMessage(Vec<u8>),
+}
+
+/// A response from the daemon to the client.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Response {
+    Pubkey(Pubkey),
+    Signature(Signature),
+    Error(SignerError),
+}
+
+impl Request {
+    pub fn encode(&self) -> Vec<u8> {
+        let (tag, body): (u8, &[u8]) = match self {
+            Self::GetPubkey => (REQUEST_GET_PUBKEY, &[]),
+            Self::SignMessage(message) => (REQUEST_SIGN_MESSAGE, message),
+            Self::SignOffchainMessage(message) => (REQUEST_SIGN_OFFCHAIN_MESSAGE, message),
+        };
+        let mut payload = Vec::with_capacity(body.len().saturating_add(2));
+        payload.push(VERSION);
+        payload.push(tag);
+        payload.extend_from_slice(body);
+        payload
+    }
+
+    pub fn decode(payload: &[u8]) -> Result<Self, SignerError> {
+        let [version, tag, body @ ..] = payload else {
+            return Err(SignerError::Protocol("truncated request".to_string()));
+        };
+        if *version != VERSION {
+            return Err(SignerError::Protocol(format!(
+                "unsupported protocol version {version}"
+            )));
+        }
+        match *tag {
+            REQUEST_GET_PUBKEY if body.is_empty() => Ok(Self::GetPubkey),
+            REQUEST_SIGN_MESSAGE => Ok(Self::SignMessage(body.to_vec())),
+            REQUEST_SIGN_OFFCHAIN_MESSAGE => Ok(Self::SignOffchainMessage(body.to_vec())),
+            _ => Err(SignerError::Protocol(format!(
+                "invalid request tag {tag:#04x}"
+            ))),
+        }
+    }
+}
+
+impl Response {
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::Pubkey(pubkey) => [&[RESPONSE_PUBKEY], pubkey.as_ref()].concat(),
+            Self::Signature(signature) => [&[RESPONSE_SIGNATURE], signature.as_ref()].concat(),
+            Self::Error(err) => {
+                let (code, message) = encode_error(err);
+                let mut payload = vec![RESPONSE_ERROR, code];
+                payload.extend_from_slice(message.as_bytes());
+                payload.truncate(MAX_FRAME_LEN);
+                payload
+            }
+        }
+    }
+
+    pub fn decode(payload: &[u8]) -> Result<Self, SignerError> {
+        let invalid = || SignerError::Protocol("invalid response".to_string());
+        match payload {
+            [RESPONSE_PUBKEY, body @ ..] => <[u8; 32]>::try_from(body)
+                .map(|bytes| Self::Pubkey(Pubkey::from(bytes)))
+                .map_err(|_| invalid()),
+            [RESPONSE_SIGNATURE, body @ ..] => <[u8; 64]>::try_from(body)
+                .map(|bytes| Self::Signature(Signature::from(bytes)))
+                .map_err(|_| invalid()),
+            [RESPONSE_ERROR, code, message @ ..] => Ok(Self::Error(decode_error(
+                *code,
+                String::from_utf8_lossy(message).into_owned(),
+            ))),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Maps a `SignerError` to its wire code and message:
+///
+/// | Code | Error                   |
+/// |------|-------------------------|
+/// | 0    | `Custom`                |
+/// | 1    | `KeypairPubkeyMismatch` |
+/// | 2    | `NotEnoughSigners`      |
+/// | 3    | `TooManySigners`        |
+/// | 4    | `Connection`            |
+/// | 5    | `InvalidInput`          |
+/// | 6    | `NoDeviceFound`         |
+/// | 7    | `Protocol`              |
+/// | 8    | `UserCancel`            |
+///
+/// Transaction and presigner errors are sent as `Custom` with their
+/// description.
+pub fn encode_error(err: &SignerError) -> (u8, String) {
+    match err {
+        SignerError::Custom(message) => (0, message.clone()),
+        SignerError::KeypairPubkeyMismatch => (1, String::new()),
+        SignerError::NotEnoughSigners => (2, String::new()),
+        SignerError::TooManySigners => (3, String::new()),
+        SignerError::Connection(message) => (4, message.clone()),
+        SignerError::InvalidInput(message) => (5, message.clone()),
+        SignerError::NoDeviceFound => (6, String::new()),
+        SignerError::Protocol(message) => (7, message.clone()),
+        SignerError::UserCancel(message) => (8, message.clone()),
+        SignerError::TransactionError(err) => (0, format!("transaction error: {err}")),
+        SignerError::PresignerError(err) => (0, format!("presigner error: {err}")),
+    }
+}
+
+/// Maps a wire code and message back to a `SignerError`, see
+/// [`encode_error`]. Unknown codes become `Custom` errors.
+pub fn decode_error(code: u8, message: String) -> SignerError {
+    match code {
+        1 => SignerError::KeypairPubkeyMismatch,
+        2 => SignerError::NotEnoughSigners,
+        3 => SignerError::TooManySigners,
+        4 => SignerError::Connection(message),
+        5 => SignerError::InvalidInput(message),
+        6 => SignerError::NoDeviceFound,
+        7 => SignerError::Protocol(message),
+        8 => SignerError::UserCancel(message),
+        _ => SignerError::Custom(message),
+    }
+}
+
+/// Writes one length-prefixed frame.
+pub fn write_frame<W: Write>(writer: &mut W, payload: &[u8]) -> io::Result<()> {
+    if payload.len() > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("frame of {} bytes exceeds the maximum", payload.len()),
+        ));
+    }
+    let len = u32::try_from(payload.len()).map_err(io::Error::other)?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(payload)?;
+    writer.flush()
+}
+
+/// Reads one length-prefixed frame, or returns `None` if the stream ended
+/// cleanly before the frame.
+///
+/// A stream ending within the frame, including within its length, fails
+/// with `UnexpectedEof`.
+pub fn read_frame<R: Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0; 4];
+    let mut filled = 0;
+    while filled < len.len() {
+        match reader.read(&mut len[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "stream ended within a frame length",
+                ))
+            }
+            Ok(read) => filled = filled.saturating_add(read),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    let len = usize::try_from(u32::from_le_bytes(len)).map_err(io::Error::other)?;
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {len} bytes exceeds the maximum"),
+        ));
+    }
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_signer::PresignerError};
+
+    #[test]
+    fn test_request_round_trip() {
+        for request in [
+            Request::GetPubkey,
+            Request::SignMessage(vec![1, 2, 3]),
+            Request::SignMessage(vec![]),
+            Request::SignOffchainMessage(vec![0xff; 100]),
+        ] {
+            assert_eq!(Request::decode(&request.encode()).unwrap(), request);
+        }
+        assert_eq!(Request::GetPubkey.encode(), vec![VERSION, 0x01]);
+
+        assert!(Request::decode(&[VERSION]).is_err());
+        assert!(Request::decode(&[VERSION, 0x01, 0]).is_err());
+        assert!(Request::decode(&[VERSION, 0x04]).is_err());
+        assert_eq!(
+            Request::decode(&[2, 0x01]).unwrap_err(),
+            SignerError::Protocol("unsupported protocol version 2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_response_round_trip() {
+        for response in [
+            Response::Pubkey(Pubkey::new_from_array([7; 32])),
+            Response::Signature(Signature::from([9; 64])),
+            Response::Error(SignerError::UserCancel("denied".to_string())),
+            Response::Error(SignerError::NoDeviceFound),
+        ] {
+            assert_eq!(Response::decode(&response.encode()).unwrap(), response);
+        }
+        assert!(Response::decode(&[RESPONSE_PUBKEY, 0]).is_err());
+        assert!(Response::decode(&[]).is_err());
+    }
+
+    #[test]
+    fn test_error_mapping() {
+        for err in [
+            SignerError::Custom("custom".to_string()),
+            SignerError::KeypairPubkeyMismatch,
+            SignerError::NotEnoughSigners,
+            SignerError::TooManySigners,
+            SignerError::Connection("connection".to_string()),
+            SignerError::InvalidInput("input".to_string()),
+            SignerError::NoDeviceFound,
+            SignerError::Protocol("protocol".to_string()),
+            SignerError::UserCancel("cancel".to_string()),
+        ] {
+            let (code, message) = encode_error(&err);
+            assert_eq!(decode_error(code, message), err);
+        }
+        let (code, message) = encode_error(&SignerError::PresignerError(
+            PresignerError::VerificationFailure,
+        ));
+        assert_eq!(
+            decode_error(code, message),
+            SignerError::Custom(
+                "presigner error: pre-generated signature cannot verify data".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_frames() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, &[1, 2, 3]).unwrap();
+        write_frame(&mut buffer, &[]).unwrap();
+        assert_eq!(&buffer[..7], &[3, 0, 0, 0, 1, 2, 3]);
+
+        let mut reader = buffer.as_slice();
+        assert_eq!(read_frame(&mut reader).unwrap(), Some(vec![1, 2, 3]));
+        assert_eq!(read_frame(&mut reader).unwrap(), Some(vec![]));
+        assert_eq!(read_frame(&mut reader).unwrap(), None);
+
+        assert!(write_frame(&mut Vec::new(), &vec![0; MAX_FRAME_LEN + 1]).is_err());
+        let oversized = u32::try_from(MAX_FRAME_LEN + 1).unwrap().to_le_bytes();
+        assert!(read_frame(&mut oversized.as_slice()).is_err());
+        // A frame cut short is an error, not a clean end of stream
+        assert!(read_frame(&mut [3, 0, 0, 0, 1].as_slice()).is_err());
+        for len in 1..4 {
+            assert_eq!(
+                read_frame(&mut [3, 0, 0, 0][..len].as_ref())
+                    .unwrap_err()
+                    .kind(),
+                io::ErrorKind::UnexpectedEof
+            );
+        }
+    }
+}
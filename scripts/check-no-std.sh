@@ -8,6 +8,7 @@ src_root="$(readlink -f "${here}/..")"
 cd "${src_root}"
 
 no_std_crates=(
+  -p solana-account-validation
   -p solana-account-view
   -p solana-address
   -p solana-blake3-hasher
@@ -63,6 +63,7 @@ all_crate_dirs=(
   packet
   poh-config
   poseidon
+  precompile-builder
   precompile-error
   presigner
   program
@@ -5,4 +5,4 @@ here="$(dirname "$0")"
 src_root="$(readlink -f "${here}/..")"
 cd "${src_root}"
 # miri is very slow; so only run very few of selective tests!
-./cargo nightly miri test -p solana-hash -p solana-account-info -p solana-account-view -p solana-instruction-view
+./cargo nightly miri test -p solana-hash -p solana-account-info -p solana-account-view -p solana-instruction-view -p solana-zero-copy
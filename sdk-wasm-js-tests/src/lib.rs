@@ -3,8 +3,14 @@
 #![cfg(target_arch = "wasm32")]
 #![allow(non_snake_case)]
 pub use solana_sdk_wasm_js::{
-    address::Address, hash::Hash, instruction::Instruction, keypair::Keypair, solana_program_init,
-    transaction::Transaction,
+    address::Address,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    keypair::Keypair,
+    message::{AddressLookupTableAccount, TransactionConfig, VersionedMessage},
+    signature::Signature,
+    solana_program_init,
+    transaction::{Transaction, VersionedTransaction},
 };
 use wasm_bindgen::prelude::*;
 
@@ -0,0 +1,158 @@
+import { expect } from "chai";
+import {
+  solana_program_init,
+  AccountMeta,
+  Address,
+  AddressLookupTableAccount,
+  Hash,
+  Instruction,
+  Keypair,
+  Signature,
+  TransactionConfig,
+  VersionedMessage,
+  VersionedTransaction,
+} from "crate";
+solana_program_init();
+
+const MAX_V1_TRANSACTION_SIZE = 4096;
+
+const programId = new Address("11111111111111111111111111111111");
+const dst = new Address("11111111111111111111111111111112");
+const recentBlockhash = new Hash(
+  "EETubP5AKHgjPAhzPAFcb8BAY1hMH639CWCFTqi3hq1k"
+);
+
+function transferInstruction(from, to) {
+  const instruction = new Instruction(programId);
+  instruction.setData(new Uint8Array([2, 0, 0, 0, 123, 0, 0, 0, 0, 0, 0, 0]));
+  instruction.addAccount(AccountMeta.newWritable(from, true));
+  instruction.addAccount(AccountMeta.newWritable(to, false));
+  return instruction;
+}
+
+function roundTrip(transaction) {
+  const bytes = transaction.toBytes();
+  const decoded = VersionedTransaction.fromBytes(bytes);
+  expect(Buffer.from(decoded.toBytes())).to.deep.equal(Buffer.from(bytes));
+  return decoded;
+}
+
+describe("VersionedTransaction", function () {
+  it("legacy", () => {
+    const payer = new Keypair();
+    const message = VersionedMessage.newLegacy(
+      payer.pubkey(),
+      [transferInstruction(payer.pubkey(), dst)],
+      recentBlockhash
+    );
+    expect(message.version()).to.equal("legacy");
+    expect(message.config()).to.be.undefined;
+
+    const transaction = new VersionedTransaction(message);
+    expect(transaction.isSigned()).to.be.false;
+    transaction.partialSign(payer);
+    expect(transaction.isSigned()).to.be.true;
+    transaction.verify();
+
+    const decoded = roundTrip(transaction);
+    decoded.verify();
+    expect(decoded.message().recentBlockhash().equals(recentBlockhash)).to.be
+      .true;
+  });
+
+  it("v0 with lookup tables", () => {
+    const payer = new Keypair();
+    const lookupTable = new AddressLookupTableAccount(
+      new Address("11111111111111111111111111111113"),
+      [new Address("11111111111111111111111111111114"), dst]
+    );
+    expect(lookupTable.addresses()[1].equals(dst)).to.be.true;
+
+    const message = VersionedMessage.newV0(
+      payer.pubkey(),
+      [transferInstruction(payer.pubkey(), dst)],
+      [lookupTable],
+      recentBlockhash
+    );
+    expect(message.version()).to.equal("v0");
+    expect(
+      message.staticAccountKeys().some((key) => key.equals(dst))
+    ).to.be.false;
+
+    const decodedMessage = VersionedMessage.fromBytes(message.toBytes());
+    expect(decodedMessage.version()).to.equal("v0");
+    expect(Buffer.from(decodedMessage.toBytes())).to.deep.equal(
+      Buffer.from(message.toBytes())
+    );
+
+    const transaction = new VersionedTransaction(message);
+    transaction.partialSign(payer);
+    transaction.verify();
+    roundTrip(transaction).verify();
+  });
+
+  it("v1 with config and external signature", () => {
+    const payer = new Keypair();
+    const cosigner = new Keypair();
+    const config = new TransactionConfig();
+    expect(config.priorityFee).to.be.undefined;
+    config.priorityFee = 5000n;
+    config.computeUnitLimit = 200000;
+    config.heapSize = 64 * 1024;
+    config.loadedAccountsDataSizeLimit = 1024 * 1024;
+
+    const message = VersionedMessage.newV1(
+      payer.pubkey(),
+      [transferInstruction(cosigner.pubkey(), dst)],
+      [],
+      recentBlockhash,
+      config
+    );
+    expect(message.version()).to.equal("v1");
+    const messageConfig = message.config();
+    expect(messageConfig.priorityFee).to.equal(5000n);
+    expect(messageConfig.computeUnitLimit).to.equal(200000);
+    expect(messageConfig.heapSize).to.equal(64 * 1024);
+    expect(messageConfig.loadedAccountsDataSizeLimit).to.equal(1024 * 1024);
+
+    // The cosigner signs a copy of the transaction, e.g. on another device,
+    // and only the signature is sent back.
+    const transaction = new VersionedTransaction(message);
+    const cosignerCopy = VersionedTransaction.fromBytes(transaction.toBytes());
+    cosignerCopy.partialSign(cosigner);
+    const signature = new Signature(cosignerCopy.signatures()[1].toString());
+    expect(signature.verify(cosigner.pubkey(), transaction.messageData())).to
+      .be.true;
+
+    transaction.partialSign(payer);
+    expect(transaction.isSigned()).to.be.false;
+    transaction.addSignature(cosigner.pubkey(), signature);
+    expect(transaction.isSigned()).to.be.true;
+    transaction.verify();
+
+    const decoded = roundTrip(transaction);
+    decoded.verify();
+    expect(decoded.message().config().priorityFee).to.equal(5000n);
+    expect(decoded.signatures()[1].equals(signature)).to.be.true;
+  });
+
+  it("rejects signatures of unexpected signers", () => {
+    const payer = new Keypair();
+    const message = VersionedMessage.newLegacy(
+      payer.pubkey(),
+      [transferInstruction(payer.pubkey(), dst)],
+      recentBlockhash
+    );
+    const transaction = new VersionedTransaction(message);
+    expect(() => {
+      transaction.partialSign(new Keypair());
+    }).to.throw(/is not a required signer/);
+  });
+
+  it("input length validation", () => {
+    const oversizedTxBytes = new Uint8Array(MAX_V1_TRANSACTION_SIZE + 1);
+    expect(() => {
+      VersionedTransaction.fromBytes(oversizedTxBytes);
+    }).to.throw(/Transaction size too large/);
+  });
+});
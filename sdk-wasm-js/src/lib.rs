@@ -12,6 +12,7 @@ pub mod hash;
 pub mod instruction;
 pub mod keypair;
 pub mod message;
+pub mod signature;
 pub mod transaction;
 
 /// Initialize Javascript logging and panic handler
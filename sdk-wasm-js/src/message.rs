@@ -1,4 +1,10 @@
-use wasm_bindgen::prelude::wasm_bindgen;
+#![allow(non_snake_case)]
+use {
+    crate::{address::Address, display_to_jsvalue, hash::Hash, instruction::Instruction},
+    js_sys::Uint8Array,
+    solana_message::{v0, v1, MessageVersion},
+    wasm_bindgen::prelude::{wasm_bindgen, JsValue},
+};
 
 #[wasm_bindgen]
 #[derive(Default, Debug, PartialEq, Eq, Clone)]
@@ -7,3 +13,223 @@ pub struct Message {
 }
 
 crate::conversion::impl_inner_conversion!(Message, solana_message::Message);
+
+/// wasm-bindgen version of the AddressLookupTableAccount struct, holding the
+/// addresses of an on-chain lookup table.
+#[wasm_bindgen]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AddressLookupTableAccount {
+    pub(crate) inner: solana_message::AddressLookupTableAccount,
+}
+
+crate::conversion::impl_inner_conversion!(
+    AddressLookupTableAccount,
+    solana_message::AddressLookupTableAccount
+);
+
+#[wasm_bindgen]
+impl AddressLookupTableAccount {
+    /// Create a new `AddressLookupTableAccount`
+    #[wasm_bindgen(constructor)]
+    pub fn constructor(key: Address, addresses: Vec<Address>) -> Self {
+        solana_message::AddressLookupTableAccount {
+            key: key.inner,
+            addresses: addresses.into_iter().map(|x| x.inner).collect(),
+        }
+        .into()
+    }
+
+    /// Return the address of the lookup table account
+    pub fn key(&self) -> Address {
+        self.inner.key.into()
+    }
+
+    /// Return the addresses stored in the lookup table
+    pub fn addresses(&self) -> Vec<Address> {
+        self.inner
+            .addresses
+            .iter()
+            .copied()
+            .map(Into::into)
+            .collect()
+    }
+}
+
+/// wasm-bindgen version of the V1 TransactionConfig struct.
+#[wasm_bindgen]
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TransactionConfig {
+    pub(crate) inner: v1::TransactionConfig,
+}
+
+crate::conversion::impl_inner_conversion!(TransactionConfig, v1::TransactionConfig);
+
+#[wasm_bindgen]
+impl TransactionConfig {
+    /// Create a new `TransactionConfig` with no values set
+    #[wasm_bindgen(constructor)]
+    pub fn constructor() -> Self {
+        v1::TransactionConfig::empty().into()
+    }
+
+    /// Priority fee in lamports
+    #[wasm_bindgen(getter)]
+    pub fn priorityFee(&self) -> Option<u64> {
+        self.inner.priority_fee
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_priorityFee(&mut self, priority_fee: Option<u64>) {
+        self.inner.priority_fee = priority_fee;
+    }
+
+    /// Maximum compute units
+    #[wasm_bindgen(getter)]
+    pub fn computeUnitLimit(&self) -> Option<u32> {
+        self.inner.compute_unit_limit
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_computeUnitLimit(&mut self, compute_unit_limit: Option<u32>) {
+        self.inner.compute_unit_limit = compute_unit_limit;
+    }
+
+    /// Maximum bytes of account data that may be loaded
+    #[wasm_bindgen(getter)]
+    pub fn loadedAccountsDataSizeLimit(&self) -> Option<u32> {
+        self.inner.loaded_accounts_data_size_limit
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_loadedAccountsDataSizeLimit(
+        &mut self,
+        loaded_accounts_data_size_limit: Option<u32>,
+    ) {
+        self.inner.loaded_accounts_data_size_limit = loaded_accounts_data_size_limit;
+    }
+
+    /// Heap size in bytes, a multiple of 1024
+    #[wasm_bindgen(getter)]
+    pub fn heapSize(&self) -> Option<u32> {
+        self.inner.heap_size
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_heapSize(&mut self, heap_size: Option<u32>) {
+        self.inner.heap_size = heap_size;
+    }
+}
+
+/// wasm-bindgen version of the VersionedMessage enum.
+#[wasm_bindgen]
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct VersionedMessage {
+    pub(crate) inner: solana_message::VersionedMessage,
+}
+
+crate::conversion::impl_inner_conversion!(VersionedMessage, solana_message::VersionedMessage);
+
+fn into_instructions(instructions: Vec<Instruction>) -> Vec<solana_instruction::Instruction> {
+    instructions.into_iter().map(|x| x.inner).collect()
+}
+
+fn into_lookup_tables(
+    lookup_tables: Vec<AddressLookupTableAccount>,
+) -> Vec<solana_message::AddressLookupTableAccount> {
+    lookup_tables.into_iter().map(|x| x.inner).collect()
+}
+
+#[wasm_bindgen]
+impl VersionedMessage {
+    /// Create a legacy message
+    pub fn newLegacy(
+        payer: &Address,
+        instructions: Vec<Instruction>,
+        recent_blockhash: &Hash,
+    ) -> Self {
+        solana_message::VersionedMessage::Legacy(solana_message::Message::new_with_blockhash(
+            &into_instructions(instructions),
+            Some(&payer.inner),
+            &recent_blockhash.inner,
+        ))
+        .into()
+    }
+
+    /// Create a v0 message, loading accounts from `lookupTables` where possible
+    pub fn newV0(
+        payer: &Address,
+        instructions: Vec<Instruction>,
+        lookup_tables: Vec<AddressLookupTableAccount>,
+        recent_blockhash: &Hash,
+    ) -> Result<VersionedMessage, JsValue> {
+        v0::Message::try_compile(
+            &payer.inner,
+            &into_instructions(instructions),
+            &into_lookup_tables(lookup_tables),
+            recent_blockhash.inner,
+        )
+        .map(|message| solana_message::VersionedMessage::V0(message).into())
+        .map_err(display_to_jsvalue)
+    }
+
+    /// Create a v1 message with the given transaction config
+    pub fn newV1(
+        payer: &Address,
+        instructions: Vec<Instruction>,
+        recent_blockhash: &Hash,
+        config: &TransactionConfig,
+    ) -> Result<VersionedMessage, JsValue> {
+        v1::Message::try_compile_with_config(
+            &payer.inner,
+            &into_instructions(instructions),
+            recent_blockhash.inner,
+            config.inner,
+        )
+        .map(|message| solana_message::VersionedMessage::V1(message).into())
+        .map_err(display_to_jsvalue)
+    }
+
+    /// Return the message version: "legacy", "v0" or "v1"
+    pub fn version(&self) -> String {
+        match &self.inner {
+            solana_message::VersionedMessage::Legacy(_) => MessageVersion::Legacy,
+            solana_message::VersionedMessage::V0(_) => MessageVersion::V0,
+            solana_message::VersionedMessage::V1(_) => MessageVersion::V1,
+        }
+        .to_string()
+    }
+
+    /// Return the recent blockhash, or lifetime specifier of a v1 message
+    pub fn recentBlockhash(&self) -> Hash {
+        (*self.inner.recent_blockhash()).into()
+    }
+
+    /// Return the account keys stored in the message, excluding those loaded
+    /// from lookup tables
+    pub fn staticAccountKeys(&self) -> Vec<Address> {
+        self.inner
+            .static_account_keys()
+            .iter()
+            .copied()
+            .map(Into::into)
+            .collect()
+    }
+
+    /// Return the transaction config of a v1 message
+    pub fn config(&self) -> Option<TransactionConfig> {
+        match &self.inner {
+            solana_message::VersionedMessage::V1(message) => Some(message.config.into()),
+            _ => None,
+        }
+    }
+
+    pub fn toBytes(&self) -> Box<[u8]> {
+        self.inner.serialize().into()
+    }
+
+    pub fn fromBytes(uint8_array: Uint8Array) -> Result<VersionedMessage, JsValue> {
+        wincode::deserialize::<solana_message::VersionedMessage>(&uint8_array.to_vec())
+            .map(Into::into)
+            .map_err(display_to_jsvalue)
+    }
+}
@@ -0,0 +1,67 @@
+//! Wrapper over `solana_signature::Signature` with wasm-bindgen
+use {
+    crate::{address::Address, display_to_jsvalue},
+    js_sys::Uint8Array,
+    solana_signature::SIGNATURE_BYTES,
+    std::{boxed::Box, format, string::String},
+    wasm_bindgen::{prelude::*, JsCast},
+};
+
+#[wasm_bindgen]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Signature {
+    pub(crate) inner: solana_signature::Signature,
+}
+
+crate::conversion::impl_inner_conversion!(Signature, solana_signature::Signature);
+
+#[allow(non_snake_case)]
+#[wasm_bindgen]
+impl Signature {
+    /// Create a new Signature object
+    ///
+    /// * `value` - signature as a base58 encoded string or `Uint8Array`
+    #[wasm_bindgen(constructor)]
+    pub fn constructor(value: JsValue) -> Result<Self, JsValue> {
+        if let Some(base58_str) = value.as_string() {
+            base58_str
+                .parse::<solana_signature::Signature>()
+                .map(Into::into)
+                .map_err(display_to_jsvalue)
+        } else if let Some(uint8_array) = value.dyn_ref::<Uint8Array>() {
+            if uint8_array.length() as usize != SIGNATURE_BYTES {
+                return Err(format!(
+                    "Invalid Uint8Array length: expected {}, got {}",
+                    SIGNATURE_BYTES,
+                    uint8_array.length()
+                )
+                .into());
+            }
+            let mut bytes = [0u8; SIGNATURE_BYTES];
+            uint8_array.copy_to(&mut bytes);
+            Ok(solana_signature::Signature::from(bytes).into())
+        } else {
+            Err("Unsupported argument".into())
+        }
+    }
+
+    /// Return the base58 string representation of the signature
+    pub fn toString(&self) -> String {
+        self.inner.to_string()
+    }
+
+    /// Checks if two `Signature`s are equal
+    pub fn equals(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+
+    /// Return the `Uint8Array` representation of the signature
+    pub fn toBytes(&self) -> Box<[u8]> {
+        self.inner.as_ref().into()
+    }
+
+    /// Verify the signature of `message` by `address`
+    pub fn verify(&self, address: &Address, message: &[u8]) -> bool {
+        self.inner.verify(address.inner.as_ref(), message)
+    }
+}
@@ -2,10 +2,18 @@
 #![allow(non_snake_case)]
 use {
     crate::{
-        address::Address, hash::Hash, instruction::Instruction, keypair::Keypair, message::Message,
+        address::Address,
+        display_to_jsvalue,
+        hash::Hash,
+        instruction::Instruction,
+        keypair::Keypair,
+        message::{Message, VersionedMessage},
+        signature::Signature,
     },
     js_sys::Uint8Array,
+    solana_message::{v1, MessageVersion},
     solana_packet::PACKET_DATA_SIZE,
+    solana_signer::Signer,
     wasm_bindgen::prelude::{wasm_bindgen, JsValue},
 };
 
@@ -59,7 +67,7 @@ impl Transaction {
 
     pub fn partialSign(&mut self, keypair: &Keypair, recent_blockhash: &Hash) {
         self.inner
-            .partial_sign(&[&keypair.inner], recent_blockhash.inner.clone());
+            .partial_sign(&[&keypair.inner], recent_blockhash.inner);
     }
 
     pub fn isSigned(&self) -> bool {
@@ -87,3 +95,146 @@ impl Transaction {
             .map_err(|x| std::string::ToString::to_string(&x).into())
     }
 }
+
+/// wasm-bindgen version of the VersionedTransaction struct, which can carry a
+/// legacy, v0 or v1 message.
+#[wasm_bindgen]
+#[derive(Debug, PartialEq, Default, Eq, Clone)]
+pub struct VersionedTransaction {
+    pub(crate) inner: solana_transaction::versioned::VersionedTransaction,
+}
+
+crate::conversion::impl_inner_conversion!(
+    VersionedTransaction,
+    solana_transaction::versioned::VersionedTransaction
+);
+
+#[wasm_bindgen]
+impl VersionedTransaction {
+    /// Create a new unsigned `VersionedTransaction`
+    #[wasm_bindgen(constructor)]
+    pub fn constructor(message: VersionedMessage) -> Self {
+        let num_required_signatures = message.inner.header().num_required_signatures;
+        solana_transaction::versioned::VersionedTransaction {
+            signatures: vec![
+                solana_signature::Signature::default();
+                usize::from(num_required_signatures)
+            ],
+            message: message.inner,
+        }
+        .into()
+    }
+
+    /// Return the message containing all data that should be signed.
+    #[wasm_bindgen(js_name = message)]
+    pub fn js_message(&self) -> VersionedMessage {
+        self.inner.message.clone().into()
+    }
+
+    /// Return the serialized message data to sign.
+    pub fn messageData(&self) -> Box<[u8]> {
+        self.inner.message.serialize().into()
+    }
+
+    /// Return the signatures, in the order of the required signers.
+    pub fn signatures(&self) -> Vec<Signature> {
+        self.inner
+            .signatures
+            .iter()
+            .copied()
+            .map(Into::into)
+            .collect()
+    }
+
+    /// Sign the message with `keypair`, which must be a required signer.
+    pub fn partialSign(&mut self, keypair: &Keypair) -> Result<(), JsValue> {
+        let signature = keypair.inner.sign_message(&self.inner.message.serialize());
+        self.addSignature(&keypair.js_pubkey(), &signature.into())
+    }
+
+    /// Add a signature produced elsewhere for the required signer `address`.
+    pub fn addSignature(
+        &mut self,
+        address: &Address,
+        signature: &Signature,
+    ) -> Result<(), JsValue> {
+        let num_required_signatures =
+            usize::from(self.inner.message.header().num_required_signatures);
+        let position = self
+            .inner
+            .message
+            .static_account_keys()
+            .get(..num_required_signatures)
+            .ok_or_else(|| JsValue::from("Message is missing required signer keys"))?
+            .iter()
+            .position(|key| *key == address.inner)
+            .ok_or_else(|| {
+                JsValue::from(std::format!("{} is not a required signer", address.inner))
+            })?;
+        let slot = self
+            .inner
+            .signatures
+            .get_mut(position)
+            .ok_or_else(|| JsValue::from("Transaction is missing signature slots"))?;
+        *slot = signature.inner;
+        Ok(())
+    }
+
+    /// Return true if every required signer has signed.
+    pub fn isSigned(&self) -> bool {
+        !self.inner.signatures.is_empty()
+            && self
+                .inner
+                .signatures
+                .iter()
+                .all(|signature| *signature != solana_signature::Signature::default())
+    }
+
+    /// Verify the transaction
+    #[wasm_bindgen(js_name = verify)]
+    pub fn js_verify(&self) -> Result<(), JsValue> {
+        self.inner.sanitize().map_err(display_to_jsvalue)?;
+        if self.inner.verify_with_results().iter().all(|x| *x) {
+            Ok(())
+        } else {
+            Err("Transaction failed signature verification".into())
+        }
+    }
+
+    pub fn toBytes(&self) -> Result<Box<[u8]>, JsValue> {
+        wincode::serialize(&self.inner)
+            .map(Into::into)
+            .map_err(display_to_jsvalue)
+    }
+
+    pub fn fromBytes(uint8_array: Uint8Array) -> Result<Self, JsValue> {
+        if uint8_array.length() as usize > v1::MAX_TRANSACTION_SIZE {
+            return Err(std::format!(
+                "Transaction size too large: {} > {}",
+                uint8_array.length(),
+                v1::MAX_TRANSACTION_SIZE
+            )
+            .into());
+        }
+
+        let bytes_vec = uint8_array.to_vec();
+        let transaction =
+            wincode::deserialize::<solana_transaction::versioned::VersionedTransaction>(&bytes_vec)
+                .map_err(display_to_jsvalue)?;
+
+        let version = match transaction.message {
+            solana_message::VersionedMessage::Legacy(_) => MessageVersion::Legacy,
+            solana_message::VersionedMessage::V0(_) => MessageVersion::V0,
+            solana_message::VersionedMessage::V1(_) => MessageVersion::V1,
+        };
+        if bytes_vec.len() > version.max_transaction_size() {
+            return Err(std::format!(
+                "Transaction size too large: {} > {}",
+                bytes_vec.len(),
+                version.max_transaction_size()
+            )
+            .into());
+        }
+        Ok(transaction.into())
+    }
+}
@@ -797,9 +797,18 @@ use serde_derive::{Deserialize, Serialize};
     feature = "bincode",
     not(any(target_os = "solana", target_arch = "bpf"))
 ))]
-use solana_instruction::Instruction;
+pub use solana_precompile_builder::DataRef;
 #[cfg(not(any(target_os = "solana", target_arch = "bpf")))]
 use solana_signature::error::Error;
+#[cfg(all(
+    feature = "bincode",
+    not(any(target_os = "solana", target_arch = "bpf"))
+))]
+use {
+    solana_instruction::Instruction,
+    solana_precompile_builder::{DataLocation, InstructionData},
+    solana_precompile_error::PrecompileError,
+};
 
 pub const SECP256K1_PUBKEY_SIZE: usize = 64;
 pub const SECP256K1_PRIVATE_KEY_SIZE: usize = 32;
@@ -833,6 +842,26 @@ pub struct SecpSignatureOffsets {
     pub message_instruction_index: u8,
 }
 
+#[cfg(all(
+    feature = "verify",
+    not(any(target_os = "solana", target_arch = "bpf"))
+))]
+impl SecpSignatureOffsets {
+    /// Reads offsets in their serialized, little-endian layout.
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        let u16_at = |i: usize| u16::from_le_bytes([bytes[i], bytes[i.saturating_add(1)]]);
+        Self {
+            signature_offset: u16_at(0),
+            signature_instruction_index: bytes[2],
+            eth_address_offset: u16_at(3),
+            eth_address_instruction_index: bytes[5],
+            message_data_offset: u16_at(6),
+            message_data_size: u16_at(8),
+            message_instruction_index: bytes[10],
+        }
+    }
+}
+
 /// Signs a message from the given private key bytes
 #[cfg(not(any(target_os = "solana", target_arch = "bpf")))]
 pub fn sign_message(
@@ -901,6 +930,106 @@ pub fn new_secp256k1_instruction_with_signature(
     }
 }
 
+/// Builds a secp256k1 instruction verifying many signatures.
+///
+/// Signatures are passed as the 64-byte signature followed by its recovery
+/// id, the layout in which the program reads them. Instruction indexes of
+/// [`DataRef::Instruction`] must fit in a `u8`.
+///
+/// ```
+/// # use solana_secp256k1_program::Secp256k1InstructionBuilder;
+/// # let (address_a, address_b, signature_a, signature_b) = ([1; 20], [2; 20], [3; 65], [4; 65]);
+/// let message = b"guardian set update";
+/// // The secp256k1 instruction is the first instruction of the transaction
+/// let instruction = Secp256k1InstructionBuilder::new(0)
+///     .add_signature(&address_a, &message[..], &signature_a)
+///     .add_signature(&address_b, &message[..], &signature_b)
+///     .build()
+///     .unwrap();
+/// ```
+#[cfg(all(
+    feature = "bincode",
+    not(any(target_os = "solana", target_arch = "bpf"))
+))]
+#[derive(Debug, Clone)]
+pub struct Secp256k1InstructionBuilder<'a> {
+    instruction_index: u8,
+    signatures: Vec<[DataRef<'a, [u8]>; 3]>,
+}
+
+#[cfg(all(
+    feature = "bincode",
+    not(any(target_os = "solana", target_arch = "bpf"))
+))]
+impl<'a> Secp256k1InstructionBuilder<'a> {
+    /// Creates a builder for the instruction at `instruction_index` of the
+    /// transaction, which inline data is referenced by.
+    pub fn new(instruction_index: u8) -> Self {
+        Self {
+            instruction_index,
+            signatures: Vec::new(),
+        }
+    }
+
+    /// Adds a signature of `message` by the key of `eth_address` to verify.
+    pub fn add_signature(
+        &mut self,
+        eth_address: impl Into<DataRef<'a, [u8; HASHED_PUBKEY_SERIALIZED_SIZE]>>,
+        message: impl Into<DataRef<'a, [u8]>>,
+        signature: impl Into<DataRef<'a, [u8; SIGNATURE_SERIALIZED_SIZE + 1]>>,
+    ) -> &mut Self {
+        self.signatures.push([
+            eth_address.into().as_bytes(),
+            message.into().as_bytes(),
+            signature.into().as_bytes(),
+        ]);
+        self
+    }
+
+    /// Builds the instruction.
+    ///
+    /// Fails with `InvalidInstructionDataSize` if more than 255 signatures
+    /// were added or if the inline data does not fit in the 16-bit offsets,
+    /// and with `InvalidDataOffsets` if an instruction index does not fit in
+    /// a `u8`.
+    pub fn build(&self) -> Result<Instruction, PrecompileError> {
+        let mut instruction_data = InstructionData::new(
+            self.signatures.len(),
+            1,
+            SIGNATURE_OFFSETS_SERIALIZED_SIZE,
+            u16::from(self.instruction_index),
+        )?;
+        // Only data in other instructions can have an out of range index
+        let instruction_index = |location: DataLocation| {
+            u8::try_from(location.instruction_index)
+                .map_err(|_| PrecompileError::InvalidDataOffsets)
+        };
+        for (i, [eth_address, message, signature]) in self.signatures.iter().enumerate() {
+            let eth_address = instruction_data.locate(*eth_address)?;
+            let message = instruction_data.locate(*message)?;
+            let signature = instruction_data.locate(*signature)?;
+            let offsets = SecpSignatureOffsets {
+                signature_offset: signature.offset,
+                signature_instruction_index: instruction_index(signature)?,
+                eth_address_offset: eth_address.offset,
+                eth_address_instruction_index: instruction_index(eth_address)?,
+                message_data_offset: message.offset,
+                message_data_size: message.size,
+                message_instruction_index: instruction_index(message)?,
+            };
+            let offsets =
+                bincode::serialize(&offsets).map_err(|_| PrecompileError::InvalidDataOffsets)?;
+            instruction_data.set_offsets(i, &offsets)?;
+        }
+
+        Ok(Instruction {
+            program_id: solana_sdk_ids::secp256k1_program::id(),
+            accounts: vec![],
+            data: instruction_data.into_vec(),
+        })
+    }
+}
+
 /// Creates an Ethereum address from a secp256k1 public key.
 pub fn eth_address_from_pubkey(
     pubkey: &[u8; SECP256K1_PUBKEY_SIZE],
@@ -912,6 +1041,134 @@ pub fn eth_address_from_pubkey(
     addr
 }
 
+/// Verifies a secp256k1 program instruction the way the runtime does.
+///
+/// `data` is the instruction data of the secp256k1 instruction and
+/// `instruction_datas` the data of every instruction in the transaction, in
+/// order, which the offsets refer to by index.
+///
+/// Like the runtime, this accepts signatures with a high `s` value.
+#[cfg(all(
+    feature = "verify",
+    not(any(target_os = "solana", target_arch = "bpf"))
+))]
+pub fn verify(
+    data: &[u8],
+    instruction_datas: &[&[u8]],
+) -> Result<(), solana_precompile_error::PrecompileError> {
+    use {
+        k256::{
+            ecdsa::{RecoveryId, Signature, VerifyingKey},
+            elliptic_curve::PrimeField,
+        },
+        solana_precompile_error::PrecompileError,
+    };
+
+    if data.is_empty() {
+        return Err(PrecompileError::InvalidInstructionDataSize);
+    }
+    let count = data[0] as usize;
+    if count == 0 && data.len() > 1 {
+        // count is zero but the instruction data indicates that is probably not
+        // correct, fail the instruction to catch probable invalid secp256k1
+        // instruction construction.
+        return Err(PrecompileError::InvalidInstructionDataSize);
+    }
+    let expected_data_size = count
+        .saturating_mul(SIGNATURE_OFFSETS_SERIALIZED_SIZE)
+        .saturating_add(1);
+    if data.len() < expected_data_size {
+        return Err(PrecompileError::InvalidInstructionDataSize);
+    }
+    for i in 0..count {
+        let start = i
+            .saturating_mul(SIGNATURE_OFFSETS_SERIALIZED_SIZE)
+            .saturating_add(1);
+        let end = start.saturating_add(SIGNATURE_OFFSETS_SERIALIZED_SIZE);
+        let offsets = SecpSignatureOffsets::from_le_bytes(&data[start..end]);
+
+        // Parse out signature
+        let signature_instruction = instruction_datas
+            .get(offsets.signature_instruction_index as usize)
+            .ok_or(PrecompileError::InvalidInstructionDataSize)?;
+        let sig_start = offsets.signature_offset as usize;
+        let sig_end = sig_start.saturating_add(SIGNATURE_SERIALIZED_SIZE);
+        // The recovery id follows the signature
+        if sig_end >= signature_instruction.len() {
+            return Err(PrecompileError::InvalidSignature);
+        }
+        let signature_bytes = &signature_instruction[sig_start..sig_end];
+        // Only out of range scalars are rejected here; zero scalars fail the
+        // recovery below, as in the runtime
+        let (r, s) = signature_bytes.split_at(SIGNATURE_SERIALIZED_SIZE / 2);
+        if [r, s].into_iter().any(|scalar| {
+            k256::Scalar::from_repr(*k256::FieldBytes::from_slice(scalar))
+                .is_none()
+                .into()
+        }) {
+            return Err(PrecompileError::InvalidSignature);
+        }
+        let recovery_id = RecoveryId::from_byte(signature_instruction[sig_end])
+            .ok_or(PrecompileError::InvalidRecoveryId)?;
+
+        let eth_address_slice = get_data_slice(
+            instruction_datas,
+            offsets.eth_address_instruction_index,
+            offsets.eth_address_offset,
+            HASHED_PUBKEY_SERIALIZED_SIZE,
+        )?;
+        let message_slice = get_data_slice(
+            instruction_datas,
+            offsets.message_instruction_index,
+            offsets.message_data_offset,
+            offsets.message_data_size as usize,
+        )?;
+        let message_hash = solana_keccak_hasher::hash(message_slice).to_bytes();
+
+        let mut signature = Signature::from_slice(signature_bytes)
+            .map_err(|_| PrecompileError::InvalidSignature)?;
+        let mut recovery_id = recovery_id;
+        // k256 only recovers from low-s signatures, negating s flips the parity
+        // of the recovered point
+        if let Some(normalized) = signature.normalize_s() {
+            signature = normalized;
+            recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), recovery_id.is_x_reduced());
+        }
+        let pubkey = VerifyingKey::recover_from_prehash(&message_hash, &signature, recovery_id)
+            .map_err(|_| PrecompileError::InvalidSignature)?;
+        let pubkey = pubkey.to_encoded_point(false);
+        let pubkey: &[u8; SECP256K1_PUBKEY_SIZE] = pubkey.as_bytes()[1..]
+            .try_into()
+            .map_err(|_| PrecompileError::InvalidSignature)?;
+        if eth_address_slice != eth_address_from_pubkey(pubkey) {
+            return Err(PrecompileError::InvalidSignature);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(
+    feature = "verify",
+    not(any(target_os = "solana", target_arch = "bpf"))
+))]
+fn get_data_slice<'a>(
+    instruction_datas: &'a [&[u8]],
+    instruction_index: u8,
+    offset_start: u16,
+    size: usize,
+) -> Result<&'a [u8], solana_precompile_error::PrecompileError> {
+    use solana_precompile_error::PrecompileError;
+
+    let instruction = instruction_datas
+        .get(instruction_index as usize)
+        .ok_or(PrecompileError::InvalidDataOffsets)?;
+    let start = offset_start as usize;
+    let end = start.saturating_add(size);
+    instruction
+        .get(start..end)
+        .ok_or(PrecompileError::InvalidSignature)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -935,4 +1192,210 @@ mod tests {
             ]
         );
     }
+
+    fn signing_key(seed: u8) -> ([u8; SECP256K1_PRIVATE_KEY_SIZE], [u8; 20]) {
+        let priv_key = [seed; SECP256K1_PRIVATE_KEY_SIZE];
+        let pubkey = k256::ecdsa::SigningKey::from_slice(&priv_key)
+            .unwrap()
+            .verifying_key()
+            .to_encoded_point(false);
+        let eth_address = eth_address_from_pubkey(pubkey.as_bytes()[1..].try_into().unwrap());
+        (priv_key, eth_address)
+    }
+
+    fn sign(priv_key: &[u8; SECP256K1_PRIVATE_KEY_SIZE], message: &[u8]) -> [u8; 65] {
+        let (signature, recovery_id) = sign_message(priv_key, message).unwrap();
+        let mut signature_with_recovery_id = [recovery_id; 65];
+        signature_with_recovery_id[..SIGNATURE_SERIALIZED_SIZE].copy_from_slice(&signature);
+        signature_with_recovery_id
+    }
+
+    fn signed_instruction(message: &[u8]) -> Instruction {
+        let (priv_key, eth_address) = signing_key(7);
+        let (signature, recovery_id) = sign_message(&priv_key, message).unwrap();
+        new_secp256k1_instruction_with_signature(message, &signature, recovery_id, &eth_address)
+    }
+
+    #[test]
+    fn test_verify() {
+        use solana_precompile_error::PrecompileError;
+
+        let data = signed_instruction(b"hello").data;
+        assert_eq!(verify(&data, &[&data]), Ok(()));
+
+        let mut tampered = data.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            verify(&tampered, &[&tampered]),
+            Err(PrecompileError::InvalidSignature)
+        );
+
+        let mut tampered = data.clone();
+        tampered[DATA_START + HASHED_PUBKEY_SERIALIZED_SIZE + SIGNATURE_SERIALIZED_SIZE] = 4;
+        assert_eq!(
+            verify(&tampered, &[&tampered]),
+            Err(PrecompileError::InvalidRecoveryId)
+        );
+
+        // Signatures are only read from the instructions of the transaction
+        assert_eq!(
+            verify(&data, &[]),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+    }
+
+    #[test]
+    fn test_verify_high_s() {
+        use {k256::ecdsa::Signature, solana_precompile_error::PrecompileError};
+
+        let mut data = signed_instruction(b"hello").data;
+        let sig_start = DATA_START + HASHED_PUBKEY_SERIALIZED_SIZE;
+        let sig_end = sig_start + SIGNATURE_SERIALIZED_SIZE;
+        let signature = Signature::from_slice(&data[sig_start..sig_end]).unwrap();
+        let (r, s) = signature.split_scalars();
+        let high_s = Signature::from_scalars(r, -*s).unwrap();
+        data[sig_start..sig_end].copy_from_slice(&high_s.to_bytes());
+        data[sig_end] ^= 1;
+        assert_eq!(verify(&data, &[&data]), Ok(()));
+
+        data[sig_end] ^= 1;
+        assert_eq!(
+            verify(&data, &[&data]),
+            Err(PrecompileError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_verify_data_size() {
+        use solana_precompile_error::PrecompileError;
+
+        assert_eq!(
+            verify(&[], &[]),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+        assert_eq!(verify(&[0], &[]), Ok(()));
+        assert_eq!(
+            verify(&[0, 0], &[]),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+        let data = signed_instruction(b"hello").data;
+        assert_eq!(
+            verify(&data[..DATA_START - 1], &[]),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+    }
+
+    #[test]
+    fn test_builder() {
+        let keys: Vec<_> = (1..=3).map(signing_key).collect();
+        let message = b"guardian set";
+        let signatures: Vec<_> = keys
+            .iter()
+            .map(|(priv_key, _)| sign(priv_key, message))
+            .collect();
+        let other_message = b"other message";
+        let other_signature = sign(&keys[0].0, other_message);
+
+        let mut builder = Secp256k1InstructionBuilder::new(1);
+        for ((_, eth_address), signature) in keys.iter().zip(&signatures) {
+            builder.add_signature(eth_address, &message[..], signature);
+        }
+        builder.add_signature(&keys[0].1, &other_message[..], &other_signature);
+        let data = builder.build().unwrap().data;
+        assert_eq!(data[0], 4);
+        // The repeated address and message are stored once
+        assert_eq!(
+            data.len(),
+            1 + 4 * SIGNATURE_OFFSETS_SERIALIZED_SIZE
+                + 3 * HASHED_PUBKEY_SERIALIZED_SIZE
+                + 4 * (SIGNATURE_SERIALIZED_SIZE + 1)
+                + message.len()
+                + other_message.len()
+        );
+        assert_eq!(verify(&data, &[&[], &data]), Ok(()));
+    }
+
+    #[test]
+    fn test_builder_instruction_data() {
+        use solana_precompile_error::PrecompileError;
+
+        let (priv_key, eth_address) = signing_key(7);
+        let message = b"message in another instruction";
+        let signature = sign(&priv_key, message);
+        let other_data = [&[0; 5][..], &message[..]].concat();
+
+        let data = Secp256k1InstructionBuilder::new(1)
+            .add_signature(
+                &eth_address,
+                DataRef::Instruction {
+                    index: 0,
+                    offset: 5,
+                    data: &message[..],
+                },
+                &signature,
+            )
+            .build()
+            .unwrap()
+            .data;
+        assert_eq!(verify(&data, &[&other_data, &data]), Ok(()));
+        assert_eq!(
+            verify(&data, &[&data, &other_data]),
+            Err(PrecompileError::InvalidSignature)
+        );
+
+        // The program reads 8-bit instruction indexes
+        assert_eq!(
+            Secp256k1InstructionBuilder::new(1)
+                .add_signature(
+                    &eth_address,
+                    DataRef::Instruction {
+                        index: 256,
+                        offset: 5,
+                        data: &message[..],
+                    },
+                    &signature,
+                )
+                .build(),
+            Err(PrecompileError::InvalidDataOffsets)
+        );
+    }
+
+    #[test]
+    fn test_verify_cross_instruction() {
+        use solana_precompile_error::PrecompileError;
+
+        let signed = signed_instruction(b"hello").data;
+        let offsets = SecpSignatureOffsets::from_le_bytes(&signed[1..DATA_START]);
+        let instruction_data = |offsets: &SecpSignatureOffsets| {
+            let mut data = vec![1];
+            data.extend(bincode::serialize(offsets).unwrap());
+            data
+        };
+
+        let data = instruction_data(&SecpSignatureOffsets {
+            signature_instruction_index: 1,
+            eth_address_instruction_index: 1,
+            message_instruction_index: 1,
+            ..offsets
+        });
+        assert_eq!(verify(&data, &[&data, &signed]), Ok(()));
+
+        let data = instruction_data(&SecpSignatureOffsets {
+            eth_address_instruction_index: 2,
+            ..offsets
+        });
+        assert_eq!(
+            verify(&data, &[&signed]),
+            Err(PrecompileError::InvalidDataOffsets)
+        );
+
+        let data = instruction_data(&SecpSignatureOffsets {
+            message_data_size: u16::MAX,
+            ..offsets
+        });
+        assert_eq!(
+            verify(&data, &[&signed]),
+            Err(PrecompileError::InvalidSignature)
+        );
+    }
 }
@@ -13,6 +13,17 @@
 use bytemuck::{Pod, Zeroable};
 pub use solana_sdk_ids::secp256r1_program::{check_id, id, ID};
 
+#[cfg(feature = "webauthn")]
+pub mod webauthn;
+
+pub const COMPRESSED_PUBKEY_SERIALIZED_SIZE: usize = 33;
+pub const SIGNATURE_SERIALIZED_SIZE: usize = 64;
+pub const SIGNATURE_OFFSETS_SERIALIZED_SIZE: usize = 14;
+pub const SIGNATURE_OFFSETS_START: usize = 2;
+pub const DATA_START: usize = SIGNATURE_OFFSETS_SERIALIZED_SIZE + SIGNATURE_OFFSETS_START;
+/// Most signatures the runtime verifies in one secp256r1 instruction.
+pub const MAX_SIGNATURES: usize = 8;
+
 #[derive(Default, Debug, Copy, Clone, Zeroable, Pod, Eq, PartialEq)]
 #[repr(C)]
 pub struct Secp256r1SignatureOffsets {
@@ -40,19 +51,20 @@ pub struct Secp256r1SignatureOffsets {
 
 #[cfg(all(not(target_arch = "wasm32"), not(target_os = "solana")))]
 mod target_arch {
+    pub use solana_precompile_builder::DataRef;
     use {
-        crate::Secp256r1SignatureOffsets,
+        crate::{
+            Secp256r1SignatureOffsets, COMPRESSED_PUBKEY_SERIALIZED_SIZE, DATA_START,
+            MAX_SIGNATURES, SIGNATURE_OFFSETS_SERIALIZED_SIZE, SIGNATURE_OFFSETS_START,
+            SIGNATURE_SERIALIZED_SIZE,
+        },
         bytemuck::bytes_of,
         openssl::{bn::BigNum, ec::EcKey, ecdsa::EcdsaSig, nid::Nid, pkey::PKey, sign::Signer},
         solana_instruction::Instruction,
+        solana_precompile_builder::InstructionData,
+        solana_precompile_error::PrecompileError,
     };
 
-    pub const COMPRESSED_PUBKEY_SERIALIZED_SIZE: usize = 33;
-    pub const SIGNATURE_SERIALIZED_SIZE: usize = 64;
-    pub const SIGNATURE_OFFSETS_SERIALIZED_SIZE: usize = 14;
-    pub const SIGNATURE_OFFSETS_START: usize = 2;
-    pub const DATA_START: usize = SIGNATURE_OFFSETS_SERIALIZED_SIZE + SIGNATURE_OFFSETS_START;
-
     // Order as defined in SEC2: 2.7.2 Recommended Parameters secp256r1
     pub const SECP256R1_ORDER: [u8; FIELD_SIZE] = [
         0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
@@ -90,10 +102,20 @@ mod target_arch {
         let mut signer = Signer::new(openssl::hash::MessageDigest::sha256(), &signing_key_pkey)?;
         signer.update(message)?;
         let signature = signer.sign_to_vec()?;
+        compact_signature_from_der(&signature)
+    }
 
-        let ecdsa_sig = EcdsaSig::from_der(&signature)?;
+    /// Converts a DER-encoded ECDSA signature to the compact form the
+    /// precompile expects, normalizing `s` to the lower half of the order.
+    pub fn compact_signature_from_der(
+        der: &[u8],
+    ) -> Result<[u8; SIGNATURE_SERIALIZED_SIZE], Box<dyn core::error::Error>> {
+        let ecdsa_sig = EcdsaSig::from_der(der)?;
         let r = ecdsa_sig.r().to_vec();
         let s = ecdsa_sig.s().to_vec();
+        if r.len() > FIELD_SIZE || s.len() > FIELD_SIZE {
+            return Err("Signature scalars must be at most 32 bytes".into());
+        }
         let mut signature = [0u8; SIGNATURE_SERIALIZED_SIZE];
 
         // Incase of an r or s value of 31 bytes we need to pad it to 32 bytes
@@ -164,6 +186,416 @@ mod target_arch {
             data: instruction_data,
         }
     }
+
+    /// Builds a secp256r1 instruction verifying many signatures.
+    ///
+    /// ```
+    /// # use solana_secp256r1_program::Secp256r1InstructionBuilder;
+    /// # let (pubkey_a, pubkey_b, signature_a, signature_b) = ([1; 33], [2; 33], [3; 64], [4; 64]);
+    /// let message = b"guardian set update";
+    /// let instruction = Secp256r1InstructionBuilder::default()
+    ///     .add_signature(&pubkey_a, &message[..], &signature_a)
+    ///     .add_signature(&pubkey_b, &message[..], &signature_b)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[derive(Debug, Default, Clone)]
+    pub struct Secp256r1InstructionBuilder<'a> {
+        signatures: Vec<[DataRef<'a, [u8]>; 3]>,
+    }
+
+    impl<'a> Secp256r1InstructionBuilder<'a> {
+        /// Adds a signature of `message` by `pubkey` to verify.
+        pub fn add_signature(
+            &mut self,
+            pubkey: impl Into<DataRef<'a, [u8; COMPRESSED_PUBKEY_SERIALIZED_SIZE]>>,
+            message: impl Into<DataRef<'a, [u8]>>,
+            signature: impl Into<DataRef<'a, [u8; SIGNATURE_SERIALIZED_SIZE]>>,
+        ) -> &mut Self {
+            self.signatures.push([
+                pubkey.into().as_bytes(),
+                message.into().as_bytes(),
+                signature.into().as_bytes(),
+            ]);
+            self
+        }
+
+        /// Builds the instruction.
+        ///
+        /// Fails with `InvalidInstructionDataSize` unless between 1 and
+        /// [`MAX_SIGNATURES`] signatures were added, which the runtime
+        /// requires, or if the inline data does not fit in the 16-bit offsets.
+        pub fn build(&self) -> Result<Instruction, PrecompileError> {
+            if !(1..=MAX_SIGNATURES).contains(&self.signatures.len()) {
+                return Err(PrecompileError::InvalidInstructionDataSize);
+            }
+            let mut instruction_data = InstructionData::new(
+                self.signatures.len(),
+                SIGNATURE_OFFSETS_START,
+                SIGNATURE_OFFSETS_SERIALIZED_SIZE,
+                u16::MAX,
+            )?;
+            for (i, [pubkey, message, signature]) in self.signatures.iter().enumerate() {
+                let pubkey = instruction_data.locate(*pubkey)?;
+                let message = instruction_data.locate(*message)?;
+                let signature = instruction_data.locate(*signature)?;
+                let offsets = Secp256r1SignatureOffsets {
+                    signature_offset: signature.offset,
+                    signature_instruction_index: signature.instruction_index,
+                    public_key_offset: pubkey.offset,
+                    public_key_instruction_index: pubkey.instruction_index,
+                    message_data_offset: message.offset,
+                    message_data_size: message.size,
+                    message_instruction_index: message.instruction_index,
+                };
+                instruction_data.set_offsets(i, bytes_of(&offsets))?;
+            }
+
+            Ok(Instruction {
+                program_id: crate::id(),
+                accounts: vec![],
+                data: instruction_data.into_vec(),
+            })
+        }
+    }
+
+    /// Verifies a secp256r1 program instruction the way the runtime does.
+    ///
+    /// `data` is the instruction data of the secp256r1 instruction and
+    /// `instruction_datas` the data of every instruction in the transaction,
+    /// in order, which the offsets can refer to by index. An instruction index
+    /// of `u16::MAX` refers to `data` itself.
+    #[cfg(feature = "verify")]
+    pub fn verify(
+        data: &[u8],
+        instruction_datas: &[&[u8]],
+    ) -> Result<(), solana_precompile_error::PrecompileError> {
+        use {
+            openssl::{
+                bn::BigNumContext,
+                ec::{EcGroup, EcPoint},
+                sign::Verifier,
+            },
+            solana_precompile_error::PrecompileError,
+        };
+
+        if data.len() < SIGNATURE_OFFSETS_START {
+            return Err(PrecompileError::InvalidInstructionDataSize);
+        }
+        let num_signatures = data[0] as usize;
+        if num_signatures == 0 || num_signatures > MAX_SIGNATURES {
+            return Err(PrecompileError::InvalidInstructionDataSize);
+        }
+        let expected_data_size = num_signatures
+            .saturating_mul(SIGNATURE_OFFSETS_SERIALIZED_SIZE)
+            .saturating_add(SIGNATURE_OFFSETS_START);
+        // The byte at data[1] is padding and is not checked
+        if data.len() < expected_data_size {
+            return Err(PrecompileError::InvalidInstructionDataSize);
+        }
+
+        let half_order = BigNum::from_slice(&SECP256R1_HALF_ORDER)
+            .map_err(|_| PrecompileError::InvalidSignature)?;
+        let order_minus_one = BigNum::from_slice(&SECP256R1_ORDER_MINUS_ONE)
+            .map_err(|_| PrecompileError::InvalidSignature)?;
+        let one = BigNum::from_u32(1).map_err(|_| PrecompileError::InvalidSignature)?;
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)
+            .map_err(|_| PrecompileError::InvalidSignature)?;
+        let mut ctx = BigNumContext::new().map_err(|_| PrecompileError::InvalidSignature)?;
+
+        for i in 0..num_signatures {
+            let start = i
+                .saturating_mul(SIGNATURE_OFFSETS_SERIALIZED_SIZE)
+                .saturating_add(SIGNATURE_OFFSETS_START);
+            let end = start.saturating_add(SIGNATURE_OFFSETS_SERIALIZED_SIZE);
+            let offsets: Secp256r1SignatureOffsets =
+                bytemuck::pod_read_unaligned(&data[start..end]);
+
+            let signature = get_data_slice(
+                data,
+                instruction_datas,
+                offsets.signature_instruction_index,
+                offsets.signature_offset,
+                SIGNATURE_SERIALIZED_SIZE,
+            )?;
+            let pubkey = get_data_slice(
+                data,
+                instruction_datas,
+                offsets.public_key_instruction_index,
+                offsets.public_key_offset,
+                COMPRESSED_PUBKEY_SERIALIZED_SIZE,
+            )?;
+            let message = get_data_slice(
+                data,
+                instruction_datas,
+                offsets.message_instruction_index,
+                offsets.message_data_offset,
+                offsets.message_data_size as usize,
+            )?;
+
+            let r = BigNum::from_slice(&signature[..FIELD_SIZE])
+                .map_err(|_| PrecompileError::InvalidSignature)?;
+            let s = BigNum::from_slice(&signature[FIELD_SIZE..])
+                .map_err(|_| PrecompileError::InvalidSignature)?;
+            // Only low-s signatures are accepted
+            if r < one || r > order_minus_one || s < one || s > half_order {
+                return Err(PrecompileError::InvalidSignature);
+            }
+            let ecdsa_sig = EcdsaSig::from_private_components(r, s)
+                .and_then(|sig| sig.to_der())
+                .map_err(|_| PrecompileError::InvalidSignature)?;
+
+            let public_key = EcPoint::from_bytes(&group, pubkey, &mut ctx)
+                .and_then(|point| EcKey::from_public_key(&group, &point))
+                .and_then(PKey::from_ec_key)
+                .map_err(|_| PrecompileError::InvalidPublicKey)?;
+
+            let mut verifier = Verifier::new(openssl::hash::MessageDigest::sha256(), &public_key)
+                .map_err(|_| PrecompileError::InvalidSignature)?;
+            verifier
+                .update(message)
+                .map_err(|_| PrecompileError::InvalidSignature)?;
+            if !verifier
+                .verify(&ecdsa_sig)
+                .map_err(|_| PrecompileError::InvalidSignature)?
+            {
+                return Err(PrecompileError::InvalidSignature);
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "verify")]
+    fn get_data_slice<'a>(
+        data: &'a [u8],
+        instruction_datas: &'a [&[u8]],
+        instruction_index: u16,
+        offset_start: u16,
+        size: usize,
+    ) -> Result<&'a [u8], solana_precompile_error::PrecompileError> {
+        use solana_precompile_error::PrecompileError;
+
+        let instruction = if instruction_index == u16::MAX {
+            data
+        } else {
+            instruction_datas
+                .get(instruction_index as usize)
+                .ok_or(PrecompileError::InvalidDataOffsets)?
+        };
+        let start = offset_start as usize;
+        let end = start.saturating_add(size);
+        instruction
+            .get(start..end)
+            .ok_or(PrecompileError::InvalidDataOffsets)
+    }
 }
 
 pub use self::target_arch::*;
+
+#[cfg(all(test, not(target_arch = "wasm32"), not(target_os = "solana")))]
+mod tests {
+    use {
+        super::*,
+        bytemuck::bytes_of,
+        openssl::{
+            bn::{BigNum, BigNumContext},
+            ec::{EcGroup, EcKey, PointConversionForm},
+            nid::Nid,
+        },
+        solana_precompile_error::PrecompileError,
+    };
+
+    fn signing_key() -> (Vec<u8>, [u8; COMPRESSED_PUBKEY_SERIALIZED_SIZE]) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = EcKey::generate(&group).unwrap();
+        let mut ctx = BigNumContext::new().unwrap();
+        let pubkey = key
+            .public_key()
+            .to_bytes(&group, PointConversionForm::COMPRESSED, &mut ctx)
+            .unwrap();
+        (
+            key.private_key_to_der().unwrap(),
+            pubkey.try_into().unwrap(),
+        )
+    }
+
+    fn signed_instruction_data(message: &[u8]) -> Vec<u8> {
+        let (der, pubkey) = signing_key();
+        let signature = sign_message(message, &der).unwrap();
+        new_secp256r1_instruction_with_signature(message, &signature, &pubkey).data
+    }
+
+    #[test]
+    fn test_verify() {
+        let data = signed_instruction_data(b"hello");
+        assert_eq!(verify(&data, &[&data]), Ok(()));
+
+        let mut tampered = data.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            verify(&tampered, &[&tampered]),
+            Err(PrecompileError::InvalidSignature)
+        );
+
+        let mut tampered = data.clone();
+        tampered[DATA_START] = 0xff;
+        assert_eq!(
+            verify(&tampered, &[&tampered]),
+            Err(PrecompileError::InvalidPublicKey)
+        );
+    }
+
+    #[test]
+    fn test_verify_high_s() {
+        let mut data = signed_instruction_data(b"hello");
+        let s_start = DATA_START + COMPRESSED_PUBKEY_SERIALIZED_SIZE + FIELD_SIZE;
+        let s_end = s_start + FIELD_SIZE;
+        let s = BigNum::from_slice(&data[s_start..s_end]).unwrap();
+        let order = BigNum::from_slice(&SECP256R1_ORDER).unwrap();
+        let mut high_s = BigNum::new().unwrap();
+        high_s.checked_sub(&order, &s).unwrap();
+        data[s_start..s_end].copy_from_slice(&high_s.to_vec_padded(FIELD_SIZE as i32).unwrap());
+        assert_eq!(
+            verify(&data, &[&data]),
+            Err(PrecompileError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_verify_data_size() {
+        assert_eq!(
+            verify(&[], &[]),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+        assert_eq!(
+            verify(&[0, 0], &[]),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+        assert_eq!(
+            verify(&[9, 0], &[]),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+        let data = signed_instruction_data(b"hello");
+        assert_eq!(
+            verify(&data[..DATA_START - 1], &[]),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+    }
+
+    #[test]
+    fn test_builder() {
+        let keys: Vec<_> = (0..3).map(|_| signing_key()).collect();
+        let message = b"guardian set";
+        let signatures: Vec<_> = keys
+            .iter()
+            .map(|(der, _)| sign_message(message, der).unwrap())
+            .collect();
+        let other_message = b"other message";
+        let other_signature = sign_message(other_message, &keys[0].0).unwrap();
+
+        let mut builder = Secp256r1InstructionBuilder::default();
+        for ((_, pubkey), signature) in keys.iter().zip(&signatures) {
+            builder.add_signature(pubkey, &message[..], signature);
+        }
+        builder.add_signature(&keys[0].1, &other_message[..], &other_signature);
+        let data = builder.build().unwrap().data;
+        assert_eq!(data[0], 4);
+        // The repeated pubkey and message are stored once
+        assert_eq!(
+            data.len(),
+            SIGNATURE_OFFSETS_START
+                + 4 * SIGNATURE_OFFSETS_SERIALIZED_SIZE
+                + 3 * COMPRESSED_PUBKEY_SERIALIZED_SIZE
+                + 4 * SIGNATURE_SERIALIZED_SIZE
+                + message.len()
+                + other_message.len()
+        );
+        assert_eq!(verify(&data, &[&data]), Ok(()));
+    }
+
+    #[test]
+    fn test_builder_signature_count() {
+        let (der, pubkey) = signing_key();
+        let message = b"message";
+        let signature = sign_message(message, &der).unwrap();
+
+        let mut builder = Secp256r1InstructionBuilder::default();
+        assert_eq!(
+            builder.build(),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+        for _ in 0..MAX_SIGNATURES {
+            builder.add_signature(&pubkey, &message[..], &signature);
+        }
+        let data = builder.build().unwrap().data;
+        assert_eq!(verify(&data, &[&data]), Ok(()));
+        builder.add_signature(&pubkey, &message[..], &signature);
+        assert_eq!(
+            builder.build(),
+            Err(PrecompileError::InvalidInstructionDataSize)
+        );
+    }
+
+    #[test]
+    fn test_builder_instruction_data() {
+        let (der, pubkey) = signing_key();
+        let message = b"message in another instruction";
+        let signature = sign_message(message, &der).unwrap();
+        let other_data = [&[0; 5][..], &message[..]].concat();
+
+        let data = Secp256r1InstructionBuilder::default()
+            .add_signature(
+                &pubkey,
+                DataRef::Instruction {
+                    index: 0,
+                    offset: 5,
+                    data: &message[..],
+                },
+                &signature,
+            )
+            .build()
+            .unwrap()
+            .data;
+        assert_eq!(verify(&data, &[&other_data, &data]), Ok(()));
+        assert_eq!(
+            verify(&data, &[&data, &other_data]),
+            Err(PrecompileError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_verify_cross_instruction() {
+        let signed = signed_instruction_data(b"hello");
+        let offsets: Secp256r1SignatureOffsets =
+            bytemuck::pod_read_unaligned(&signed[SIGNATURE_OFFSETS_START..DATA_START]);
+        let instruction_data = |offsets: &Secp256r1SignatureOffsets| {
+            let mut data = vec![1, 0];
+            data.extend_from_slice(bytes_of(offsets));
+            data
+        };
+
+        let data = instruction_data(&Secp256r1SignatureOffsets {
+            signature_instruction_index: 1,
+            public_key_instruction_index: 1,
+            message_instruction_index: 1,
+            ..offsets
+        });
+        assert_eq!(verify(&data, &[&data, &signed]), Ok(()));
+        assert_eq!(
+            verify(&data, &[&data]),
+            Err(PrecompileError::InvalidDataOffsets)
+        );
+
+        let data = instruction_data(&Secp256r1SignatureOffsets {
+            signature_instruction_index: 0,
+            public_key_instruction_index: 0,
+            message_instruction_index: 0,
+            message_data_size: u16::MAX,
+            ..offsets
+        });
+        assert_eq!(
+            verify(&data, &[&signed]),
+            Err(PrecompileError::InvalidDataOffsets)
+        );
+    }
+}
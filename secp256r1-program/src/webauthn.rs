@@ -0,0 +1,499 @@
+//! Signatures made by WebAuthn authenticators, such as passkeys.
+//!
+//! An authenticator does not sign the challenge directly; it signs
+//! `authenticatorData || sha256(clientDataJSON)`, where `clientDataJSON`
+//! carries the challenge. [`WebAuthnAssertion::to_instruction`] builds a
+//! secp256r1 instruction verifying that message and appends `clientDataJSON`
+//! to its data. A program reading the instruction through the instructions
+//! sysvar can then use [`WebAuthnInstruction`] to check the challenge.
+//!
+//! The precompile only proves that the key signed the message. Besides the
+//! challenge, a program must check that the relying party ID hash, origin
+//! and user presence flag are the ones it expects, which
+//! [`WebAuthnInstruction::check`] does in one call.
+
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "solana")))]
+use {
+    crate::{compact_signature_from_der, new_secp256r1_instruction_with_signature},
+    base64::engine::{general_purpose::GeneralPurposeConfig, DecodePaddingMode, GeneralPurpose},
+    solana_instruction::Instruction,
+};
+use {
+    crate::{
+        Secp256r1SignatureOffsets, COMPRESSED_PUBKEY_SERIALIZED_SIZE, DATA_START,
+        SIGNATURE_OFFSETS_START, SIGNATURE_SERIALIZED_SIZE,
+    },
+    base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine},
+    core::fmt,
+};
+
+/// Minimum length of authenticator data: the RP ID hash, flags and signature
+/// counter.
+pub const AUTHENTICATOR_DATA_MIN_LEN: usize = 37;
+/// Length of the client data hash at the end of the signed message.
+pub const CLIENT_DATA_HASH_LEN: usize = 32;
+
+/// Length of the relying party ID hash at the start of authenticator data.
+pub const RP_ID_HASH_LEN: usize = 32;
+/// Authenticator data flag set when the user was present.
+pub const FLAG_USER_PRESENT: u8 = 0x01;
+/// Authenticator data flag set when the user was verified.
+pub const FLAG_USER_VERIFIED: u8 = 0x04;
+
+// Start of client data serialized as in the limited verification algorithm
+// of the WebAuthn specification, which fixes the order of the members
+const CLIENT_DATA_PREFIX: &str = r#"{"type":"webauthn.get","challenge":""#;
+const ORIGIN_KEY: &str = r#","origin":""#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebAuthnError {
+    /// The assertion response is not valid JSON or lacks a field.
+    InvalidAssertion,
+    /// The signature is not a valid DER-encoded P-256 signature.
+    InvalidSignature,
+    /// The instruction data is not a single self-contained secp256r1
+    /// signature followed by the client data.
+    InvalidInstructionData,
+    /// The client data does not hash to the hash in the signed message.
+    ClientDataHashMismatch,
+    /// The client data does not start with the type of a `webauthn.get`
+    /// ceremony, the challenge and the origin, in that order.
+    InvalidClientData,
+    /// The challenge in the client data is not the expected one.
+    ChallengeMismatch,
+    /// The origin in the client data is not the expected one.
+    OriginMismatch,
+    /// The authenticator data is not for the expected relying party ID.
+    RpIdMismatch,
+    /// The authenticator data does not have the user present flag.
+    UserNotPresent,
+}
+
+impl fmt::Display for WebAuthnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidAssertion => f.write_str("invalid WebAuthn assertion response"),
+            Self::InvalidSignature => f.write_str("invalid WebAuthn signature"),
+            Self::InvalidInstructionData => f.write_str("invalid WebAuthn instruction data"),
+            Self::ClientDataHashMismatch => f.write_str("client data hash mismatch"),
+            Self::InvalidClientData => f.write_str("invalid client data"),
+            Self::ChallengeMismatch => f.write_str("challenge mismatch"),
+            Self::OriginMismatch => f.write_str("origin mismatch"),
+            Self::RpIdMismatch => f.write_str("relying party ID mismatch"),
+            Self::UserNotPresent => f.write_str("user not present"),
+        }
+    }
+}
+
+impl core::error::Error for WebAuthnError {}
+
+/// A WebAuthn assertion response, as returned by `navigator.credentials.get`.
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "solana")))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebAuthnAssertion {
+    pub authenticator_data: Vec<u8>,
+    pub client_data_json: Vec<u8>,
+    /// DER-encoded signature, possibly with a high `s` value.
+    pub signature: Vec<u8>,
+}
+
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "solana")))]
+impl WebAuthnAssertion {
+    /// Parses the JSON serialization of a `PublicKeyCredential`, as produced
+    /// by its `toJSON` method, or of just its `response` member.
+    ///
+    /// The binary fields are base64url-encoded, with or without padding.
+    pub fn from_json(json: &str) -> Result<Self, WebAuthnError> {
+        const BASE64_URL_SAFE_INDIFFERENT: GeneralPurpose = GeneralPurpose::new(
+            &base64::alphabet::URL_SAFE,
+            GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+        );
+
+        let value: serde_json::Value =
+            serde_json::from_str(json).map_err(|_| WebAuthnError::InvalidAssertion)?;
+        let response = value.get("response").unwrap_or(&value);
+        let field = |name| {
+            response
+                .get(name)
+                .and_then(serde_json::Value::as_str)
+                .and_then(|field| BASE64_URL_SAFE_INDIFFERENT.decode(field).ok())
+                .ok_or(WebAuthnError::InvalidAssertion)
+        };
+        Ok(Self {
+            authenticator_data: field("authenticatorData")?,
+            client_data_json: field("clientDataJSON")?,
+            signature: field("signature")?,
+        })
+    }
+
+    /// Returns the message the authenticator signed,
+    /// `authenticatorData || sha256(clientDataJSON)`.
+    pub fn signed_message(&self) -> Vec<u8> {
+        let mut message = self.authenticator_data.clone();
+        message.extend_from_slice(solana_sha256_hasher::hash(&self.client_data_json).as_bytes());
+        message
+    }
+
+    /// Returns the signature in the low-S compact form the precompile
+    /// expects.
+    pub fn compact_signature(&self) -> Result<[u8; SIGNATURE_SERIALIZED_SIZE], WebAuthnError> {
+        compact_signature_from_der(&self.signature).map_err(|_| WebAuthnError::InvalidSignature)
+    }
+
+    /// Builds a secp256r1 instruction verifying the assertion of `pubkey`,
+    /// with the client data appended so that programs can check the
+    /// challenge with [`WebAuthnInstruction`].
+    pub fn to_instruction(
+        &self,
+        pubkey: &[u8; COMPRESSED_PUBKEY_SERIALIZED_SIZE],
+    ) -> Result<Instruction, WebAuthnError> {
+        if self.authenticator_data.len() < AUTHENTICATOR_DATA_MIN_LEN {
+            return Err(WebAuthnError::InvalidAssertion);
+        }
+        let signature = self.compact_signature()?;
+        let mut instruction =
+            new_secp256r1_instruction_with_signature(&self.signed_message(), &signature, pubkey);
+        instruction.data.extend_from_slice(&self.client_data_json);
+        Ok(instruction)
+    }
+}
+
+/// The parts of a secp256r1 instruction built by
+/// [`WebAuthnAssertion::to_instruction`].
+///
+/// Parsing does not check that the data belongs to a secp256r1 instruction;
+/// programs must check the program id of the instruction they read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WebAuthnInstruction<'a> {
+    pub public_key: &'a [u8; COMPRESSED_PUBKEY_SERIALIZED_SIZE],
+    pub authenticator_data: &'a [u8],
+    pub client_data_json: &'a [u8],
+}
+
+impl<'a> WebAuthnInstruction<'a> {
+    /// Parses secp256r1 instruction data, checking that the appended client
+    /// data is the one whose hash was signed.
+    pub fn parse(data: &'a [u8]) -> Result<Self, WebAuthnError> {
+        if data.len() < DATA_START || data[0] != 1 {
+            return Err(WebAuthnError::InvalidInstructionData);
+        }
+        let offsets: Secp256r1SignatureOffsets =
+            bytemuck::pod_read_unaligned(&data[SIGNATURE_OFFSETS_START..DATA_START]);
+        // Data in other instructions would not be covered by the check below
+        if [
+            offsets.signature_instruction_index,
+            offsets.public_key_instruction_index,
+            offsets.message_instruction_index,
+        ]
+        .iter()
+        .any(|index| *index != u16::MAX)
+        {
+            return Err(WebAuthnError::InvalidInstructionData);
+        }
+
+        let public_key_start = offsets.public_key_offset as usize;
+        let public_key = data
+            .get(
+                public_key_start
+                    ..public_key_start.saturating_add(COMPRESSED_PUBKEY_SERIALIZED_SIZE),
+            )
+            .and_then(|public_key| public_key.try_into().ok())
+            .ok_or(WebAuthnError::InvalidInstructionData)?;
+        let message_start = offsets.message_data_offset as usize;
+        let message_end = message_start.saturating_add(offsets.message_data_size as usize);
+        let message = data
+            .get(message_start..message_end)
+            .ok_or(WebAuthnError::InvalidInstructionData)?;
+        let client_data_json = &data[message_end..];
+
+        let Some(authenticator_data_len) = message
+            .len()
+            .checked_sub(CLIENT_DATA_HASH_LEN)
+            .filter(|len| *len >= AUTHENTICATOR_DATA_MIN_LEN)
+        else {
+            return Err(WebAuthnError::InvalidInstructionData);
+        };
+        let (authenticator_data, client_data_hash) = message.split_at(authenticator_data_len);
+        if solana_sha256_hasher::hash(client_data_json).as_bytes() != client_data_hash {
+            return Err(WebAuthnError::ClientDataHashMismatch);
+        }
+
+        Ok(Self {
+            public_key,
+            authenticator_data,
+            client_data_json,
+        })
+    }
+
+    /// Returns the base64url-encoded challenge of the client data.
+    ///
+    /// The client data must be serialized the way browsers do, starting with
+    /// `{"type":"webauthn.get","challenge":"`, as required by the limited
+    /// verification algorithm of the WebAuthn specification.
+    pub fn challenge(&self) -> Result<&'a str, WebAuthnError> {
+        Ok(self.split_client_data()?.0)
+    }
+
+    /// Returns the origin of the client data, which immediately follows the
+    /// challenge.
+    ///
+    /// Origins containing JSON escape sequences are rejected rather than
+    /// unescaped.
+    pub fn origin(&self) -> Result<&'a str, WebAuthnError> {
+        let (_, rest) = self.split_client_data()?;
+        let (origin, _) = rest
+            .strip_prefix(ORIGIN_KEY)
+            .and_then(|rest| rest.split_once('"'))
+            .ok_or(WebAuthnError::InvalidClientData)?;
+        if origin.contains('\\') {
+            return Err(WebAuthnError::InvalidClientData);
+        }
+        Ok(origin)
+    }
+
+    /// Splits the client data into the challenge and what follows its
+    /// closing quote.
+    fn split_client_data(&self) -> Result<(&'a str, &'a str), WebAuthnError> {
+        core::str::from_utf8(self.client_data_json)
+            .ok()
+            .and_then(|client_data| client_data.strip_prefix(CLIENT_DATA_PREFIX))
+            .and_then(|rest| rest.split_once('"'))
+            .ok_or(WebAuthnError::InvalidClientData)
+    }
+
+    /// Returns the flags of the authenticator data.
+    pub fn flags(&self) -> Result<u8, WebAuthnError> {
+        self.authenticator_data
+            .get(RP_ID_HASH_LEN)
+            .copied()
+            .ok_or(WebAuthnError::InvalidInstructionData)
+    }
+
+    /// Checks that the challenge of the client data is `expected`.
+    pub fn check_challenge(&self, expected: &[u8]) -> Result<(), WebAuthnError> {
+        let challenge = self.challenge()?.trim_end_matches('=');
+        if challenge != BASE64_URL_SAFE_NO_PAD.encode(expected) {
+            return Err(WebAuthnError::ChallengeMismatch);
+        }
+        Ok(())
+    }
+
+    /// Checks that the origin of the client data is `expected`, such as
+    /// `https://example.com`.
+    pub fn check_origin(&self, expected: &str) -> Result<(), WebAuthnError> {
+        if self.origin()? != expected {
+            return Err(WebAuthnError::OriginMismatch);
+        }
+        Ok(())
+    }
+
+    /// Checks that the authenticator data starts with the SHA256 hash of the
+    /// relying party ID `rp_id`, such as `example.com`.
+    pub fn check_rp_id(&self, rp_id: &str) -> Result<(), WebAuthnError> {
+        let rp_id_hash = self
+            .authenticator_data
+            .get(..RP_ID_HASH_LEN)
+            .ok_or(WebAuthnError::InvalidInstructionData)?;
+        if rp_id_hash != solana_sha256_hasher::hash(rp_id.as_bytes()).as_bytes() {
+            return Err(WebAuthnError::RpIdMismatch);
+        }
+        Ok(())
+    }
+
+    /// Checks that the authenticator data has the user present flag.
+    pub fn check_user_present(&self) -> Result<(), WebAuthnError> {
+        if self.flags()? & FLAG_USER_PRESENT == 0 {
+            return Err(WebAuthnError::UserNotPresent);
+        }
+        Ok(())
+    }
+
+    /// Checks the challenge, relying party ID, origin and user presence,
+    /// which is what a program needs to accept the assertion.
+    ///
+    /// Programs requiring user verification must also check
+    /// [`FLAG_USER_VERIFIED`] in [`flags`](Self::flags).
+    pub fn check(&self, challenge: &[u8], rp_id: &str, origin: &str) -> Result<(), WebAuthnError> {
+        self.check_challenge(challenge)?;
+        self.check_rp_id(rp_id)?;
+        self.check_origin(origin)?;
+        self.check_user_present()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{verify, SECP256R1_ORDER},
+        base64::prelude::BASE64_URL_SAFE,
+        openssl::{
+            bn::{BigNum, BigNumContext},
+            ec::{EcGroup, EcKey, PointConversionForm},
+            ecdsa::EcdsaSig,
+            nid::Nid,
+            pkey::PKey,
+            sign::Signer,
+        },
+    };
+
+    const CHALLENGE: [u8; 32] = [9; 32];
+
+    fn assertion(high_s: bool) -> (WebAuthnAssertion, [u8; COMPRESSED_PUBKEY_SERIALIZED_SIZE]) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = EcKey::generate(&group).unwrap();
+        let mut ctx = BigNumContext::new().unwrap();
+        let pubkey = key
+            .public_key()
+            .to_bytes(&group, PointConversionForm::COMPRESSED, &mut ctx)
+            .unwrap()
+            .try_into()
+            .unwrap();
+
+        let mut authenticator_data = solana_sha256_hasher::hash(b"example.com")
+            .as_bytes()
+            .to_vec();
+        // User present and verified flags, then the signature counter
+        authenticator_data.extend_from_slice(&[0x05, 0, 0, 0, 1]);
+        let client_data_json = format!(
+            r#"{{"type":"webauthn.get","challenge":"{}","origin":"https://example.com","crossOrigin":false}}"#,
+            BASE64_URL_SAFE_NO_PAD.encode(CHALLENGE)
+        );
+        let mut assertion = WebAuthnAssertion {
+            authenticator_data,
+            client_data_json: client_data_json.into_bytes(),
+            signature: vec![],
+        };
+
+        let mut signer = Signer::new(
+            openssl::hash::MessageDigest::sha256(),
+            &PKey::from_ec_key(key).unwrap(),
+        )
+        .unwrap();
+        signer.update(&assertion.signed_message()).unwrap();
+        let der = signer.sign_to_vec().unwrap();
+        // Make sure that s is on the requested side of the half order
+        let signature = EcdsaSig::from_der(&der).unwrap();
+        let order = BigNum::from_slice(&SECP256R1_ORDER).unwrap();
+        let mut other_s = BigNum::new().unwrap();
+        other_s.checked_sub(&order, signature.s()).unwrap();
+        let s = if (signature.s() > &other_s) == high_s {
+            signature.s().to_owned().unwrap()
+        } else {
+            other_s
+        };
+        assertion.signature =
+            EcdsaSig::from_private_components(signature.r().to_owned().unwrap(), s)
+                .unwrap()
+                .to_der()
+                .unwrap();
+        (assertion, pubkey)
+    }
+
+    #[test]
+    fn test_from_json() {
+        let (assertion, _) = assertion(false);
+        let json = format!(
+            r#"{{"id":"abc","type":"public-key","response":{{"authenticatorData":"{}","clientDataJSON":"{}","signature":"{}"}}}}"#,
+            BASE64_URL_SAFE_NO_PAD.encode(&assertion.authenticator_data),
+            BASE64_URL_SAFE.encode(&assertion.client_data_json),
+            BASE64_URL_SAFE_NO_PAD.encode(&assertion.signature),
+        );
+        assert_eq!(WebAuthnAssertion::from_json(&json), Ok(assertion));
+        assert_eq!(
+            WebAuthnAssertion::from_json(r#"{"response":{}}"#),
+            Err(WebAuthnError::InvalidAssertion)
+        );
+    }
+
+    #[test]
+    fn test_instruction() {
+        for high_s in [false, true] {
+            let (assertion, pubkey) = assertion(high_s);
+            let instruction = assertion.to_instruction(&pubkey).unwrap();
+            assert_eq!(verify(&instruction.data, &[&instruction.data]), Ok(()));
+
+            let parsed = WebAuthnInstruction::parse(&instruction.data).unwrap();
+            assert_eq!(parsed.public_key, &pubkey);
+            assert_eq!(parsed.authenticator_data, assertion.authenticator_data);
+            assert_eq!(parsed.client_data_json, assertion.client_data_json);
+            assert_eq!(parsed.check_challenge(&CHALLENGE), Ok(()));
+            assert_eq!(
+                parsed.check_challenge(&[8; 32]),
+                Err(WebAuthnError::ChallengeMismatch)
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_client_data_mismatch() {
+        let (assertion, pubkey) = assertion(false);
+        let mut data = assertion.to_instruction(&pubkey).unwrap().data;
+        data.push(b' ');
+        assert_eq!(
+            WebAuthnInstruction::parse(&data),
+            Err(WebAuthnError::ClientDataHashMismatch)
+        );
+        assert_eq!(
+            WebAuthnInstruction::parse(&data[..DATA_START]),
+            Err(WebAuthnError::InvalidInstructionData)
+        );
+    }
+
+    #[test]
+    fn test_challenge_requires_get() {
+        let (mut assertion, pubkey) = assertion(false);
+        assertion.client_data_json = String::from_utf8(assertion.client_data_json)
+            .unwrap()
+            .replace("webauthn.get", "webauthn.create")
+            .into_bytes();
+        let instruction = assertion.to_instruction(&pubkey).unwrap();
+        let parsed = WebAuthnInstruction::parse(&instruction.data).unwrap();
+        assert_eq!(parsed.challenge(), Err(WebAuthnError::InvalidClientData));
+    }
+
+    #[test]
+    fn test_challenge_requires_prefix() {
+        let (mut assertion, pubkey) = assertion(false);
+        // The challenge must come right after the type
+        assertion.client_data_json = format!(
+            r#"{{"type":"webauthn.get","origin":"https://example.com","challenge":"{}"}}"#,
+            BASE64_URL_SAFE_NO_PAD.encode(CHALLENGE)
+        )
+        .into_bytes();
+        let instruction = assertion.to_instruction(&pubkey).unwrap();
+        let parsed = WebAuthnInstruction::parse(&instruction.data).unwrap();
+        assert_eq!(parsed.challenge(), Err(WebAuthnError::InvalidClientData));
+        assert_eq!(parsed.origin(), Err(WebAuthnError::InvalidClientData));
+    }
+
+    #[test]
+    fn test_check() {
+        let (mut assertion, pubkey) = assertion(false);
+        let instruction = assertion.to_instruction(&pubkey).unwrap();
+        let parsed = WebAuthnInstruction::parse(&instruction.data).unwrap();
+        assert_eq!(parsed.origin(), Ok("https://example.com"));
+        assert_eq!(
+            parsed.check(&CHALLENGE, "example.com", "https://example.com"),
+            Ok(())
+        );
+        assert_eq!(
+            parsed.check(&CHALLENGE, "evil.com", "https://example.com"),
+            Err(WebAuthnError::RpIdMismatch)
+        );
+        assert_eq!(
+            parsed.check(&CHALLENGE, "example.com", "https://evil.com"),
+            Err(WebAuthnError::OriginMismatch)
+        );
+
+        // Clear the user present flag
+        assertion.authenticator_data[RP_ID_HASH_LEN] &= !FLAG_USER_PRESENT;
+        let instruction = assertion.to_instruction(&pubkey).unwrap();
+        let parsed = WebAuthnInstruction::parse(&instruction.data).unwrap();
+        assert_eq!(parsed.flags(), Ok(FLAG_USER_VERIFIED));
+        assert_eq!(
+            parsed.check(&CHALLENGE, "example.com", "https://example.com"),
+            Err(WebAuthnError::UserNotPresent)
+        );
+    }
+}
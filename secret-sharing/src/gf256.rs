@@ -0,0 +1,95 @@
+//! Arithmetic in GF(2^8) with the AES reduction polynomial
+//! `x^8 + x^4 + x^3 + x + 1`.
+//!
+//! Operations run in constant time: no branches or table lookups depend on
+//! the operands.
+
+/// Multiplies `a` by `b`.
+pub(crate) fn mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    for _ in 0..8 {
+        // all ones if the low bit of `b` is set
+        product ^= a & (b & 1).wrapping_neg();
+        // all ones if multiplying by x overflows the field
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+/// Returns the multiplicative inverse of `a`, `a^254`, or 0 if `a` is 0.
+pub(crate) fn inv(a: u8) -> u8 {
+    // 254 = 0b1111_1110
+    let a2 = mul(a, a);
+    let a3 = mul(a2, a);
+    let a6 = mul(a3, a3);
+    let a7 = mul(a6, a);
+    let a14 = mul(a7, a7);
+    let a15 = mul(a14, a);
+    let a30 = mul(a15, a15);
+    let a31 = mul(a30, a);
+    let a62 = mul(a31, a31);
+    let a63 = mul(a62, a);
+    let a126 = mul(a63, a63);
+    let a127 = mul(a126, a);
+    mul(a127, a127)
+}
+
+/// Evaluates the polynomial with the given coefficients, constant term
+/// first, at `x`.
+pub(crate) fn eval(coefficients: &[u8], x: u8) -> u8 {
+    coefficients
+        .iter()
+        .rev()
+        .fold(0, |y, coefficient| mul(y, x) ^ coefficient)
+}
+
+/// Returns the Lagrange basis weights for interpolating at 0 from the
+/// distinct non-zero points `xs`.
+pub(crate) fn lagrange_weights_at_zero(xs: &[u8]) -> Vec<u8> {
+    xs.iter()
+        .enumerate()
+        .map(|(i, &xi)| {
+            xs.iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .fold(1, |weight, (_, &xj)| mul(weight, mul(xj, inv(xj ^ xi))))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_arithmetic() {
+        // Known product from FIPS-197, section 4.2
+        assert_eq!(mul(0x57, 0x83), 0xc1);
+        assert_eq!(mul(0x57, 0x13), 0xfe);
+        assert_eq!(inv(0), 0);
+        for a in 1..=u8::MAX {
+            assert_eq!(mul(a, 1), a);
+            assert_eq!(mul(a, 0), 0);
+            assert_eq!(mul(a, inv(a)), 1);
+        }
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let coefficients = [42, 7, 199];
+        let xs = [3, 9, 200, 17];
+        let ys: Vec<u8> = xs.iter().map(|&x| eval(&coefficients, x)).collect();
+        assert_eq!(eval(&coefficients, 0), 42);
+
+        for points in [&[0, 1, 2][..], &[1, 2, 3], &[0, 1, 2, 3]] {
+            let points_xs: Vec<u8> = points.iter().map(|&i| xs[i]).collect();
+            let secret = lagrange_weights_at_zero(&points_xs)
+                .into_iter()
+                .zip(points.iter().map(|&i| ys[i]))
+                .fold(0, |acc, (weight, y)| acc ^ mul(weight, y));
+            assert_eq!(secret, 42);
+        }
+    }
+}
@@ -0,0 +1,575 @@
+//! Shamir secret sharing for Solana keypairs.
+//!
+//! [`split`] divides the secret key of a keypair into `n` shares such that
+//! any `threshold` of them reconstruct it with [`combine`], while fewer reveal
+//! nothing about it. Each byte of the secret is shared independently over
+//! GF(2^8).
+//!
+//! Every share records the pubkey fingerprint of the keypair it was split
+//! from, so a reconstruction from mismatched or corrupted shares is detected
+//! instead of silently producing a different key. Shares have a single-line
+//! text encoding protected by a checksum:
+//!
+//! ```text
+//! solana-share:v1:<kind>:<threshold>:<index>:<fingerprint>:<share>:<checksum>
+//! ```
+//!
+//! where `kind` is `ed25519` or `bls` and the last three fields are
+//! hex-encoded.
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+mod gf256;
+
+use {
+    rand::RngCore,
+    sha2::{Digest, Sha256},
+    solana_signer::EncodableKey,
+    std::{
+        error, fmt,
+        io::{Read, Write},
+        str::FromStr,
+    },
+    zeroize::Zeroizing,
+};
+
+const SHARE_PREFIX: &str = "solana-share:v1:";
+const CHECKSUM_LEN: usize = 4;
+
+/// Length of a pubkey fingerprint in bytes.
+pub const FINGERPRINT_LEN: usize = 8;
+
+/// Kind of keypair a share belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyKind {
+    /// An Ed25519 `solana_keypair::Keypair`
+    Ed25519,
+    /// A BLS `solana_bls_signatures::Keypair`
+    Bls,
+}
+
+impl fmt::Display for KeyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ed25519 => f.write_str("ed25519"),
+            Self::Bls => f.write_str("bls"),
+        }
+    }
+}
+
+impl FromStr for KeyKind {
+    type Err = SecretSharingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ed25519" => Ok(Self::Ed25519),
+            "bls" => Ok(Self::Bls),
+            _ => Err(SecretSharingError::InvalidEncoding("unknown key kind")),
+        }
+    }
+}
+
+/// Errors returned when splitting, combining or decoding shares.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretSharingError {
+    /// The threshold must be at least 2 and at most the number of shares,
+    /// which is at most 255.
+    InvalidThreshold { threshold: u8, shares: u8 },
+    /// Fewer shares than the threshold were provided.
+    NotEnoughShares { threshold: u8, provided: usize },
+    /// Two shares have the same index.
+    DuplicateShare(u8),
+    /// The shares disagree on their key kind, threshold, fingerprint or
+    /// length, so they were not split from the same keypair.
+    InconsistentShares,
+    /// The shares belong to a different kind of keypair.
+    KindMismatch { expected: KeyKind, found: KeyKind },
+    /// The text encoding of a share is malformed.
+    InvalidEncoding(&'static str),
+    /// The checksum of an encoded share does not match, it was likely
+    /// mistyped or corrupted.
+    InvalidChecksum,
+    /// The reconstructed keypair does not match the fingerprint of the
+    /// shares; at least one share is corrupted.
+    FingerprintMismatch,
+    /// The reconstructed secret is not a valid secret key.
+    InvalidSecret,
+}
+
+impl error::Error for SecretSharingError {}
+
+impl fmt::Display for SecretSharingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidThreshold { threshold, shares } => {
+                write!(f, "invalid threshold {threshold} for {shares} shares")
+            }
+            Self::NotEnoughShares {
+                threshold,
+                provided,
+            } => write!(f, "{provided} shares provided, {threshold} required"),
+            Self::DuplicateShare(index) => write!(f, "share {index} provided more than once"),
+            Self::InconsistentShares => f.write_str("shares do not belong to the same keypair"),
+            Self::KindMismatch { expected, found } => {
+                write!(f, "expected {expected} shares, found {found} shares")
+            }
+            Self::InvalidEncoding(reason) => write!(f, "invalid share encoding: {reason}"),
+            Self::InvalidChecksum => f.write_str("invalid share checksum"),
+            Self::FingerprintMismatch => {
+                f.write_str("reconstructed keypair does not match the share fingerprint")
+            }
+            Self::InvalidSecret => f.write_str("reconstructed secret is not a valid secret key"),
+        }
+    }
+}
+
+/// A keypair whose secret key can be split into shares.
+pub trait ShareableKeypair: Sized {
+    /// Kind recorded in the shares of this keypair.
+    const KIND: KeyKind;
+
+    /// Returns the secret key bytes.
+    fn secret_bytes(&self) -> Zeroizing<Vec<u8>>;
+
+    /// Returns the pubkey bytes, from which the fingerprint is computed.
+    fn pubkey_bytes(&self) -> Vec<u8>;
+
+    /// Rebuilds the keypair from its secret key bytes.
+    fn from_secret_bytes(secret: &[u8]) -> Result<Self, SecretSharingError>;
+
+    /// Returns the pubkey fingerprint stored in the shares of this keypair.
+    fn fingerprint(&self) -> [u8; FINGERPRINT_LEN] {
+        let mut fingerprint = [0; FINGERPRINT_LEN];
+        fingerprint.copy_from_slice(&Sha256::digest(self.pubkey_bytes())[..FINGERPRINT_LEN]);
+        fingerprint
+    }
+}
+
+#[cfg(feature = "ed25519")]
+impl ShareableKeypair for solana_keypair::Keypair {
+    const KIND: KeyKind = KeyKind::Ed25519;
+
+    fn secret_bytes(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(solana_keypair::Keypair::secret_bytes(self).to_vec())
+    }
+
+    fn pubkey_bytes(&self) -> Vec<u8> {
+        solana_signer::Signer::pubkey(self).to_bytes().to_vec()
+    }
+
+    fn from_secret_bytes(secret: &[u8]) -> Result<Self, SecretSharingError> {
+        let secret: [u8; solana_keypair::Keypair::SECRET_KEY_LENGTH] = secret
+            .try_into()
+            .map_err(|_| SecretSharingError::InvalidSecret)?;
+        Ok(solana_keypair::Keypair::new_from_array(secret))
+    }
+}
+
+#[cfg(feature = "bls")]
+impl ShareableKeypair for solana_bls_signatures::Keypair {
+    const KIND: KeyKind = KeyKind::Bls;
+
+    fn secret_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let secret: Zeroizing<[u8; solana_bls_signatures::secret_key::BLS_SECRET_KEY_SIZE]> =
+            (&self.secret).into();
+        Zeroizing::new(secret.to_vec())
+    }
+
+    fn pubkey_bytes(&self) -> Vec<u8> {
+        self.public.to_bytes_compressed().to_vec()
+    }
+
+    fn from_secret_bytes(secret: &[u8]) -> Result<Self, SecretSharingError> {
+        use solana_bls_signatures::{
+            pubkey::{PubkeyAffine, PubkeyProjective},
+            secret_key::SecretKey,
+        };
+
+        let secret_key =
+            SecretKey::try_from(secret).map_err(|_| SecretSharingError::InvalidSecret)?;
+        let public: PubkeyAffine = PubkeyProjective::from_secret(&secret_key).into();
+        let mut keypair_bytes = Zeroizing::new(secret.to_vec());
+        keypair_bytes.extend_from_slice(&public.to_bytes_uncompressed());
+        Self::try_from(keypair_bytes.as_slice()).map_err(|_| SecretSharingError::InvalidSecret)
+    }
+}
+
+/// One share of a keypair secret.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Share {
+    kind: KeyKind,
+    threshold: u8,
+    index: u8,
+    fingerprint: [u8; FINGERPRINT_LEN],
+    data: Zeroizing<Vec<u8>>,
+}
+
+impl fmt::Debug for Share {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Share")
+            .field("kind", &self.kind)
+            .field("threshold", &self.threshold)
+            .field("index", &self.index)
+            .field("fingerprint", &hex::encode(self.fingerprint))
+            .finish_non_exhaustive()
+    }
+}
+
+impl Share {
+    /// Returns the kind of keypair the share belongs to.
+    pub fn kind(&self) -> KeyKind {
+        self.kind
+    }
+
+    /// Returns the number of shares needed to reconstruct the keypair.
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Returns the index of the share, starting at 1.
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Returns the pubkey fingerprint of the keypair the share belongs to.
+    pub fn fingerprint(&self) -> [u8; FINGERPRINT_LEN] {
+        self.fingerprint
+    }
+
+    /// Returns true if the share was split from `keypair`.
+    pub fn belongs_to<K: ShareableKeypair>(&self, keypair: &K) -> bool {
+        self.kind == K::KIND && self.fingerprint == keypair.fingerprint()
+    }
+
+    /// Everything up to, but excluding, the checksum.
+    fn body(&self) -> String {
+        format!(
+            "{SHARE_PREFIX}{}:{}:{}:{}:{}",
+            self.kind,
+            self.threshold,
+            self.index,
+            hex::encode(self.fingerprint),
+            hex::encode(self.data.as_slice()),
+        )
+    }
+}
+
+fn checksum(body: &str) -> [u8; CHECKSUM_LEN] {
+    let mut checksum = [0; CHECKSUM_LEN];
+    checksum.copy_from_slice(&Sha256::digest(body.as_bytes())[..CHECKSUM_LEN]);
+    checksum
+}
+
+impl fmt::Display for Share {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let body = self.body();
+        write!(f, "{body}:{}", hex::encode(checksum(&body)))
+    }
+}
+
+impl FromStr for Share {
+    type Err = SecretSharingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (body, encoded_checksum) = s
+            .rsplit_once(':')
+            .ok_or(SecretSharingError::InvalidEncoding("missing checksum"))?;
+        let fields = body
+            .strip_prefix(SHARE_PREFIX)
+            .ok_or(SecretSharingError::InvalidEncoding("missing prefix"))?;
+        if hex::decode(encoded_checksum).ok().as_deref() != Some(&checksum(body)[..]) {
+            return Err(SecretSharingError::InvalidChecksum);
+        }
+
+        let mut fields = fields.split(':');
+        let mut next = |name| {
+            fields
+                .next()
+                .ok_or(SecretSharingError::InvalidEncoding(name))
+        };
+        let kind = next("missing key kind")?.parse()?;
+        let threshold = next("missing threshold")?
+            .parse()
+            .map_err(|_| SecretSharingError::InvalidEncoding("invalid threshold"))?;
+        let index = next("missing index")?
+            .parse()
+            .ok()
+            .filter(|index| *index != 0)
+            .ok_or(SecretSharingError::InvalidEncoding("invalid index"))?;
+        let mut fingerprint = [0; FINGERPRINT_LEN];
+        hex::decode_to_slice(next("missing fingerprint")?, &mut fingerprint)
+            .map_err(|_| SecretSharingError::InvalidEncoding("invalid fingerprint"))?;
+        let data = Zeroizing::new(
+            hex::decode(next("missing share")?)
+                .map_err(|_| SecretSharingError::InvalidEncoding("invalid share"))?,
+        );
+        if fields.next().is_some() {
+            return Err(SecretSharingError::InvalidEncoding("trailing fields"));
+        }
+        if threshold < 2 {
+            return Err(SecretSharingError::InvalidEncoding("invalid threshold"));
+        }
+
+        Ok(Self {
+            kind,
+            threshold,
+            index,
+            fingerprint,
+            data,
+        })
+    }
+}
+
+impl EncodableKey for Share {
+    fn read<R: Read>(reader: &mut R) -> Result<Self, Box<dyn error::Error>> {
+        let mut buffer = Zeroizing::new(String::new());
+        reader.read_to_string(&mut buffer)?;
+        Ok(buffer.parse()?)
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<String, Box<dyn error::Error>> {
+        let encoded = self.to_string();
+        writer.write_all(encoded.as_bytes())?;
+        Ok(encoded)
+    }
+}
+
+/// Splits the secret key of `keypair` into `shares` shares, any `threshold`
+/// of which reconstruct it.
+pub fn split<K: ShareableKeypair>(
+    keypair: &K,
+    threshold: u8,
+    shares: u8,
+) -> Result<Vec<Share>, SecretSharingError> {
+    if threshold < 2 || threshold > shares {
+        return Err(SecretSharingError::InvalidThreshold { threshold, shares });
+    }
+    let secret = keypair.secret_bytes();
+    let fingerprint = keypair.fingerprint();
+
+    // One polynomial per secret byte, with the byte as constant term and
+    // `threshold - 1` random coefficients.
+    let degree = usize::from(threshold).saturating_sub(1);
+    let mut random = Zeroizing::new(vec![0; secret.len().saturating_mul(degree)]);
+    rand::rng().fill_bytes(&mut random);
+    let mut coefficients = Zeroizing::new(vec![0; usize::from(threshold)]);
+
+    let mut result: Vec<Share> = (1..=shares)
+        .map(|index| Share {
+            kind: K::KIND,
+            threshold,
+            index,
+            fingerprint,
+            data: Zeroizing::new(Vec::with_capacity(secret.len())),
+        })
+        .collect();
+    for (byte, random) in secret.iter().zip(random.chunks_exact(degree)) {
+        coefficients[0] = *byte;
+        coefficients[1..].copy_from_slice(random);
+        for share in result.iter_mut() {
+            share.data.push(gf256::eval(&coefficients, share.index));
+        }
+    }
+    Ok(result)
+}
+
+/// Reconstructs a keypair from at least `threshold` of its shares.
+///
+/// Fails if the shares are inconsistent or if the reconstructed keypair does
+/// not match their fingerprint.
+pub fn combine<K: ShareableKeypair>(shares: &[Share]) -> Result<K, SecretSharingError> {
+    let first = shares.first().ok_or(SecretSharingError::NotEnoughShares {
+        threshold: 2,
+        provided: 0,
+    })?;
+    if first.kind != K::KIND {
+        return Err(SecretSharingError::KindMismatch {
+            expected: K::KIND,
+            found: first.kind,
+        });
+    }
+    if shares.len() < usize::from(first.threshold) {
+        return Err(SecretSharingError::NotEnoughShares {
+            threshold: first.threshold,
+            provided: shares.len(),
+        });
+    }
+    let mut indexes = Vec::with_capacity(shares.len());
+    for share in shares {
+        if share.kind != first.kind
+            || share.threshold != first.threshold
+            || share.fingerprint != first.fingerprint
+            || share.data.len() != first.data.len()
+        {
+            return Err(SecretSharingError::InconsistentShares);
+        }
+        if indexes.contains(&share.index) {
+            return Err(SecretSharingError::DuplicateShare(share.index));
+        }
+        indexes.push(share.index);
+    }
+
+    let weights = gf256::lagrange_weights_at_zero(&indexes);
+    let secret: Zeroizing<Vec<u8>> = Zeroizing::new(
+        (0..first.data.len())
+            .map(|i| {
+                shares
+                    .iter()
+                    .zip(&weights)
+                    .fold(0, |byte, (share, weight)| {
+                        byte ^ gf256::mul(*weight, share.data[i])
+                    })
+            })
+            .collect(),
+    );
+    let keypair = K::from_secret_bytes(&secret).map_err(|_| {
+        // A corrupted share yields an arbitrary secret, which may not even
+        // be a valid key.
+        SecretSharingError::FingerprintMismatch
+    })?;
+    if keypair.fingerprint() != first.fingerprint {
+        return Err(SecretSharingError::FingerprintMismatch);
+    }
+    Ok(keypair)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_signer::Signer};
+
+    #[test]
+    fn test_split_combine_ed25519() {
+        let keypair = solana_keypair::Keypair::new();
+        let shares = split(&keypair, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+        assert!(shares.iter().all(|share| share.belongs_to(&keypair)));
+        assert_eq!(
+            shares.iter().map(Share::index).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5]
+        );
+
+        for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let subset: Vec<Share> = subset.iter().map(|&i| shares[i].clone()).collect();
+            let recovered: solana_keypair::Keypair = combine(&subset).unwrap();
+            assert_eq!(recovered.to_bytes(), keypair.to_bytes());
+        }
+        let recovered: solana_keypair::Keypair = combine(&shares).unwrap();
+        assert_eq!(recovered.pubkey(), keypair.pubkey());
+
+        assert_eq!(
+            combine::<solana_keypair::Keypair>(&shares[..2]).unwrap_err(),
+            SecretSharingError::NotEnoughShares {
+                threshold: 3,
+                provided: 2
+            }
+        );
+        assert_eq!(
+            combine::<solana_keypair::Keypair>(&[
+                shares[0].clone(),
+                shares[1].clone(),
+                shares[0].clone()
+            ])
+            .unwrap_err(),
+            SecretSharingError::DuplicateShare(1)
+        );
+        assert_eq!(
+            combine::<solana_bls_signatures::Keypair>(&shares).unwrap_err(),
+            SecretSharingError::KindMismatch {
+                expected: KeyKind::Bls,
+                found: KeyKind::Ed25519
+            }
+        );
+    }
+
+    #[test]
+    fn test_split_combine_bls() {
+        let keypair = solana_bls_signatures::Keypair::new();
+        let shares = split(&keypair, 2, 3).unwrap();
+        let recovered: solana_bls_signatures::Keypair =
+            combine(&[shares[2].clone(), shares[0].clone()]).unwrap();
+        assert_eq!(recovered, keypair);
+    }
+
+    #[test]
+    fn test_invalid_threshold() {
+        let keypair = solana_keypair::Keypair::new();
+        for (threshold, shares) in [(0, 3), (1, 3), (4, 3)] {
+            assert_eq!(
+                split(&keypair, threshold, shares).unwrap_err(),
+                SecretSharingError::InvalidThreshold { threshold, shares }
+            );
+        }
+        assert_eq!(split(&keypair, 255, 255).unwrap().len(), 255);
+    }
+
+    #[test]
+    fn test_wrong_reconstruction_detected() {
+        let keypair = solana_keypair::Keypair::new();
+        let mut shares = split(&keypair, 2, 3).unwrap();
+        shares[1].data[0] ^= 1;
+        assert_eq!(
+            combine::<solana_keypair::Keypair>(&shares[..2]).unwrap_err(),
+            SecretSharingError::FingerprintMismatch
+        );
+
+        let other = split(&solana_keypair::Keypair::new(), 2, 3).unwrap();
+        assert_eq!(
+            combine::<solana_keypair::Keypair>(&[shares[0].clone(), other[1].clone()]).unwrap_err(),
+            SecretSharingError::InconsistentShares
+        );
+    }
+
+    #[test]
+    fn test_text_encoding() {
+        let keypair = solana_keypair::Keypair::new();
+        let shares = split(&keypair, 2, 2).unwrap();
+        let encoded = shares[1].to_string();
+        assert!(encoded.starts_with(&format!(
+            "solana-share:v1:ed25519:2:2:{}:",
+            hex::encode(keypair.fingerprint())
+        )));
+        assert_eq!(encoded.parse::<Share>().unwrap(), shares[1]);
+        assert_eq!(format!(" {encoded}\n").parse::<Share>().unwrap(), shares[1]);
+
+        // A single mistyped character is caught by the checksum
+        let position = encoded.len() - 20;
+        let mut typo = encoded.clone().into_bytes();
+        typo[position] = if typo[position] == b'0' { b'1' } else { b'0' };
+        assert_eq!(
+            String::from_utf8(typo)
+                .unwrap()
+                .parse::<Share>()
+                .unwrap_err(),
+            SecretSharingError::InvalidChecksum
+        );
+        assert_eq!(
+            "solana-share:v2:ed25519:2:2:00:00:00"
+                .parse::<Share>()
+                .unwrap_err(),
+            SecretSharingError::InvalidEncoding("missing prefix")
+        );
+    }
+
+    #[test]
+    fn test_encodable_key_round_trip() {
+        let keypair = solana_bls_signatures::Keypair::new();
+        let shares = split(&keypair, 2, 3).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        let paths: Vec<_> = shares
+            .iter()
+            .map(|share| {
+                let path = dir.path().join(format!("share-{}", share.index()));
+                share.write_to_file(&path).unwrap();
+                path
+            })
+            .collect();
+        let read: Vec<Share> = paths[1..]
+            .iter()
+            .map(|path| Share::read_from_file(path).unwrap())
+            .collect();
+        assert_eq!(read, shares[1..]);
+        let recovered: solana_bls_signatures::Keypair = combine(&read).unwrap();
+        assert_eq!(recovered, keypair);
+    }
+}
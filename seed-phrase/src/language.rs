@@ -0,0 +1,159 @@
+//! [BIP-39] wordlists.
+//!
+//! [BIP-39]: https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki
+//!
+//! English is always available; every other language is enabled by the
+//! feature of the same name.
+
+use {
+    std::{fmt, sync::OnceLock},
+    unicode_normalization::UnicodeNormalization,
+};
+
+/// Number of words in a BIP-39 wordlist.
+pub const WORDLIST_LENGTH: usize = 2048;
+
+/// Language of a BIP-39 wordlist.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    #[default]
+    English,
+    #[cfg(feature = "chinese-simplified")]
+    ChineseSimplified,
+    #[cfg(feature = "chinese-traditional")]
+    ChineseTraditional,
+    #[cfg(feature = "french")]
+    French,
+    #[cfg(feature = "italian")]
+    Italian,
+    #[cfg(feature = "japanese")]
+    Japanese,
+    #[cfg(feature = "korean")]
+    Korean,
+    #[cfg(feature = "spanish")]
+    Spanish,
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::English => "english",
+            #[cfg(feature = "chinese-simplified")]
+            Self::ChineseSimplified => "chinese-simplified",
+            #[cfg(feature = "chinese-traditional")]
+            Self::ChineseTraditional => "chinese-traditional",
+            #[cfg(feature = "french")]
+            Self::French => "french",
+            #[cfg(feature = "italian")]
+            Self::Italian => "italian",
+            #[cfg(feature = "japanese")]
+            Self::Japanese => "japanese",
+            #[cfg(feature = "korean")]
+            Self::Korean => "korean",
+            #[cfg(feature = "spanish")]
+            Self::Spanish => "spanish",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Wordlist with every word in Unicode NFKD form.
+struct Wordlist(OnceLock<Vec<String>>);
+
+impl Wordlist {
+    const fn new() -> Self {
+        Self(OnceLock::new())
+    }
+
+    fn get(&self, words: &str) -> &[String] {
+        self.0.get_or_init(|| {
+            let words: Vec<String> = words
+                .split_whitespace()
+                .map(|word| word.nfkd().collect())
+                .collect();
+            debug_assert_eq!(words.len(), WORDLIST_LENGTH);
+            words
+        })
+    }
+}
+
+impl Language {
+    /// Every language enabled in this build.
+    pub const ALL: &'static [Language] = &[
+        Self::English,
+        #[cfg(feature = "chinese-simplified")]
+        Self::ChineseSimplified,
+        #[cfg(feature = "chinese-traditional")]
+        Self::ChineseTraditional,
+        #[cfg(feature = "french")]
+        Self::French,
+        #[cfg(feature = "italian")]
+        Self::Italian,
+        #[cfg(feature = "japanese")]
+        Self::Japanese,
+        #[cfg(feature = "korean")]
+        Self::Korean,
+        #[cfg(feature = "spanish")]
+        Self::Spanish,
+    ];
+
+    /// Returns the 2048 words of the wordlist, in Unicode NFKD form.
+    pub fn wordlist(&self) -> &'static [String] {
+        macro_rules! wordlist {
+            ($file:literal) => {{
+                static WORDLIST: Wordlist = Wordlist::new();
+                WORDLIST.get(include_str!(concat!("wordlists/", $file)))
+            }};
+        }
+        match self {
+            Self::English => wordlist!("english.txt"),
+            #[cfg(feature = "chinese-simplified")]
+            Self::ChineseSimplified => wordlist!("chinese_simplified.txt"),
+            #[cfg(feature = "chinese-traditional")]
+            Self::ChineseTraditional => wordlist!("chinese_traditional.txt"),
+            #[cfg(feature = "french")]
+            Self::French => wordlist!("french.txt"),
+            #[cfg(feature = "italian")]
+            Self::Italian => wordlist!("italian.txt"),
+            #[cfg(feature = "japanese")]
+            Self::Japanese => wordlist!("japanese.txt"),
+            #[cfg(feature = "korean")]
+            Self::Korean => wordlist!("korean.txt"),
+            #[cfg(feature = "spanish")]
+            Self::Spanish => wordlist!("spanish.txt"),
+        }
+    }
+
+    /// Returns the index of `word` in the wordlist. `word` must be in NFKD
+    /// form.
+    pub fn index_of(&self, word: &str) -> Option<u16> {
+        self.wordlist()
+            .iter()
+            .position(|candidate| candidate == word)
+            .and_then(|index| u16::try_from(index).ok())
+    }
+
+    /// Returns the words starting with `prefix`, for autocompletion.
+    pub fn words_by_prefix(&self, prefix: &str) -> impl Iterator<Item = &'static str> {
+        let prefix: String = prefix.nfkd().collect();
+        self.wordlist()
+            .iter()
+            .filter(move |word| word.starts_with(&prefix))
+            .map(String::as_str)
+    }
+
+    /// Returns the language whose wordlist contains every word of
+    /// `phrase`, if there is exactly one.
+    pub fn detect(phrase: &str) -> Option<Language> {
+        let phrase: String = phrase.nfkd().collect();
+        let mut candidates = Self::ALL.iter().filter(|language| {
+            phrase
+                .split_whitespace()
+                .all(|word| language.index_of(word).is_some())
+        });
+        match (candidates.next(), candidates.next()) {
+            (Some(language), None) => Some(*language),
+            _ => None,
+        }
+    }
+}
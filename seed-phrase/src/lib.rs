@@ -1,6 +1,14 @@
 //! Functions for generating keypairs from seed phrases.
 #![cfg_attr(docsrs, feature(doc_cfg))]
-use hmac::Hmac;
+use {hmac::Hmac, unicode_normalization::UnicodeNormalization, zeroize::Zeroizing};
+
+pub mod language;
+pub mod mnemonic;
+
+pub use {
+    language::Language,
+    mnemonic::{Mnemonic, MnemonicError, WordCount},
+};
 
 pub fn generate_seed_from_seed_phrase_and_passphrase(
     seed_phrase: &str,
@@ -20,3 +28,33 @@ pub fn generate_seed_from_seed_phrase_and_passphrase(
     );
     seed
 }
+
+/// Derives the BIP-39 seed of `seed_phrase` protected by `passphrase`,
+/// after normalizing both to Unicode NFKD form as BIP-39 requires
+///
+/// [`generate_seed_from_seed_phrase_and_passphrase`] hashes the strings as
+/// given. The two functions agree on ASCII input, which normalization leaves
+/// unchanged, but derive different seeds, and so different keys, from a
+/// phrase or passphrase which is not in NFKD form. Keys created with the
+/// other function must keep being derived with it. The phrase is not
+/// validated, see [`Mnemonic::from_phrase`].
+pub fn generate_seed_from_normalized_seed_phrase_and_passphrase(
+    seed_phrase: &str,
+    passphrase: &str,
+) -> Vec<u8> {
+    const PBKDF2_ROUNDS: u32 = 2048;
+    const PBKDF2_BYTES: usize = 64;
+
+    let seed_phrase: Zeroizing<String> = Zeroizing::new(seed_phrase.nfkd().collect());
+    let salt: Zeroizing<String> =
+        Zeroizing::new("mnemonic".chars().chain(passphrase.nfkd()).collect());
+
+    let mut seed = vec![0u8; PBKDF2_BYTES];
+    pbkdf2::pbkdf2::<Hmac<sha2::Sha512>>(
+        seed_phrase.as_bytes(),
+        salt.as_bytes(),
+        PBKDF2_ROUNDS,
+        &mut seed,
+    );
+    seed
+}
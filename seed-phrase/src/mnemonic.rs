@@ -0,0 +1,474 @@
+//! [BIP-39] mnemonic generation and validation.
+//!
+//! [BIP-39]: https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki
+
+use {
+    crate::{generate_seed_from_normalized_seed_phrase_and_passphrase, language::Language},
+    sha2::{Digest, Sha256},
+    std::{error, fmt},
+    unicode_normalization::UnicodeNormalization,
+    zeroize::Zeroizing,
+};
+
+const BITS_PER_WORD: usize = 11;
+
+/// Number of words of a mnemonic.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WordCount {
+    /// 128 bits of entropy
+    #[default]
+    Words12,
+    /// 160 bits of entropy
+    Words15,
+    /// 192 bits of entropy
+    Words18,
+    /// 224 bits of entropy
+    Words21,
+    /// 256 bits of entropy
+    Words24,
+}
+
+impl WordCount {
+    /// Returns the `WordCount` of a mnemonic with `words` words.
+    pub fn from_words(words: usize) -> Result<Self, MnemonicError> {
+        match words {
+            12 => Ok(Self::Words12),
+            15 => Ok(Self::Words15),
+            18 => Ok(Self::Words18),
+            21 => Ok(Self::Words21),
+            24 => Ok(Self::Words24),
+            _ => Err(MnemonicError::InvalidWordCount(words)),
+        }
+    }
+
+    /// Returns the `WordCount` of a mnemonic encoding `len` bytes of
+    /// entropy.
+    pub fn from_entropy_len(len: usize) -> Result<Self, MnemonicError> {
+        match len {
+            16 => Ok(Self::Words12),
+            20 => Ok(Self::Words15),
+            24 => Ok(Self::Words18),
+            28 => Ok(Self::Words21),
+            32 => Ok(Self::Words24),
+            _ => Err(MnemonicError::InvalidEntropyLength(len)),
+        }
+    }
+
+    /// Returns the number of words.
+    pub const fn words(&self) -> usize {
+        match self {
+            Self::Words12 => 12,
+            Self::Words15 => 15,
+            Self::Words18 => 18,
+            Self::Words21 => 21,
+            Self::Words24 => 24,
+        }
+    }
+
+    /// Returns the number of bytes of entropy.
+    pub const fn entropy_len(&self) -> usize {
+        match self {
+            Self::Words12 => 16,
+            Self::Words15 => 20,
+            Self::Words18 => 24,
+            Self::Words21 => 28,
+            Self::Words24 => 32,
+        }
+    }
+
+    /// Returns the number of checksum bits, one per 32 bits of entropy.
+    pub const fn checksum_bits(&self) -> u32 {
+        match self {
+            Self::Words12 => 4,
+            Self::Words15 => 5,
+            Self::Words18 => 6,
+            Self::Words21 => 7,
+            Self::Words24 => 8,
+        }
+    }
+}
+
+/// Errors returned when creating or validating a mnemonic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MnemonicError {
+    /// Entropy must be 16, 20, 24, 28 or 32 bytes long.
+    InvalidEntropyLength(usize),
+    /// A mnemonic must have 12, 15, 18, 21 or 24 words.
+    InvalidWordCount(usize),
+    /// The word at zero-based `position` is not in the wordlist.
+    UnknownWord { position: usize, word: String },
+    /// All words are valid, but the checksum stored in the last word does
+    /// not match the entropy.
+    InvalidChecksum,
+}
+
+impl error::Error for MnemonicError {}
+
+impl fmt::Display for MnemonicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidEntropyLength(len) => write!(
+                f,
+                "invalid entropy length {len}, expected 16, 20, 24, 28 or 32 bytes"
+            ),
+            Self::InvalidWordCount(words) => write!(
+                f,
+                "invalid number of words {words}, expected 12, 15, 18, 21 or 24"
+            ),
+            Self::UnknownWord { position, word } => write!(
+                f,
+                "word {} `{word}` is not in the wordlist",
+                position.saturating_add(1)
+            ),
+            Self::InvalidChecksum => f.write_str("invalid mnemonic checksum"),
+        }
+    }
+}
+
+/// A validated BIP-39 mnemonic.
+///
+/// The phrase is stored in Unicode NFKD form with words separated by single
+/// spaces, so it can be passed directly to
+/// [`generate_seed_from_normalized_seed_phrase_and_passphrase`] or
+/// `SeedDerivable::from_seed_phrase_and_passphrase`. The phrase and the
+/// entropy are zeroed when the mnemonic is dropped.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Mnemonic {
+    phrase: Zeroizing<String>,
+    entropy: Zeroizing<Vec<u8>>,
+    language: Language,
+}
+
+impl fmt::Debug for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mnemonic")
+            .field("language", &self.language)
+            .field("word_count", &self.word_count())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Mnemonic {
+    /// Generates a new random mnemonic.
+    #[cfg(feature = "rand")]
+    pub fn generate(word_count: WordCount, language: Language) -> Self {
+        let entropy = Zeroizing::new(rand::random::<[u8; 32]>());
+        Self::from_entropy(&entropy[..word_count.entropy_len()], language)
+            .expect("entropy length matches the word count")
+    }
+
+    /// Encodes `entropy` as a mnemonic.
+    pub fn from_entropy(entropy: &[u8], language: Language) -> Result<Self, MnemonicError> {
+        let word_count = WordCount::from_entropy_len(entropy.len())?;
+        let mut bits = Zeroizing::new(entropy.to_vec());
+        bits.push(checksum(entropy, word_count));
+
+        let wordlist = language.wordlist();
+        let phrase = (0..word_count.words())
+            .map(|position| wordlist[usize::from(read_word_index(&bits, position))].as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok(Self {
+            phrase: Zeroizing::new(phrase),
+            entropy: Zeroizing::new(entropy.to_vec()),
+            language,
+        })
+    }
+
+    /// Parses and validates a mnemonic phrase.
+    ///
+    /// The phrase is normalized to Unicode NFKD form and may separate words
+    /// with any whitespace.
+    pub fn from_phrase(phrase: &str, language: Language) -> Result<Self, MnemonicError> {
+        let phrase: Zeroizing<String> = Zeroizing::new(phrase.nfkd().collect());
+        let words: Vec<&str> = phrase.split_whitespace().collect();
+        let word_count = WordCount::from_words(words.len())?;
+
+        let mut bits = Zeroizing::new(vec![0; word_count.entropy_len().saturating_add(1)]);
+        for (position, word) in words.iter().enumerate() {
+            let index = language
+                .index_of(word)
+                .ok_or_else(|| MnemonicError::UnknownWord {
+                    position,
+                    word: word.to_string(),
+                })?;
+            write_word_index(&mut bits, position, index);
+        }
+        let stored_checksum = bits.pop().unwrap_or_default();
+        if stored_checksum != checksum(&bits, word_count) {
+            return Err(MnemonicError::InvalidChecksum);
+        }
+
+        Ok(Self {
+            phrase: Zeroizing::new(words.join(" ")),
+            entropy: bits,
+            language,
+        })
+    }
+
+    /// Checks that `phrase` is a valid mnemonic.
+    pub fn validate(phrase: &str, language: Language) -> Result<(), MnemonicError> {
+        Self::from_phrase(phrase, language).map(|_| ())
+    }
+
+    /// Returns the phrase in Unicode NFKD form.
+    pub fn phrase(&self) -> &str {
+        &self.phrase
+    }
+
+    /// Returns the encoded entropy.
+    pub fn entropy(&self) -> &[u8] {
+        &self.entropy
+    }
+
+    /// Returns the language of the wordlist.
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// Returns the number of words.
+    pub fn word_count(&self) -> WordCount {
+        WordCount::from_entropy_len(self.entropy.len())
+            .expect("entropy length was validated on construction")
+    }
+
+    /// Derives the 64-byte seed of the mnemonic, protected by `passphrase`,
+    /// which is normalized to Unicode NFKD form.
+    pub fn to_seed(&self, passphrase: &str) -> Vec<u8> {
+        generate_seed_from_normalized_seed_phrase_and_passphrase(&self.phrase, passphrase)
+    }
+}
+
+/// Returns the checksum bits of `entropy`, left-aligned in a byte as they
+/// follow the entropy in the mnemonic.
+fn checksum(entropy: &[u8], word_count: WordCount) -> u8 {
+    let mask = u8::MAX
+        .checked_shl(8u32.saturating_sub(word_count.checksum_bits()))
+        .unwrap_or_default();
+    Sha256::digest(entropy)[0] & mask
+}
+
+/// Reads the 11-bit wordlist index at `position` from `bits`.
+#[allow(clippy::arithmetic_side_effects)]
+fn read_word_index(bits: &[u8], position: usize) -> u16 {
+    // `position` is below 24, so the bit offsets cannot overflow
+    (0..BITS_PER_WORD).fold(0, |index, bit| {
+        let offset = position * BITS_PER_WORD + bit;
+        let value = (bits[offset / 8] >> (7 - offset % 8)) & 1;
+        (index << 1) | u16::from(value)
+    })
+}
+
+/// Writes the 11-bit wordlist index at `position` into `bits`.
+#[allow(clippy::arithmetic_side_effects)]
+fn write_word_index(bits: &mut [u8], position: usize, index: u16) {
+    // `position` is below 24, so the bit offsets cannot overflow
+    for bit in 0..BITS_PER_WORD {
+        if (index >> (BITS_PER_WORD - 1 - bit)) & 1 == 1 {
+            let offset = position * BITS_PER_WORD + bit;
+            bits[offset / 8] |= 0x80 >> (offset % 8);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORD_COUNTS: [WordCount; 5] = [
+        WordCount::Words12,
+        WordCount::Words15,
+        WordCount::Words18,
+        WordCount::Words21,
+        WordCount::Words24,
+    ];
+
+    #[test]
+    fn test_word_count() {
+        for word_count in WORD_COUNTS {
+            assert_eq!(
+                WordCount::from_words(word_count.words()).unwrap(),
+                word_count
+            );
+            assert_eq!(
+                WordCount::from_entropy_len(word_count.entropy_len()).unwrap(),
+                word_count
+            );
+            // Entropy and checksum exactly fill the words
+            assert_eq!(
+                word_count.entropy_len() * 8 + word_count.checksum_bits() as usize,
+                word_count.words() * BITS_PER_WORD
+            );
+        }
+        assert_eq!(
+            WordCount::from_words(13).unwrap_err(),
+            MnemonicError::InvalidWordCount(13)
+        );
+        assert_eq!(
+            WordCount::from_entropy_len(17).unwrap_err(),
+            MnemonicError::InvalidEntropyLength(17)
+        );
+    }
+
+    #[test]
+    fn test_from_entropy() {
+        let mnemonic = Mnemonic::from_entropy(&[0; 16], Language::English).unwrap();
+        assert_eq!(
+            mnemonic.phrase(),
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+             abandon about"
+        );
+        let mnemonic = Mnemonic::from_entropy(&[0xff; 32], Language::English).unwrap();
+        assert_eq!(mnemonic.phrase(), format!("{}vote", "zoo ".repeat(23)));
+
+        for word_count in WORD_COUNTS {
+            let entropy: Vec<u8> = (0..word_count.entropy_len() as u8).collect();
+            let mnemonic = Mnemonic::from_entropy(&entropy, Language::English).unwrap();
+            let expected =
+                bip39::Mnemonic::from_entropy(&entropy, bip39::Language::English).unwrap();
+            assert_eq!(mnemonic.phrase(), expected.phrase());
+            assert_eq!(mnemonic.word_count(), word_count);
+            assert_eq!(
+                mnemonic.to_seed("TREZOR"),
+                bip39::Seed::new(&expected, "TREZOR").as_bytes()
+            );
+
+            let parsed = Mnemonic::from_phrase(mnemonic.phrase(), Language::English).unwrap();
+            assert_eq!(parsed, mnemonic);
+            assert_eq!(parsed.entropy(), &entropy[..]);
+        }
+
+        assert_eq!(
+            Mnemonic::from_entropy(&[0; 15], Language::English).unwrap_err(),
+            MnemonicError::InvalidEntropyLength(15)
+        );
+    }
+
+    #[test]
+    fn test_from_phrase_errors() {
+        let phrase = Mnemonic::from_entropy(&[7; 16], Language::English)
+            .unwrap()
+            .phrase()
+            .to_string();
+        let mut words: Vec<&str> = phrase.split(' ').collect();
+
+        // Extra whitespace is accepted
+        let spaced = format!("  {}\n", words.join("\t "));
+        assert_eq!(
+            Mnemonic::from_phrase(&spaced, Language::English)
+                .unwrap()
+                .phrase(),
+            phrase
+        );
+
+        assert_eq!(
+            Mnemonic::from_phrase(&words[..11].join(" "), Language::English).unwrap_err(),
+            MnemonicError::InvalidWordCount(11)
+        );
+
+        let original = words[4];
+        words[4] = "solana";
+        assert_eq!(
+            Mnemonic::from_phrase(&words.join(" "), Language::English).unwrap_err(),
+            MnemonicError::UnknownWord {
+                position: 4,
+                word: "solana".to_string()
+            }
+        );
+        assert_eq!(
+            MnemonicError::UnknownWord {
+                position: 4,
+                word: "solana".to_string()
+            }
+            .to_string(),
+            "word 5 `solana` is not in the wordlist"
+        );
+
+        words[4] = if original == "abandon" {
+            "ability"
+        } else {
+            "abandon"
+        };
+        assert_eq!(
+            Mnemonic::validate(&words.join(" "), Language::English).unwrap_err(),
+            MnemonicError::InvalidChecksum
+        );
+    }
+
+    #[cfg(feature = "japanese")]
+    #[test]
+    fn test_japanese() {
+        let entropy = [0x5a; 32];
+        let mnemonic = Mnemonic::from_entropy(&entropy, Language::Japanese).unwrap();
+        let expected = bip39::Mnemonic::from_entropy(&entropy, bip39::Language::Japanese).unwrap();
+        assert_eq!(
+            mnemonic.to_seed("パスワード"),
+            bip39::Seed::new(&expected, "パスワード").as_bytes()
+        );
+
+        // Ideographic spaces and composed characters are normalized
+        let composed: String = mnemonic
+            .phrase()
+            .nfc()
+            .collect::<String>()
+            .replace(' ', "\u{3000}");
+        assert_ne!(composed, mnemonic.phrase());
+        let parsed = Mnemonic::from_phrase(&composed, Language::Japanese).unwrap();
+        assert_eq!(parsed, mnemonic);
+        assert_eq!(Language::detect(&composed), Some(Language::Japanese));
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_generate() {
+        for word_count in WORD_COUNTS {
+            let mnemonic = Mnemonic::generate(word_count, Language::English);
+            assert_eq!(mnemonic.word_count(), word_count);
+            assert_eq!(mnemonic.phrase().split(' ').count(), word_count.words());
+            Mnemonic::validate(mnemonic.phrase(), Language::English).unwrap();
+            bip39::Mnemonic::validate(mnemonic.phrase(), bip39::Language::English).unwrap();
+        }
+        assert_ne!(
+            Mnemonic::generate(WordCount::Words12, Language::English),
+            Mnemonic::generate(WordCount::Words12, Language::English)
+        );
+    }
+
+    #[test]
+    fn test_normalized_seed() {
+        use crate::generate_seed_from_seed_phrase_and_passphrase;
+
+        let phrase = Mnemonic::from_entropy(&[7; 16], Language::English).unwrap();
+        assert_eq!(
+            generate_seed_from_normalized_seed_phrase_and_passphrase(phrase.phrase(), "pass"),
+            generate_seed_from_seed_phrase_and_passphrase(phrase.phrase(), "pass")
+        );
+
+        // "é" composed, which NFKD decomposes into "e" and a combining accent
+        let composed = "caf\u{e9}";
+        let decomposed = "cafe\u{301}";
+        assert_eq!(
+            generate_seed_from_normalized_seed_phrase_and_passphrase(phrase.phrase(), composed),
+            generate_seed_from_seed_phrase_and_passphrase(phrase.phrase(), decomposed)
+        );
+        assert_ne!(
+            generate_seed_from_seed_phrase_and_passphrase(phrase.phrase(), composed),
+            generate_seed_from_seed_phrase_and_passphrase(phrase.phrase(), decomposed)
+        );
+        assert_eq!(
+            phrase.to_seed(composed),
+            generate_seed_from_normalized_seed_phrase_and_passphrase(phrase.phrase(), decomposed)
+        );
+    }
+
+    #[test]
+    fn test_words_by_prefix() {
+        assert_eq!(
+            Language::English.words_by_prefix("zo").collect::<Vec<_>>(),
+            vec!["zone", "zoo"]
+        );
+        assert_eq!(Language::English.words_by_prefix("xyz").count(), 0);
+        assert_eq!(Language::detect("abandon zoo"), Some(Language::English));
+        assert_eq!(Language::detect("abandon solana"), None);
+    }
+}
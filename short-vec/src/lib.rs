@@ -291,6 +291,18 @@ pub fn decode_shortu16_len(bytes: &[u8]) -> Result<(usize, usize), ()> {
     Err(())
 }
 
+/// Return how many bytes the encoding of the length `len` takes.
+///
+/// Lengths above `u16::MAX` cannot be encoded, and are reported with the
+/// maximum encoding length of 3 bytes.
+pub const fn encoded_shortu16_len_size(len: usize) -> usize {
+    match len {
+        0..0x80 => 1,
+        0x80..0x4000 => 2,
+        _ => MAX_ENCODING_LENGTH,
+    }
+}
+
 #[cfg(all(test, feature = "serde"))]
 mod tests {
     use {
@@ -312,6 +324,11 @@ mod tests {
             (usize::from(len), bytes.len()),
             "unexpected usize decoding"
         );
+        assert_eq!(
+            encoded_shortu16_len_size(usize::from(len)),
+            bytes.len(),
+            "unexpected encoding size"
+        );
     }
 
     #[test]
@@ -321,8 +338,11 @@ mod tests {
         assert_len_encoding(0x80, &[0x80, 0x01]);
         assert_len_encoding(0xff, &[0xff, 0x01]);
         assert_len_encoding(0x100, &[0x80, 0x02]);
+        assert_len_encoding(0x3fff, &[0xff, 0x7f]);
+        assert_len_encoding(0x4000, &[0x80, 0x80, 0x01]);
         assert_len_encoding(0x7fff, &[0xff, 0xff, 0x01]);
         assert_len_encoding(0xffff, &[0xff, 0xff, 0x03]);
+        assert_eq!(encoded_shortu16_len_size(0x10000), MAX_ENCODING_LENGTH);
     }
 
     fn assert_good_deserialized_value(value: u16, bytes: &[u8]) {
@@ -0,0 +1,582 @@
+//! Sign-In-With-Solana (SIWS) messages.
+//!
+//! A [`SiwsMessage`] is the request a website makes to a wallet to prove
+//! ownership of an address. Its canonical text form is
+//!
+//! ```text
+//! ${domain} wants you to sign in with your Solana account:
+//! ${address}
+//!
+//! ${statement}
+//!
+//! URI: ${uri}
+//! Version: ${version}
+//! Chain ID: ${chain-id}
+//! Nonce: ${nonce}
+//! Issued At: ${issued-at}
+//! Expiration Time: ${expiration-time}
+//! Not Before: ${not-before}
+//! Request ID: ${request-id}
+//! Resources:
+//! - ${resources[0]}
+//! - ${resources[1]}
+//! ```
+//!
+//! where every field after the address is optional and omitted lines are
+//! left out.
+//!
+//! By default the text is signed as a version 1 [`OffchainMessage`] whose only
+//! signer is the address, and whose application domain is the SHA256 hash of
+//! the domain, so that a sign-in signature can never be mistaken for a
+//! transaction or for a message of another application.
+//!
+//! Wallets implementing the `solana:signIn` feature of the Wallet Standard
+//! sign the UTF-8 bytes of the text as they are instead. Signatures returned
+//! by such wallets are checked with [`SigningFormat::RawText`].
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+mod parse;
+
+pub use parse::{ParseError, ParseErrorKind};
+use {
+    chrono::{DateTime, FixedOffset, SecondsFormat, Utc},
+    parse::*,
+    solana_offchain_message::OffchainMessage,
+    solana_pubkey::Pubkey,
+    solana_sanitize::SanitizeError,
+    solana_sha256_hasher::hash,
+    solana_signature::Signature,
+    solana_signer::{Signer, SignerError},
+    std::{error, fmt, str::FromStr, time::SystemTime},
+};
+
+const HEADER_SUFFIX: &str = " wants you to sign in with your Solana account:";
+
+/// An RFC 3339 timestamp, kept with its original text so that parsed
+/// messages serialize back to the exact bytes that were signed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timestamp {
+    time: DateTime<FixedOffset>,
+    text: String,
+}
+
+impl Timestamp {
+    pub fn to_system_time(&self) -> SystemTime {
+        SystemTime::from(self.time)
+    }
+}
+
+/// Formats the time in UTC with millisecond precision, like JavaScript's
+/// `Date.prototype.toISOString`.
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        let time = DateTime::<Utc>::from(time);
+        Self {
+            text: time.to_rfc3339_opts(SecondsFormat::Millis, true),
+            time: time.fixed_offset(),
+        }
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = chrono::ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            time: DateTime::parse_from_rfc3339(text)?,
+            text: text.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// A Sign-In-With-Solana message.
+///
+/// Fields are public for construction; a message is only signed or verified
+/// if its text form parses back to the same message, see
+/// [`validate`](Self::validate).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiwsMessage {
+    /// RFC 3986 authority requesting the sign-in.
+    pub domain: String,
+    /// Account signing in.
+    pub address: Pubkey,
+    /// Human-readable assertion, on a single line.
+    pub statement: Option<String>,
+    /// RFC 3986 URI referring to the subject of the sign-in.
+    pub uri: Option<String>,
+    /// Message version, only `1` is supported.
+    pub version: Option<String>,
+    pub chain_id: Option<String>,
+    /// At least 8 alphanumeric characters chosen by the website, to prevent
+    /// replays.
+    pub nonce: Option<String>,
+    pub issued_at: Option<Timestamp>,
+    pub expiration_time: Option<Timestamp>,
+    pub not_before: Option<Timestamp>,
+    pub request_id: Option<String>,
+    /// RFC 3986 URIs the user wishes to have resolved as part of the sign-in.
+    pub resources: Vec<String>,
+}
+
+/// The bytes a [`SiwsMessage`] is signed as.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SigningFormat {
+    /// The text wrapped in a version 1 off-chain message, see
+    /// [`SiwsMessage::to_offchain_message`].
+    #[default]
+    OffchainMessage,
+    /// The UTF-8 bytes of the text, as signed by wallets implementing the
+    /// Wallet Standard `solana:signIn` feature.
+    RawText,
+}
+
+/// Error signing or verifying a message.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SiwsError {
+    /// The message does not round-trip through its text form.
+    InvalidMessage(ParseError),
+    /// The text cannot be wrapped in an off-chain message.
+    OffchainMessage(SanitizeError),
+    /// The signer failed, or is not the message address.
+    Signer(SignerError),
+    DomainMismatch {
+        expected: String,
+        found: String,
+    },
+    /// The clock is at or past the expiration time.
+    Expired,
+    /// The clock is before the not-before time.
+    NotYetValid,
+    /// The clock is before the issued-at time.
+    IssuedInFuture,
+    InvalidSignature,
+}
+
+impl error::Error for SiwsError {}
+
+impl fmt::Display for SiwsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidMessage(err) => write!(f, "invalid message: {err}"),
+            Self::OffchainMessage(err) => write!(f, "invalid off-chain message: {err}"),
+            Self::Signer(err) => write!(f, "signer error: {err}"),
+            Self::DomainMismatch { expected, found } => {
+                write!(f, "message is for domain {found}, expected {expected}")
+            }
+            Self::Expired => f.write_str("message has expired"),
+            Self::NotYetValid => f.write_str("message is not valid yet"),
+            Self::IssuedInFuture => f.write_str("message is issued in the future"),
+            Self::InvalidSignature => f.write_str("invalid signature"),
+        }
+    }
+}
+
+impl From<ParseError> for SiwsError {
+    fn from(err: ParseError) -> Self {
+        Self::InvalidMessage(err)
+    }
+}
+
+impl From<SanitizeError> for SiwsError {
+    fn from(err: SanitizeError) -> Self {
+        Self::OffchainMessage(err)
+    }
+}
+
+impl From<SignerError> for SiwsError {
+    fn from(err: SignerError) -> Self {
+        Self::Signer(err)
+    }
+}
+
+impl SiwsMessage {
+    /// Creates a message with only the required fields.
+    pub fn new(domain: &str, address: Pubkey) -> Self {
+        Self {
+            domain: domain.to_string(),
+            address,
+            statement: None,
+            uri: None,
+            version: None,
+            chain_id: None,
+            nonce: None,
+            issued_at: None,
+            expiration_time: None,
+            not_before: None,
+            request_id: None,
+            resources: Vec::new(),
+        }
+    }
+
+    /// Checks that the text form of the message parses back to the same
+    /// message, which rules out fields that would be read differently than
+    /// they were written.
+    pub fn validate(&self) -> Result<(), SiwsError> {
+        let text = self.to_string();
+        let parsed = SiwsMessage::from_str(&text)?;
+        if parsed == *self {
+            return Ok(());
+        }
+        // The text parsed, but into something else: either the statement
+        // reads as a field, or a resource contains a line break
+        let err = if parsed.statement != self.statement {
+            ParseError {
+                line: 4,
+                kind: ParseErrorKind::InvalidStatement,
+            }
+        } else {
+            ParseError {
+                line: text.split('\n').count(),
+                kind: ParseErrorKind::UnexpectedLine,
+            }
+        };
+        Err(SiwsError::InvalidMessage(err))
+    }
+
+    /// Wraps the text form of the message in the off-chain message that is
+    /// signed in the [`SigningFormat::OffchainMessage`] format.
+    pub fn to_offchain_message(&self) -> Result<OffchainMessage, SiwsError> {
+        self.validate()?;
+        Ok(OffchainMessage::new_with_signers(
+            hash(self.domain.as_bytes()).to_bytes(),
+            &[self.address],
+            self.to_string().as_bytes(),
+        )?)
+    }
+
+    /// Returns the bytes that are signed in `format`, after checking that the
+    /// text form of the message round-trips.
+    pub fn to_signable_bytes(&self, format: SigningFormat) -> Result<Vec<u8>, SiwsError> {
+        match format {
+            SigningFormat::OffchainMessage => Ok(self.to_offchain_message()?.serialize()?),
+            SigningFormat::RawText => {
+                self.validate()?;
+                Ok(self.to_string().into_bytes())
+            }
+        }
+    }
+
+    /// Signs the message as an off-chain message; `signer` must be the
+    /// message address.
+    pub fn sign(&self, signer: &dyn Signer) -> Result<Signature, SiwsError> {
+        self.sign_with_format(signer, SigningFormat::default())
+    }
+
+    /// Signs the message in `format`; `signer` must be the message address.
+    pub fn sign_with_format(
+        &self,
+        signer: &dyn Signer,
+        format: SigningFormat,
+    ) -> Result<Signature, SiwsError> {
+        let message = self.to_signable_bytes(format)?;
+        if signer.try_pubkey()? != self.address {
+            return Err(SignerError::KeypairPubkeyMismatch.into());
+        }
+        Ok(signer.try_sign_message(&message)?)
+    }
+
+    /// Verifies that `signature` is the address signing this message as an
+    /// off-chain message, that the message was made for `domain`, and that it
+    /// is valid at `now`: not issued after `now`, and within its not-before
+    /// and expiration times.
+    pub fn verify(
+        &self,
+        signature: &Signature,
+        domain: &str,
+        now: SystemTime,
+    ) -> Result<(), SiwsError> {
+        self.verify_with_format(signature, domain, now, SigningFormat::default())
+    }
+
+    /// Like [`verify`](Self::verify), for a message signed in `format`.
+    pub fn verify_with_format(
+        &self,
+        signature: &Signature,
+        domain: &str,
+        now: SystemTime,
+        format: SigningFormat,
+    ) -> Result<(), SiwsError> {
+        let message = self.to_signable_bytes(format)?;
+        if self.domain != domain {
+            return Err(SiwsError::DomainMismatch {
+                expected: domain.to_string(),
+                found: self.domain.clone(),
+            });
+        }
+        if let Some(expiration_time) = &self.expiration_time {
+            if now >= expiration_time.to_system_time() {
+                return Err(SiwsError::Expired);
+            }
+        }
+        if let Some(not_before) = &self.not_before {
+            if now < not_before.to_system_time() {
+                return Err(SiwsError::NotYetValid);
+            }
+        }
+        if let Some(issued_at) = &self.issued_at {
+            if now < issued_at.to_system_time() {
+                return Err(SiwsError::IssuedInFuture);
+            }
+        }
+        if signature.verify(self.address.as_ref(), &message) {
+            Ok(())
+        } else {
+            Err(SiwsError::InvalidSignature)
+        }
+    }
+}
+
+impl fmt::Display for SiwsMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{HEADER_SUFFIX}\n{}", self.domain, self.address)?;
+        if let Some(statement) = &self.statement {
+            write!(f, "\n\n{statement}")?;
+        }
+
+        let fields = [
+            (URI_TAG, self.uri.clone()),
+            (VERSION_TAG, self.version.clone()),
+            (CHAIN_ID_TAG, self.chain_id.clone()),
+            (NONCE_TAG, self.nonce.clone()),
+            (
+                ISSUED_AT_TAG,
+                self.issued_at.as_ref().map(Timestamp::to_string),
+            ),
+            (
+                EXPIRATION_TIME_TAG,
+                self.expiration_time.as_ref().map(Timestamp::to_string),
+            ),
+            (
+                NOT_BEFORE_TAG,
+                self.not_before.as_ref().map(Timestamp::to_string),
+            ),
+            (REQUEST_ID_TAG, self.request_id.clone()),
+        ];
+        let mut separator = "\n\n";
+        for (tag, value) in fields {
+            if let Some(value) = value {
+                write!(f, "{separator}{tag}{value}")?;
+                separator = "\n";
+            }
+        }
+        if !self.resources.is_empty() {
+            write!(f, "{separator}{RESOURCES_TAG}")?;
+            for resource in &self.resources {
+                write!(f, "\n{RESOURCE_PREFIX}{resource}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_keypair::Keypair,
+        std::time::{Duration, UNIX_EPOCH},
+    };
+
+    fn message(address: Pubkey) -> SiwsMessage {
+        SiwsMessage {
+            statement: Some("Sign in to Example".to_string()),
+            uri: Some("https://example.com/login".to_string()),
+            version: Some("1".to_string()),
+            chain_id: Some("mainnet".to_string()),
+            nonce: Some("32891756".to_string()),
+            issued_at: Some("2024-01-01T00:00:00.000Z".parse().unwrap()),
+            expiration_time: Some("2024-01-01T02:00:00+01:00".parse().unwrap()),
+            not_before: Some("2023-12-31T23:59:00Z".parse().unwrap()),
+            request_id: Some("request-1".to_string()),
+            resources: vec![
+                "ipfs://bafybeiemxf5abjwjbikoz4mc3a3dla6ual3jsgpdr4cjr3oz3evfyavhwq/".to_string(),
+                "https://example.com/my-web2-claim.json".to_string(),
+            ],
+            ..SiwsMessage::new("example.com", address)
+        }
+    }
+
+    fn at(rfc3339: &str) -> SystemTime {
+        Timestamp::from_str(rfc3339).unwrap().to_system_time()
+    }
+
+    #[test]
+    fn test_canonical_text() {
+        let message = message(Pubkey::default());
+        let text = "example.com wants you to sign in with your Solana account:
+11111111111111111111111111111111
+
+Sign in to Example
+
+URI: https://example.com/login
+Version: 1
+Chain ID: mainnet
+Nonce: 32891756
+Issued At: 2024-01-01T00:00:00.000Z
+Expiration Time: 2024-01-01T02:00:00+01:00
+Not Before: 2023-12-31T23:59:00Z
+Request ID: request-1
+Resources:
+- ipfs://bafybeiemxf5abjwjbikoz4mc3a3dla6ual3jsgpdr4cjr3oz3evfyavhwq/
+- https://example.com/my-web2-claim.json";
+        assert_eq!(message.to_string(), text);
+        assert_eq!(SiwsMessage::from_str(text).unwrap(), message);
+        message.validate().unwrap();
+
+        // Fields without a statement
+        let message = SiwsMessage {
+            statement: None,
+            nonce: None,
+            ..message
+        };
+        assert!(message
+            .to_string()
+            .contains("11111111111111111111111111111111\n\nURI: "));
+        assert!(message
+            .to_string()
+            .contains("Chain ID: mainnet\nIssued At: "));
+        assert_eq!(
+            SiwsMessage::from_str(&message.to_string()).unwrap(),
+            message
+        );
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let timestamp = Timestamp::from(UNIX_EPOCH + Duration::from_millis(1_704_067_200_500));
+        assert_eq!(timestamp.to_string(), "2024-01-01T00:00:00.500Z");
+        assert_eq!(at("2024-01-01T01:00:00+01:00"), at("2024-01-01T00:00:00Z"));
+        assert!(Timestamp::from_str("2024-01-01").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let address = Pubkey::default();
+        let invalid = [
+            SiwsMessage::new("example.com/path", address),
+            SiwsMessage {
+                statement: Some("two\nlines".to_string()),
+                ..SiwsMessage::new("example.com", address)
+            },
+            SiwsMessage {
+                nonce: Some("1234".to_string()),
+                ..SiwsMessage::new("example.com", address)
+            },
+            SiwsMessage {
+                resources: vec!["not a uri".to_string()],
+                ..SiwsMessage::new("example.com", address)
+            },
+        ];
+        // Mistaken for a field when parsed back
+        let statement = SiwsMessage {
+            statement: Some("URI: https://example.com".to_string()),
+            ..SiwsMessage::new("example.com", address)
+        };
+        assert_eq!(
+            statement.validate(),
+            Err(SiwsError::InvalidMessage(ParseError {
+                line: 4,
+                kind: ParseErrorKind::InvalidStatement
+            }))
+        );
+        for message in invalid {
+            assert!(
+                matches!(message.validate(), Err(SiwsError::InvalidMessage(_))),
+                "{message}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let keypair = Keypair::new();
+        let message = message(keypair.pubkey());
+        let signature = message.sign(&keypair).unwrap();
+        let now = at("2024-01-01T00:00:30Z");
+        message.verify(&signature, "example.com", now).unwrap();
+
+        // The signature covers the off-chain message of the text
+        let offchain = message.to_offchain_message().unwrap();
+        assert_eq!(
+            offchain.get_message().as_slice(),
+            message.to_string().as_bytes()
+        );
+        assert_eq!(
+            offchain.get_application_domain(),
+            Some(&hash(b"example.com").to_bytes())
+        );
+        assert!(signature.verify(keypair.pubkey().as_ref(), &offchain.serialize().unwrap()));
+        assert_eq!(
+            message.verify_with_format(&signature, "example.com", now, SigningFormat::RawText),
+            Err(SiwsError::InvalidSignature)
+        );
+
+        assert_eq!(
+            message.verify(&signature, "evil.com", now),
+            Err(SiwsError::DomainMismatch {
+                expected: "evil.com".to_string(),
+                found: "example.com".to_string(),
+            })
+        );
+        assert_eq!(
+            message.verify(&signature, "example.com", at("2024-01-01T01:00:00Z")),
+            Err(SiwsError::Expired)
+        );
+        assert_eq!(
+            message.verify(&signature, "example.com", at("2023-12-31T23:58:59Z")),
+            Err(SiwsError::NotYetValid)
+        );
+        assert_eq!(
+            message.verify(&signature, "example.com", at("2023-12-31T23:59:30Z")),
+            Err(SiwsError::IssuedInFuture)
+        );
+
+        let tampered = SiwsMessage {
+            nonce: Some("32891757".to_string()),
+            ..message.clone()
+        };
+        assert_eq!(
+            tampered.verify(&signature, "example.com", now),
+            Err(SiwsError::InvalidSignature)
+        );
+
+        // Only the address can sign
+        assert_eq!(
+            message.sign(&Keypair::new()),
+            Err(SiwsError::Signer(SignerError::KeypairPubkeyMismatch))
+        );
+    }
+
+    #[test]
+    fn test_sign_and_verify_raw_text() {
+        let keypair = Keypair::new();
+        let message = message(keypair.pubkey());
+        let signature = message
+            .sign_with_format(&keypair, SigningFormat::RawText)
+            .unwrap();
+        let now = at("2024-01-01T00:00:30Z");
+        message
+            .verify_with_format(&signature, "example.com", now, SigningFormat::RawText)
+            .unwrap();
+
+        // The signature covers the text itself
+        assert!(signature.verify(keypair.pubkey().as_ref(), message.to_string().as_bytes()));
+        assert_eq!(
+            message.verify(&signature, "example.com", now),
+            Err(SiwsError::InvalidSignature)
+        );
+        assert_eq!(
+            message.verify_with_format(&signature, "evil.com", now, SigningFormat::RawText),
+            Err(SiwsError::DomainMismatch {
+                expected: "evil.com".to_string(),
+                found: "example.com".to_string(),
+            })
+        );
+    }
+}
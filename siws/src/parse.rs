@@ -0,0 +1,383 @@
+//! Strict parser for the text form of a [`SiwsMessage`].
+
+use {
+    crate::{SiwsMessage, Timestamp, HEADER_SUFFIX},
+    solana_pubkey::Pubkey,
+    std::{error, fmt, str::FromStr},
+};
+
+/// Error parsing a message, with the 1-based line where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The message ended before a required line.
+    UnexpectedEnd,
+    /// The first line is not `<domain> wants you to sign in with your Solana
+    /// account:`.
+    InvalidHeader,
+    InvalidDomain,
+    InvalidAddress,
+    /// An empty line was expected between sections.
+    ExpectedEmptyLine,
+    InvalidStatement,
+    /// A line that is not a known field, or a field repeated or out of order.
+    UnexpectedLine,
+    InvalidUri,
+    InvalidVersion,
+    InvalidChainId,
+    InvalidNonce,
+    InvalidTimestamp,
+    /// `Resources:` is not followed by any resource.
+    EmptyResources,
+}
+
+impl error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self.kind {
+            ParseErrorKind::UnexpectedEnd => "unexpected end of message",
+            ParseErrorKind::InvalidHeader => "invalid header",
+            ParseErrorKind::InvalidDomain => "invalid domain",
+            ParseErrorKind::InvalidAddress => "invalid address",
+            ParseErrorKind::ExpectedEmptyLine => "expected an empty line",
+            ParseErrorKind::InvalidStatement => "invalid statement",
+            ParseErrorKind::UnexpectedLine => "unexpected line",
+            ParseErrorKind::InvalidUri => "invalid URI",
+            ParseErrorKind::InvalidVersion => "unsupported version",
+            ParseErrorKind::InvalidChainId => "invalid chain ID",
+            ParseErrorKind::InvalidNonce => "invalid nonce",
+            ParseErrorKind::InvalidTimestamp => "invalid timestamp",
+            ParseErrorKind::EmptyResources => "empty resources",
+        };
+        write!(f, "line {}: {description}", self.line)
+    }
+}
+
+pub(crate) const URI_TAG: &str = "URI: ";
+pub(crate) const VERSION_TAG: &str = "Version: ";
+pub(crate) const CHAIN_ID_TAG: &str = "Chain ID: ";
+pub(crate) const NONCE_TAG: &str = "Nonce: ";
+pub(crate) const ISSUED_AT_TAG: &str = "Issued At: ";
+pub(crate) const EXPIRATION_TIME_TAG: &str = "Expiration Time: ";
+pub(crate) const NOT_BEFORE_TAG: &str = "Not Before: ";
+pub(crate) const REQUEST_ID_TAG: &str = "Request ID: ";
+pub(crate) const RESOURCES_TAG: &str = "Resources:";
+pub(crate) const RESOURCE_PREFIX: &str = "- ";
+
+/// Field tags, in the order the fields must appear.
+const FIELD_TAGS: [&str; 9] = [
+    URI_TAG,
+    VERSION_TAG,
+    CHAIN_ID_TAG,
+    NONCE_TAG,
+    ISSUED_AT_TAG,
+    EXPIRATION_TIME_TAG,
+    NOT_BEFORE_TAG,
+    REQUEST_ID_TAG,
+    RESOURCES_TAG,
+];
+
+fn field_index(line: &str) -> Option<usize> {
+    FIELD_TAGS.iter().position(|tag| {
+        if *tag == RESOURCES_TAG {
+            line == RESOURCES_TAG
+        } else {
+            line.starts_with(tag)
+        }
+    })
+}
+
+/// Returns true if `domain` is a valid RFC 3986 authority, a host with an
+/// optional port.
+pub(crate) fn is_domain(domain: &str) -> bool {
+    !domain.is_empty()
+        && domain
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"-._~%!$&'()*+,;=:[]@".contains(&byte))
+}
+
+/// Returns true if `uri` has an RFC 3986 scheme and no whitespace or control
+/// characters.
+pub(crate) fn is_uri(uri: &str) -> bool {
+    let Some((scheme, _)) = uri.split_once(':') else {
+        return false;
+    };
+    scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        && !uri.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+/// Returns true if `nonce` has at least 8 alphanumeric characters.
+pub(crate) fn is_nonce(nonce: &str) -> bool {
+    nonce.len() >= 8 && nonce.bytes().all(|byte| byte.is_ascii_alphanumeric())
+}
+
+struct Lines<'a> {
+    lines: std::str::Split<'a, char>,
+    number: usize,
+}
+
+impl<'a> Lines<'a> {
+    fn next(&mut self) -> Option<&'a str> {
+        let line = self.lines.next()?;
+        self.number = self.number.saturating_add(1);
+        Some(line)
+    }
+
+    fn expect(&mut self) -> Result<&'a str, ParseError> {
+        match self.next() {
+            Some(line) => Ok(line),
+            None => Err(self.error_after(ParseErrorKind::UnexpectedEnd)),
+        }
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            line: self.number,
+            kind,
+        }
+    }
+
+    /// Error on the line after the current one, which is missing.
+    fn error_after(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            line: self.number.saturating_add(1),
+            kind,
+        }
+    }
+}
+
+impl FromStr for SiwsMessage {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut lines = Lines {
+            lines: text.split('\n'),
+            number: 0,
+        };
+
+        let domain = lines
+            .expect()?
+            .strip_suffix(HEADER_SUFFIX)
+            .ok_or_else(|| lines.error(ParseErrorKind::InvalidHeader))?;
+        if !is_domain(domain) {
+            return Err(lines.error(ParseErrorKind::InvalidDomain));
+        }
+        let address = Pubkey::from_str(lines.expect()?)
+            .map_err(|_| lines.error(ParseErrorKind::InvalidAddress))?;
+        let mut message = SiwsMessage::new(domain, address);
+
+        if !expect_section(&mut lines)? {
+            return Ok(message);
+        }
+        let mut line = lines.expect()?;
+        if field_index(line).is_none() {
+            if line.is_empty() {
+                return Err(lines.error(ParseErrorKind::InvalidStatement));
+            }
+            message.statement = Some(line.to_string());
+            if !expect_section(&mut lines)? {
+                return Ok(message);
+            }
+            line = lines.expect()?;
+        }
+
+        // Fields, in order and each at most once
+        let mut next_field = 0;
+        loop {
+            let index = field_index(line)
+                .filter(|index| *index >= next_field)
+                .ok_or_else(|| lines.error(ParseErrorKind::UnexpectedLine))?;
+            next_field = index.saturating_add(1);
+            let value = &line[FIELD_TAGS[index].len()..];
+            match FIELD_TAGS[index] {
+                URI_TAG if is_uri(value) => message.uri = Some(value.to_string()),
+                URI_TAG => return Err(lines.error(ParseErrorKind::InvalidUri)),
+                VERSION_TAG if value == "1" => message.version = Some(value.to_string()),
+                VERSION_TAG => return Err(lines.error(ParseErrorKind::InvalidVersion)),
+                CHAIN_ID_TAG if !value.is_empty() && !value.contains(char::is_whitespace) => {
+                    message.chain_id = Some(value.to_string())
+                }
+                CHAIN_ID_TAG => return Err(lines.error(ParseErrorKind::InvalidChainId)),
+                NONCE_TAG if is_nonce(value) => message.nonce = Some(value.to_string()),
+                NONCE_TAG => return Err(lines.error(ParseErrorKind::InvalidNonce)),
+                ISSUED_AT_TAG => message.issued_at = Some(parse_timestamp(&lines, value)?),
+                EXPIRATION_TIME_TAG => {
+                    message.expiration_time = Some(parse_timestamp(&lines, value)?)
+                }
+                NOT_BEFORE_TAG => message.not_before = Some(parse_timestamp(&lines, value)?),
+                REQUEST_ID_TAG => message.request_id = Some(value.to_string()),
+                _ => {
+                    // Resources are the last field and run to the end
+                    while let Some(line) = lines.next() {
+                        let resource = line
+                            .strip_prefix(RESOURCE_PREFIX)
+                            .ok_or_else(|| lines.error(ParseErrorKind::UnexpectedLine))?;
+                        if !is_uri(resource) {
+                            return Err(lines.error(ParseErrorKind::InvalidUri));
+                        }
+                        message.resources.push(resource.to_string());
+                    }
+                    if message.resources.is_empty() {
+                        return Err(lines.error(ParseErrorKind::EmptyResources));
+                    }
+                    return Ok(message);
+                }
+            }
+            match lines.next() {
+                Some(next) => line = next,
+                None => return Ok(message),
+            }
+        }
+    }
+}
+
+/// Consumes the empty line starting a new section, returning false at the
+/// end of the message.
+fn expect_section(lines: &mut Lines) -> Result<bool, ParseError> {
+    match lines.next() {
+        None => Ok(false),
+        Some("") => Ok(true),
+        Some(_) => Err(lines.error(ParseErrorKind::ExpectedEmptyLine)),
+    }
+}
+
+fn parse_timestamp(lines: &Lines, value: &str) -> Result<Timestamp, ParseError> {
+    Timestamp::from_str(value).map_err(|_| lines.error(ParseErrorKind::InvalidTimestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "11111111111111111111111111111111";
+
+    fn parse_error(text: &str) -> ParseError {
+        SiwsMessage::from_str(text).unwrap_err()
+    }
+
+    #[test]
+    fn test_parse_minimal() {
+        let text = format!("example.com wants you to sign in with your Solana account:\n{ADDRESS}");
+        let message = SiwsMessage::from_str(&text).unwrap();
+        assert_eq!(message.domain, "example.com");
+        assert_eq!(message.address, Pubkey::default());
+        assert_eq!(message.statement, None);
+        assert_eq!(message.to_string(), text);
+
+        let text = format!("{text}\n\nSign in");
+        let message = SiwsMessage::from_str(&text).unwrap();
+        assert_eq!(message.statement.as_deref(), Some("Sign in"));
+        assert_eq!(message.to_string(), text);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let header = "example.com wants you to sign in with your Solana account:";
+        let cases = [
+            ("", 1, ParseErrorKind::InvalidHeader),
+            (
+                "example.com wants you to sign in",
+                1,
+                ParseErrorKind::InvalidHeader,
+            ),
+            (
+                " wants you to sign in with your Solana account:",
+                1,
+                ParseErrorKind::InvalidDomain,
+            ),
+            (
+                "exa mple.com wants you to sign in with your Solana account:",
+                1,
+                ParseErrorKind::InvalidDomain,
+            ),
+            (header, 2, ParseErrorKind::UnexpectedEnd),
+            (
+                &format!("{header}\nnot-an-address"),
+                2,
+                ParseErrorKind::InvalidAddress,
+            ),
+            (
+                &format!("{header}\n{ADDRESS}\n"),
+                4,
+                ParseErrorKind::UnexpectedEnd,
+            ),
+            (
+                &format!("{header}\n{ADDRESS}\nhi"),
+                3,
+                ParseErrorKind::ExpectedEmptyLine,
+            ),
+            (
+                &format!("{header}\n{ADDRESS}\n\n"),
+                4,
+                ParseErrorKind::InvalidStatement,
+            ),
+            (
+                &format!("{header}\n{ADDRESS}\n\nhi\nthere"),
+                5,
+                ParseErrorKind::ExpectedEmptyLine,
+            ),
+            (
+                &format!("{header}\n{ADDRESS}\n\nhi\n\nURI: not a uri"),
+                6,
+                ParseErrorKind::InvalidUri,
+            ),
+            (
+                &format!("{header}\n{ADDRESS}\n\nVersion: 2"),
+                4,
+                ParseErrorKind::InvalidVersion,
+            ),
+            (
+                &format!("{header}\n{ADDRESS}\n\nNonce: short"),
+                4,
+                ParseErrorKind::InvalidNonce,
+            ),
+            (
+                &format!("{header}\n{ADDRESS}\n\nIssued At: yesterday"),
+                4,
+                ParseErrorKind::InvalidTimestamp,
+            ),
+            // Out of order and repeated fields
+            (
+                &format!("{header}\n{ADDRESS}\n\nNonce: 12345678\nURI: https://example.com"),
+                5,
+                ParseErrorKind::UnexpectedLine,
+            ),
+            (
+                &format!("{header}\n{ADDRESS}\n\nNonce: 12345678\nNonce: 12345678"),
+                5,
+                ParseErrorKind::UnexpectedLine,
+            ),
+            (
+                &format!("{header}\n{ADDRESS}\n\nNonce: 12345678\nUnknown: field"),
+                5,
+                ParseErrorKind::UnexpectedLine,
+            ),
+            (
+                &format!("{header}\n{ADDRESS}\n\nResources:"),
+                4,
+                ParseErrorKind::EmptyResources,
+            ),
+            (
+                &format!("{header}\n{ADDRESS}\n\nResources:\n- https://a.com\nhttps://b.com"),
+                6,
+                ParseErrorKind::UnexpectedLine,
+            ),
+        ];
+        for (text, line, kind) in cases {
+            assert_eq!(parse_error(text), ParseError { line, kind }, "{text:?}");
+        }
+        assert_eq!(
+            parse_error(header).to_string(),
+            "line 2: unexpected end of message"
+        );
+    }
+}
@@ -0,0 +1,676 @@
+//! Decode transactions into a human-readable form.
+//!
+//! [`inspect_transaction`] walks a legacy, v0 or v1 transaction, resolves the
+//! account indexes of the message to addresses along with their signer and
+//! writable flags, and decodes the instruction data of well-known native
+//! programs. The resulting [`InspectedTransaction`] can be serialized with
+//! `serde` or pretty-printed through its [`Display`](fmt::Display)
+//! implementation.
+//!
+//! Accounts loaded from address lookup tables can only be resolved when the
+//! addresses loaded from the tables are provided. Otherwise they are reported
+//! by lookup table and index, with no address.
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+use {
+    serde_derive::{Deserialize, Serialize},
+    solana_address::Address,
+    solana_address_lookup_table_interface::instruction::ProgramInstruction as AddressLookupTableInstruction,
+    solana_compute_budget_interface::ComputeBudgetInstruction,
+    solana_feature_gate_interface::instruction::FeatureGateInstruction,
+    solana_hash::Hash,
+    solana_loader_v3_interface::instruction::UpgradeableLoaderInstruction,
+    solana_message::{v0::LoadedAddresses, v1::TransactionConfig, AccountKeys, VersionedMessage},
+    solana_sdk_ids::{
+        address_lookup_table, bpf_loader_upgradeable, compute_budget, feature, system_program, vote,
+    },
+    solana_signature::Signature,
+    solana_system_interface::instruction::SystemInstruction,
+    solana_transaction::versioned::{TransactionVersion, VersionedTransaction},
+    solana_vote_interface::instruction::VoteInstruction,
+    std::fmt,
+};
+
+/// A decoded transaction.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InspectedTransaction {
+    /// Message version of the transaction.
+    pub version: TransactionVersion,
+    /// Signatures of the transaction, in the order of the signers.
+    pub signatures: Vec<InspectedSignature>,
+    /// Recent blockhash, or lifetime specifier of a v1 message.
+    pub recent_blockhash: Hash,
+    /// Transaction configuration of a v1 message.
+    pub config: Option<TransactionConfig>,
+    /// Accounts referenced by the message: static keys first, followed by
+    /// writable and then readonly accounts loaded from lookup tables.
+    pub accounts: Vec<InspectedAccount>,
+    /// Instructions of the message, in execution order.
+    pub instructions: Vec<InspectedInstruction>,
+}
+
+/// A transaction signature and the account which is expected to produce it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InspectedSignature {
+    /// Address of the signer, or `None` if the message does not require a
+    /// signature at this index.
+    pub signer: Option<Address>,
+    pub signature: Signature,
+}
+
+/// An account referenced by a message.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InspectedAccount {
+    /// Index of the account in the message.
+    pub index: u8,
+    /// Address of the account, or `None` if the account is loaded from a
+    /// lookup table whose addresses were not provided or the index is out of
+    /// bounds.
+    pub address: Option<Address>,
+    pub is_signer: bool,
+    /// Whether the message requests a write lock for the account. Write
+    /// locks of reserved accounts may still be demoted by the runtime.
+    pub is_writable: bool,
+    /// Lookup table the account is loaded from, if any.
+    pub lookup_table: Option<LookupTableEntry>,
+}
+
+/// Location of an account loaded from an address lookup table.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LookupTableEntry {
+    /// Address of the lookup table account.
+    pub table: Address,
+    /// Index of the address in the lookup table.
+    pub index: u8,
+}
+
+/// A decoded instruction.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InspectedInstruction {
+    /// The invoked program account, or `None` if the program index is out of
+    /// bounds.
+    pub program: Option<InspectedAccount>,
+    /// Accounts passed to the instruction, in order.
+    pub accounts: Vec<InspectedAccount>,
+    /// Raw instruction data.
+    pub data: Vec<u8>,
+    /// Instruction data decoded for a well-known program, or `None` if the
+    /// program is not known or the data does not decode.
+    pub decoded: Option<DecodedInstruction>,
+}
+
+/// Instruction data decoded for a well-known program.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", tag = "program", content = "instruction")]
+pub enum DecodedInstruction {
+    System(SystemInstruction),
+    ComputeBudget(ComputeBudgetInstruction),
+    Vote(VoteInstruction),
+    UpgradeableLoader(UpgradeableLoaderInstruction),
+    AddressLookupTable(AddressLookupTableInstruction),
+    FeatureGate(FeatureGateInstruction),
+}
+
+impl DecodedInstruction {
+    /// Decode `data` of an instruction for `program_id`.
+    pub fn decode(program_id: &Address, data: &[u8]) -> Option<Self> {
+        if system_program::check_id(program_id) {
+            wincode::deserialize(data).ok().map(Self::System)
+        } else if compute_budget::check_id(program_id) {
+            borsh::from_slice(data).ok().map(Self::ComputeBudget)
+        } else if vote::check_id(program_id) {
+            wincode::deserialize(data).ok().map(Self::Vote)
+        } else if bpf_loader_upgradeable::check_id(program_id) {
+            wincode::deserialize(data).ok().map(Self::UpgradeableLoader)
+        } else if address_lookup_table::check_id(program_id) {
+            wincode::deserialize(data)
+                .ok()
+                .map(Self::AddressLookupTable)
+        } else if feature::check_id(program_id) {
+            FeatureGateInstruction::unpack(data)
+                .ok()
+                .map(Self::FeatureGate)
+        } else {
+            None
+        }
+    }
+
+    /// Name of the program the instruction is decoded for.
+    pub fn program_name(&self) -> &'static str {
+        match self {
+            Self::System(_) => "System Program",
+            Self::ComputeBudget(_) => "Compute Budget Program",
+            Self::Vote(_) => "Vote Program",
+            Self::UpgradeableLoader(_) => "BPF Upgradeable Loader",
+            Self::AddressLookupTable(_) => "Address Lookup Table Program",
+            Self::FeatureGate(_) => "Feature Gate Program",
+        }
+    }
+}
+
+/// Error returned when a transaction cannot be inspected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InspectError {
+    /// The serialized transaction could not be deserialized.
+    InvalidEncoding(String),
+    /// The provided loaded addresses do not match the lookups of the message.
+    LoadedAddressesMismatch,
+}
+
+impl std::error::Error for InspectError {}
+
+impl fmt::Display for InspectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidEncoding(err) => write!(f, "invalid transaction encoding: {err}"),
+            Self::LoadedAddressesMismatch => {
+                f.write_str("loaded addresses do not match the address table lookups")
+            }
+        }
+    }
+}
+
+/// Deserialize and inspect a transaction in its wire format.
+pub fn inspect_transaction_bytes(
+    bytes: &[u8],
+    loaded_addresses: Option<&LoadedAddresses>,
+) -> Result<InspectedTransaction, InspectError> {
+    let transaction: VersionedTransaction = wincode::deserialize(bytes)
+        .map_err(|err| InspectError::InvalidEncoding(err.to_string()))?;
+    inspect_transaction(&transaction, loaded_addresses)
+}
+
+/// Inspect a transaction.
+///
+/// `loaded_addresses` are the addresses loaded from the lookup tables of a
+/// v0 or v1 message, used to resolve loaded accounts to addresses. The
+/// transaction is not sanitized, so malformed transactions can be inspected
+/// too; indexes which are out of bounds resolve to no address.
+pub fn inspect_transaction(
+    transaction: &VersionedTransaction,
+    loaded_addresses: Option<&LoadedAddresses>,
+) -> Result<InspectedTransaction, InspectError> {
+    let message = &transaction.message;
+    let accounts = inspect_accounts(message, loaded_addresses)?;
+    let account = |index: u8| accounts.get(usize::from(index)).cloned();
+
+    let signatures = transaction
+        .signatures
+        .iter()
+        .enumerate()
+        .map(|(index, signature)| InspectedSignature {
+            signer: message
+                .is_signer(index)
+                .then(|| message.static_account_keys().get(index).copied())
+                .flatten(),
+            signature: *signature,
+        })
+        .collect();
+
+    let instructions = message
+        .instructions()
+        .iter()
+        .map(|instruction| {
+            let program = account(instruction.program_id_index);
+            let decoded = program
+                .as_ref()
+                .and_then(|program| program.address.as_ref())
+                .and_then(|program_id| DecodedInstruction::decode(program_id, &instruction.data));
+            InspectedInstruction {
+                program,
+                accounts: instruction
+                    .accounts
+                    .iter()
+                    .map(|index| {
+                        account(*index).unwrap_or(InspectedAccount {
+                            index: *index,
+                            address: None,
+                            is_signer: false,
+                            is_writable: false,
+                            lookup_table: None,
+                        })
+                    })
+                    .collect(),
+                data: instruction.data.clone(),
+                decoded,
+            }
+        })
+        .collect();
+
+    let config = match message {
+        VersionedMessage::V1(message) => Some(message.config),
+        VersionedMessage::Legacy(_) | VersionedMessage::V0(_) => None,
+    };
+
+    Ok(InspectedTransaction {
+        version: transaction.version(),
+        signatures,
+        recent_blockhash: *message.recent_blockhash(),
+        config,
+        accounts,
+        instructions,
+    })
+}
+
+fn inspect_accounts(
+    message: &VersionedMessage,
+    loaded_addresses: Option<&LoadedAddresses>,
+) -> Result<Vec<InspectedAccount>, InspectError> {
+    let lookups = message.address_table_lookups().unwrap_or_default();
+    let writable_entries = lookups.iter().flat_map(|lookup| {
+        lookup
+            .writable_indexes
+            .iter()
+            .map(|index| (true, lookup.account_key, *index))
+    });
+    let readonly_entries = lookups.iter().flat_map(|lookup| {
+        lookup
+            .readonly_indexes
+            .iter()
+            .map(|index| (false, lookup.account_key, *index))
+    });
+
+    if let Some(loaded_addresses) = loaded_addresses {
+        if loaded_addresses.writable.len() != writable_entries.clone().count()
+            || loaded_addresses.readonly.len() != readonly_entries.clone().count()
+        {
+            return Err(InspectError::LoadedAddressesMismatch);
+        }
+    }
+
+    let static_keys = message.static_account_keys();
+    let account_keys = AccountKeys::new(static_keys, loaded_addresses);
+    let static_accounts = static_keys
+        .iter()
+        .enumerate()
+        .map(|(index, address)| (index, Some(*address), None));
+    let loaded_accounts = writable_entries.chain(readonly_entries).enumerate().map(
+        |(position, (is_writable, table, index))| {
+            let index_in_message = static_keys.len().saturating_add(position);
+            (
+                index_in_message,
+                account_keys.get(index_in_message).copied(),
+                Some((is_writable, LookupTableEntry { table, index })),
+            )
+        },
+    );
+
+    Ok(static_accounts
+        .chain(loaded_accounts)
+        // Compiled instructions can only reference the first 256 accounts.
+        .map_while(|(index, address, lookup_table)| {
+            let index_u8 = u8::try_from(index).ok()?;
+            Some(match lookup_table {
+                None => InspectedAccount {
+                    index: index_u8,
+                    address,
+                    is_signer: message.is_signer(index),
+                    is_writable: message.is_maybe_writable(index, None),
+                    lookup_table: None,
+                },
+                Some((is_writable, lookup_table)) => InspectedAccount {
+                    index: index_u8,
+                    address,
+                    is_signer: false,
+                    is_writable,
+                    lookup_table: Some(lookup_table),
+                },
+            })
+        })
+        .collect())
+}
+
+impl fmt::Display for InspectedTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.version {
+            TransactionVersion::Legacy(_) => writeln!(f, "Transaction (legacy)")?,
+            TransactionVersion::Number(version) => writeln!(f, "Transaction (v{version})")?,
+        }
+        writeln!(f, "  Recent blockhash: {}", self.recent_blockhash)?;
+        if let Some(config) = &self.config {
+            writeln!(f, "  Config: {config:?}")?;
+        }
+
+        writeln!(f, "  Signatures:")?;
+        for (index, signature) in self.signatures.iter().enumerate() {
+            write!(f, "    {index}: {}", signature.signature)?;
+            match &signature.signer {
+                Some(signer) => writeln!(f, " ({signer})")?,
+                None => writeln!(f, " (missing signer)")?,
+            }
+        }
+
+        writeln!(f, "  Accounts:")?;
+        for account in &self.accounts {
+            writeln!(f, "    {account}")?;
+        }
+
+        writeln!(f, "  Instructions:")?;
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            write!(f, "    {index}: ")?;
+            match &instruction.program {
+                Some(program) => write!(f, "{}", DisplayAddress(&program.address))?,
+                None => f.write_str("<invalid program index>")?,
+            }
+            match &instruction.decoded {
+                Some(decoded) => writeln!(f, " ({})", decoded.program_name())?,
+                None => writeln!(f)?,
+            }
+            for account in &instruction.accounts {
+                writeln!(f, "      {account}")?;
+            }
+            match &instruction.decoded {
+                Some(decoded) => writeln!(f, "      {decoded:?}")?,
+                None => {
+                    write!(f, "      Data ({} bytes): ", instruction.data.len())?;
+                    for byte in &instruction.data {
+                        write!(f, "{byte:02x}")?;
+                    }
+                    writeln!(f)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for InspectedAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.index, DisplayAddress(&self.address))?;
+        match (self.is_signer, self.is_writable) {
+            (true, true) => f.write_str(" [signer, writable]")?,
+            (true, false) => f.write_str(" [signer]")?,
+            (false, true) => f.write_str(" [writable]")?,
+            (false, false) => {}
+        }
+        if let Some(lookup_table) = &self.lookup_table {
+            write!(
+                f,
+                " (lookup table {} index {})",
+                lookup_table.table, lookup_table.index
+            )?;
+        }
+        Ok(())
+    }
+}
+
+struct DisplayAddress<'a>(&'a Option<Address>);
+
+impl fmt::Display for DisplayAddress<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(address) => write!(f, "{address}"),
+            None => f.write_str("<unresolved>"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_address_lookup_table_interface::instruction::freeze_lookup_table,
+        solana_feature_gate_interface::instruction::revoke_pending_activation,
+        solana_instruction::{AccountMeta, Instruction},
+        solana_keypair::Keypair,
+        solana_loader_v3_interface::instruction::upgrade,
+        solana_message::{v0, v1, AddressLookupTableAccount, Message},
+        solana_signer::Signer,
+        solana_system_interface::instruction::transfer,
+        solana_vote_interface::instruction::withdraw,
+    };
+
+    fn sign(message: VersionedMessage, payer: &Keypair) -> VersionedTransaction {
+        VersionedTransaction::try_new(message, &[payer]).unwrap()
+    }
+
+    #[test]
+    fn inspects_legacy_transaction() {
+        let payer = Keypair::new();
+        let recipient = Address::new_unique();
+        let message = Message::new_with_blockhash(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(500),
+                transfer(&payer.pubkey(), &recipient, 42),
+            ],
+            Some(&payer.pubkey()),
+            &Hash::new_unique(),
+        );
+        let transaction = sign(VersionedMessage::Legacy(message), &payer);
+        let bytes = wincode::serialize(&transaction).unwrap();
+        let inspected = inspect_transaction_bytes(&bytes, None).unwrap();
+
+        assert_eq!(inspected.version, TransactionVersion::LEGACY);
+        assert_eq!(
+            inspected.signatures,
+            vec![InspectedSignature {
+                signer: Some(payer.pubkey()),
+                signature: transaction.signatures[0],
+            }]
+        );
+        assert_eq!(inspected.config, None);
+        let account = |address: &Address| {
+            inspected
+                .accounts
+                .iter()
+                .find(|account| account.address.as_ref() == Some(address))
+                .unwrap()
+        };
+        assert!(account(&payer.pubkey()).is_signer);
+        assert!(account(&payer.pubkey()).is_writable);
+        assert!(!account(&recipient).is_signer);
+        assert!(account(&recipient).is_writable);
+        assert!(!account(&system_program::id()).is_writable);
+        assert!(!account(&compute_budget::id()).is_writable);
+
+        assert_eq!(
+            inspected.instructions[0].decoded,
+            Some(DecodedInstruction::ComputeBudget(
+                ComputeBudgetInstruction::SetComputeUnitLimit(500)
+            ))
+        );
+        let transfer = &inspected.instructions[1];
+        assert_eq!(
+            transfer.decoded,
+            Some(DecodedInstruction::System(SystemInstruction::Transfer {
+                lamports: 42
+            }))
+        );
+        assert_eq!(
+            transfer
+                .accounts
+                .iter()
+                .map(|account| account.address)
+                .collect::<Vec<_>>(),
+            vec![Some(payer.pubkey()), Some(recipient)]
+        );
+
+        let printed = inspected.to_string();
+        assert!(printed.starts_with("Transaction (legacy)\n"));
+        assert!(printed.contains(&format!("{} [signer, writable]", payer.pubkey())));
+        assert!(printed.contains("(System Program)"));
+        assert!(printed.contains("System(Transfer { lamports: 42 })"));
+
+        let json = serde_json::to_string_pretty(&inspected).unwrap();
+        assert!(json.contains(r#""program": "system""#));
+        assert_eq!(
+            serde_json::from_str::<InspectedTransaction>(&json).unwrap(),
+            inspected
+        );
+    }
+
+    #[test]
+    fn inspects_lookup_table_accounts() {
+        let payer = Keypair::new();
+        let (writable, readonly) = (Address::new_unique(), Address::new_unique());
+        let lookup_table = AddressLookupTableAccount {
+            key: Address::new_unique(),
+            addresses: vec![Address::new_unique(), readonly, writable],
+        };
+        let program_id = Address::new_unique();
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &[1, 2, 3],
+            vec![
+                AccountMeta::new(writable, false),
+                AccountMeta::new_readonly(readonly, false),
+            ],
+        );
+        let message = v0::Message::try_compile(
+            &payer.pubkey(),
+            &[instruction],
+            std::slice::from_ref(&lookup_table),
+            Hash::new_unique(),
+        )
+        .unwrap();
+        let transaction = sign(VersionedMessage::V0(message), &payer);
+
+        let inspected = inspect_transaction(&transaction, None).unwrap();
+        assert_eq!(inspected.version, TransactionVersion::Number(0));
+        let loaded = &inspected.instructions[0].accounts;
+        assert_eq!(
+            loaded,
+            &vec![
+                InspectedAccount {
+                    index: 2,
+                    address: None,
+                    is_signer: false,
+                    is_writable: true,
+                    lookup_table: Some(LookupTableEntry {
+                        table: lookup_table.key,
+                        index: 2,
+                    }),
+                },
+                InspectedAccount {
+                    index: 3,
+                    address: None,
+                    is_signer: false,
+                    is_writable: false,
+                    lookup_table: Some(LookupTableEntry {
+                        table: lookup_table.key,
+                        index: 1,
+                    }),
+                },
+            ]
+        );
+        assert_eq!(inspected.instructions[0].decoded, None);
+        let printed = inspected.to_string();
+        assert!(printed.contains(&format!(
+            "2: <unresolved> [writable] (lookup table {} index 2)",
+            lookup_table.key
+        )));
+        assert!(printed.contains("Data (3 bytes): 010203"));
+
+        let loaded_addresses = LoadedAddresses {
+            writable: vec![writable],
+            readonly: vec![readonly],
+        };
+        let inspected = inspect_transaction(&transaction, Some(&loaded_addresses)).unwrap();
+        assert_eq!(
+            inspected.instructions[0]
+                .accounts
+                .iter()
+                .map(|account| account.address)
+                .collect::<Vec<_>>(),
+            vec![Some(writable), Some(readonly)]
+        );
+
+        let loaded_addresses = LoadedAddresses {
+            writable: vec![writable],
+            readonly: vec![],
+        };
+        assert_eq!(
+            inspect_transaction(&transaction, Some(&loaded_addresses)),
+            Err(InspectError::LoadedAddressesMismatch)
+        );
+    }
+
+    #[test]
+    fn decodes_native_program_instructions() {
+        let payer = Keypair::new();
+        let authority = Address::new_unique();
+        let instructions = [
+            withdraw(&Address::new_unique(), &authority, 7, &payer.pubkey()),
+            upgrade(
+                &Address::new_unique(),
+                &Address::new_unique(),
+                &authority,
+                &payer.pubkey(),
+                true,
+            ),
+            freeze_lookup_table(Address::new_unique(), authority),
+            revoke_pending_activation(&Address::new_unique()),
+        ];
+        let message = v1::Message::try_compile_with_config(
+            &payer.pubkey(),
+            &instructions,
+            Hash::new_unique(),
+            TransactionConfig::empty().with_priority_fee(9),
+        )
+        .unwrap();
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default(); 4],
+            message: VersionedMessage::V1(message),
+        };
+
+        let inspected = inspect_transaction(&transaction, None).unwrap();
+        assert_eq!(inspected.version, TransactionVersion::Number(1));
+        assert_eq!(
+            inspected.config,
+            Some(TransactionConfig::empty().with_priority_fee(9))
+        );
+        assert_eq!(
+            inspected
+                .instructions
+                .into_iter()
+                .map(|instruction| instruction.decoded.unwrap())
+                .collect::<Vec<_>>(),
+            vec![
+                DecodedInstruction::Vote(VoteInstruction::Withdraw(7)),
+                DecodedInstruction::UpgradeableLoader(UpgradeableLoaderInstruction::Upgrade {
+                    close_buffer: true
+                }),
+                DecodedInstruction::AddressLookupTable(
+                    AddressLookupTableInstruction::FreezeLookupTable
+                ),
+                DecodedInstruction::FeatureGate(FeatureGateInstruction::RevokePendingActivation),
+            ]
+        );
+    }
+
+    #[test]
+    fn inspects_malformed_transactions() {
+        assert!(matches!(
+            inspect_transaction_bytes(&[1, 2, 3], None),
+            Err(InspectError::InvalidEncoding(_))
+        ));
+
+        let payer = Keypair::new();
+        let mut message = Message::new(
+            &[transfer(&payer.pubkey(), &Address::new_unique(), 1)],
+            Some(&payer.pubkey()),
+        );
+        message.instructions[0].program_id_index = 9;
+        message.instructions[0].accounts.push(10);
+        message.instructions[0].data = vec![0xff];
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default(); 2],
+            message: VersionedMessage::Legacy(message),
+        };
+
+        let inspected = inspect_transaction(&transaction, None).unwrap();
+        assert_eq!(inspected.signatures[1].signer, None);
+        let instruction = &inspected.instructions[0];
+        assert_eq!(instruction.program, None);
+        assert_eq!(instruction.accounts[2].address, None);
+        assert_eq!(instruction.decoded, None);
+        let printed = inspected.to_string();
+        assert!(printed.contains("(missing signer)"));
+        assert!(printed.contains("0: <invalid program index>"));
+    }
+}
@@ -0,0 +1,264 @@
+//! Batch verification of the signatures of many transactions.
+//!
+//! Verifying the Ed25519 signatures of many transactions in one batch is
+//! much faster than verifying them one at a time, but a failing batch does
+//! not tell which signature is invalid. [`verify_transactions`] bisects
+//! failing batches until every invalid transaction is isolated, so the cost
+//! stays close to a single batch when almost all transactions are valid.
+
+#[cfg(feature = "std")]
+use crate::sanitized::SanitizedTransaction;
+use {
+    crate::{versioned::VersionedTransaction, Transaction},
+    alloc::vec::Vec,
+    core::ops::Range,
+    solana_address::Address,
+    solana_signature::Signature,
+    solana_transaction_error::{TransactionError, TransactionResult},
+};
+
+/// A transaction whose signatures can be batch-verified.
+pub trait BatchVerifiable {
+    /// Signatures of the transaction, in signer order.
+    fn signatures(&self) -> &[Signature];
+
+    /// Account keys, starting with the signers.
+    fn signer_keys(&self) -> &[Address];
+
+    /// Serialized message that was signed.
+    fn message_data(&self) -> Vec<u8>;
+}
+
+impl BatchVerifiable for Transaction {
+    fn signatures(&self) -> &[Signature] {
+        &self.signatures
+    }
+
+    fn signer_keys(&self) -> &[Address] {
+        &self.message.account_keys
+    }
+
+    fn message_data(&self) -> Vec<u8> {
+        Transaction::message_data(self)
+    }
+}
+
+impl BatchVerifiable for VersionedTransaction {
+    fn signatures(&self) -> &[Signature] {
+        &self.signatures
+    }
+
+    fn signer_keys(&self) -> &[Address] {
+        self.message.static_account_keys()
+    }
+
+    fn message_data(&self) -> Vec<u8> {
+        self.message.serialize()
+    }
+}
+
+#[cfg(feature = "std")]
+impl BatchVerifiable for SanitizedTransaction {
+    fn signatures(&self) -> &[Signature] {
+        SanitizedTransaction::signatures(self)
+    }
+
+    fn signer_keys(&self) -> &[Address] {
+        self.message().static_account_keys()
+    }
+
+    fn message_data(&self) -> Vec<u8> {
+        SanitizedTransaction::message_data(self)
+    }
+}
+
+/// Verifies the signatures of all `transactions` in one batch, returning the
+/// result of each transaction.
+///
+/// A transaction passes under the same conditions as its own `verify`
+/// method; failing transactions get [`TransactionError::SignatureFailure`].
+pub fn verify_transactions<T: BatchVerifiable>(transactions: &[T]) -> Vec<TransactionResult<()>> {
+    let messages: Vec<_> = transactions.iter().map(T::message_data).collect();
+    let batch = Batch::new(transactions, &messages);
+    let mut results = Vec::with_capacity(transactions.len());
+    results.resize(transactions.len(), Ok(()));
+    batch.verify(0..transactions.len(), &mut results);
+    results
+}
+
+/// Like [`verify_transactions`], but verifies chunks of the transactions in
+/// parallel.
+#[cfg(feature = "parallel")]
+pub fn par_verify_transactions<T: BatchVerifiable + Sync>(
+    transactions: &[T],
+) -> Vec<TransactionResult<()>> {
+    use rayon::prelude::*;
+
+    if transactions.is_empty() {
+        return Vec::new();
+    }
+    let messages: Vec<_> = transactions.par_iter().map(T::message_data).collect();
+    let batch = Batch::new(transactions, &messages);
+    let mut results = Vec::with_capacity(transactions.len());
+    results.resize(transactions.len(), Ok(()));
+    let chunk_size = transactions.len().div_ceil(rayon::current_num_threads());
+    results
+        .par_chunks_mut(chunk_size)
+        .enumerate()
+        .for_each(|(chunk, results)| {
+            let start = chunk.saturating_mul(chunk_size);
+            batch.verify(start..start.saturating_add(results.len()), results);
+        });
+    results
+}
+
+/// The signatures of a set of transactions, flattened for batch
+/// verification.
+struct Batch<'a> {
+    items: Vec<(&'a Signature, &'a [u8], &'a [u8])>,
+    /// Range of `items` of each transaction.
+    ranges: Vec<Range<usize>>,
+}
+
+impl<'a> Batch<'a> {
+    fn new<T: BatchVerifiable>(transactions: &'a [T], messages: &'a [Vec<u8>]) -> Self {
+        let mut items = Vec::new();
+        let mut ranges = Vec::with_capacity(transactions.len());
+        for (transaction, message) in transactions.iter().zip(messages) {
+            let start = items.len();
+            items.extend(
+                transaction
+                    .signatures()
+                    .iter()
+                    .zip(transaction.signer_keys())
+                    .map(|(signature, pubkey)| (signature, pubkey.as_ref(), message.as_slice())),
+            );
+            ranges.push(start..items.len());
+        }
+        Self { items, ranges }
+    }
+
+    /// Verifies the transactions in `transactions`, storing their results in
+    /// `results`, which starts at the first of them.
+    fn verify(&self, transactions: Range<usize>, results: &mut [TransactionResult<()>]) {
+        let (Some(first), Some(last)) = (
+            self.ranges.get(transactions.start),
+            transactions
+                .end
+                .checked_sub(1)
+                .and_then(|last| self.ranges.get(last)),
+        ) else {
+            return;
+        };
+        let items = &self.items[first.start..last.end];
+        if Signature::batch_verify(items.iter().copied()) {
+            return;
+        }
+        if let [result] = results {
+            *result = Err(TransactionError::SignatureFailure);
+            return;
+        }
+        // Bisect until the failing transactions are isolated
+        let mid = results.len() / 2;
+        let (left, right) = results.split_at_mut(mid);
+        let split = transactions.start.saturating_add(mid);
+        self.verify(transactions.start..split, left);
+        self.verify(split..transactions.end, right);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::sanitized::MessageHash,
+        alloc::vec,
+        solana_instruction::{AccountMeta, Instruction},
+        solana_keypair::Keypair,
+        solana_message::{Message, SimpleAddressLoader, VersionedMessage},
+        solana_signer::Signer,
+        std::collections::HashSet,
+    };
+
+    fn transaction(fail: bool) -> VersionedTransaction {
+        let payer = Keypair::new();
+        let other = Keypair::new();
+        let message = Message::new(
+            &[Instruction::new_with_bytes(
+                Address::new_unique(),
+                &[],
+                vec![AccountMeta::new_readonly(other.pubkey(), true)],
+            )],
+            Some(&payer.pubkey()),
+        );
+        let mut transaction =
+            VersionedTransaction::try_new(VersionedMessage::Legacy(message), &[&payer, &other])
+                .unwrap();
+        if fail {
+            transaction.signatures[1] = Signature::from([1; 64]);
+        }
+        transaction
+    }
+
+    fn check(failing: &[usize], len: usize) {
+        let transactions: Vec<_> = (0..len)
+            .map(|i| transaction(failing.contains(&i)))
+            .collect();
+        let expected: Vec<_> = transactions
+            .iter()
+            .map(|transaction| {
+                if transaction.verify_with_results().iter().all(|ok| *ok) {
+                    Ok(())
+                } else {
+                    Err(TransactionError::SignatureFailure)
+                }
+            })
+            .collect();
+        assert_eq!(verify_transactions(&transactions), expected);
+        #[cfg(feature = "parallel")]
+        assert_eq!(par_verify_transactions(&transactions), expected);
+    }
+
+    #[test]
+    fn test_verify_transactions() {
+        check(&[], 0);
+        check(&[], 1);
+        check(&[0], 1);
+        check(&[], 17);
+        check(&[0], 17);
+        check(&[16], 17);
+        check(&[3, 4, 11], 17);
+        check(&(0..17).collect::<Vec<_>>(), 17);
+    }
+
+    #[test]
+    fn test_verify_sanitized_transactions() {
+        let transactions: Vec<_> = [false, true, false]
+            .into_iter()
+            .map(|fail| {
+                SanitizedTransaction::try_create(
+                    transaction(fail),
+                    MessageHash::Compute,
+                    None,
+                    SimpleAddressLoader::Disabled,
+                    &HashSet::new(),
+                )
+                .unwrap()
+            })
+            .collect();
+        assert_eq!(
+            verify_transactions(&transactions),
+            vec![Ok(()), Err(TransactionError::SignatureFailure), Ok(())]
+        );
+
+        let legacy: Vec<_> = transactions
+            .iter()
+            .map(|transaction| transaction.to_versioned_transaction())
+            .filter_map(VersionedTransaction::into_legacy_transaction)
+            .collect();
+        assert_eq!(
+            verify_transactions(&legacy),
+            vec![Ok(()), Err(TransactionError::SignatureFailure), Ok(())]
+        );
+    }
+}
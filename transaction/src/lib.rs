@@ -145,6 +145,11 @@ pub use {
     wincode::{containers, SchemaRead, SchemaWrite},
 };
 
+#[cfg(feature = "batch-verify")]
+pub mod batch_verify;
+#[cfg(feature = "wincode")]
+pub mod partially_signed;
+pub mod planner;
 #[cfg(feature = "std")]
 pub mod sanitized;
 pub mod simple_vote_transaction_checker;
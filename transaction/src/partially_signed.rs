@@ -0,0 +1,602 @@
+//! A portable envelope for transactions collecting signatures from co-signers.
+//!
+//! A [`PartiallySignedTransaction`] carries a message, the public keys of the
+//! signers it requires, and the signatures collected so far. Co-signers can
+//! exchange it in its binary form ([`PartiallySignedTransaction::to_bytes`],
+//! which matches `bincode` for legacy and v0 messages), as base64 text, or as
+//! JSON through `serde`, sign it independently, and [merge] the results.
+//!
+//! [merge]: PartiallySignedTransaction::merge
+
+#[cfg(feature = "serde")]
+use serde_derive::{Deserialize, Serialize};
+use {
+    crate::versioned::VersionedTransaction,
+    alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    },
+    core::{fmt, mem::MaybeUninit},
+    solana_address::Address,
+    solana_message::VersionedMessage,
+    solana_signature::Signature,
+    solana_signer::{signers::Signers, SignerError},
+    wincode::{config::Config, io::Reader, ReadError, ReadResult, SchemaRead, SchemaWrite},
+};
+
+/// A transaction message and the signatures collected for it so far.
+///
+/// Deserializing an envelope, with `serde` or `wincode`, checks that its
+/// required signers match the signers of its message.
+#[cfg_attr(
+    feature = "serde",
+    derive(Deserialize, Serialize),
+    serde(
+        rename_all = "camelCase",
+        try_from = "UncheckedPartiallySignedTransaction"
+    )
+)]
+#[derive(SchemaWrite, Debug, PartialEq, Eq, Clone)]
+pub struct PartiallySignedTransaction {
+    /// The message every signer signs.
+    message: VersionedMessage,
+    /// The signers required by `message`, in signature order.
+    signers: Vec<RequiredSigner>,
+}
+
+/// A deserialized [`PartiallySignedTransaction`] whose signers have not been
+/// checked yet.
+#[cfg_attr(
+    feature = "serde",
+    derive(Deserialize),
+    serde(rename_all = "camelCase")
+)]
+#[derive(SchemaRead)]
+struct UncheckedPartiallySignedTransaction {
+    message: VersionedMessage,
+    signers: Vec<RequiredSigner>,
+}
+
+impl TryFrom<UncheckedPartiallySignedTransaction> for PartiallySignedTransaction {
+    type Error = PartiallySignedTransactionError;
+
+    fn try_from(envelope: UncheckedPartiallySignedTransaction) -> Result<Self, Self::Error> {
+        let UncheckedPartiallySignedTransaction { message, signers } = envelope;
+        let envelope = Self { message, signers };
+        envelope.check_signers()?;
+        Ok(envelope)
+    }
+}
+
+unsafe impl<'de, C: Config> SchemaRead<'de, C> for PartiallySignedTransaction {
+    type Dst = Self;
+
+    fn read(reader: impl Reader<'de>, dst: &mut MaybeUninit<Self::Dst>) -> ReadResult<()> {
+        let envelope = <UncheckedPartiallySignedTransaction as SchemaRead<C>>::get(reader)?;
+        dst.write(
+            Self::try_from(envelope)
+                .map_err(|_| ReadError::Custom("required signers do not match the message"))?,
+        );
+        Ok(())
+    }
+}
+
+/// A signer required by a [`PartiallySignedTransaction`].
+#[cfg_attr(
+    feature = "serde",
+    derive(Deserialize, Serialize),
+    serde(rename_all = "camelCase")
+)]
+#[derive(SchemaWrite, SchemaRead, Debug, PartialEq, Eq, Clone)]
+pub struct RequiredSigner {
+    /// Public key of the signer.
+    pub pubkey: Address,
+    /// Signature of the message, if collected.
+    pub signature: Option<Signature>,
+}
+
+/// Error returned by [`PartiallySignedTransaction`] operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartiallySignedTransactionError {
+    /// The envelope could not be encoded or decoded.
+    InvalidEncoding(String),
+    /// The required signers do not match the signers of the message.
+    SignerMismatch,
+    /// The envelopes being merged carry different messages.
+    MessageMismatch,
+    /// The envelopes being merged carry different signatures for a signer.
+    ConflictingSignature(Address),
+    /// A signature was provided for a key which is not a required signer.
+    UnexpectedSigner(Address),
+    /// Signatures are missing for the listed signers.
+    MissingSignatures(Vec<Address>),
+    /// The signature of a signer does not verify against the message.
+    InvalidSignature(Address),
+}
+
+impl core::error::Error for PartiallySignedTransactionError {}
+
+impl fmt::Display for PartiallySignedTransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidEncoding(err) => write!(f, "invalid encoding: {err}"),
+            Self::SignerMismatch => f.write_str("required signers do not match the message"),
+            Self::MessageMismatch => f.write_str("messages do not match"),
+            Self::ConflictingSignature(pubkey) => {
+                write!(f, "conflicting signatures for signer {pubkey}")
+            }
+            Self::UnexpectedSigner(pubkey) => write!(f, "{pubkey} is not a required signer"),
+            Self::MissingSignatures(pubkeys) => {
+                f.write_str("missing signatures for")?;
+                for (i, pubkey) in pubkeys.iter().enumerate() {
+                    let separator = if i == 0 { " " } else { ", " };
+                    write!(f, "{separator}{pubkey}")?;
+                }
+                Ok(())
+            }
+            Self::InvalidSignature(pubkey) => write!(f, "invalid signature for signer {pubkey}"),
+        }
+    }
+}
+
+impl PartiallySignedTransaction {
+    /// Create an envelope for `message` without any signatures.
+    pub fn new(message: VersionedMessage) -> Self {
+        let signers = required_signers(&message)
+            .iter()
+            .map(|pubkey| RequiredSigner {
+                pubkey: *pubkey,
+                signature: None,
+            })
+            .collect();
+        Self { message, signers }
+    }
+
+    /// The message every signer signs.
+    pub fn message(&self) -> &VersionedMessage {
+        &self.message
+    }
+
+    /// The serialized message, which is the data every signer signs.
+    pub fn message_data(&self) -> Vec<u8> {
+        self.message.serialize()
+    }
+
+    /// The signers required by the message, in signature order.
+    pub fn signers(&self) -> &[RequiredSigner] {
+        &self.signers
+    }
+
+    /// Public keys of the signers which have not signed yet.
+    pub fn missing_signers(&self) -> Vec<Address> {
+        self.signers
+            .iter()
+            .filter(|signer| signer.signature.is_none())
+            .map(|signer| signer.pubkey)
+            .collect()
+    }
+
+    /// Returns true if every required signer has signed.
+    pub fn is_complete(&self) -> bool {
+        self.signers.iter().all(|signer| signer.signature.is_some())
+    }
+
+    /// Sign the message with `keypairs`, each of which must be a required
+    /// signer.
+    ///
+    /// Signatures already collected for other signers are kept, and those
+    /// for the signers in `keypairs` are replaced.
+    pub fn try_sign<T: Signers + ?Sized>(&mut self, keypairs: &T) -> Result<(), SignerError> {
+        let positions = keypairs
+            .try_pubkeys()?
+            .iter()
+            .map(|pubkey| self.position(pubkey))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(SignerError::KeypairPubkeyMismatch)?;
+        let signatures = keypairs.try_sign_message(&self.message_data())?;
+        for (position, signature) in positions.into_iter().zip(signatures) {
+            self.signers[position].signature = Some(signature);
+        }
+        Ok(())
+    }
+
+    /// Add a signature produced elsewhere for the required signer `pubkey`.
+    ///
+    /// The signature is not verified until `verify` is called.
+    pub fn add_signature(
+        &mut self,
+        pubkey: &Address,
+        signature: Signature,
+    ) -> Result<(), PartiallySignedTransactionError> {
+        let position = self
+            .position(pubkey)
+            .ok_or(PartiallySignedTransactionError::UnexpectedSigner(*pubkey))?;
+        self.signers[position].signature = Some(signature);
+        Ok(())
+    }
+
+    /// Merge the signatures collected in `other` into this envelope.
+    ///
+    /// Fails if `other` carries a message which does not serialize to the
+    /// same bytes, or a different signature for a signer which has already
+    /// signed. Nothing is merged if an error is returned.
+    pub fn merge(&mut self, other: &Self) -> Result<(), PartiallySignedTransactionError> {
+        self.check_signers()?;
+        if self.message_data() != other.message_data() {
+            return Err(PartiallySignedTransactionError::MessageMismatch);
+        }
+        other.check_signers()?;
+        for (signer, other) in self.signers.iter().zip(&other.signers) {
+            if let (Some(signature), Some(other_signature)) = (&signer.signature, &other.signature)
+            {
+                if signature != other_signature {
+                    return Err(PartiallySignedTransactionError::ConflictingSignature(
+                        signer.pubkey,
+                    ));
+                }
+            }
+        }
+        for (signer, other) in self.signers.iter_mut().zip(&other.signers) {
+            if signer.signature.is_none() {
+                signer.signature = other.signature;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that every required signer has signed.
+    pub fn check_complete(&self) -> Result<(), PartiallySignedTransactionError> {
+        let missing_signers = self.missing_signers();
+        if missing_signers.is_empty() {
+            Ok(())
+        } else {
+            Err(PartiallySignedTransactionError::MissingSignatures(
+                missing_signers,
+            ))
+        }
+    }
+
+    /// Check that every required signer has signed, and that every
+    /// signature verifies against the message.
+    #[cfg(feature = "verify")]
+    pub fn verify(&self) -> Result<(), PartiallySignedTransactionError> {
+        self.check_complete()?;
+        let message_data = self.message_data();
+        for signer in &self.signers {
+            if let Some(signature) = &signer.signature {
+                if !signature.verify(signer.pubkey.as_ref(), &message_data) {
+                    return Err(PartiallySignedTransactionError::InvalidSignature(
+                        signer.pubkey,
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert the envelope into a transaction, once every required signer
+    /// has signed.
+    pub fn try_into_transaction(
+        self,
+    ) -> Result<VersionedTransaction, PartiallySignedTransactionError> {
+        self.check_complete()?;
+        Ok(VersionedTransaction {
+            signatures: self
+                .signers
+                .into_iter()
+                .map(|signer| signer.signature.unwrap_or_default())
+                .collect(),
+            message: self.message,
+        })
+    }
+
+    /// Serialize the envelope into its binary form, which matches `bincode`
+    /// for legacy and v0 messages.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, PartiallySignedTransactionError> {
+        wincode::serialize(self)
+            .map_err(|err| PartiallySignedTransactionError::InvalidEncoding(err.to_string()))
+    }
+
+    /// Deserialize an envelope from its binary form.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PartiallySignedTransactionError> {
+        let envelope: UncheckedPartiallySignedTransaction = wincode::deserialize(bytes)
+            .map_err(|err| PartiallySignedTransactionError::InvalidEncoding(err.to_string()))?;
+        Self::try_from(envelope)
+    }
+
+    /// Encode the binary form of the envelope as base64.
+    #[cfg(feature = "base64")]
+    pub fn to_base64(&self) -> Result<String, PartiallySignedTransactionError> {
+        use base64::{prelude::BASE64_STANDARD, Engine};
+        Ok(BASE64_STANDARD.encode(self.to_bytes()?))
+    }
+
+    /// Decode an envelope from the base64 encoding of its binary form.
+    #[cfg(feature = "base64")]
+    pub fn from_base64(encoded: &str) -> Result<Self, PartiallySignedTransactionError> {
+        use base64::{prelude::BASE64_STANDARD, Engine};
+        let bytes = BASE64_STANDARD
+            .decode(encoded)
+            .map_err(|err| PartiallySignedTransactionError::InvalidEncoding(err.to_string()))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Check that the required signers match the signers of the message.
+    ///
+    /// Envelopes built or deserialized by this module always pass.
+    pub fn check_signers(&self) -> Result<(), PartiallySignedTransactionError> {
+        let expected = required_signers(&self.message);
+        if expected.len() != self.signers.len()
+            || expected
+                .iter()
+                .zip(&self.signers)
+                .any(|(pubkey, signer)| *pubkey != signer.pubkey)
+        {
+            return Err(PartiallySignedTransactionError::SignerMismatch);
+        }
+        Ok(())
+    }
+
+    fn position(&self, pubkey: &Address) -> Option<usize> {
+        self.signers
+            .iter()
+            .position(|signer| signer.pubkey == *pubkey)
+    }
+}
+
+impl From<VersionedTransaction> for PartiallySignedTransaction {
+    /// Default signatures, as left by partial signing, are treated as
+    /// missing.
+    fn from(transaction: VersionedTransaction) -> Self {
+        let mut envelope = Self::new(transaction.message);
+        for (signer, signature) in envelope.signers.iter_mut().zip(transaction.signatures) {
+            if signature != Signature::default() {
+                signer.signature = Some(signature);
+            }
+        }
+        envelope
+    }
+}
+
+fn required_signers(message: &VersionedMessage) -> &[Address] {
+    let num_required_signatures = usize::from(message.header().num_required_signatures);
+    let account_keys = message.static_account_keys();
+    &account_keys[..num_required_signatures.min(account_keys.len())]
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::Transaction,
+        alloc::vec,
+        solana_hash::Hash,
+        solana_keypair::Keypair,
+        solana_message::{v1::TransactionConfig, Message},
+        solana_signer::Signer,
+        solana_system_interface::instruction::transfer,
+    };
+
+    fn multisig_message(signers: &[&Keypair]) -> VersionedMessage {
+        let instructions: Vec<_> = signers
+            .iter()
+            .map(|signer| transfer(&signer.pubkey(), &Address::new_unique(), 1))
+            .collect();
+        VersionedMessage::Legacy(Message::new_with_blockhash(
+            &instructions,
+            Some(&signers[0].pubkey()),
+            &Hash::new_unique(),
+        ))
+    }
+
+    #[test]
+    fn collects_signatures_from_cosigners() {
+        let (a, b, c) = (Keypair::new(), Keypair::new(), Keypair::new());
+        let envelope = PartiallySignedTransaction::new(multisig_message(&[&a, &b, &c]));
+        let required_signers = envelope.missing_signers();
+        assert_eq!(
+            required_signers,
+            envelope.message().static_account_keys()[..3]
+        );
+        assert!([a.pubkey(), b.pubkey(), c.pubkey()]
+            .iter()
+            .all(|pubkey| required_signers.contains(pubkey)));
+
+        let mut from_a =
+            PartiallySignedTransaction::from_bytes(&envelope.to_bytes().unwrap()).unwrap();
+        from_a.try_sign(&[&a]).unwrap();
+        let mut from_bc = envelope.clone();
+        from_bc.try_sign(&[&c, &b]).unwrap();
+
+        let mut merged = envelope;
+        merged.merge(&from_a).unwrap();
+        let missing_signers: Vec<_> = required_signers
+            .iter()
+            .filter(|pubkey| **pubkey != a.pubkey())
+            .copied()
+            .collect();
+        assert_eq!(merged.missing_signers(), missing_signers);
+        assert_eq!(
+            merged.check_complete(),
+            Err(PartiallySignedTransactionError::MissingSignatures(
+                missing_signers
+            ))
+        );
+        merged.merge(&from_bc).unwrap();
+        assert!(merged.is_complete());
+        merged.verify().unwrap();
+
+        let transaction = merged.try_into_transaction().unwrap();
+        assert_eq!(transaction.signatures.len(), 3);
+        assert!(transaction.verify_with_results().iter().all(|ok| *ok));
+    }
+
+    #[test]
+    fn rejects_merge_of_different_messages() {
+        let (a, b) = (Keypair::new(), Keypair::new());
+        let mut envelope = PartiallySignedTransaction::new(multisig_message(&[&a, &b]));
+        let mut other = envelope.clone();
+        let mut message = other.message.clone();
+        message.set_recent_blockhash(Hash::new_unique());
+        other = PartiallySignedTransaction {
+            message,
+            signers: other.signers,
+        };
+        other.try_sign(&[&b]).unwrap();
+
+        assert_eq!(
+            envelope.merge(&other),
+            Err(PartiallySignedTransactionError::MessageMismatch)
+        );
+        assert_eq!(envelope.missing_signers().len(), 2);
+    }
+
+    #[test]
+    fn rejects_conflicting_signatures() {
+        let (a, b) = (Keypair::new(), Keypair::new());
+        let mut envelope = PartiallySignedTransaction::new(multisig_message(&[&a, &b]));
+        envelope.try_sign(&[&a]).unwrap();
+        let mut other = envelope.clone();
+        other.try_sign(&[&b]).unwrap();
+        other
+            .add_signature(&a.pubkey(), Signature::from([7; 64]))
+            .unwrap();
+
+        assert_eq!(
+            envelope.merge(&other),
+            Err(PartiallySignedTransactionError::ConflictingSignature(
+                a.pubkey()
+            ))
+        );
+        assert_eq!(envelope.missing_signers(), vec![b.pubkey()]);
+    }
+
+    #[test]
+    fn rejects_unexpected_signers() {
+        let (a, b) = (Keypair::new(), Keypair::new());
+        let mut envelope = PartiallySignedTransaction::new(multisig_message(&[&a]));
+
+        assert_eq!(
+            envelope.try_sign(&[&b]),
+            Err(SignerError::KeypairPubkeyMismatch)
+        );
+        assert_eq!(
+            envelope.add_signature(&b.pubkey(), Signature::default()),
+            Err(PartiallySignedTransactionError::UnexpectedSigner(
+                b.pubkey()
+            ))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_signature() {
+        let (a, b) = (Keypair::new(), Keypair::new());
+        let mut envelope = PartiallySignedTransaction::new(multisig_message(&[&a, &b]));
+        envelope.try_sign(&[&a]).unwrap();
+        envelope
+            .add_signature(&b.pubkey(), a.sign_message(&envelope.message_data()))
+            .unwrap();
+
+        assert_eq!(
+            envelope.verify(),
+            Err(PartiallySignedTransactionError::InvalidSignature(
+                b.pubkey()
+            ))
+        );
+    }
+
+    #[test]
+    fn converts_partially_signed_transaction() {
+        let (a, b) = (Keypair::new(), Keypair::new());
+        let VersionedMessage::Legacy(message) = multisig_message(&[&a, &b]) else {
+            unreachable!()
+        };
+        let recent_blockhash = message.recent_blockhash;
+        let mut transaction = Transaction::new_unsigned(message);
+        transaction.partial_sign(&[&b], recent_blockhash);
+
+        let envelope = PartiallySignedTransaction::from(VersionedTransaction::from(transaction));
+        assert_eq!(envelope.missing_signers(), vec![a.pubkey()]);
+        assert_eq!(
+            envelope.try_into_transaction(),
+            Err(PartiallySignedTransactionError::MissingSignatures(vec![
+                a.pubkey()
+            ]))
+        );
+    }
+
+    #[test]
+    fn roundtrips_encodings() {
+        let (a, b) = (Keypair::new(), Keypair::new());
+        let message = VersionedMessage::V1(
+            solana_message::v1::Message::try_compile_with_config(
+                &a.pubkey(),
+                &[transfer(&b.pubkey(), &Address::new_unique(), 1)],
+                Hash::new_unique(),
+                TransactionConfig::empty().with_priority_fee(5),
+            )
+            .unwrap(),
+        );
+        let mut envelope = PartiallySignedTransaction::new(message);
+        envelope.try_sign(&[&b]).unwrap();
+
+        let legacy_envelope = PartiallySignedTransaction::new(multisig_message(&[&a, &b]));
+        assert_eq!(
+            bincode::serialize(&legacy_envelope).unwrap(),
+            legacy_envelope.to_bytes().unwrap()
+        );
+
+        let bytes = envelope.to_bytes().unwrap();
+        assert_eq!(
+            PartiallySignedTransaction::from_bytes(&bytes),
+            Ok(envelope.clone())
+        );
+        assert_eq!(
+            PartiallySignedTransaction::from_base64(&envelope.to_base64().unwrap()),
+            Ok(envelope.clone())
+        );
+        let json = serde_json::to_string(&envelope).unwrap();
+        assert_eq!(
+            serde_json::from_str::<PartiallySignedTransaction>(&json).unwrap(),
+            envelope
+        );
+
+        assert!(matches!(
+            PartiallySignedTransaction::from_base64("not base64!"),
+            Err(PartiallySignedTransactionError::InvalidEncoding(_))
+        ));
+        assert!(matches!(
+            PartiallySignedTransaction::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(PartiallySignedTransactionError::InvalidEncoding(_))
+        ));
+
+        let mut tampered = envelope.clone();
+        tampered.signers.pop();
+        let tampered_bytes = tampered.to_bytes().unwrap();
+        assert_eq!(
+            PartiallySignedTransaction::from_bytes(&tampered_bytes),
+            Err(PartiallySignedTransactionError::SignerMismatch)
+        );
+        assert!(
+            wincode::deserialize::<PartiallySignedTransaction>(&tampered_bytes)
+                .unwrap_err()
+                .to_string()
+                .contains("required signers")
+        );
+        assert!(serde_json::from_str::<PartiallySignedTransaction>(
+            &serde_json::to_string(&tampered).unwrap()
+        )
+        .unwrap_err()
+        .to_string()
+        .starts_with("required signers"));
+        assert!(tampered
+            .merge(&tampered.clone())
+            .unwrap_err()
+            .to_string()
+            .starts_with("required signers"));
+        assert_eq!(
+            tampered.merge(&envelope),
+            Err(PartiallySignedTransactionError::SignerMismatch)
+        );
+    }
+}
@@ -0,0 +1,435 @@
+//! Splitting instructions into transactions that fit the size limit.
+//!
+//! [`TransactionPlanner`] packs a sequence of [`Instruction`]s into the fewest
+//! transactions of a given [`MessageVersion`], preserving instruction order
+//! and keeping caller-declared atomic groups of instructions in the same
+//! transaction.
+
+use {
+    alloc::vec::Vec,
+    core::{fmt, ops::Range},
+    solana_address::Address,
+    solana_hash::Hash,
+    solana_instruction::Instruction,
+    solana_message::{
+        v1::TransactionConfig, AddressLookupTableAccount, MessageVersion, TransactionSizeTracker,
+        VersionRejection, VersionedMessage,
+    },
+};
+
+/// Plans how to split instructions into transactions of a single message
+/// version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionPlanner {
+    payer: Address,
+    version: MessageVersion,
+    address_lookup_table_accounts: Vec<AddressLookupTableAccount>,
+    config: TransactionConfig,
+}
+
+/// A transaction planned by [`TransactionPlanner::plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedTransaction {
+    /// Indexes of the planned instructions included in this transaction.
+    pub instruction_range: Range<usize>,
+    /// The instructions included in this transaction, in order.
+    pub instructions: Vec<Instruction>,
+    /// The compiled message.
+    ///
+    /// The message is compiled with a default blockhash, which must be
+    /// replaced with [`VersionedMessage::set_recent_blockhash`] before
+    /// signing.
+    pub message: VersionedMessage,
+    /// Serialized size of the transaction, including signatures.
+    pub transaction_size: usize,
+    /// Number of signatures required by the transaction.
+    pub num_signatures: usize,
+}
+
+/// Error returned by [`TransactionPlanner::plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanError {
+    /// An atomic group is empty, out of bounds or overlaps another group.
+    InvalidAtomicGroup(Range<usize>),
+    /// An instruction or atomic group does not fit in a transaction on its
+    /// own.
+    DoesNotFit {
+        instruction_range: Range<usize>,
+        rejection: VersionRejection,
+    },
+}
+
+impl core::error::Error for PlanError {}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidAtomicGroup(range) => {
+                write!(f, "invalid atomic group {range:?}")
+            }
+            Self::DoesNotFit {
+                instruction_range,
+                rejection,
+            } => write!(
+                f,
+                "instructions {instruction_range:?} do not fit in a transaction: {rejection}"
+            ),
+        }
+    }
+}
+
+impl TransactionPlanner {
+    /// Create a planner for transactions of `version` paid for by `payer`.
+    pub fn new(payer: Address, version: MessageVersion) -> Self {
+        Self {
+            payer,
+            version,
+            address_lookup_table_accounts: Vec::new(),
+            config: TransactionConfig::empty(),
+        }
+    }
+
+    /// Lookup tables used to compile v0 and v1 messages.
+    #[must_use]
+    pub fn with_address_lookup_table_accounts(
+        mut self,
+        address_lookup_table_accounts: Vec<AddressLookupTableAccount>,
+    ) -> Self {
+        self.address_lookup_table_accounts = address_lookup_table_accounts;
+        self
+    }
+
+    /// Transaction config included in every v1 message.
+    #[must_use]
+    pub fn with_config(mut self, config: TransactionConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Split `instructions` into the fewest transactions that fit the size and
+    /// account limits of the planner's message version.
+    ///
+    /// Each range in `atomic_groups` indexes a run of `instructions` that must
+    /// be included in the same transaction. Instructions outside of any group
+    /// may be placed in any transaction, but the order of the instructions is
+    /// always preserved.
+    ///
+    /// Removing instructions from a message never makes it larger, so greedily
+    /// filling each transaction before starting the next one yields the fewest
+    /// transactions.
+    ///
+    /// Each instruction or atomic group is compiled on its own once, to check
+    /// that it fits in a transaction, and each planned transaction is compiled
+    /// once. The size of a transaction as it grows is tracked incrementally
+    /// with a [`TransactionSizeTracker`], so planning takes time linear in the
+    /// number of instructions.
+    pub fn plan(
+        &self,
+        instructions: Vec<Instruction>,
+        atomic_groups: &[Range<usize>],
+    ) -> Result<Vec<PlannedTransaction>, PlanError> {
+        let units = split_units(instructions.len(), atomic_groups)?;
+
+        let mut planned = Vec::new();
+        let mut tracker = TransactionSizeTracker::new(
+            self.version,
+            self.payer,
+            &self.address_lookup_table_accounts,
+            self.config,
+        );
+        let mut start = 0;
+        for unit in units {
+            let unit_instructions = &instructions[unit.clone()];
+            self.try_compile(unit_instructions)
+                .map_err(|rejection| PlanError::DoesNotFit {
+                    instruction_range: unit.clone(),
+                    rejection,
+                })?;
+
+            if !tracker.try_push(unit_instructions) {
+                planned.push(self.compile_planned(&instructions, start..unit.start)?);
+                start = unit.start;
+                tracker.reset();
+                // The unit fits in a transaction on its own.
+                let pushed = tracker.try_push(unit_instructions);
+                debug_assert!(pushed);
+            }
+        }
+        if start < instructions.len() {
+            planned.push(self.compile_planned(&instructions, start..instructions.len())?);
+        }
+
+        Ok(planned)
+    }
+
+    fn try_compile(
+        &self,
+        instructions: &[Instruction],
+    ) -> Result<(VersionedMessage, usize), VersionRejection> {
+        self.version.try_compile(
+            &self.payer,
+            instructions,
+            &self.address_lookup_table_accounts,
+            Hash::default(),
+            self.config,
+        )
+    }
+
+    fn compile_planned(
+        &self,
+        instructions: &[Instruction],
+        instruction_range: Range<usize>,
+    ) -> Result<PlannedTransaction, PlanError> {
+        let instructions = instructions[instruction_range.clone()].to_vec();
+        let (message, transaction_size) =
+            self.try_compile(&instructions)
+                .map_err(|rejection| PlanError::DoesNotFit {
+                    instruction_range: instruction_range.clone(),
+                    rejection,
+                })?;
+        Ok(PlannedTransaction {
+            instructions,
+            instruction_range,
+            num_signatures: usize::from(message.header().num_required_signatures),
+            message,
+            transaction_size,
+        })
+    }
+}
+
+/// Split `0..num_instructions` into the smallest runs that may not be
+/// separated: each atomic group, and each instruction outside of a group.
+fn split_units(
+    num_instructions: usize,
+    atomic_groups: &[Range<usize>],
+) -> Result<Vec<Range<usize>>, PlanError> {
+    let mut groups = atomic_groups.to_vec();
+    groups.sort_by_key(|group| group.start);
+
+    let mut units = Vec::with_capacity(num_instructions);
+    let mut next = 0;
+    for group in groups {
+        if group.is_empty() || group.start < next || group.end > num_instructions {
+            return Err(PlanError::InvalidAtomicGroup(group));
+        }
+        units.extend((next..group.start).map(|i| i..i.saturating_add(1)));
+        next = group.end;
+        units.push(group);
+    }
+    units.extend((next..num_instructions).map(|i| i..i.saturating_add(1)));
+    Ok(units)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::versioned::VersionedTransaction,
+        alloc::{string::ToString, vec},
+        solana_message::v1,
+        solana_packet::PACKET_DATA_SIZE,
+        solana_signature::Signature,
+        solana_system_interface::instruction::transfer,
+    };
+
+    fn transfers(payer: &Address, count: usize) -> Vec<Instruction> {
+        (0..count)
+            .map(|_| transfer(payer, &Address::new_unique(), 1))
+            .collect()
+    }
+
+    fn serialized_size(planned: &PlannedTransaction) -> usize {
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default(); planned.num_signatures],
+            message: planned.message.clone(),
+        };
+        wincode::serialize(&transaction).unwrap().len()
+    }
+
+    fn assert_fewest(planner: &TransactionPlanner, planned: &[PlannedTransaction]) {
+        for (transaction, next) in planned.iter().zip(planned.iter().skip(1)) {
+            let mut instructions = transaction.instructions.clone();
+            instructions.push(next.instructions[0].clone());
+            assert!(planner.try_compile(&instructions).is_err());
+        }
+    }
+
+    #[test]
+    fn plans_nothing_for_no_instructions() {
+        let planner = TransactionPlanner::new(Address::new_unique(), MessageVersion::Legacy);
+        assert_eq!(planner.plan(vec![], &[]), Ok(vec![]));
+    }
+
+    #[test]
+    fn splits_into_fewest_transactions() {
+        for version in MessageVersion::ALL {
+            let payer = Address::new_unique();
+            let instructions = transfers(&payer, 100);
+            let planner = TransactionPlanner::new(payer, version);
+            let planned = planner.plan(instructions.clone(), &[]).unwrap();
+
+            assert!(planned.len() > 1);
+            assert_fewest(&planner, &planned);
+            let mut next = 0;
+            for transaction in &planned {
+                assert_eq!(transaction.instruction_range.start, next);
+                next = transaction.instruction_range.end;
+                assert_eq!(
+                    transaction.instructions,
+                    instructions[transaction.instruction_range.clone()]
+                );
+                assert_eq!(transaction.num_signatures, 1);
+                assert_eq!(transaction.transaction_size, serialized_size(transaction));
+                assert!(transaction.transaction_size <= version.max_transaction_size());
+            }
+            assert_eq!(next, instructions.len());
+        }
+    }
+
+    #[test]
+    fn v1_packs_more_instructions() {
+        let payer = Address::new_unique();
+        let instructions = transfers(&payer, 40);
+        let legacy = TransactionPlanner::new(payer, MessageVersion::Legacy)
+            .plan(instructions.clone(), &[])
+            .unwrap();
+        let v1 = TransactionPlanner::new(payer, MessageVersion::V1)
+            .plan(instructions, &[])
+            .unwrap();
+
+        assert!(legacy.len() > 1);
+        assert_eq!(v1.len(), 1);
+        assert!(v1[0].transaction_size > PACKET_DATA_SIZE);
+        assert!(v1[0].transaction_size <= v1::MAX_TRANSACTION_SIZE);
+    }
+
+    #[test]
+    fn keeps_atomic_groups_together() {
+        let payer = Address::new_unique();
+        let instructions = transfers(&payer, 60);
+        let planner = TransactionPlanner::new(payer, MessageVersion::Legacy);
+        let ungrouped = planner.plan(instructions.clone(), &[]).unwrap();
+
+        // A group straddling the first boundary of the ungrouped plan.
+        let boundary = ungrouped[0].instruction_range.end;
+        let group = boundary.saturating_sub(2)..boundary.saturating_add(2);
+        let grouped = planner
+            .plan(instructions, core::slice::from_ref(&group))
+            .unwrap();
+
+        assert_eq!(grouped[0].instruction_range, 0..group.start);
+        assert_eq!(grouped[1].instruction_range.start, group.start);
+        assert!(grouped[1].instruction_range.end >= group.end);
+    }
+
+    #[test]
+    fn counts_signers() {
+        let payer = Address::new_unique();
+        let instructions = vec![
+            transfer(&payer, &Address::new_unique(), 1),
+            transfer(&Address::new_unique(), &payer, 1),
+        ];
+        let planned = TransactionPlanner::new(payer, MessageVersion::V0)
+            .plan(instructions, &[])
+            .unwrap();
+
+        assert_eq!(planned.len(), 1);
+        assert_eq!(planned[0].num_signatures, 2);
+        assert_eq!(planned[0].transaction_size, serialized_size(&planned[0]));
+    }
+
+    #[test]
+    fn rejects_group_too_large() {
+        let payer = Address::new_unique();
+        let instructions = transfers(&payer, 40);
+        let err = TransactionPlanner::new(payer, MessageVersion::Legacy)
+            .plan(instructions, core::slice::from_ref(&(10..40)))
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            PlanError::DoesNotFit {
+                instruction_range,
+                rejection: VersionRejection::TransactionTooLarge { .. },
+            } if instruction_range == (10..40)
+        ));
+    }
+
+    #[test]
+    fn rejects_instruction_too_large() {
+        let payer = Address::new_unique();
+        let instructions = vec![
+            transfer(&payer, &Address::new_unique(), 1),
+            Instruction::new_with_bytes(Address::new_unique(), &[0; PACKET_DATA_SIZE], vec![]),
+        ];
+        let err = TransactionPlanner::new(payer, MessageVersion::V0)
+            .plan(instructions, &[])
+            .unwrap_err();
+
+        assert!(matches!(
+            &err,
+            PlanError::DoesNotFit { instruction_range, .. } if *instruction_range == (1..2)
+        ));
+        assert!(err
+            .to_string()
+            .starts_with("instructions 1..2 do not fit in a transaction"));
+    }
+
+    #[test]
+    fn rejects_config_for_legacy() {
+        let payer = Address::new_unique();
+        let err = TransactionPlanner::new(payer, MessageVersion::Legacy)
+            .with_config(TransactionConfig::empty().with_priority_fee(1))
+            .plan(transfers(&payer, 1), &[])
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            PlanError::DoesNotFit {
+                instruction_range: 0..1,
+                rejection: VersionRejection::ConfigNotSupported,
+            }
+        );
+    }
+
+    #[test]
+    fn uses_lookup_tables() {
+        let payer = Address::new_unique();
+        let instructions = transfers(&payer, 60);
+        let table = AddressLookupTableAccount {
+            key: Address::new_unique(),
+            addresses: instructions
+                .iter()
+                .map(|instruction| instruction.accounts[1].pubkey)
+                .collect(),
+        };
+        let planner = TransactionPlanner::new(payer, MessageVersion::V0);
+        let without_table = planner.plan(instructions.clone(), &[]).unwrap();
+        let planner = planner.with_address_lookup_table_accounts(vec![table]);
+        let with_table = planner.plan(instructions, &[]).unwrap();
+
+        assert!(with_table.len() < without_table.len());
+        assert_fewest(&planner, &with_table);
+        for transaction in &with_table {
+            assert_eq!(transaction.transaction_size, serialized_size(transaction));
+        }
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn rejects_invalid_atomic_groups() {
+        let payer = Address::new_unique();
+        let planner = TransactionPlanner::new(payer, MessageVersion::Legacy);
+        for (groups, invalid) in [
+            (vec![2..2], 2..2),
+            (vec![3..6], 3..6),
+            (vec![0..2, 1..3], 1..3),
+            (vec![1..3, 0..2], 1..3),
+        ] {
+            assert_eq!(
+                planner.plan(transfers(&payer, 5), &groups),
+                Err(PlanError::InvalidAtomicGroup(invalid))
+            );
+        }
+    }
+}
@@ -269,7 +269,7 @@ impl SanitizedTransaction {
 
     #[cfg(feature = "verify")]
     /// Return the serialized message data to sign.
-    fn message_data(&self) -> Vec<u8> {
+    pub(crate) fn message_data(&self) -> Vec<u8> {
         match &self.message {
             SanitizedMessage::Legacy(legacy_message) => legacy_message.message.serialize(),
             SanitizedMessage::V0(loaded_msg) => loaded_msg.message.serialize(),
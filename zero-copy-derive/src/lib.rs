@@ -0,0 +1,171 @@
+//! Derive macro for [`solana-zero-copy`] account layouts.
+//!
+//! [`solana-zero-copy`]: https://docs.rs/solana-zero-copy
+
+use {
+    proc_macro::TokenStream,
+    proc_macro2::TokenStream as TokenStream2,
+    quote::{quote, quote_spanned},
+    syn::{
+        parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Expr, ExprLit, Fields, Lit,
+        Meta,
+    },
+};
+
+/// Implements `ZeroCopy`, `Pod` and `Zeroable` for a struct made only of
+/// alignment-1 `Pod` fields.
+///
+/// The struct must be `#[repr(C)]` or `#[repr(transparent)]`, must not be
+/// generic and must implement `Copy`. Each field is checked at compile time
+/// to be `Pod` with an alignment of `1`, which guarantees that the struct has
+/// no padding.
+///
+/// A discriminator expected at the start of the account data can be declared
+/// as an array or byte string literal:
+///
+/// ```ignore
+/// #[derive(Clone, Copy, ZeroCopy)]
+/// #[repr(C)]
+/// #[zero_copy(discriminator = [1, 0, 0, 0])]
+/// struct Vault {
+///     authority: [u8; 32],
+///     balance: U64,
+/// }
+/// ```
+#[proc_macro_derive(ZeroCopy, attributes(zero_copy))]
+pub fn derive_zero_copy(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "ZeroCopy cannot be derived for generic structs",
+        ));
+    }
+    check_repr(&input)?;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect::<Vec<_>>(),
+            Fields::Unnamed(fields) => fields.unnamed.iter().collect::<Vec<_>>(),
+            Fields::Unit => Vec::new(),
+        },
+        _ => {
+            return Err(Error::new(
+                name.span(),
+                "ZeroCopy can only be derived for structs",
+            ))
+        }
+    };
+    let discriminator = parse_discriminator(&input)?;
+
+    let field_assertions = fields.iter().enumerate().map(|(index, field)| {
+        let ty = &field.ty;
+        let message = match &field.ident {
+            Some(ident) => format!("field `{ident}` of `{name}` must have an alignment of 1"),
+            None => format!("field {index} of `{name}` must have an alignment of 1"),
+        };
+        quote_spanned! {ty.span()=>
+            assert_pod::<#ty>();
+            assert!(::core::mem::align_of::<#ty>() == 1, #message);
+        }
+    });
+    let field_sizes = fields.iter().map(|field| {
+        let ty = &field.ty;
+        quote!(::core::mem::size_of::<#ty>())
+    });
+    let size_message = format!("`{name}` must not contain padding");
+
+    Ok(quote! {
+        const _: () = {
+            use ::solana_zero_copy::__private::bytemuck::Pod;
+
+            const fn assert_pod<T: Pod>() {}
+
+            #(#field_assertions)*
+            assert!(
+                ::core::mem::size_of::<#name>() == 0 #(+ #field_sizes)*,
+                #size_message
+            );
+        };
+
+        // SAFETY: Every field is `Pod` with an alignment of 1, so the struct
+        // has no padding and any bit pattern is valid.
+        unsafe impl ::solana_zero_copy::__private::bytemuck::Zeroable for #name {}
+        unsafe impl ::solana_zero_copy::__private::bytemuck::Pod for #name {}
+
+        impl ::solana_zero_copy::account::ZeroCopy for #name {
+            const DISCRIMINATOR: &'static [u8] = #discriminator;
+            const LEN: usize = ::core::mem::size_of::<#name>();
+            const SPACE: usize = Self::DISCRIMINATOR.len() + Self::LEN;
+        }
+    })
+}
+
+/// Checks that the struct has a `C` or `transparent` representation, which
+/// fixes the order of its fields.
+fn check_repr(input: &DeriveInput) -> syn::Result<()> {
+    let mut has_repr = false;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("repr"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("C") || meta.path.is_ident("transparent") {
+                has_repr = true;
+            }
+            Ok(())
+        })?;
+    }
+    if has_repr {
+        Ok(())
+    } else {
+        Err(Error::new(
+            input.ident.span(),
+            "ZeroCopy requires `#[repr(C)]` or `#[repr(transparent)]`",
+        ))
+    }
+}
+
+/// Parses the `#[zero_copy(discriminator = ...)]` attribute into a
+/// `&'static [u8]` expression.
+fn parse_discriminator(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let mut discriminator = quote!(&[]);
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("zero_copy"))
+    {
+        if let Meta::Path(path) = &attr.meta {
+            return Err(Error::new(path.span(), "expected `discriminator = ...`"));
+        }
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("discriminator") {
+                return Err(meta.error("unsupported zero_copy attribute"));
+            }
+            discriminator = match meta.value()?.parse::<Expr>()? {
+                Expr::Array(array) => quote!(&#array),
+                Expr::Lit(ExprLit {
+                    lit: Lit::ByteStr(bytes),
+                    ..
+                }) => quote!(#bytes),
+                expr => {
+                    return Err(Error::new(
+                        expr.span(),
+                        "expected an array or byte string literal",
+                    ))
+                }
+            };
+            Ok(())
+        })?;
+    }
+    Ok(discriminator)
+}
@@ -0,0 +1,280 @@
+//! Typed overlays of account data.
+//!
+//! A struct made only of alignment-1 `Pod` fields, such as the
+//! [`unaligned`](crate::unaligned) wrappers and byte arrays, can be cast
+//! directly from account data at any offset. The [`ZeroCopy`] derive checks
+//! that at compile time, and implements the [`ZeroCopy`] trait to read the
+//! struct from account data after an optional discriminator:
+//!
+//! ```
+//! # #[cfg(feature = "derive")]
+//! # {
+//! use solana_zero_copy::{account::ZeroCopy, unaligned::U64, ZeroCopy};
+//!
+//! #[derive(Clone, Copy, ZeroCopy)]
+//! #[repr(C)]
+//! #[zero_copy(discriminator = [1, 0, 0, 0])]
+//! struct Vault {
+//!     authority: [u8; 32],
+//!     balance: U64,
+//! }
+//!
+//! assert_eq!(Vault::LEN, 40);
+//! assert_eq!(Vault::SPACE, 44);
+//!
+//! let mut data = [0; Vault::SPACE];
+//! Vault::initialize(&mut data).unwrap().balance = 42.into();
+//! assert_eq!(u64::from(Vault::from_bytes(&data).unwrap().balance), 42);
+//! # }
+//! ```
+//!
+//! [`ZeroCopy`]: derive@crate::ZeroCopy
+
+use {
+    bytemuck::{Pod, PodCastError},
+    core::fmt,
+};
+#[cfg(feature = "account-view")]
+use {
+    solana_account_view::{Ref, RefMut},
+    solana_program_error::ProgramError,
+};
+
+/// Errors when overlaying a [`ZeroCopy`] type on account data.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ZeroCopyError {
+    /// The data is too small for the discriminator and the type.
+    InvalidLength,
+    /// The data does not start with the discriminator of the type.
+    InvalidDiscriminator,
+    /// The data is not aligned for the type.
+    Misaligned,
+    /// A collection has no room for another item.
+    CapacityExceeded,
+}
+
+impl fmt::Display for ZeroCopyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength => f.write_str("account data is too small"),
+            Self::InvalidDiscriminator => f.write_str("account discriminator does not match"),
+            Self::Misaligned => f.write_str("account data is misaligned"),
+            Self::CapacityExceeded => f.write_str("collection capacity exceeded"),
+        }
+    }
+}
+
+impl core::error::Error for ZeroCopyError {}
+
+#[cfg(feature = "account-view")]
+impl From<ZeroCopyError> for ProgramError {
+    fn from(error: ZeroCopyError) -> Self {
+        match error {
+            ZeroCopyError::InvalidLength | ZeroCopyError::CapacityExceeded => {
+                ProgramError::AccountDataTooSmall
+            }
+            ZeroCopyError::InvalidDiscriminator | ZeroCopyError::Misaligned => {
+                ProgramError::InvalidAccountData
+            }
+        }
+    }
+}
+
+impl From<PodCastError> for ZeroCopyError {
+    fn from(error: PodCastError) -> Self {
+        match error {
+            PodCastError::TargetAlignmentGreaterAndInputNotAligned
+            | PodCastError::AlignmentMismatch => Self::Misaligned,
+            PodCastError::OutputSliceWouldHaveSlop | PodCastError::SizeMismatch => {
+                Self::InvalidLength
+            }
+        }
+    }
+}
+
+/// A typed layout of account data.
+///
+/// The data of an account holding a `T` starts with `T::DISCRIMINATOR`,
+/// followed by the `T::LEN` bytes of `T`. Any bytes after `T::SPACE` are
+/// ignored, so they can hold data of dynamic length.
+///
+/// Implement this trait with the [`ZeroCopy`](derive@crate::ZeroCopy) derive,
+/// which also asserts that `T` has an alignment of 1 so that it can be read
+/// from any account.
+pub trait ZeroCopy: Pod {
+    /// The bytes expected at the start of the account data.
+    const DISCRIMINATOR: &'static [u8];
+    /// The size of the type, in bytes.
+    const LEN: usize;
+    /// The size of the discriminator and the type, in bytes.
+    const SPACE: usize;
+
+    /// Reads `Self` from `data`, after checking the discriminator.
+    fn from_bytes(data: &[u8]) -> Result<&Self, ZeroCopyError> {
+        let data = data
+            .get(..Self::SPACE)
+            .ok_or(ZeroCopyError::InvalidLength)?;
+        let (discriminator, data) = data.split_at(Self::DISCRIMINATOR.len());
+        if discriminator != Self::DISCRIMINATOR {
+            return Err(ZeroCopyError::InvalidDiscriminator);
+        }
+        Ok(bytemuck::try_from_bytes(data)?)
+    }
+
+    /// Reads `Self` mutably from `data`, after checking the discriminator.
+    fn from_bytes_mut(data: &mut [u8]) -> Result<&mut Self, ZeroCopyError> {
+        let data = data
+            .get_mut(..Self::SPACE)
+            .ok_or(ZeroCopyError::InvalidLength)?;
+        let (discriminator, data) = data.split_at_mut(Self::DISCRIMINATOR.len());
+        if discriminator != Self::DISCRIMINATOR {
+            return Err(ZeroCopyError::InvalidDiscriminator);
+        }
+        Ok(bytemuck::try_from_bytes_mut(data)?)
+    }
+
+    /// Writes the discriminator at the start of `data` and returns the
+    /// `Self` that follows it.
+    ///
+    /// The bytes of `Self` are left as they are; new accounts are zeroed.
+    fn initialize(data: &mut [u8]) -> Result<&mut Self, ZeroCopyError> {
+        let data = data
+            .get_mut(..Self::SPACE)
+            .ok_or(ZeroCopyError::InvalidLength)?;
+        let (discriminator, data) = data.split_at_mut(Self::DISCRIMINATOR.len());
+        discriminator.copy_from_slice(Self::DISCRIMINATOR);
+        Ok(bytemuck::try_from_bytes_mut(data)?)
+    }
+
+    /// Reads `Self` from borrowed account data, keeping the borrow.
+    #[cfg(feature = "account-view")]
+    fn from_ref(data: Ref<'_, [u8]>) -> Result<Ref<'_, Self>, ZeroCopyError> {
+        Ref::try_map(data, Self::from_bytes).map_err(|(_, error)| error)
+    }
+
+    /// Reads `Self` mutably from borrowed account data, keeping the borrow.
+    #[cfg(feature = "account-view")]
+    fn from_ref_mut(data: RefMut<'_, [u8]>) -> Result<RefMut<'_, Self>, ZeroCopyError> {
+        RefMut::try_map(data, Self::from_bytes_mut).map_err(|(_, error)| error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            unaligned::{Bool, U16, U64},
+            ZeroCopy,
+        },
+    };
+
+    #[derive(Clone, Copy, Debug, PartialEq, ZeroCopy)]
+    #[repr(C)]
+    #[zero_copy(discriminator = [7, 0])]
+    struct Counter {
+        authority: [u8; 32],
+        count: U64,
+        bump: u8,
+        closed: Bool,
+    }
+
+    #[derive(Clone, Copy, ZeroCopy)]
+    #[repr(C)]
+    #[zero_copy(discriminator = b"config")]
+    struct Config(U16);
+
+    #[derive(Clone, Copy, ZeroCopy)]
+    #[repr(transparent)]
+    struct Plain([u8; 3]);
+
+    #[test]
+    fn test_constants() {
+        assert_eq!(Counter::DISCRIMINATOR, &[7, 0]);
+        assert_eq!(Counter::LEN, 42);
+        assert_eq!(Counter::SPACE, 44);
+        assert_eq!(Config::DISCRIMINATOR, b"config");
+        assert_eq!(Config::SPACE, 8);
+        assert!(Plain::DISCRIMINATOR.is_empty());
+        assert_eq!(Plain::SPACE, 3);
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        // One byte more than needed, with the layout at an odd address.
+        let mut buffer = [0u8; 46];
+        let data = &mut buffer[1..];
+
+        assert_eq!(
+            Counter::from_bytes(data),
+            Err(ZeroCopyError::InvalidDiscriminator)
+        );
+        let counter = Counter::initialize(data).unwrap();
+        counter.count = 5.into();
+        counter.closed = true.into();
+        assert_eq!(&data[..2], &[7, 0]);
+
+        let counter = Counter::from_bytes_mut(data).unwrap();
+        counter.count += 1u64;
+        let counter = Counter::from_bytes(data).unwrap();
+        assert_eq!(u64::from(counter.count), 6);
+        assert!(bool::from(counter.closed));
+
+        assert_eq!(
+            Counter::from_bytes(&data[..43]),
+            Err(ZeroCopyError::InvalidLength)
+        );
+        assert_eq!(
+            Counter::from_bytes_mut(&mut data[..43]),
+            Err(ZeroCopyError::InvalidLength)
+        );
+        data[1] = 1;
+        assert_eq!(
+            Counter::from_bytes_mut(data),
+            Err(ZeroCopyError::InvalidDiscriminator)
+        );
+    }
+
+    #[test]
+    fn test_from_ref() {
+        use {
+            core::mem::size_of,
+            solana_account_view::{AccountView, RuntimeAccount, NOT_BORROWED},
+        };
+
+        const WORDS: usize = (size_of::<RuntimeAccount>() + 48) / 8;
+        let mut memory = [0u64; WORDS];
+        let raw = memory.as_mut_ptr() as *mut RuntimeAccount;
+        unsafe {
+            (*raw).borrow_state = NOT_BORROWED;
+            (*raw).data_len = Counter::SPACE as u64;
+        }
+        let mut account = unsafe { AccountView::new_unchecked(raw) };
+        let observer = unsafe { AccountView::new_unchecked(raw) };
+
+        assert_eq!(
+            Counter::from_ref(account.try_borrow().unwrap()).err(),
+            Some(ZeroCopyError::InvalidDiscriminator)
+        );
+        assert!(!observer.is_borrowed());
+
+        let mut data = account.try_borrow_mut().unwrap();
+        Counter::initialize(&mut data).unwrap();
+        drop(data);
+        let mut counter = Counter::from_ref_mut(account.try_borrow_mut().unwrap()).unwrap();
+        counter.bump = 255;
+        assert!(observer.try_borrow().is_err());
+        drop(counter);
+
+        let counter = Counter::from_ref(account.try_borrow().unwrap()).unwrap();
+        assert_eq!(counter.bump, 255);
+        assert!(observer.is_borrowed());
+        drop(counter);
+        assert!(!observer.is_borrowed());
+
+        assert_eq!(
+            ProgramError::from(ZeroCopyError::InvalidLength),
+            ProgramError::AccountDataTooSmall
+        );
+    }
+}
@@ -0,0 +1,661 @@
+//! Variable-length collections over account data.
+//!
+//! Each collection is a view over a byte buffer that starts with a small
+//! header of [`U32`] integers, followed by the items. The items must be `Pod`
+//! with an alignment of 1, like the [`unaligned`](crate::unaligned) wrappers
+//! and [`ZeroCopy`](crate::account::ZeroCopy) layouts, so that a collection
+//! can start at any offset of the account data:
+//!
+//! - [`PodSlice`] reads a length-prefixed list of items.
+//! - [`PodVec`] pushes and pops items in a length-prefixed list, up to the
+//!   number of items that fit in the buffer.
+//! - [`PodRing`] is a double-ended queue with a capacity fixed when it is
+//!   created, for example of events that are consumed in order.
+//!
+//! ```
+//! use solana_zero_copy::{collections::PodVec, unaligned::U64};
+//!
+//! let mut data = [0; PodVec::<U64>::space(2)];
+//! let mut prices = PodVec::<U64>::init(&mut data).unwrap();
+//! prices.push(10.into()).unwrap();
+//! prices.push(20.into()).unwrap();
+//! assert!(prices.push(30.into()).is_err());
+//! assert_eq!(prices.pop().map(u64::from), Some(20));
+//! ```
+
+use {
+    crate::{account::ZeroCopyError, unaligned::U32},
+    bytemuck::Pod,
+    core::{
+        mem::size_of,
+        ops::{Deref, DerefMut},
+    },
+};
+
+const U32_LEN: usize = size_of::<U32>();
+
+/// Returns the number of bytes of `header_len` bytes followed by `capacity`
+/// items, saturating at `usize::MAX`.
+const fn space<T>(header_len: usize, capacity: usize) -> usize {
+    capacity
+        .saturating_mul(size_of::<T>())
+        .saturating_add(header_len)
+}
+
+/// Casts the items that fit in `data`, up to `u32::MAX` of them.
+fn items<T: Pod>(data: &[u8]) -> &[T] {
+    let len = fitting_len::<T>(data.len());
+    bytemuck::cast_slice(&data[..len])
+}
+
+/// Casts the items that fit in `data` mutably, up to `u32::MAX` of them.
+fn items_mut<T: Pod>(data: &mut [u8]) -> &mut [T] {
+    let len = fitting_len::<T>(data.len());
+    bytemuck::cast_slice_mut(&mut data[..len])
+}
+
+/// Returns the length of the longest prefix of `len` bytes holding up to
+/// `u32::MAX` whole items.
+fn fitting_len<T>(len: usize) -> usize {
+    const {
+        assert!(
+            core::mem::align_of::<T>() == 1 && size_of::<T>() != 0,
+            "items must be non-zero-sized with an alignment of 1"
+        )
+    };
+    let capacity = len
+        .checked_div(size_of::<T>())
+        .unwrap_or_default()
+        .min(u32::MAX as usize);
+    capacity.saturating_mul(size_of::<T>())
+}
+
+/// Reads the [`U32`] at the start of `data`, returning it with the rest.
+fn split_u32(data: &[u8]) -> Result<(usize, &[u8]), ZeroCopyError> {
+    let (value, rest) = data
+        .split_first_chunk::<U32_LEN>()
+        .ok_or(ZeroCopyError::InvalidLength)?;
+    Ok((u32::from_le_bytes(*value) as usize, rest))
+}
+
+/// Splits the [`U32`] at the start of `data` from the rest, mutably.
+fn split_u32_mut(data: &mut [u8]) -> Result<(&mut U32, &mut [u8]), ZeroCopyError> {
+    let (value, rest) = data
+        .split_first_chunk_mut::<U32_LEN>()
+        .ok_or(ZeroCopyError::InvalidLength)?;
+    Ok((bytemuck::cast_mut(value), rest))
+}
+
+/// A length-prefixed list of items.
+///
+/// The buffer holds a [`U32`] length followed by that many items; any bytes
+/// after the items are ignored. The items are accessed through the slice
+/// methods.
+#[derive(Debug)]
+pub struct PodSlice<'a, T> {
+    items: &'a [T],
+}
+
+impl<'a, T: Pod> PodSlice<'a, T> {
+    /// The size of the length header, in bytes.
+    pub const HEADER_LEN: usize = U32_LEN;
+
+    /// Returns the number of bytes needed to hold `len` items.
+    pub const fn space(len: usize) -> usize {
+        space::<T>(Self::HEADER_LEN, len)
+    }
+
+    /// Reads the list at the start of `data`.
+    ///
+    /// Fails if `data` is smaller than the length header says.
+    pub fn unpack(data: &'a [u8]) -> Result<Self, ZeroCopyError> {
+        let (len, data) = split_u32(data)?;
+        let items = items::<T>(data)
+            .get(..len)
+            .ok_or(ZeroCopyError::InvalidLength)?;
+        Ok(Self { items })
+    }
+
+    /// Returns the items, for the lifetime of the buffer.
+    pub fn as_slice(&self) -> &'a [T] {
+        self.items
+    }
+}
+
+impl<T> Deref for PodSlice<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.items
+    }
+}
+
+/// A length-prefixed list of items, with a capacity of as many items as fit
+/// in the buffer.
+///
+/// The buffer holds a [`U32`] length followed by the items. The items that
+/// have been pushed are accessed through the slice methods. Since the items
+/// are always at the start of the buffer, resizing the buffer only changes
+/// the capacity; shrinking it below the length fails [`Self::unpack`].
+#[derive(Debug)]
+pub struct PodVec<'a, T> {
+    len: &'a mut U32,
+    items: &'a mut [T],
+}
+
+impl<'a, T: Pod> PodVec<'a, T> {
+    /// The size of the length header, in bytes.
+    pub const HEADER_LEN: usize = U32_LEN;
+
+    /// Returns the number of bytes needed for a capacity of `capacity` items.
+    pub const fn space(capacity: usize) -> usize {
+        space::<T>(Self::HEADER_LEN, capacity)
+    }
+
+    /// Creates an empty list at the start of `data`.
+    pub fn init(data: &'a mut [u8]) -> Result<Self, ZeroCopyError> {
+        let (len, data) = split_u32_mut(data)?;
+        *len = U32::from(0);
+        Ok(Self {
+            len,
+            items: items_mut(data),
+        })
+    }
+
+    /// Reads the list at the start of `data`.
+    ///
+    /// Fails if `data` is smaller than the length header says.
+    pub fn unpack(data: &'a mut [u8]) -> Result<Self, ZeroCopyError> {
+        let (len, data) = split_u32_mut(data)?;
+        let items = items_mut(data);
+        if usize::from(*len) > items.len() {
+            return Err(ZeroCopyError::InvalidLength);
+        }
+        Ok(Self { len, items })
+    }
+
+    /// Returns the number of items the list can hold.
+    pub fn capacity(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Appends `item` to the list.
+    ///
+    /// Fails with [`ZeroCopyError::CapacityExceeded`] if the list is full.
+    pub fn push(&mut self, item: T) -> Result<(), ZeroCopyError> {
+        let len = self.len();
+        let slot = self
+            .items
+            .get_mut(len)
+            .ok_or(ZeroCopyError::CapacityExceeded)?;
+        *slot = item;
+        self.set_len(len.saturating_add(1));
+        Ok(())
+    }
+
+    /// Removes the last item of the list and returns it.
+    pub fn pop(&mut self) -> Option<T> {
+        let len = self.len().checked_sub(1)?;
+        self.set_len(len);
+        Some(self.items[len])
+    }
+
+    /// Removes the item at `index` and returns it, shifting the items after
+    /// it to the left.
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        let item = *self.get(index)?;
+        let len = self.len();
+        self.items.copy_within(index.saturating_add(1)..len, index);
+        self.set_len(len.saturating_sub(1));
+        Some(item)
+    }
+
+    /// Removes all the items.
+    pub fn clear(&mut self) {
+        self.set_len(0);
+    }
+
+    fn set_len(&mut self, len: usize) {
+        // The capacity is at most `u32::MAX`.
+        *self.len = U32::from(len as u32);
+    }
+}
+
+impl<T> Deref for PodVec<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.items[..usize::from(*self.len)]
+    }
+}
+
+impl<T> DerefMut for PodVec<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.items[..usize::from(*self.len)]
+    }
+}
+
+/// A double-ended queue with a fixed capacity.
+///
+/// The buffer holds a [`U32`] index of the front item, a [`U32`] length and
+/// a [`U32`] capacity, followed by the items, which wrap around at the
+/// capacity. The capacity is stored rather than derived from the size of the
+/// buffer so that resizing the buffer cannot move the items; any bytes after
+/// the items are ignored.
+#[derive(Debug)]
+pub struct PodRing<'a, T> {
+    head: &'a mut U32,
+    len: &'a mut U32,
+    items: &'a mut [T],
+}
+
+impl<'a, T: Pod> PodRing<'a, T> {
+    /// The size of the header, in bytes.
+    pub const HEADER_LEN: usize = 3 * U32_LEN;
+
+    /// Returns the number of bytes needed for a capacity of `capacity` items.
+    pub const fn space(capacity: usize) -> usize {
+        space::<T>(Self::HEADER_LEN, capacity)
+    }
+
+    /// Creates an empty queue of `capacity` items at the start of `data`.
+    ///
+    /// Fails with [`ZeroCopyError::InvalidLength`] if `data` is smaller than
+    /// [`Self::space`] of `capacity`.
+    pub fn init(data: &'a mut [u8], capacity: usize) -> Result<Self, ZeroCopyError> {
+        let (head, len, stored_capacity, items) = Self::split(data)?;
+        let items = items
+            .get_mut(..capacity)
+            .ok_or(ZeroCopyError::InvalidLength)?;
+        // There are at most `u32::MAX` items.
+        *stored_capacity = U32::from(capacity as u32);
+        let mut ring = Self { head, len, items };
+        ring.clear();
+        Ok(ring)
+    }
+
+    /// Reads the queue at the start of `data`.
+    ///
+    /// Fails if `data` is smaller than the capacity in the header says, or
+    /// if the front index or length is out of range.
+    pub fn unpack(data: &'a mut [u8]) -> Result<Self, ZeroCopyError> {
+        let (head, len, capacity, items) = Self::split(data)?;
+        let items = items
+            .get_mut(..usize::from(*capacity))
+            .ok_or(ZeroCopyError::InvalidLength)?;
+        let ring = Self { head, len, items };
+        let head = usize::from(*ring.head);
+        if ring.len() > ring.capacity() || (head >= ring.capacity() && head != 0) {
+            return Err(ZeroCopyError::InvalidLength);
+        }
+        Ok(ring)
+    }
+
+    /// Splits `data` into the front index, length and capacity headers and
+    /// the items that fit after them.
+    #[allow(clippy::type_complexity)]
+    fn split(
+        data: &'a mut [u8],
+    ) -> Result<(&'a mut U32, &'a mut U32, &'a mut U32, &'a mut [T]), ZeroCopyError> {
+        let (head, data) = split_u32_mut(data)?;
+        let (len, data) = split_u32_mut(data)?;
+        let (capacity, data) = split_u32_mut(data)?;
+        Ok((head, len, capacity, items_mut(data)))
+    }
+
+    /// Returns the number of items in the queue.
+    pub fn len(&self) -> usize {
+        usize::from(*self.len)
+    }
+
+    /// Returns `true` if the queue has no items.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the queue cannot hold more items.
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity()
+    }
+
+    /// Returns the number of items the queue can hold.
+    pub fn capacity(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns the item at `index` from the front.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        (index < self.len()).then(|| &self.items[self.wrap(index)])
+    }
+
+    /// Returns the item at `index` from the front, mutably.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        (index < self.len()).then(|| {
+            let index = self.wrap(index);
+            &mut self.items[index]
+        })
+    }
+
+    /// Returns the front item.
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    /// Returns the back item.
+    pub fn back(&self) -> Option<&T> {
+        self.get(self.len().checked_sub(1)?)
+    }
+
+    /// Appends `item` to the back of the queue.
+    ///
+    /// Fails with [`ZeroCopyError::CapacityExceeded`] if the queue is full.
+    pub fn push_back(&mut self, item: T) -> Result<(), ZeroCopyError> {
+        if self.is_full() {
+            return Err(ZeroCopyError::CapacityExceeded);
+        }
+        let len = self.len();
+        let index = self.wrap(len);
+        self.items[index] = item;
+        self.set_len(len.saturating_add(1));
+        Ok(())
+    }
+
+    /// Appends `item` to the back of the queue, removing and returning the
+    /// front item if the queue is full.
+    ///
+    /// A queue with no capacity returns `item` itself.
+    pub fn push_overwrite(&mut self, item: T) -> Option<T> {
+        if self.capacity() == 0 {
+            return Some(item);
+        }
+        let evicted = if self.is_full() {
+            self.pop_front()
+        } else {
+            None
+        };
+        // There is room for `item` now.
+        let _ = self.push_back(item);
+        evicted
+    }
+
+    /// Removes the front item and returns it.
+    pub fn pop_front(&mut self) -> Option<T> {
+        let item = *self.front()?;
+        let head = self.wrap(1);
+        *self.head = U32::from(head as u32);
+        self.set_len(self.len().saturating_sub(1));
+        Some(item)
+    }
+
+    /// Removes the back item and returns it.
+    pub fn pop_back(&mut self) -> Option<T> {
+        let item = *self.back()?;
+        self.set_len(self.len().saturating_sub(1));
+        Some(item)
+    }
+
+    /// Removes all the items.
+    pub fn clear(&mut self) {
+        *self.head = U32::from(0);
+        self.set_len(0);
+    }
+
+    /// Returns the items from the front, as the part before the end of the
+    /// buffer and the part wrapped around to its start.
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let (front_len, back_len) = self.slice_lens();
+        let (wrapped, front) = self.items.split_at(usize::from(*self.head));
+        (&front[..front_len], &wrapped[..back_len])
+    }
+
+    /// Returns the items from the front mutably, as the part before the end
+    /// of the buffer and the part wrapped around to its start.
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let (front_len, back_len) = self.slice_lens();
+        let (wrapped, front) = self.items.split_at_mut(usize::from(*self.head));
+        (&mut front[..front_len], &mut wrapped[..back_len])
+    }
+
+    /// Returns an iterator over the items, from the front.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator {
+        let (front, back) = self.as_slices();
+        Iter {
+            front: front.iter(),
+            back: back.iter(),
+        }
+    }
+
+    /// Returns an iterator over the items mutably, from the front.
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut T> + ExactSizeIterator {
+        let (front, back) = self.as_mut_slices();
+        Iter {
+            front: front.iter_mut(),
+            back: back.iter_mut(),
+        }
+    }
+
+    /// Returns the lengths of the two parts returned by [`Self::as_slices`].
+    fn slice_lens(&self) -> (usize, usize) {
+        let len = self.len();
+        let front_len = len.min(self.capacity().saturating_sub(usize::from(*self.head)));
+        (front_len, len.saturating_sub(front_len))
+    }
+
+    /// Returns the position in the buffer of the item at `index` from the
+    /// front, for an `index` up to the capacity.
+    fn wrap(&self, index: usize) -> usize {
+        let position = usize::from(*self.head).saturating_add(index);
+        position.checked_sub(self.capacity()).unwrap_or(position)
+    }
+
+    fn set_len(&mut self, len: usize) {
+        // The capacity is at most `u32::MAX`.
+        *self.len = U32::from(len as u32);
+    }
+}
+
+/// Iterator over the two parts of a [`PodRing`], which unlike
+/// [`core::iter::Chain`] knows its exact length.
+struct Iter<I> {
+    front: I,
+    back: I,
+}
+
+impl<I: ExactSizeIterator> Iterator for Iter<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        self.front.next().or_else(|| self.back.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl<I: DoubleEndedIterator + ExactSizeIterator> DoubleEndedIterator for Iter<I> {
+    fn next_back(&mut self) -> Option<I::Item> {
+        self.back.next_back().or_else(|| self.front.next_back())
+    }
+}
+
+impl<I: ExactSizeIterator> ExactSizeIterator for Iter<I> {
+    fn len(&self) -> usize {
+        self.front.len().saturating_add(self.back.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use {
+        super::*,
+        crate::unaligned::{U16, U64},
+        std::vec::Vec,
+    };
+
+    #[test]
+    fn test_pod_slice() {
+        // The list starts at an odd address, with trailing bytes.
+        let mut buffer = [0u8; 16];
+        buffer[1..5].copy_from_slice(&3u32.to_le_bytes());
+        buffer[5..11].copy_from_slice(&[1, 0, 2, 0, 3, 0]);
+
+        let slice = PodSlice::<U16>::unpack(&buffer[1..]).unwrap();
+        assert_eq!(slice.len(), 3);
+        assert_eq!(
+            slice.iter().copied().map(u16::from).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert_eq!(PodSlice::<U16>::space(3), 10);
+
+        assert_eq!(
+            PodSlice::<U16>::unpack(&buffer[1..10]).unwrap_err(),
+            ZeroCopyError::InvalidLength
+        );
+        assert_eq!(
+            PodSlice::<U16>::unpack(&buffer[..3]).unwrap_err(),
+            ZeroCopyError::InvalidLength
+        );
+    }
+
+    #[test]
+    fn test_pod_vec() {
+        let mut buffer = [0xffu8; 1 + PodVec::<U64>::space(3) + 7];
+        let data = &mut buffer[1..];
+
+        let mut vec = PodVec::<U64>::init(data).unwrap();
+        assert!(vec.is_empty());
+        assert_eq!(vec.capacity(), 3);
+        assert_eq!(vec.pop(), None);
+        for n in 1..=3u64 {
+            vec.push(n.into()).unwrap();
+        }
+        assert_eq!(vec.push(4.into()), Err(ZeroCopyError::CapacityExceeded));
+        vec[0] += 10u64;
+
+        let mut vec = PodVec::<U64>::unpack(data).unwrap();
+        assert_eq!(
+            vec.iter().copied().map(u64::from).collect::<Vec<_>>(),
+            [11, 2, 3]
+        );
+        assert_eq!(vec.remove(0).map(u64::from), Some(11));
+        assert_eq!(vec.remove(2), None);
+        assert_eq!(vec.pop().map(u64::from), Some(3));
+        assert_eq!(vec.iter().copied().map(u64::from).collect::<Vec<_>>(), [2]);
+        vec.clear();
+        assert!(vec.is_empty());
+
+        data[..4].copy_from_slice(&4u32.to_le_bytes());
+        assert_eq!(
+            PodVec::<U64>::unpack(data).unwrap_err(),
+            ZeroCopyError::InvalidLength
+        );
+        assert_eq!(
+            PodVec::<U64>::init(&mut data[..3]).unwrap_err(),
+            ZeroCopyError::InvalidLength
+        );
+        let mut vec = PodVec::<U64>::init(&mut data[..4]).unwrap();
+        assert_eq!(vec.push(1.into()), Err(ZeroCopyError::CapacityExceeded));
+    }
+
+    #[test]
+    fn test_pod_ring() {
+        let mut buffer = [0u8; 1 + PodRing::<U16>::space(3)];
+        let data = &mut buffer[1..];
+
+        let mut ring = PodRing::<U16>::init(data, 3).unwrap();
+        assert_eq!(ring.capacity(), 3);
+        assert!(ring.is_empty());
+        assert_eq!(ring.pop_front(), None);
+        assert_eq!(ring.pop_back(), None);
+        for n in 1..=3u16 {
+            ring.push_back(n.into()).unwrap();
+        }
+        assert!(ring.is_full());
+        assert_eq!(
+            ring.push_back(4.into()),
+            Err(ZeroCopyError::CapacityExceeded)
+        );
+        assert_eq!(ring.pop_front().map(u16::from), Some(1));
+        ring.push_back(4.into()).unwrap();
+        assert_eq!(ring.push_overwrite(5.into()).map(u16::from), Some(2));
+
+        // The items wrap around the end of the buffer.
+        let mut ring = PodRing::<U16>::unpack(data).unwrap();
+        assert_eq!(ring.as_slices().0.len(), 1);
+        assert_eq!(ring.as_slices().1.len(), 2);
+        let items = |ring: &PodRing<U16>| ring.iter().copied().map(u16::from).collect::<Vec<_>>();
+        assert_eq!(items(&ring), [3, 4, 5]);
+        assert_eq!(ring.iter().len(), 3);
+        assert_eq!(
+            ring.iter()
+                .rev()
+                .copied()
+                .map(u16::from)
+                .collect::<Vec<_>>(),
+            [5, 4, 3]
+        );
+        assert_eq!(ring.front().copied().map(u16::from), Some(3));
+        assert_eq!(ring.back().copied().map(u16::from), Some(5));
+        assert_eq!(ring.get(1).copied().map(u16::from), Some(4));
+        assert_eq!(ring.get(3), None);
+        for item in ring.iter_mut() {
+            *item += 10u16;
+        }
+        *ring.get_mut(2).unwrap() = 0.into();
+        assert_eq!(items(&ring), [13, 14, 0]);
+
+        assert_eq!(ring.pop_back().map(u16::from), Some(0));
+        assert_eq!(ring.pop_front().map(u16::from), Some(13));
+        assert_eq!(ring.pop_front().map(u16::from), Some(14));
+        assert!(ring.is_empty());
+        assert!(items(&ring).is_empty());
+
+        data[..4].copy_from_slice(&3u32.to_le_bytes());
+        assert_eq!(
+            PodRing::<U16>::unpack(data).unwrap_err(),
+            ZeroCopyError::InvalidLength
+        );
+        data[..8].copy_from_slice(&[0, 0, 0, 0, 4, 0, 0, 0]);
+        assert_eq!(
+            PodRing::<U16>::unpack(data).unwrap_err(),
+            ZeroCopyError::InvalidLength
+        );
+
+        let mut ring = PodRing::<U16>::init(&mut data[..12], 0).unwrap();
+        assert!(ring.is_full());
+        assert_eq!(ring.push_overwrite(1.into()).map(u16::from), Some(1));
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn test_pod_ring_capacity() {
+        let mut buffer = [0u8; PodRing::<U16>::space(4)];
+        assert_eq!(
+            PodRing::<U16>::init(&mut buffer[..PodRing::<U16>::space(3)], 4).unwrap_err(),
+            ZeroCopyError::InvalidLength
+        );
+
+        let mut ring = PodRing::<U16>::init(&mut buffer[..PodRing::<U16>::space(3)], 3).unwrap();
+        for n in 1..=4u16 {
+            ring.push_overwrite(n.into());
+        }
+
+        // Growing the buffer keeps the capacity, so the items stay in place.
+        let ring = PodRing::<U16>::unpack(&mut buffer).unwrap();
+        assert_eq!(ring.capacity(), 3);
+        assert_eq!(
+            ring.iter().copied().map(u16::from).collect::<Vec<_>>(),
+            [2, 3, 4]
+        );
+
+        // The buffer cannot shrink below the capacity.
+        assert_eq!(
+            PodRing::<U16>::unpack(&mut buffer[..PodRing::<U16>::space(2)]).unwrap_err(),
+            ZeroCopyError::InvalidLength
+        );
+    }
+}
@@ -1,7 +1,9 @@
 //! Solana zero-copy types.
 //!
 //! This crate provides unaligned primitive wrappers for use in Solana
-//! zero-copy data structures.
+//! zero-copy data structures, the [`ZeroCopy`](account::ZeroCopy) trait to
+//! overlay typed layouts built from them on account data, and variable-length
+//! [`collections`] of such layouts.
 
 #![no_std]
 #![cfg_attr(docsrs, feature(doc_cfg))]
@@ -9,4 +11,21 @@
 #[cfg(feature = "borsh")]
 extern crate alloc;
 
+// Allows the derive macro to refer to this crate from its own tests.
+#[cfg(test)]
+extern crate self as solana_zero_copy;
+
+#[cfg(feature = "bytemuck")]
+pub mod account;
+#[cfg(feature = "bytemuck")]
+pub mod collections;
 pub mod unaligned;
+
+#[cfg(feature = "derive")]
+pub use solana_zero_copy_derive::ZeroCopy;
+
+#[cfg(feature = "bytemuck")]
+#[doc(hidden)]
+pub mod __private {
+    pub use bytemuck;
+}